@@ -12,7 +12,7 @@ use std::{sync::Arc, collections::HashMap};
 use std::panic::panic_any;
 
 use crate::utils::ToMutable;
-use crate::{value::JValue, prelude, builtins::{self, JObject}, vm::VmContext};
+use crate::{value::{JValue, JValueEnum}, prelude, builtins::{self, JObject}, vm::VmContext};
 
 pub fn bind_function<F, Args, T>(f:F) -> Arc<dyn Fn(&mut VmContext, JValue, &[JValue]) -> JValue + 'static>
 where F:Fn<Args, Output = T> +'static, Args:Arguments, T:Returnable{
@@ -25,7 +25,7 @@ where F:Fn<Args, Output = T> +'static, Args:Arguments, T:Returnable{
 }
 
 
-pub trait Arguments{
+pub trait Arguments:std::marker::Tuple{
     fn from_values(this:JValue, values:&[JValue]) -> Self;
 }
 
@@ -51,9 +51,9 @@ macro_rules! gen_args {
     };
 }
 
-impl<This> Arguments for (This) where This:Bindable{
+impl<This> Arguments for (This,) where This:Bindable{
     fn from_values(this:JValue, values:&[JValue]) -> Self {
-        (This::from_jvalue(this))
+        (This::from_jvalue(this),)
     }
 }
 gen_args!();
@@ -147,8 +147,8 @@ impl Bindable for (){
 
 impl Bindable for prelude::JObject<'_>{
     fn from_jvalue(value:JValue) -> Self {
-        match value{
-            JValue::Object(o) => prelude::JObject { 
+        match value.unbox(){
+            JValueEnum::Object(o) => prelude::JObject { 
                 obj: unsafe{o.as_mut().unwrap()}, 
                 mark: std::marker::PhantomData
             },
@@ -180,6 +180,17 @@ impl Bindable for Option<&mut builtins::Array>{
     }
 }
 
+impl Bindable for Option<&mut builtins::generator::Generator>{
+    fn from_jvalue(value:JValue) -> Self {
+        if let Some(o) = value.object(){
+            if let Some(g) = o.inner.generator(){
+                return Some(g.to_mut())
+            }
+        }
+        None
+    }
+}
+
 pub trait Last{
     fn from_remain(values:&[JValue]) -> Self;
 }
@@ -206,7 +217,7 @@ impl Last for &[JValue] {
 
 impl<T> Last for T where T:Bindable{
     fn from_remain(values:&[JValue]) -> Self {
-        return T::from_jvalue(values[0])
+        return T::from_jvalue(values.get(0).copied().unwrap_or(JValue::Undefined))
     }
 }
 
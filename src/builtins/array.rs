@@ -4,7 +4,7 @@
 use std::sync::Arc;
 
 use crate::utils::ToMutable;
-use crate::{value::JValue, vm::VmContext};
+use crate::value::JValue;
 use crate::operator;
 
 
@@ -13,6 +13,8 @@ use super::object::{
     JObjectInner, JObjectInnerEnum
 };
 use super::function::Function;
+use super::prototypes::{resolve_prototype, PrototypeKind};
+use super::realm::Realm;
 
 pub struct Array{
     pub(crate) values:Vec<JValue>
@@ -20,6 +22,7 @@ pub struct Array{
 
 impl Array{
     pub fn new(object:&'static mut JObject, values:&[JValue]) -> JValue{
+        object.set_prototype(resolve_prototype(PrototypeKind::Array));
         object.inner = JObjectInnerEnum::Array(Array{
             values:values.to_vec()
         });
@@ -30,14 +33,23 @@ impl Array{
     pub unsafe fn new_raw(object:&'static mut JObject, argv:*mut JValue, argc:i64, spread:bool) -> JValue{
         let mut args = std::mem::transmute::<_, &[JValue]>((argv, argc as usize));
         if spread{
-            let mut v = args.to_vec();
-            v.extend(operator::IteratorCollect(args[args.len() -1]));
+            let v = operator::collect_args(argv, argc, spread);
             return Self::new(object, &v)
         };
 
         Self::new(object, args)
     }
     
+    pub fn get(&self, key:&str) -> Option<JValue>{
+        if key == "length"{
+            return Some(JValue::Number(self.values.len() as f64))
+        }
+        if let Ok(v) = key.parse::<usize>(){
+            return self.values.get(v).copied()
+        }
+        None
+    }
+
     pub fn set(&self, key:&str, value:JValue) -> bool{
         if let Ok(mut v) = key.parse::<i64>(){
             if v < 0{
@@ -47,7 +59,7 @@ impl Array{
                 return false
             }
 
-            if self.values.len() < v as usize{
+            if self.values.len() <= v as usize{
                 self.to_mut().values.resize(v as usize+1, JValue::Undefined);
             }
             self.values.to_mut()[v as usize] = value;
@@ -128,13 +140,10 @@ fn check_self<'a>(this:Option<&'a mut Array>, name:&'static str) -> &'a mut Arra
     operator::throw(super::Error::newTypeError(format!("Array.prototype.{}: require this to be array.", name)))
 }
 
-pub unsafe fn init(ctx:&mut VmContext, global:&'static mut JObject){
+pub unsafe fn init(realm:&mut Realm){
 
     let constructor = Function::native(Array::constructor).object().unwrap();
-    let proto = JObject::new();
-
-    global.builtin_member("Array", std::ptr::read(&constructor));
-    constructor.builtin_member("prototype", JValue::Object(proto));
+    let proto = realm.prototype(PrototypeKind::Array).as_mut().unwrap();
 
     constructor.builtin_member("from", Function::native(Array::from_));
     constructor.builtin_member("isArray", Function::native(Array::is_array));
@@ -142,4 +151,6 @@ pub unsafe fn init(ctx:&mut VmContext, global:&'static mut JObject){
 
     proto.builtin_member("length", 0i32);
     proto.builtin_member("at", Function::native(Array::at));
+
+    realm.add_constructor("Array", constructor, Some(PrototypeKind::Array));
 }
\ No newline at end of file
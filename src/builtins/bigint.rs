@@ -0,0 +1,333 @@
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, FromPrimitive, Num, Zero, One, Signed};
+
+use crate::value::{JValue, JValueEnum};
+use crate::operator;
+use crate::string_allocator::STRING_ALLOCATOR;
+
+use super::{JObject, Function, Error};
+use super::prototypes::PrototypeKind;
+use super::realm::Realm;
+
+/// JBigInt is an arbitrary-precision integer allocated on a runtime local allocator.
+///
+/// the pointer addresses a header holding the length followed by
+/// the little endian two's complement bytes, a bigint fits the payload of a `JValue`.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct JBigInt{
+    ptr:*const u8,
+}
+
+/// the size of the length header
+const HEADER:usize = std::mem::size_of::<u32>();
+
+impl JBigInt{
+    pub fn new(value:&BigInt) -> JBigInt{
+        let bytes = value.to_signed_bytes_le();
+        unsafe{
+            let ptr = STRING_ALLOCATOR.lock().alloc(std::alloc::Layout::from_size_align(HEADER + bytes.len(), HEADER).unwrap());
+            (ptr as *mut u32).write_unaligned(bytes.len() as u32);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.add(HEADER), bytes.len());
+            JBigInt{ptr}
+        }
+    }
+
+    pub(crate) fn from_ptr(ptr:*const u8) -> JBigInt{
+        JBigInt{ptr}
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const u8{
+        self.ptr
+    }
+
+    /// the shortest two's complement bytes of the value, equal values have equal bytes.
+    pub(crate) fn bytes(&self) -> &'static [u8]{
+        unsafe{
+            let len = (self.ptr as *const u32).read_unaligned() as usize;
+            std::slice::from_raw_parts(self.ptr.add(HEADER), len)
+        }
+    }
+
+    pub fn value(&self) -> BigInt{
+        BigInt::from_signed_bytes_le(self.bytes())
+    }
+}
+
+
+/// the value of a bigint literal
+pub(crate) fn from_literal(lit:&swc_ecma_ast::BigInt) -> BigInt{
+    // the parser may depend on another version of num-bigint
+    BigInt::from_signed_bytes_le(&lit.value.to_signed_bytes_le())
+}
+
+/// StringToBigInt, none if the string is not an integer literal
+pub(crate) fn string_to_bigint(s:&str) -> Option<BigInt>{
+    let s = s.trim();
+    if s.is_empty(){
+        return Some(BigInt::zero())
+    }
+
+    let radix = match s.get(0..2){
+        Some("0x") | Some("0X") => 16,
+        Some("0o") | Some("0O") => 8,
+        Some("0b") | Some("0B") => 2,
+        _ => 10
+    };
+    let digits = if radix == 10 {s} else {&s[2..]};
+    let unsigned = digits.strip_prefix(|c|c == '+' || c == '-').unwrap_or(digits);
+
+    // a sign is only allowed in decimal, rust also accepts underscores
+    if unsigned.is_empty() || (radix != 10 && unsigned.len() != digits.len()) || !unsigned.chars().all(|c|c.is_digit(radix)){
+        return None
+    }
+    BigInt::from_str_radix(digits, radix).ok()
+}
+
+/// NumberToBigInt, the number must be an integer
+pub(crate) fn number_to_bigint(n:f64) -> BigInt{
+    if n.fract() != 0.0 || !n.is_finite(){
+        operator::throw(Error::newRangeError(format!("The number {} cannot be converted to a BigInt because it is not an integer", JValue::Number(n).to_string())))
+    }
+    BigInt::from_f64(n).unwrap()
+}
+
+/// ToBigInt, numbers are not converted implicitly
+pub(crate) fn to_bigint(value:JValue) -> BigInt{
+    let prim = value.to_primitive(Some("number"));
+    match prim.unbox(){
+        JValueEnum::BigInt(b) => b.value(),
+        JValueEnum::Boolean(b) => BigInt::from(b as u8),
+        JValueEnum::String(s) => match string_to_bigint(&s){
+            Some(b) => b,
+            None => operator::throw(Error::newSyntaxError(format!("Cannot convert {} to a BigInt", s.as_str())))
+        },
+        JValueEnum::Symbol(_) => operator::throw(Error::newTypeError("Cannot convert a Symbol value to a BigInt")),
+        _ => operator::throw(Error::newTypeError(format!("Cannot convert {} to a BigInt", prim.to_string())))
+    }
+}
+
+/// compare a bigint to a number by their mathematical values, none if the number is NaN
+pub(crate) fn compare_number(a:&BigInt, n:f64) -> Option<Ordering>{
+    if n.is_nan(){
+        return None
+    }
+    if n.is_infinite(){
+        return Some(if n > 0.0 {Ordering::Less} else {Ordering::Greater})
+    }
+    let floor = n.floor();
+    match a.cmp(&BigInt::from_f64(floor).unwrap()){
+        // the number is between floor and floor + 1
+        Ordering::Equal if floor != n => Some(Ordering::Less),
+        o => Some(o)
+    }
+}
+
+/// the shift amount of a bigint shift operator
+fn shift_amount(b:&BigInt) -> i64{
+    match b.to_i64(){
+        Some(n) if n.unsigned_abs() <= u32::MAX as u64 => n,
+        _ => operator::throw(Error::newRangeError("Maximum BigInt size exceeded"))
+    }
+}
+
+/// `a << b`, a negative amount shifts right
+pub(crate) fn shift_left(a:BigInt, b:BigInt) -> BigInt{
+    let n = shift_amount(&b);
+    if n >= 0 {a << n as usize} else {a >> n.unsigned_abs() as usize}
+}
+
+/// `a >> b`, the result is rounded towards negative infinity
+pub(crate) fn shift_right(a:BigInt, b:BigInt) -> BigInt{
+    let n = shift_amount(&b);
+    if n >= 0 {a >> n as usize} else {a << n.unsigned_abs() as usize}
+}
+
+/// `a ** b`, the exponent must not be negative
+pub(crate) fn pow(a:BigInt, b:BigInt) -> Option<BigInt>{
+    if b.is_negative(){
+        operator::throw(Error::newRangeError("Exponent must be non-negative"))
+    }
+    match b.to_u32(){
+        Some(e) => Some(a.pow(e)),
+        // 0, 1 and -1 do not grow
+        None if a.is_zero() || a.abs().is_one() => Some(if a.is_negative() && b.bit(0) {a} else {a.abs()}),
+        None => operator::throw(Error::newRangeError("Maximum BigInt size exceeded"))
+    }
+}
+
+/// ToIndex, the number of bits of `asIntN` and `asUintN`
+fn to_bits(value:JValue) -> u64{
+    let n = value.to_number().to_float();
+    let n = if n.is_nan() {0.0} else {n.trunc()};
+    if n < 0.0 || n > 9007199254740991.0{
+        operator::throw(Error::newRangeError("Invalid value: not (convertible to) a safe integer"))
+    }
+    n as u64
+}
+
+/// the size of the largest bigint `asUintN` creates from a negative value
+const MAX_BITS:u64 = 1 << 30;
+
+/// `value` modulo 2^bits, in [0, 2^bits)
+fn uint_n(value:&BigInt, bits:u64) -> BigInt{
+    // a non-negative value shorter than `bits` is already in range
+    if !value.is_negative() && value.bits() <= bits{
+        return value.clone()
+    }
+    // a negative value wraps to a value of `bits` bits,
+    // a non-negative value is longer than the mask and only shrinks.
+    if value.is_negative() && bits > MAX_BITS{
+        operator::throw(Error::newRangeError("Maximum BigInt size exceeded"))
+    }
+    // the bitwise and of a negative bigint uses its two's complement
+    value & ((BigInt::one() << bits as usize) - 1u8)
+}
+
+/// `value` wrapped to a signed integer of `bits` bits, in [-2^(bits-1), 2^(bits-1))
+fn int_n(value:&BigInt, bits:u64) -> BigInt{
+    if bits == 0{
+        return BigInt::zero()
+    }
+    // the magnitude fits in `bits - 1` bits
+    if value.bits() < bits{
+        return value.clone()
+    }
+    // the value is at least `bits` long, the mask is not larger than it
+    let m = uint_n(value, bits);
+    if m.bit(bits - 1){
+        m - (BigInt::one() << bits as usize)
+    } else{
+        m
+    }
+}
+
+/// the bigint of `this` for the prototype methods
+fn this_bigint(this:JValue, method:&str) -> BigInt{
+    match this.bigint(){
+        Some(b) => b,
+        None => operator::throw(Error::newTypeError(format!("BigInt.prototype.{} requires that 'this' be a BigInt", method)))
+    }
+}
+
+/// `BigInt(value)`, integral numbers are converted
+fn constructor(this:JValue, args:&[JValue]) -> JValue{
+    let value = args.first().copied().unwrap_or(JValue::Undefined);
+    let prim = value.to_primitive(Some("number"));
+    match prim.number(){
+        Some(n) => JValue::BigInt(number_to_bigint(n)),
+        None => JValue::BigInt(to_bigint(prim))
+    }
+}
+
+/// `BigInt.asIntN(bits, bigint)`, wrap to a signed integer of `bits` bits
+fn as_int_n(this:JValue, bits:JValue, bigint:JValue) -> JValue{
+    let bits = to_bits(bits);
+    JValue::BigInt(int_n(&to_bigint(bigint), bits))
+}
+
+/// `BigInt.asUintN(bits, bigint)`, wrap to an unsigned integer of `bits` bits
+fn as_uint_n(this:JValue, bits:JValue, bigint:JValue) -> JValue{
+    let bits = to_bits(bits);
+    JValue::BigInt(uint_n(&to_bigint(bigint), bits))
+}
+
+/// `BigInt.prototype.toString(radix)`
+fn to_string(this:JValue, radix:JValue) -> JValue{
+    let b = this_bigint(this, "toString");
+    let radix = if radix.is_undefined(){
+        10.0
+    } else{
+        radix.to_number().to_float().trunc()
+    };
+    if !(2.0..=36.0).contains(&radix){
+        operator::throw(Error::newRangeError("toString() radix must be between 2 and 36"))
+    }
+    b.to_str_radix(radix as u32).into()
+}
+
+fn to_locale_string(this:JValue) -> JValue{
+    this_bigint(this, "toLocaleString").to_string().into()
+}
+
+fn value_of(this:JValue) -> JValue{
+    JValue::BigInt(this_bigint(this, "valueOf"))
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let constructor = Function::native(constructor).object().unwrap();
+    let prototype = realm.prototype(PrototypeKind::BigInt).as_mut().unwrap();
+
+    prototype.builtin_member("toString", Function::native(to_string));
+    prototype.builtin_member("toLocaleString", Function::native(to_locale_string));
+    prototype.builtin_member("valueOf", Function::native(value_of));
+
+    constructor.builtin_member("asIntN", Function::native(as_int_n));
+    constructor.builtin_member("asUintN", Function::native(as_uint_n));
+
+    realm.add_constructor("BigInt", constructor, Some(PrototypeKind::BigInt));
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn big(s:&str) -> BigInt{
+        BigInt::from_str_radix(s, 10).unwrap()
+    }
+
+    #[test]
+    fn as_int_n(){
+        assert_eq!(int_n(&big("127"), 8), big("127"));
+        assert_eq!(int_n(&big("128"), 8), big("-128"));
+        assert_eq!(int_n(&big("255"), 8), big("-1"));
+        assert_eq!(int_n(&big("-129"), 8), big("127"));
+        assert_eq!(int_n(&big("-128"), 8), big("-128"));
+        assert_eq!(int_n(&big("12345"), 0), big("0"));
+
+        let two_127 = BigInt::one() << 127usize;
+        assert_eq!(int_n(&two_127, 128), -two_127.clone());
+        assert_eq!(int_n(&(two_127.clone() - 1u8), 128), two_127 - 1u8);
+    }
+
+    #[test]
+    fn as_uint_n(){
+        assert_eq!(uint_n(&big("-1"), 8), big("255"));
+        assert_eq!(uint_n(&big("256"), 8), big("0"));
+        assert_eq!(uint_n(&big("255"), 8), big("255"));
+        assert_eq!(uint_n(&big("-1"), 0), big("0"));
+
+        let two_128 = BigInt::one() << 128usize;
+        assert_eq!(uint_n(&(two_128.clone() + 5u8), 64), big("5"));
+        assert_eq!(uint_n(&big("-1"), 128), two_128 - 1u8);
+    }
+
+    fn eval(script:&str) -> Option<String>{
+        let runtime = Runtime::new();
+        runtime.exec("test.js", script).ok().and_then(|v|v.value.string())
+    }
+
+    #[test]
+    fn to_string_radix(){
+        assert_eq!(eval("(255n).toString(16)").as_deref(), Some("ff"));
+        assert_eq!(eval("(-255n).toString(2)").as_deref(), Some("-11111111"));
+        assert_eq!(eval("(0n).toString(36)").as_deref(), Some("0"));
+        assert_eq!(eval("(2n ** 128n).toString(36)"), Some((BigInt::one() << 128usize).to_str_radix(36)));
+        assert_eq!(eval("(10n).toString()").as_deref(), Some("10"));
+
+        let range_error = "let r = 'no error'; try{ (1n).toString(1) } catch(e){ r = e instanceof RangeError ? 'RangeError' : 'other' } r";
+        assert_eq!(eval(range_error).as_deref(), Some("RangeError"));
+    }
+
+    #[test]
+    fn as_int_n_from_script(){
+        assert_eq!(eval("String(BigInt.asIntN(64, 2n ** 63n))").as_deref(), Some("-9223372036854775808"));
+        assert_eq!(eval("String(BigInt.asUintN(64, -1n))").as_deref(), Some("18446744073709551615"));
+
+        let range_error = "let r = 'no error'; try{ BigInt.asIntN(-1, 1n) } catch(e){ r = e instanceof RangeError ? 'RangeError' : 'other' } r";
+        assert_eq!(eval(range_error).as_deref(), Some("RangeError"));
+    }
+}
@@ -17,6 +17,7 @@ pub trait DynamicObject:Any{
 }
 
 impl dyn DynamicObject{
+    #[allow(clippy::mut_from_ref)]
     fn downcast_ref<T>(&self) -> Option<&mut T> where T:DynamicObject{
         if self.type_id() == TypeId::of::<T>(){
             Some(unsafe{(self as *const Self as *mut T).as_mut().unwrap()})
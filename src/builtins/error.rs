@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
+use crate::operator;
 use crate::value::JValue;
 
 use super::object::{JObjectInner, JObject, JObjectInnerEnum};
+use super::prototypes::{resolve_prototype, PrototypeKind};
+use super::realm::Realm;
+use super::{Array, Function};
 
 pub trait Named {
     fn name(&self) -> &str;
@@ -14,12 +18,92 @@ pub struct Error{
 }
 
 impl Error{
-    pub fn newTypeError<S>(message:S) -> JValue where S:Into<String>{
+    /// an error object inheriting from the prototype of `kind` in the current realm
+    fn create(kind:PrototypeKind, name:&str, message:String) -> &'static mut JObject{
         let obj = JObject::new();
+        obj.set_prototype(resolve_prototype(kind));
+        if !message.is_empty(){
+            obj.builtin_member("message", message.as_str());
+        }
         obj.inner = JObjectInnerEnum::Error(Error{
-            name:"TypeError".into(),
-            message:message.into()
+            name:name.into(),
+            message
         });
+        obj
+    }
+
+    pub fn newError<S>(message:S) -> JValue where S:Into<String>{
+        JValue::Object(Self::create(PrototypeKind::Error, "Error", message.into()))
+    }
+
+    /// an error wrapping several errors, thrown by `Promise.any`
+    pub fn newAggregateError<S>(errors:&[JValue], message:S) -> JValue where S:Into<String>{
+        let obj = Self::create(PrototypeKind::AggregateError, "AggregateError", message.into());
+        obj.builtin_member("errors", Array::new(JObject::new(), errors));
         JValue::Object(obj)
     }
+
+    pub fn newTypeError<S>(message:S) -> JValue where S:Into<String>{
+        JValue::Object(Self::create(PrototypeKind::TypeError, "TypeError", message.into()))
+    }
+
+    pub fn newRangeError<S>(message:S) -> JValue where S:Into<String>{
+        JValue::Object(Self::create(PrototypeKind::RangeError, "RangeError", message.into()))
+    }
+
+    pub fn newReferenceError<S>(message:S) -> JValue where S:Into<String>{
+        JValue::Object(Self::create(PrototypeKind::ReferenceError, "ReferenceError", message.into()))
+    }
+
+    pub fn newSyntaxError<S>(message:S) -> JValue where S:Into<String>{
+        JValue::Object(Self::create(PrototypeKind::SyntaxError, "SyntaxError", message.into()))
+    }
+}
+
+/// the message argument of the error constructors, undefined is no message
+fn message_arg(message:JValue) -> String{
+    if message.is_undefined(){
+        String::new()
+    } else{
+        message.to_js_string()
+    }
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let errors:[(&'static str, PrototypeKind, fn(String) -> JValue); 5] = [
+        ("Error", PrototypeKind::Error, |m|Error::newError(m)),
+        ("TypeError", PrototypeKind::TypeError, |m|Error::newTypeError(m)),
+        ("RangeError", PrototypeKind::RangeError, |m|Error::newRangeError(m)),
+        ("ReferenceError", PrototypeKind::ReferenceError, |m|Error::newReferenceError(m)),
+        ("SyntaxError", PrototypeKind::SyntaxError, |m|Error::newSyntaxError(m)),
+    ];
+    for (name, kind, new) in errors{
+        let proto = realm.prototype(kind).as_mut().unwrap();
+        proto.builtin_member("name", name);
+        proto.builtin_member("message", "");
+
+        // `Error(message)` and `new Error(message)` both create an error
+        let constructor = Function::native(move |this:JValue, message:JValue|new(message_arg(message))).object().unwrap();
+        realm.add_constructor(name, constructor, Some(kind));
+    }
+
+    let proto = realm.prototype(PrototypeKind::AggregateError).as_mut().unwrap();
+    proto.builtin_member("name", "AggregateError");
+    proto.builtin_member("message", "");
+
+    // `AggregateError(errors, message)`
+    let constructor = Function::native(|this:JValue, errors:JValue, message:JValue|{
+        Error::newAggregateError(&operator::IteratorCollect(errors), message_arg(message))
+    }).object().unwrap();
+    realm.add_constructor("AggregateError", constructor, Some(PrototypeKind::AggregateError));
+}
+
+impl ToString for Error{
+    fn to_string(&self) -> String {
+        if self.message.is_empty(){
+            self.name.clone()
+        } else{
+            format!("{}: {}", self.name, self.message)
+        }
+    }
 }
\ No newline at end of file
@@ -3,15 +3,22 @@ use std::sync::Arc;
 
 use crate::runtime::RUNTIME;
 use crate::utils::BuildNoHasher;
-use crate::value::JValue;
+use crate::value::{JValue, JValueEnum};
 use crate::vm::{
+    CapturedVariable,
     Variable,
     VmContext
 };
 use crate::bindgen;
+use crate::utils::ToMutable;
 
-use super::JObject;
+use crate::operator;
+
+use super::{JObject, Error};
+use super::generator::Generator;
 use super::object::{JObjectInner, JObjectInnerEnum};
+use super::prototypes::{resolve_prototype, PrototypeKind};
+use super::realm::{self, Realm, RealmScope};
 
 
 #[test]
@@ -19,11 +26,40 @@ fn assert_arg_size(){
     unsafe{std::mem::transmute::<&[u8], (usize, usize)>(b"")};
 }
 
+/// the code of a function, a thrown exception is returned with false
+pub(crate) type Code = Arc<dyn Fn(&mut VmContext, JValue, &[JValue]) -> (JValue, bool)>;
+
+pub(crate) struct ClassInfo{
+    pub(crate) is_derived:bool,
+    /// the super class constructor, none if the class extends null
+    pub(crate) parent:Option<JValue>,
+    /// fn(this) initializing the private methods and fields of an instance
+    pub(crate) fields:Option<JValue>,
+}
+
 pub struct Function{
-    captures:Arc<HashMap<u64, Arc<JValue>, BuildNoHasher>>,
+    captures:Arc<HashMap<u64, Arc<CapturedVariable>, BuildNoHasher>>,
+    /// the bindings captured by compiled code, in the order the code addresses them
+    closure:Arc<Vec<Arc<CapturedVariable>>>,
 
-    func:Arc<dyn Fn(&mut VmContext, JValue, &[JValue]) -> JValue>,
+    func:Code,
     is_async:bool,
+    is_generator:bool,
+
+    /// the `this` value of arrow functions
+    bound_this:Option<JValue>,
+    /// the function providing `super` to arrow functions
+    lexical:Option<JValue>,
+
+    /// the object a method is defined on, `super` lookups start from its prototype
+    pub(crate) home_object:Option<JValue>,
+    /// some if the function is a class constructor
+    pub(crate) class:Option<ClassInfo>,
+
+    /// the object owning this function
+    object:*mut JObject,
+    /// the realm the function was created in, its body runs in it
+    realm:*mut Realm,
 
     mem:Option<*mut u8>
 }
@@ -35,11 +71,33 @@ impl Function{
         Self::from_object(obj, func, false, false)
     }
 
+    /// create a function from a native closure, the values it throws are caught when it returns
     pub fn from_object(obj:&'static mut JObject, func:Arc<dyn Fn(&mut VmContext, JValue, &[JValue]) -> JValue>, is_async:bool, is_generator:bool) -> JValue{
+        let code:Code = Arc::new(move |vmctx:&mut VmContext, this:JValue, args:&[JValue]|{
+            operator::catch(||func(vmctx, this, args))
+        });
+        Self::from_code(obj, code, is_async, is_generator)
+    }
+
+    /// create a function from code returning its exception
+    pub(crate) fn from_code(obj:&'static mut JObject, func:Code, is_async:bool, is_generator:bool) -> JValue{
+        let object = obj as *mut JObject;
+        obj.set_prototype(resolve_prototype(PrototypeKind::Function));
         obj.inner = JObjectInnerEnum::Function(Function{
             captures:Arc::new(Default::default()),
+            closure:Arc::new(Vec::new()),
             func:func,
             is_async,
+            is_generator,
+
+            bound_this:None,
+            lexical:None,
+
+            home_object:None,
+            class:None,
+
+            object,
+            realm:realm::current(),
 
             mem:None
         });
@@ -51,7 +109,7 @@ impl Function{
     /// call after declaration of function
     pub(crate) fn try_capture(&mut self, vmctx:&'static mut VmContext, names:&[u64]){
 
-        let captures = unsafe{(self.captures.as_ref() as *const _ as *mut HashMap<u64, Arc<JValue>, BuildNoHasher>).as_mut().unwrap()};
+        let captures = unsafe{(self.captures.as_ref() as *const _ as *mut HashMap<u64, Arc<CapturedVariable>, BuildNoHasher>).as_mut().unwrap()};
         // no need to capture in global context
         if vmctx.parent.is_some() {
             for name in names{
@@ -62,8 +120,259 @@ impl Function{
         }
     }
 
-    pub(crate) fn new_from_memory(vmctx:&'static mut VmContext, mem:*mut u8, is_async:bool, is_generator:bool){
+    /// create a function from jit compiled code.
+    /// 
+    /// the code must follow the abi `fn(*mut VmContext, this:JValue, argv:*const JValue, argc:i64) -> JValue`
+    pub(crate) fn new_from_memory(vmctx:&'static mut VmContext, mem:*mut u8, is_async:bool, is_generator:bool) -> JValue{
+        let func = Self::from_code(JObject::new(), Self::compiled_code(mem), is_async, is_generator);
+
+        if let Some(f) = func.object().unwrap().inner.function(){
+            f.to_mut().mem = Some(mem);
+        }
+        func
+    }
+
+    /// wrap jit compiled code following the function abi into a callable
+    pub(crate) fn compiled_code(mem:*mut u8) -> Code{
+        let code:fn(*mut VmContext, JValue, *const JValue, i64) -> JValue = unsafe{std::mem::transmute(mem)};
+
+        Arc::new(move |vmctx:&mut VmContext, this:JValue, args:&[JValue]|{
+            let re = code(vmctx, this, args.as_ptr(), args.len() as i64);
+
+            // the compiled code returns normally with the exception set
+            match vmctx.exception.take(){
+                Some(e) => (e, false),
+                None => (re, true)
+            }
+        })
+    }
+
+    /// replace the code of the function with jit compiled code.
+    ///
+    /// running calls keep the previous code, 
+    /// the following calls and generators run the compiled code.
+    pub(crate) fn replace_code(&mut self, mem:*mut u8){
+        self.func = Self::compiled_code(mem);
+        self.mem = Some(mem);
+    }
+
+    /// capture the variable `name` from `vmctx` into the function
+    pub(crate) fn capture_raw(func:JValue, vmctx:&'static mut VmContext, name:u64){
+        if let Some(f) = func.object().unwrap().inner.function(){
+            f.to_mut().try_capture(vmctx, &[name]);
+        }
+    }
+
+    /// capture the binding `index` of the record of `vmctx` into the closure of the function
+    pub(crate) fn capture_slot_raw(func:JValue, vmctx:&mut VmContext, index:usize){
+        if let Some(f) = func.object().unwrap().inner.function(){
+            let v = vmctx.capture_slot(index);
+            Arc::make_mut(&mut f.to_mut().closure).push(v);
+        }
+    }
+
+    /// capture the binding `index` captured by the function executed in `vmctx`
+    pub(crate) fn capture_closure_raw(func:JValue, vmctx:&mut VmContext, index:usize){
+        if let Some(f) = func.object().unwrap().inner.function(){
+            let v = vmctx.closure.as_ref().map(|c|c[index].clone());
+            Arc::make_mut(&mut f.to_mut().closure).extend(v);
+        }
+    }
+
+    /// bind the lexical `this` and `super` of an arrow function
+    pub(crate) fn bind_this(func:JValue, vmctx:&mut VmContext, this:JValue){
+        if let Some(f) = func.object().unwrap().inner.function(){
+            f.to_mut().bound_this = Some(this);
+            f.to_mut().lexical = vmctx.function;
+        }
+    }
+
+    pub(crate) fn set_home_object(func:JValue, home:JValue){
+        if let Some(f) = func.object().unwrap().inner.function(){
+            f.to_mut().home_object = Some(home);
+        }
+    }
+
+    pub fn is_async(&self) -> bool{
+        self.is_async
+    }
+
+    pub fn is_generator(&self) -> bool{
+        self.is_generator
+    }
+
+    pub fn is_constructor(&self) -> bool{
+        // arrow functions, async functions, generators and methods are not constructors
+        self.bound_this.is_none() && !self.is_async && !self.is_generator 
+        && (self.class.is_some() || self.home_object.is_none())
+    }
+
+    /// the realm the function was created in
+    pub(crate) fn realm(&self) -> *mut Realm{
+        self.realm
+    }
+
+    /// run the function in a new context, return the exception thrown with false
+    fn invoke(&mut self, vmctx:&mut VmContext, this:JValue, args:&[JValue], new_target:Option<JValue>) -> (JValue, bool){
+        // a function called from another realm runs in its own realm,
+        // the names it does not declare resolve in the script context of that realm.
+        let (ctx, _scope) = if vmctx.realm == self.realm{
+            (vmctx.new_child(), None)
+        } else{
+            (unsafe{&mut *self.realm}.context.new_child(), Some(RealmScope::enter(self.realm)))
+        };
+        ctx.attach_captures(self.captures.clone());
+        ctx.attach_closure(self.closure.clone());
+        ctx.function = Some(self.lexical.unwrap_or(JValue::Object(self.object)));
+        ctx.new_target = new_target;
+
+        let this = self.bound_this.unwrap_or(this);
 
+        // the body of a generator runs when the generator is resumed
+        if self.is_generator{
+            return (Generator::new(self.func.clone(), ctx, this, args), true)
+        }
+        // async functions are resumed after every `await`
+        if self.is_async{
+            return (Generator::new_async(self.func.clone(), ctx, this, args), true)
+        }
+        // the code may be replaced while it runs
+        let code = self.func.clone();
+        let re = code(ctx, this, args);
+
+        ctx.done();
+        re
+    }
+
+    /// call the function, return the exception thrown with false
+    pub(crate) fn call_raw(&mut self, vmctx:&mut VmContext, this:JValue, args:&[JValue]) -> (JValue, bool){
+        if self.class.is_some(){
+            return (Error::newTypeError("Class constructor cannot be invoked without 'new'"), false)
+        }
+        self.invoke(vmctx, this, args, None)
+    }
+
+    /// call the function as a constructor on an allocated `this`,
+    /// `new_target` is the constructor `new` was applied to.
+    /// return the exception thrown with false.
+    pub(crate) fn construct(&mut self, vmctx:&mut VmContext, this:JValue, args:&[JValue], new_target:JValue) -> (JValue, bool){
+        if let Some(c) = &self.class{
+            // fields of derived classes are initialized after super() returns
+            if !c.is_derived{
+                if let Some(fields) = c.fields{
+                    let (re, ok) = Self::init_fields(fields, vmctx, this);
+                    if !ok{
+                        return (re, false)
+                    }
+                }
+            }
+        }
+        self.invoke(vmctx, this, args, Some(new_target))
+    }
+
+    fn init_fields(fields:JValue, vmctx:&mut VmContext, this:JValue) -> (JValue, bool){
+        unsafe{fields.call_raw(vmctx, this, std::ptr::NonNull::dangling().as_ptr(), 0, false)}
+    }
+
+    /// turn `ctor` into a class constructor, creating its prototype object.
+    pub(crate) fn class_new(ctor:JValue, parent:JValue, has_parent:bool) -> JValue{
+        let ctor_obj = ctor.object().unwrap();
+        let proto = JObject::new();
+
+        if has_parent{
+            if parent.is_null(){
+                proto.set_prototype(0 as *mut JObject);
+
+            } else if parent.object().map(|o|o.inner.function().map(|f|f.is_constructor())) == Some(Some(true)){
+
+                match parent.member_str("prototype").unbox(){
+                    JValueEnum::Object(o) => proto.set_prototype(o),
+                    JValueEnum::Null => proto.set_prototype(0 as *mut JObject),
+                    _ => operator::throw(Error::newTypeError("Class extends value does not have valid prototype property"))
+                }
+                // static members are inherited
+                ctor_obj.set_prototype(parent.object().unwrap());
+
+            } else{
+                operator::throw(Error::newTypeError(format!("Class extends value {} is not a constructor or null", parent.to_string())))
+            }
+        }
+
+        ctor_obj.define_own("prototype", JValue::Object(proto));
+        proto.define_own("constructor", ctor);
+
+        let f = ctor_obj.inner.function().unwrap().to_mut();
+        f.class = Some(ClassInfo{
+            is_derived:has_parent,
+            parent:if has_parent && !parent.is_null(){Some(parent)} else{None},
+            fields:None
+        });
+        f.home_object = Some(JValue::Object(proto));
+
+        ctor
+    }
+
+    pub(crate) fn class_fields(ctor:JValue, fields:JValue){
+        if let Some(f) = ctor.object().unwrap().inner.function(){
+            if let Some(c) = &mut f.to_mut().class{
+                c.fields = Some(fields);
+            }
+        }
+    }
+
+    /// define a method, getter or setter on `target`
+    /// 
+    /// kind: 0 for method, 1 for getter, 2 for setter
+    pub(crate) fn define_method(target:JValue, key:JValue, func:JValue, kind:i8){
+        Self::set_home_object(func, target);
+
+        let target = target.object().unwrap();
+        let key = key.to_property_key();
+        match kind{
+            1 => target.define_accessor(&key, Some(func), None),
+            2 => target.define_accessor(&key, None, Some(func)),
+            _ => target.define_own(&key, func)
+        }
+    }
+
+    /// `super(...args)` inside a derived constructor
+    pub(crate) unsafe fn super_call_raw(vmctx:&mut VmContext, this:JValue, argv:*const JValue, argc:i64, spread:bool) -> (JValue, bool){
+        let mut args = Vec::new();
+        let (e, ok) = operator::catch(||{
+            args = operator::collect_args(argv, argc, spread);
+            JValue::Undefined
+        });
+        if !ok{
+            return (e, false)
+        }
+
+        let func = vmctx.function.and_then(|f|f.object());
+        let (parent, fields) = match func.as_ref().and_then(|o|o.inner.function()).and_then(|f|f.class.as_ref()){
+            Some(c) if c.is_derived => (c.parent, c.fields),
+            _ => return (Error::newTypeError("'super' keyword unexpected here"), false)
+        };
+
+        let parent = match parent.and_then(|p|p.object()){
+            Some(p) => p,
+            None => return (Error::newTypeError("Super constructor null of anonymous class is not a constructor"), false)
+        };
+        // the super constructor runs for the constructor `new` was applied to
+        let new_target = vmctx.new_target.or(vmctx.function).unwrap();
+        let (re, ok) = match parent.inner.function(){
+            Some(f) => f.to_mut().construct(vmctx, this, &args, new_target),
+            None => return (Error::newTypeError("Super constructor is not a constructor"), false)
+        };
+        if !ok{
+            return (re, false)
+        }
+
+        if let Some(fields) = fields{
+            let (re, ok) = Self::init_fields(fields, vmctx, this);
+            if !ok{
+                return (re, false)
+            }
+        }
+        (this, true)
     }
     
 }
@@ -73,14 +382,11 @@ unsafe impl Send for Function{}
 
 impl JObjectInner for Function{
     fn call(&mut self, vmctx:&mut VmContext, this:JValue, args:&[JValue]) -> JValue {
-
-        let ctx = vmctx.new_child();
-        ctx.attach_captures(self.captures.clone());
-
-        let re = (self.func)(ctx, this, args);
-
-        ctx.done();
-        return re;
+        let (re, ok) = self.call_raw(vmctx, this, args);
+        if !ok{
+            operator::throw(re)
+        }
+        re
     }
 }
 
@@ -92,4 +398,41 @@ impl Drop for Function{
             })
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    fn run(mode:ExecutionMode, script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        runtime.set_tier_up_threshold(1);
+        match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    #[test]
+    fn throws_cross_native_frames(){
+        for mode in [ExecutionMode::Jit, ExecutionMode::Tiered, ExecutionMode::Bytecode, ExecutionMode::Interpreter]{
+            assert_eq!(run(mode, "let o = { get x(){ throw 2 } }; let r = 0; for (let i = 0; i < 5; i++){ try { o.x } catch(e) { r += e } } r"), "10");
+            assert_eq!(run(mode, "let o = { set x(v){ throw v * 2 } }; let r; try { o.x = 3 } catch(e) { r = e } r"), "6");
+            assert_eq!(run(mode, "let inner = { get y(){ throw 6 } }; let o = { get x(){ return inner.y } }; function f(){ return o.x } let r; try { f() } catch(e) { r = e } r"), "6");
+            assert_eq!(run(mode, "function f(x){ throw new TypeError('t' + x) } let r; try { Array.from([1], f) } catch(e) { r = e.message } r"), "t1");
+            assert_eq!(run(mode, "function* g(){ throw 8 } let r; try { g().next() } catch(e) { r = e } r"), "8");
+            assert_eq!(run(mode, "function f(){ throw 1 } f()"), "throw 1");
+        }
+    }
+
+    #[test]
+    fn constructors_return_thrown_values(){
+        for mode in [ExecutionMode::Jit, ExecutionMode::Tiered, ExecutionMode::Interpreter]{
+            assert_eq!(run(mode, "class A { constructor(){ throw 5 } } class B extends A { constructor(){ super() } } let r; try { new B() } catch(e) { r = e } r"), "5");
+            assert_eq!(run(mode, "class C { x = (() => { throw 4 })() } let r; try { new C() } catch(e) { r = e } r"), "4");
+            assert_eq!(run(mode, "class C {} let r; try { C() } catch(e) { r = e instanceof TypeError } r"), "true");
+        }
+    }
+}
@@ -1,3 +1,373 @@
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::operator;
+use crate::utils::ToMutable;
+use crate::value::JValue;
+use crate::vm::VmContext;
+
+use super::object::JObjectInnerEnum;
+use super::symbol;
+use super::promise::{Promise, Reaction};
+use super::{JObject, Function, Error};
+use super::function::Code;
+use super::prototypes::{resolve_prototype, PrototypeKind};
+use super::realm::{Realm, RealmScope};
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum GeneratorState{
+    SuspendedStart,
+    SuspendedYield,
+    Executing,
+    Completed,
+}
+
+/// how a suspended generator is resumed, passed to the compiled body by `generator_resume`
+#[derive(Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub(crate) enum ResumeMode{
+    Next = 0,
+    Throw = 1,
+    Return = 2,
+}
+
+/// a generator function compiled into a resumable function.
+///
+/// every `yield` saves its resume point and returns from the compiled body,
+/// the next call to the body jumps to the saved resume point.
 pub struct Generator{
-    
-}
\ No newline at end of file
+    code:Code,
+
+    /// the context of the generator body, kept across resumes
+    vmctx:&'static mut VmContext,
+    this:JValue,
+    args:Vec<JValue>,
+
+    pub(crate) state:GeneratorState,
+
+    /// the resume point saved by the last `yield`, 0 before the first resume
+    pub(crate) resume_point:i64,
+    /// the innermost block scope of the compiled body at the last `yield`
+    scope:*mut VmContext,
+    /// the value passed to `next`, `throw` or `return`
+    pub(crate) sent:JValue,
+    pub(crate) mode:ResumeMode,
+    /// the value of the last `yield`, none if the body returned
+    pub(crate) yielded:Option<JValue>,
+
+    /// the promise returned by an async function, every `yield` of the body is an `await`
+    promise:Option<*mut JObject>,
+
+    /// the suspended body of an interpreted function, started by the first resume
+    pub(crate) body:Option<Pin<Box<dyn Future<Output = Result<JValue, crate::error::Error>>>>>,
+    /// the frame of a bytecode function, saved by every `yield`
+    pub(crate) frame:Option<Box<dyn Any>>,
+}
+
+impl Generator{
+    /// create a suspended generator object, the body is not run until `next` is called.
+    pub(crate) fn new(code:Code, vmctx:&'static mut VmContext, this:JValue, args:&[JValue]) -> JValue{
+        let obj = Self::create(code, vmctx, this, args, None);
+        obj.set_prototype(Self::prototype());
+        JValue::Object(obj)
+    }
+
+    /// run an async function until the first `await` and return its promise.
+    pub(crate) fn new_async(code:Code, vmctx:&'static mut VmContext, this:JValue, args:&[JValue]) -> JValue{
+        let promise = Promise::new_object();
+        let obj = Self::create(code, vmctx, this, args, Some(promise));
+
+        Self::async_step(obj, ResumeMode::Next, JValue::Undefined);
+        JValue::Object(promise)
+    }
+
+    fn create(code:Code, vmctx:&'static mut VmContext, this:JValue, args:&[JValue], promise:Option<*mut JObject>) -> &'static mut JObject{
+        let obj = JObject::new();
+        obj.inner = JObjectInnerEnum::Generator(Generator{
+            code,
+            vmctx,
+            this,
+            args:args.to_vec(),
+
+            state:GeneratorState::SuspendedStart,
+            resume_point:0,
+            scope:std::ptr::null_mut(),
+            sent:JValue::Undefined,
+            mode:ResumeMode::Next,
+            yielded:None,
+            promise,
+            body:None,
+            frame:None,
+        });
+
+        if let JObjectInnerEnum::Generator(g) = &obj.inner{
+            g.to_mut().vmctx.generator = Some(g as *const Generator as *mut Generator);
+        }
+        obj
+    }
+
+    /// resume an async function until the next `await` or completion.
+    ///
+    /// the awaited value is resolved to a promise which resumes the function once settled.
+    pub(crate) fn async_step(obj:*mut JObject, mode:ResumeMode, value:JValue){
+        let g = match unsafe{&obj.as_ref().unwrap().inner}{
+            JObjectInnerEnum::Generator(g) => g.to_mut(),
+            _ => unreachable!()
+        };
+        let promise = g.promise.unwrap();
+
+        let mut done = false;
+        let (re, ok) = operator::catch(||{
+            let (v, d) = g.resume(mode, value);
+            done = d;
+            v
+        });
+
+        if !ok{
+            Promise::reject(promise, re);
+        } else if done{
+            Promise::resolve(promise, re);
+        } else{
+            Promise::add_reaction(Promise::promise_resolve(re), Reaction::Await(obj));
+        }
+    }
+
+    /// %GeneratorPrototype% of the current realm
+    fn prototype() -> *mut JObject{
+        resolve_prototype(PrototypeKind::Generator)
+    }
+
+    /// run the body until the next `yield` or completion.
+    ///
+    /// return the value and whether the generator is done.
+    pub(crate) fn resume(&mut self, mode:ResumeMode, value:JValue) -> (JValue, bool){
+        match self.state{
+            GeneratorState::Executing => operator::throw(Error::newTypeError("Generator is already running")),
+
+            // a generator that has not started completes immediately on throw or return
+            GeneratorState::SuspendedStart if mode != ResumeMode::Next => {
+                self.finish();
+                return self.resume(mode, value)
+            },
+
+            GeneratorState::Completed => match mode{
+                ResumeMode::Next => return (JValue::Undefined, true),
+                ResumeMode::Throw => operator::throw(value),
+                ResumeMode::Return => return (value, true),
+            },
+            _ => {}
+        };
+
+        self.state = GeneratorState::Executing;
+        self.sent = value;
+        self.mode = mode;
+        self.yielded = None;
+
+        // the body runs in the realm of the generator function
+        let _scope = RealmScope::enter(self.vmctx.realm);
+
+        let code = self.code.clone();
+        let vmctx = self.vmctx as *mut VmContext as usize;
+        let (this, args) = (self.this, self.args.as_slice() as *const [JValue]);
+
+        let (re, ok) = unsafe{
+            code((vmctx as *mut VmContext).as_mut().unwrap(), this, &*args)
+        };
+
+        if !ok{
+            self.finish();
+            operator::throw(re)
+        }
+
+        match self.yielded.take(){
+            Some(v) => {
+                self.state = GeneratorState::SuspendedYield;
+                (v, false)
+            },
+            None => {
+                self.finish();
+                (re, true)
+            }
+        }
+    }
+
+    fn finish(&mut self){
+        if self.state != GeneratorState::Completed{
+            self.state = GeneratorState::Completed;
+            self.body = None;
+            self.frame = None;
+            self.vmctx.generator = None;
+            self.vmctx.done();
+        }
+    }
+
+    /// generators are iterable
+    fn iterator(this:JValue, args:&[JValue]) -> JValue{
+        this
+    }
+
+    fn next(this:Option<&mut Self>, value:JValue) -> JValue{
+        let (v, done) = check_self(this, "next").resume(ResumeMode::Next, value);
+        operator::iterator_result(v, done)
+    }
+
+    fn return_(this:Option<&mut Self>, value:JValue) -> JValue{
+        let (v, done) = check_self(this, "return").resume(ResumeMode::Return, value);
+        operator::iterator_result(v, done)
+    }
+
+    fn throw(this:Option<&mut Self>, value:JValue) -> JValue{
+        let (v, done) = check_self(this, "throw").resume(ResumeMode::Throw, value);
+        operator::iterator_result(v, done)
+    }
+
+    /// the resume point of the running generator, 0 if the body has not started
+    pub(crate) fn state_raw(vmctx:&mut VmContext) -> i64{
+        vmctx.generator().resume_point
+    }
+
+    /// suspend the running generator at `point`, the block scope `scope` is restored on resume
+    pub(crate) fn yield_raw(vmctx:&mut VmContext, scope:*mut VmContext, value:JValue, point:i64){
+        let g = vmctx.generator();
+        g.resume_point = point;
+        g.scope = scope;
+        g.yielded = Some(value);
+    }
+
+    /// the innermost block scope of the running generator when it was suspended
+    pub(crate) fn scope_raw(vmctx:&mut VmContext) -> *mut VmContext{
+        vmctx.generator().scope
+    }
+
+    /// the value and mode the running generator is resumed with
+    pub(crate) fn resume_raw(vmctx:&mut VmContext) -> (JValue, i8){
+        let g = vmctx.generator();
+        (g.sent, g.mode as i8)
+    }
+
+    /// one step of `yield*`, forwarding the resumption to the inner iterator.
+    ///
+    /// status: 0 to yield the value, 1 if the inner iterator is done and the value is the
+    /// result of the `yield*` expression, 2 if the generator should return the value,
+    /// 3 if the value is an exception.
+    pub(crate) fn delegate_raw(vmctx:&mut VmContext, iterator:JValue, sent:JValue, mode:i8) -> (JValue, i8){
+        let mut status = 0;
+        let (re, ok) = operator::catch(||{
+            let (v, s) = Self::delegate(vmctx, iterator, sent, mode);
+            status = s;
+            v
+        });
+        if ok{
+            (re, status)
+        } else{
+            (re, 3)
+        }
+    }
+
+    fn delegate(vmctx:&mut VmContext, iterator:JValue, sent:JValue, mode:i8) -> (JValue, i8){
+        let method = match mode{
+            1 => "throw",
+            2 => "return",
+            _ => "next"
+        };
+        let func = iterator.member_str(method);
+
+        if func.is_undefined() || func.is_null(){
+            match mode{
+                1 => {
+                    operator::iterator_close(iterator);
+                    operator::throw(Error::newTypeError("The iterator does not provide a 'throw' method"))
+                },
+                // no return method, the generator returns directly
+                2 => return (sent, 2),
+                _ => {}
+            }
+        }
+
+        let (re, ok) = unsafe{func.call_raw(vmctx, iterator, &sent, 1, false)};
+        if !ok{
+            operator::throw(re)
+        }
+        if !re.is_object(){
+            operator::throw(Error::newTypeError(format!("Iterator result {} is not an object", re.to_string())))
+        }
+
+        let value = re.member_str("value");
+        if re.member_str("done").to_bool(){
+            if mode == 2{
+                (value, 2)
+            } else{
+                (value, 1)
+            }
+        } else{
+            (value, 0)
+        }
+    }
+}
+
+fn check_self<'a>(this:Option<&'a mut Generator>, name:&'static str) -> &'a mut Generator{
+    if let Some(v) = this{
+        return v
+    }
+    operator::throw(Error::newTypeError(format!("Generator.prototype.{} called on incompatible receiver", name)))
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let prototype = realm.prototype(PrototypeKind::Generator).as_mut().unwrap();
+
+    prototype.builtin_member("next", Function::native(Generator::next));
+    prototype.builtin_member("return", Function::native(Generator::return_));
+    prototype.builtin_member("throw", Function::native(Generator::throw));
+    prototype.define_own(&symbol::Iterator.to_property_key(), Function::native(Generator::iterator));
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    const MODES:[ExecutionMode; 4] = [ExecutionMode::Interpreter, ExecutionMode::Bytecode, ExecutionMode::Tiered, ExecutionMode::Jit];
+
+    fn run(mode:ExecutionMode, script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        runtime.set_tier_up_threshold(1);
+        match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    #[test]
+    fn resume_with_next_return_and_throw(){
+        let cases = [
+            ("function* g(){ let x = yield 1; let y = yield x + 1; return x + y } let it = g(); let a = it.next().value; let b = it.next(5).value; let c = it.next(10); a + ',' + b + ',' + c.value + ',' + c.done", "1,6,15,true"),
+            ("function* g(){ try { yield 1; yield 2 } finally { yield 'f' } } let it = g(); it.next(); it.return(7).value + ',' + it.next().value + ',' + it.next().done", "f,7,true"),
+            ("function* g(){ try { yield 1 } catch(e) { yield e * 2 } } let it = g(); it.next(); it.throw(21).value", "42"),
+            ("function* g(){ yield 1 } let it = g(); it.next(); it.next(); let r; try { it.throw(3) } catch(e) { r = e } r", "3"),
+        ];
+        for mode in MODES{
+            for (script, expected) in cases{
+                assert_eq!(run(mode, script), expected, "{:?} {}", mode, script);
+            }
+        }
+    }
+
+    #[test]
+    fn iteration_and_spread(){
+        let cases = [
+            ("function* g(){ yield* [1, 2]; yield 3 } let a = [...g()]; a.length + ':' + a[0] + a[1] + a[2]", "3:123"),
+            ("function* g(){ let i = 0; while (true) yield i++ } let r = ''; for (const v of g()){ if (v > 3) break; r += v } r", "0123"),
+            ("let o = { *[Symbol.iterator](){ yield 'a'; yield 'b' } }; let r = ''; for (const v of o) r += v; r", "ab"),
+            ("let closed = false; let o = { *[Symbol.iterator](){ try { yield 1; yield 2 } finally { closed = true } } }; for (const v of o) break; closed", "true"),
+        ];
+        for mode in MODES{
+            for (script, expected) in cases{
+                assert_eq!(run(mode, script), expected, "{:?} {}", mode, script);
+            }
+        }
+    }
+}
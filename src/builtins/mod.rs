@@ -9,6 +9,11 @@ pub mod error;
 pub mod regexp;
 pub mod typedarray;
 pub mod generator;
+pub mod bigint;
+pub mod shape;
+pub mod property;
+pub mod realm;
+pub mod shadow_realm;
 
 pub mod dynamic_object;
 
@@ -18,6 +23,8 @@ pub use object::{
 pub use array::Array;
 pub use symbol::Symbol;
 pub use string::JString;
+pub use bigint::JBigInt;
 pub use function::Function;
 pub use promise::Promise;
 pub use error::Error;
+pub use realm::Realm;
@@ -9,10 +9,15 @@ use std::sync::Arc;
 
 use parking_lot::RwLock;
 
-use crate::{value::JValue, vm::VmContext};
+use crate::{value::{JValue, JValueEnum}, vm::VmContext};
 use crate::heap;
+use crate::runtime::RUNTIME;
 use crate::operator;
+use crate::utils::ToMutable;
 
+use super::property::{AsKey, Attributes, Key, PropertyDescriptor, PropertyKey};
+use super::shape::{Property, Shape, Slots};
+use super::generator::Generator;
 use super::prototypes::{
     resolve_prototype,
     PrototypeKind
@@ -35,6 +40,7 @@ pub trait JObjectInner:Any + Sync + Send{
 }
 
 impl dyn JObjectInner{
+    #[allow(clippy::mut_from_ref)]
     pub fn downcast_ref<T>(&self) -> Option<&mut T> where T:JObjectInner{
         if self.type_id() == TypeId::of::<T>(){
             Some(unsafe{(self as *const dyn JObjectInner as *mut T).as_mut().unwrap()})
@@ -43,6 +49,7 @@ impl dyn JObjectInner{
         }
     }
 
+    #[allow(clippy::mut_from_ref)]
     pub fn borrow_mut(&self) -> &mut dyn JObjectInner{
         unsafe{(self as *const Self as *mut Self).as_mut().unwrap()}
     }
@@ -53,77 +60,611 @@ impl dyn JObjectInner{
 }
 
 
+/// a getter and setter pair
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Accessor{
+    pub(crate) get:Option<JValue>,
+    pub(crate) set:Option<JValue>,
+}
+
+pub(crate) enum PrivateElement{
+    Field(JValue),
+    Method(JValue),
+    Accessor(Accessor),
+}
+
 pub struct JObject{
 
     prototype:*mut JObject,
 
-    values:HashMap<String, JValue>,
+    /// the own properties and the slots of their values
+    shape:*mut Shape,
+    values:Slots,
+    accessors:HashMap<PropertyKey, Accessor>,
+
+    /// private elements keyed by the id of the private name
+    privates:HashMap<u32, PrivateElement>,
 
-    freezed:bool,
     extendable:bool,
+    /// the integrity level of the elements of an exotic object, which have no attributes of their own
+    elements:ElementIntegrity,
 
     pub(crate) inner:JObjectInnerEnum
 }
 
+/// the integrity level set by `Object.seal` and `Object.freeze` on the elements of an exotic object
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ElementIntegrity{
+    None,
+    /// the elements cannot be deleted nor redefined
+    Sealed,
+    /// the elements are also read only
+    Frozen,
+}
+
 impl RefUnwindSafe for JObject{}
 impl UnwindSafe for JObject{}
 unsafe impl Send for JObject{}
 unsafe impl Sync for JObject{}
 
+/// the offset of the shape pointer, read by inline caches
+pub(crate) const SHAPE_OFFSET:i32 = std::mem::offset_of!(JObject, shape) as i32;
+/// the offset of the pointer to the slot values, read by inline caches
+pub(crate) const SLOTS_OFFSET:i32 = (std::mem::offset_of!(JObject, values) + Slots::PTR_OFFSET) as i32;
+
 impl JObject{
 
     pub fn new() -> &'static mut JObject{
         let ptr = heap::malloc::<Self>();
 
-        *ptr = JObject { 
+        // the memory is uninitialized, the previous value must not be dropped
+        unsafe{std::ptr::write(ptr, JObject { 
 
-            prototype: resolve_prototype(TypeId::of::<JObject>()), 
-            values:HashMap::default(),
+            prototype: resolve_prototype(PrototypeKind::Object), 
+            shape:Shape::root(),
+            values:Slots::new(),
+            accessors:HashMap::default(),
+            privates:HashMap::default(),
 
-            freezed: false, 
             extendable: true, 
+            elements: ElementIntegrity::None,
             inner: JObjectInnerEnum::None
-        };
+        })};
         ptr
     }
 
     pub unsafe fn construct() -> JValue{
         let ptr = heap::malloc::<Self>();
 
-        *ptr = JObject { 
+        std::ptr::write(ptr, JObject { 
 
-            prototype: resolve_prototype(TypeId::of::<JObject>()), 
-            values:HashMap::default(),
+            prototype: resolve_prototype(PrototypeKind::Object), 
+            shape:Shape::root(),
+            values:Slots::new(),
+            accessors:HashMap::default(),
+            privates:HashMap::default(),
 
-            freezed: false, 
             extendable: true, 
+            elements: ElementIntegrity::None,
             inner: JObjectInnerEnum::None
-        };
+        });
         return JValue::Object(ptr)
     }
 
-    pub fn member_str(&mut self, name:&str) -> JValue{
+    pub fn prototype(&self) -> Option<&'static mut JObject>{
+        unsafe{self.prototype.as_mut()}
+    }
+
+    pub fn set_prototype(&mut self, proto:*mut JObject){
+        self.prototype = proto;
+    }
+
+    /// OrdinarySetPrototypeOf, return false if the object is not extensible or the chain would be cyclic
+    pub(crate) fn set_prototype_of(&mut self, proto:*mut JObject) -> bool{
+        if std::ptr::eq(self.prototype, proto){
+            return true
+        }
+        if !self.extendable{
+            return false
+        }
+        let mut p = unsafe{proto.as_ref()};
+        while let Some(o) = p{
+            if std::ptr::eq(o, self){
+                return false
+            }
+            p = o.prototype().map(|o|&*o);
+        }
+        self.prototype = proto;
+        true
+    }
+
+    pub(crate) fn shape(&self) -> *mut Shape{
+        self.shape
+    }
+
+    /// an own property held by the shape
+    pub(crate) fn own_property<'a>(&self, key:impl Into<Key<'a>>) -> Option<Property>{
+        let key = key.into();
+        unsafe{&*self.shape}.property(key)
+    }
+
+    /// an element of an exotic object, symbols are never elements
+    fn element(&self, key:Key) -> Option<JValue>{
+        key.string().and_then(|k|self.inner.get(k))
+    }
+
+    /// set an element of an exotic object, return false if `key` is not an element
+    fn set_element(&self, key:Key, value:JValue) -> bool{
+        key.string().map(|k|self.inner.set(k, value)).unwrap_or(false)
+    }
+
+    /// add an own property, the value of an accessor is held by `accessors`
+    fn add_property(&mut self, key:Key, value:JValue, attributes:Attributes){
+        let slot = self.values.push(value);
+        self.shape = Shape::add(self.shape, key, Property{slot, attributes});
+    }
+
+    /// change the attributes of an own property, the object moves to a dictionary shape
+    fn set_attributes(&mut self, key:Key, attributes:Attributes){
+        match self.own_property(key){
+            Some(p) if p.attributes == attributes => {},
+            _ => {
+                self.shape = Shape::dictionary(self.shape);
+                unsafe{&mut *self.shape}.set_attributes(key, attributes);
+            }
+        }
+    }
+
+    /// remove an own property, the object moves to a dictionary shape
+    fn remove_property(&mut self, key:Key){
+        if self.own_property(key).is_some(){
+            self.shape = Shape::dictionary(self.shape);
+            unsafe{&mut *self.shape}.remove(key);
+            self.accessors.remove(&key as &dyn AsKey);
+        }
+    }
+
+    /// write an own data property, replacing an accessor of the same key
+    fn put_own(&mut self, key:Key, value:JValue, attributes:Attributes){
+        match self.own_property(key){
+            Some(p) => {
+                self.accessors.remove(&key as &dyn AsKey);
+                self.values.set(p.slot, value);
+                self.set_attributes(key, attributes);
+            },
+            None => self.add_property(key, value, attributes)
+        }
+    }
+
+    pub fn member_str<'a>(&mut self, key:impl Into<Key<'a>>) -> JValue{
+        let key = key.into();
+        let receiver = JValue::Object(self);
+        self.member_str_with(key, receiver)
+    }
+
+    /// lookup the property through the prototype chain, 
+    /// getters are called with `receiver` as this.
+    pub(crate) fn member_str_with<'a>(&mut self, key:impl Into<Key<'a>>, receiver:JValue) -> JValue{
+        let key = key.into();
+        if let Some(p) = self.own_property(key){
+            if !p.attributes.is_accessor(){
+                return self.values.get(p.slot)
+            }
+            return match self.accessors.get(&key as &dyn AsKey).and_then(|a|a.get){
+                Some(get) => match get.call(receiver, &[]){
+                    Ok(v) => v,
+                    Err(e) => operator::throw(e)
+                },
+                None => JValue::Undefined
+            }
+        }
+
+        if let Some(v) = self.element(key){
+            return v
+        }
+
+        if let Some(p) = self.prototype(){
+            return p.member_str_with(key, receiver)
+        }
         JValue::Undefined
     }
 
-    pub fn set_member_str<T>(&mut self, name:&str, value:T) where T:Into<JValue>{
+    /// find the first property named `key` in the prototype chain, 
+    /// none if it is missing or an element of an exotic object.
+    fn lookup(&self, key:Key) -> Option<(*const JObject, Property)>{
+        if let Some(p) = self.own_property(key){
+            return Some((self as *const JObject, p))
+        }
+        if self.element(key).is_some(){
+            return None
+        }
+        self.prototype().and_then(|p|p.lookup(key))
+    }
+
+    /// OrdinarySet, a failed assignment is ignored
+    pub fn set_member_str<'a, T>(&mut self, key:impl Into<Key<'a>>, value:T) where T:Into<JValue>{
+        let key = key.into();
 
         let value = value.into();
 
-        if !self.inner.set(name, value){
-            self.values.insert(name.to_string(), value);
+        match self.lookup(key){
+            Some((owner, p)) if p.attributes.is_accessor() => {
+                let set = unsafe{&*owner}.accessors.get(&key as &dyn AsKey).and_then(|a|a.set);
+                if let Some(set) = set{
+                    if let Err(e) = set.call(JValue::Object(self), &[value]){
+                        operator::throw(e)
+                    }
+                }
+                return
+            },
+            // a read only property can neither be assigned nor shadowed
+            Some((_, p)) if !p.attributes.writable() => return,
+            Some((owner, p)) if std::ptr::eq(owner, self) => {
+                self.values.set(p.slot, value);
+                return
+            },
+            _ => {}
+        }
+
+        // a non extensible object only takes new values for its existing elements
+        if !self.extendable{
+            if self.elements != ElementIntegrity::Frozen && self.element(key).is_some(){
+                self.set_element(key, value);
+            }
+            return
+        }
+
+        if !self.set_element(key, value){
+            self.add_property(key, value, Attributes::DEFAULT);
         }
     }
 
-    pub(crate) fn builtin_member<T>(&mut self, name:&str, value:T) where T:Into<JValue>{
+    /// the own enumerable string keys of the object, symbol keys are excluded
+    pub(crate) fn keys(&self) -> Vec<String>{
+        self.own_keys(true).into_iter().filter_map(|k|match k{
+            PropertyKey::String(s) => Some(s),
+            PropertyKey::Symbol(_) => None
+        }).collect()
+    }
+
+    /// the own property keys in the order of OrdinaryOwnPropertyKeys: 
+    /// array indices ascending, then strings and symbols in the order they were added.
+    pub(crate) fn own_keys(&self, enumerable_only:bool) -> Vec<PropertyKey>{
+        let mut indices = Vec::new();
+        let mut strings = Vec::new();
+        let mut symbols = Vec::new();
+
+        if let JObjectInnerEnum::Array(a) = &self.inner{
+            indices.extend(0..a.values.len() as u32);
+            if !enumerable_only{
+                strings.push(PropertyKey::String("length".to_string()));
+            }
+        }
+        for (k, p) in unsafe{&*self.shape}.properties(){
+            if enumerable_only && !p.attributes.enumerable(){
+                continue
+            }
+            match k{
+                Key::Symbol(_) => symbols.push(k.to_owned()),
+                Key::String(s) => match array_index(s){
+                    Some(i) => indices.push(i),
+                    None => strings.push(k.to_owned())
+                }
+            }
+        }
+
+        indices.sort_unstable();
+        let mut keys = indices.into_iter().map(|i|PropertyKey::String(i.to_string())).collect::<Vec<_>>();
+        keys.extend(strings);
+        keys.extend(symbols);
+        keys
+    }
+
+    /// define an own data property, ignoring setters in the prototype chain
+    pub(crate) fn define_own<'a>(&mut self, key:impl Into<Key<'a>>, value:JValue){
+        let key = key.into();
+        if !self.set_element(key, value){
+            self.put_own(key, value, Attributes::DEFAULT);
+        }
+    }
+
+    /// define an own accessor property, merging with an existing accessor of the same key
+    pub(crate) fn define_accessor<'a>(&mut self, key:impl Into<Key<'a>>, get:Option<JValue>, set:Option<JValue>){
+        let key = key.into();
+        let attributes = Attributes::new(Attributes::ENUMERABLE | Attributes::CONFIGURABLE | Attributes::ACCESSOR);
+        match self.own_property(key){
+            Some(p) if p.attributes.is_accessor() => {},
+            Some(p) => {
+                self.values.set(p.slot, JValue::Undefined);
+                self.set_attributes(key, attributes);
+            },
+            None => self.add_property(key, JValue::Undefined, attributes)
+        }
+
+        let a = self.accessors.entry(key.to_owned()).or_default();
+        if get.is_some(){
+            a.get = get;
+        }
+        if set.is_some(){
+            a.set = set;
+        }
+    }
+
+    /// the descriptor of an own property
+    pub(crate) fn get_own_property<'a>(&self, key:impl Into<Key<'a>>) -> Option<PropertyDescriptor>{
+        let key = key.into();
+        let p = match self.own_property(key){
+            Some(p) => p,
+            None => {
+                // the length of an array is neither enumerable nor configurable
+                let element = key != Key::String("length");
+                return self.element(key).map(|v|PropertyDescriptor{
+                    value:Some(v),
+                    writable:Some(self.elements != ElementIntegrity::Frozen),
+                    enumerable:Some(element),
+                    configurable:Some(element && self.elements == ElementIntegrity::None),
+                    ..Default::default()
+                })
+            }
+        };
+
+        let mut desc = PropertyDescriptor{
+            enumerable:Some(p.attributes.enumerable()),
+            configurable:Some(p.attributes.configurable()),
+            ..Default::default()
+        };
+        if p.attributes.is_accessor(){
+            let a = self.accessors.get(&key as &dyn AsKey).copied().unwrap_or_default();
+            desc.get = Some(a.get.unwrap_or(JValue::Undefined));
+            desc.set = Some(a.set.unwrap_or(JValue::Undefined));
+        } else{
+            desc.value = Some(self.values.get(p.slot));
+            desc.writable = Some(p.attributes.writable());
+        }
+        Some(desc)
+    }
+
+    /// ValidateAndApplyPropertyDescriptor, return false if the property cannot be defined
+    pub(crate) fn define_property<'a>(&mut self, key:impl Into<Key<'a>>, desc:&PropertyDescriptor) -> bool{
+        let key = key.into();
+        let current = match self.get_own_property(key){
+            Some(c) => c,
+            None if !self.extendable => return false,
+            None => {
+                let attributes = Attributes::new(0)
+                    .with(Attributes::ENUMERABLE, desc.enumerable == Some(true))
+                    .with(Attributes::CONFIGURABLE, desc.configurable == Some(true));
+
+                if desc.is_accessor(){
+                    self.add_property(key, JValue::Undefined, attributes.with(Attributes::ACCESSOR, true));
+                    self.accessors.insert(key.to_owned(), Accessor{
+                        get:desc.get.filter(|g|!g.is_undefined()),
+                        set:desc.set.filter(|s|!s.is_undefined()),
+                    });
+                } else{
+                    let value = desc.value.unwrap_or(JValue::Undefined);
+                    if !self.set_element(key, value){
+                        self.add_property(key, value, attributes.with(Attributes::WRITABLE, desc.writable == Some(true)));
+                    }
+                }
+                return true
+            }
+        };
+
+        let p = match self.own_property(key){
+            Some(p) => p,
+            // the elements of exotic objects only take a new value
+            None => return match desc.value{
+                Some(v) if current.writable == Some(false) => current.value.map(|c|c.same_value(v)).unwrap_or(false),
+                Some(v) => self.set_element(key, v),
+                None => true
+            }
+        };
+        let is_accessor = p.attributes.is_accessor();
+
+        if !p.attributes.configurable(){
+            if desc.configurable == Some(true){
+                return false
+            }
+            if desc.enumerable.is_some() && desc.enumerable != current.enumerable{
+                return false
+            }
+            if (desc.is_accessor() && !is_accessor) || (desc.is_data() && is_accessor){
+                return false
+            }
+            let same = |new:Option<JValue>, old:Option<JValue>| match (new, old){
+                (Some(n), Some(o)) => n.same_value(o),
+                _ => true
+            };
+            if is_accessor{
+                if !same(desc.get, current.get) || !same(desc.set, current.set){
+                    return false
+                }
+            } else if !p.attributes.writable(){
+                if desc.writable == Some(true) || !same(desc.value, current.value){
+                    return false
+                }
+            }
+        }
+
+        let mut attributes = p.attributes;
+        if let Some(e) = desc.enumerable{
+            attributes = attributes.with(Attributes::ENUMERABLE, e);
+        }
+        if let Some(c) = desc.configurable{
+            attributes = attributes.with(Attributes::CONFIGURABLE, c);
+        }
+
+        if desc.is_accessor(){
+            if !is_accessor{
+                attributes = attributes.with(Attributes::ACCESSOR, true).with(Attributes::WRITABLE, false);
+                self.values.set(p.slot, JValue::Undefined);
+            }
+            let a = self.accessors.entry(key.to_owned()).or_default();
+            if let Some(g) = desc.get{
+                a.get = Some(g).filter(|g|!g.is_undefined());
+            }
+            if let Some(s) = desc.set{
+                a.set = Some(s).filter(|s|!s.is_undefined());
+            }
+        } else if desc.is_data(){
+            if is_accessor{
+                attributes = attributes.with(Attributes::ACCESSOR, false).with(Attributes::WRITABLE, false);
+                self.accessors.remove(&key as &dyn AsKey);
+            }
+            if let Some(w) = desc.writable{
+                attributes = attributes.with(Attributes::WRITABLE, w);
+            }
+            if let Some(v) = desc.value{
+                self.values.set(p.slot, v);
+            }
+        }
+        self.set_attributes(key, attributes);
+        true
+    }
+
+    /// return true if the property exists in the object or its prototype chain
+    pub(crate) fn has_property<'a>(&self, key:impl Into<Key<'a>>) -> bool{
+        let key = key.into();
+        if self.own_property(key).is_some() || self.element(key).is_some(){
+            return true
+        }
+        match self.prototype(){
+            Some(p) => p.has_property(key),
+            None => false
+        }
+    }
+
+    /// remove an own property, return false if the property is not configurable
+    pub(crate) fn delete_property<'a>(&mut self, key:impl Into<Key<'a>>) -> bool{
+        let key = key.into();
+        match self.own_property(key){
+            Some(p) if !p.attributes.configurable() => false,
+            Some(_) => {
+                self.remove_property(key);
+                true
+            },
+            None => true
+        }
+    }
+
+    pub(crate) fn has_private(&self, key:Symbol) -> bool{
+        self.privates.contains_key(&key.id)
+    }
+
+    pub(crate) fn get_private(&mut self, key:Symbol) -> JValue{
+        match self.privates.get(&key.id){
+            Some(PrivateElement::Field(v)) |
+            Some(PrivateElement::Method(v)) => *v,
+            Some(PrivateElement::Accessor(a)) => {
+                match a.get{
+                    Some(get) => match get.call(JValue::Object(self), &[]){
+                        Ok(v) => v,
+                        Err(e) => operator::throw(e)
+                    },
+                    None => operator::throw(Error::newTypeError(format!("'#{}' was defined without a getter", key.as_ref())))
+                }
+            },
+            None => operator::throw(Error::newTypeError(format!("Cannot read private member #{} from an object whose class did not declare it", key.as_ref())))
+        }
+    }
+
+    pub(crate) fn set_private(&mut self, key:Symbol, value:JValue){
+        match self.privates.get_mut(&key.id){
+            Some(PrivateElement::Field(v)) => *v = value,
+            Some(PrivateElement::Method(_)) => operator::throw(Error::newTypeError(format!("Private method #{} is not writable", key.as_ref()))),
+            Some(PrivateElement::Accessor(a)) => {
+                match a.set{
+                    Some(set) => if let Err(e) = set.call(JValue::Object(self), &[value]){
+                        operator::throw(e)
+                    },
+                    None => operator::throw(Error::newTypeError(format!("'#{}' was defined without a setter", key.as_ref())))
+                }
+            },
+            None => operator::throw(Error::newTypeError(format!("Cannot write private member #{} to an object whose class did not declare it", key.as_ref())))
+        }
+    }
+
+    pub(crate) fn define_private(&mut self, key:Symbol, element:PrivateElement){
+        match (self.privates.get_mut(&key.id), element){
+            // a getter and setter pair defines a single element
+            (Some(PrivateElement::Accessor(a)), PrivateElement::Accessor(b)) => {
+                if b.get.is_some(){
+                    a.get = b.get;
+                }
+                if b.set.is_some(){
+                    a.set = b.set;
+                }
+            },
+            (Some(_), _) => operator::throw(Error::newTypeError(format!("Cannot initialize #{} twice on the same object", key.as_ref()))),
+            (None, element) => {
+                self.privates.insert(key.id, element);
+            }
+        }
+    }
+
+    /// define a non enumerable property
+    pub(crate) fn builtin_member<'a, T>(&mut self, key:impl Into<Key<'a>>, value:T) where T:Into<JValue>{
+        let key = key.into();
 
         let value = value.into();
 
-        if !self.inner.set(name, value){
-            self.values.insert(name.to_string(), value);
+        if !self.set_element(key, value){
+            self.put_own(key, value, Attributes::BUILTIN);
         }
     }
 
+    pub(crate) fn is_extensible(&self) -> bool{
+        self.extendable
+    }
+
+    pub(crate) fn prevent_extensions(&mut self){
+        self.extendable = false;
+    }
+
+    /// SetIntegrityLevel, every property becomes non configurable 
+    /// and the data properties also read only if `frozen`.
+    fn set_integrity(&mut self, frozen:bool){
+        self.extendable = false;
+        self.elements = self.elements.max(if frozen {ElementIntegrity::Frozen} else {ElementIntegrity::Sealed});
+        let props = unsafe{&*self.shape}.properties().into_iter().map(|(k, p)|(k.to_owned(), p)).collect::<Vec<_>>();
+        for (key, p) in props{
+            let mut attributes = p.attributes.with(Attributes::CONFIGURABLE, false);
+            if frozen && !p.attributes.is_accessor(){
+                attributes = attributes.with(Attributes::WRITABLE, false);
+            }
+            self.set_attributes(key.as_key(), attributes);
+        }
+    }
+
+    /// TestIntegrityLevel
+    fn test_integrity(&self, frozen:bool) -> bool{
+        let level = if frozen {ElementIntegrity::Frozen} else {ElementIntegrity::Sealed};
+        let has_elements = matches!(&self.inner, JObjectInnerEnum::Array(a) if !a.values.is_empty());
+
+        !self.extendable && (self.elements >= level || !has_elements) && unsafe{&*self.shape}.properties().into_iter().all(|(_, p)|{
+            !p.attributes.configurable() && !(frozen && !p.attributes.is_accessor() && p.attributes.writable())
+        })
+    }
+
+    /// make every property read only and prevent new properties
+    pub(crate) fn freeze(&mut self){
+        self.set_integrity(true)
+    }
+
+    /// prevent new properties and the removal of existing ones
+    pub(crate) fn seal(&mut self){
+        self.set_integrity(false)
+    }
+
+    pub(crate) fn is_frozen(&self) -> bool{
+        self.test_integrity(true)
+    }
+
+    pub(crate) fn is_sealed(&self) -> bool{
+        self.test_integrity(false)
+    }
+
     pub(crate) fn keep_alive(&self, alive:bool){
 
     }
@@ -133,10 +674,9 @@ impl Hash for JObject{
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         state.write_usize(self.prototype as usize);
         state.write_u8(self.extendable as u8);
-        state.write_u8(self.freezed as u8);
         state.write_u8(self.inner.varient() as u8);
-        state.write_usize(self.values.len());
-        state.write_usize(self.values.capacity());
+        state.write_usize(self.shape as usize);
+        state.write_u32(self.values.len());
         state.write_usize(self as *const Self as usize);
     }
 }
@@ -160,7 +700,9 @@ pub(crate) enum JObjectInnerEnum{
     DataView(),
 
     Promise(Promise),
-    Generator(),
+    Generator(Generator),
+    /// a `ShadowRealm` object and the realm it evaluates code in
+    ShadowRealm(*mut Realm),
 
     Proxy(),
 
@@ -168,7 +710,7 @@ pub(crate) enum JObjectInnerEnum{
     
     Boolean(bool),
     Number(f64),
-    BigInt(i64),
+    BigInt(JBigInt),
     Symbol(Symbol),
     String(JString),
 
@@ -189,7 +731,7 @@ impl JObjectInnerEnum{
             Self::Date() => 7,
             Self::Error(_) => 8,
             Self::Function(_) => 9,
-            Self::Generator() => 10,
+            Self::Generator(_) => 10,
             Self::Map() => 11,
             Self::Number(_) => 12,
             Self::Promise(_) => 13,
@@ -201,7 +743,8 @@ impl JObjectInnerEnum{
             Self::Symbol(_) => 19,
             Self::WeakMap() => 20,
             Self::WeakSet() => 21,
-            Self::TypedArray() => 22
+            Self::TypedArray() => 22,
+            Self::ShadowRealm(_) => 23
         }
     }
 
@@ -233,6 +776,21 @@ impl JObjectInnerEnum{
         }
     }
 
+    pub fn generator(&self) -> Option<&Generator>{
+        match self{
+            Self::Generator(g) => Some(g),
+            _ => None
+        }
+    }
+
+    pub fn get(&self, key:&str) -> Option<JValue>{
+        match self{
+            Self::Array(a) => a.get(key),
+            Self::Custom(c) => c.borrow_mut().get(key),
+            _ => None
+        }
+    }
+
     pub fn set(&self, key:&str, value:JValue) -> bool{
         match self{
             Self::Array(a) => a.set(key, value),
@@ -242,7 +800,521 @@ impl JObjectInnerEnum{
         }
     }
 
-    pub fn call(&self, ctx:&'static VmContext, this:JValue, args:&[JValue]) -> JValue{
-        todo!()
+    pub fn call(&self, ctx:&mut VmContext, this:JValue, args:&[JValue]) -> JValue{
+        match self{
+            Self::Function(f) => f.to_mut().call(ctx, this, args),
+            Self::Custom(c) => c.borrow_mut().call(ctx, this, args),
+            _ => operator::throw(Error::newTypeError("object is not a function"))
+        }
+    }
+}
+
+/// return true if `f` is the builtin `Object.prototype.toString` of any realm
+pub(crate) fn is_builtin_to_string(f:JValue) -> bool{
+    RUNTIME.with(|runtime|runtime.realms.iter().any(|r|unsafe{&**r}.object_to_string.strict_equals(f)))
+}
+
+/// the value of a canonical array index key
+fn array_index(key:&str) -> Option<u32>{
+    let i = key.parse::<u32>().ok()?;
+    (i != u32::MAX && i.to_string() == key).then_some(i)
+}
+
+/// the object argument of the `Object` functions, primitives are not converted
+fn object_arg(value:JValue, method:&str) -> &'static mut JObject{
+    match value.object(){
+        Some(o) => o,
+        None => operator::throw(Error::newTypeError(format!("Object.{} called on non-object", method)))
+    }
+}
+
+/// `Object(value)`, primitives are wrapped
+fn constructor(this:JValue, value:JValue) -> JValue{
+    let (inner, kind) = match value.unbox(){
+        JValueEnum::Object(_) => return value,
+        JValueEnum::Undefined | JValueEnum::Null => (JObjectInnerEnum::None, PrototypeKind::Object),
+        JValueEnum::Number(n) => (JObjectInnerEnum::Number(n), PrototypeKind::Number),
+        JValueEnum::BigInt(b) => (JObjectInnerEnum::BigInt(b), PrototypeKind::BigInt),
+        JValueEnum::Boolean(b) => (JObjectInnerEnum::Boolean(b), PrototypeKind::Boolean),
+        JValueEnum::Symbol(s) => (JObjectInnerEnum::Symbol(s), PrototypeKind::Symbol),
+        JValueEnum::String(s) => (JObjectInnerEnum::String(s), PrototypeKind::String),
+    };
+    let obj = JObject::new();
+    obj.set_prototype(resolve_prototype(kind));
+    obj.inner = inner;
+    JValue::Object(obj)
+}
+
+/// throw if `value` cannot be converted to an object
+fn require_object_coercible(value:JValue){
+    if value.is_nullish(){
+        operator::throw(Error::newTypeError("Cannot convert undefined or null to object"))
+    }
+}
+
+/// return true if `value` has an own property `key`, the characters of strings are own properties
+fn has_own(value:JValue, key:Key) -> bool{
+    match value.unbox(){
+        JValueEnum::Object(o) => unsafe{&*o}.get_own_property(key).is_some(),
+        JValueEnum::String(s) => match key.string(){
+            Some(k) => k == "length" || array_index(k).map(|i|(i as usize) < s.chars().count()).unwrap_or(false),
+            None => false
+        },
+        _ => false
+    }
+}
+
+/// a prototype argument, an object or null
+fn prototype_arg(proto:JValue) -> *mut JObject{
+    match proto.unbox(){
+        JValueEnum::Object(p) => p,
+        JValueEnum::Null => std::ptr::null_mut(),
+        _ => operator::throw(Error::newTypeError(format!("Object prototype may only be an Object or null: {}", proto.to_string())))
+    }
+}
+
+/// the own enumerable string keys of `value`
+fn enumerable_keys(value:JValue) -> Vec<String>{
+    require_object_coercible(value);
+    value.owned_keys().into_iter().filter_map(|k|match k{
+        PropertyKey::String(s) => Some(s),
+        PropertyKey::Symbol(_) => None
+    }).collect()
+}
+
+/// `Object.keys(obj)`
+fn keys(this:JValue, obj:JValue) -> Vec<String>{
+    enumerable_keys(obj)
+}
+
+/// `Object.values(obj)`
+fn values(this:JValue, obj:JValue) -> Vec<JValue>{
+    enumerable_keys(obj).iter().map(|k|obj.member_str(k)).collect()
+}
+
+/// `Object.entries(obj)`
+fn entries(this:JValue, obj:JValue) -> Vec<JValue>{
+    enumerable_keys(obj).iter().map(|k|{
+        Array::new(JObject::new(), &[k.as_str().into(), obj.member_str(k)])
+    }).collect()
+}
+
+/// `Object.fromEntries(iterable)`
+fn from_entries(this:JValue, iterable:JValue) -> JValue{
+    require_object_coercible(iterable);
+    let obj = JObject::new();
+    let iterator = operator::get_iterator(iterable);
+    loop{
+        let (entry, done) = operator::iterator_next(iterator);
+        if done{
+            break
+        }
+        if !entry.is_object(){
+            operator::iterator_close(iterator);
+            operator::throw(Error::newTypeError(format!("Iterator value {} is not an entry object", entry.to_string())))
+        }
+        let key = entry.member_str("0");
+        let value = entry.member_str("1");
+        obj.define_own(&key.to_property_key(), value);
+    }
+    JValue::Object(obj)
+}
+
+/// `Object.assign(target, ...sources)`
+fn assign(this:JValue, target:JValue, sources:&[JValue]) -> JValue{
+    require_object_coercible(target);
+    for source in sources{
+        for key in source.owned_keys(){
+            target.set_member(key.to_value(), source.member_str(&key));
+        }
+    }
+    target
+}
+
+/// `Object.create(proto, props)`
+fn create(this:JValue, proto:JValue, props:JValue) -> JValue{
+    let obj = JObject::new();
+    obj.set_prototype(prototype_arg(proto));
+    let obj = JValue::Object(obj);
+    if !props.is_undefined(){
+        define_properties(this, obj, props);
+    }
+    obj
+}
+
+/// `Object.getPrototypeOf(obj)`, primitives have the prototypes of their wrappers
+fn get_prototype_of(this:JValue, obj:JValue) -> JValue{
+    require_object_coercible(obj);
+    let obj = constructor(JValue::Undefined, obj);
+    match obj.object().and_then(|o|o.prototype()){
+        Some(p) => JValue::Object(p),
+        None => JValue::Null
+    }
+}
+
+/// `Object.setPrototypeOf(obj, proto)`
+fn set_prototype_of(this:JValue, obj:JValue, proto:JValue) -> JValue{
+    if obj.is_nullish(){
+        operator::throw(Error::newTypeError("Object.setPrototypeOf called on null or undefined"))
+    }
+    let proto = prototype_arg(proto);
+    if let Some(o) = obj.object(){
+        if !o.set_prototype_of(proto){
+            if o.is_extensible(){
+                operator::throw(Error::newTypeError("Cyclic __proto__ value"))
+            }
+            operator::throw(Error::newTypeError("#<Object> is not extensible"))
+        }
+    }
+    obj
+}
+
+/// `Object.is(a, b)`
+fn is(this:JValue, a:JValue, b:JValue) -> bool{
+    a.same_value(b)
+}
+
+/// `Object.hasOwn(obj, key)`
+fn has_own_static(this:JValue, obj:JValue, key:JValue) -> bool{
+    require_object_coercible(obj);
+    has_own(obj, key.to_property_key().as_key())
+}
+
+/// `Object.groupBy(items, callback)`, the groups are in the order their keys are first seen
+fn group_by(this:JValue, items:JValue, callback:JValue) -> JValue{
+    require_object_coercible(items);
+    if !callback.is_function(){
+        operator::throw(Error::newTypeError(format!("{} is not a function", callback.to_string())))
+    }
+
+    let mut groups:Vec<(PropertyKey, Vec<JValue>)> = Vec::new();
+    let iterator = operator::get_iterator(items);
+    let mut index = 0;
+    loop{
+        let (value, done) = operator::iterator_next(iterator);
+        if done{
+            break
+        }
+        let key = match callback.call(JValue::Undefined, &[value, JValue::Number(index as f64)]){
+            Ok(k) => k.to_property_key(),
+            Err(e) => {
+                operator::iterator_close(iterator);
+                operator::throw(e)
+            }
+        };
+        match groups.iter_mut().find(|(k, _)|*k == key){
+            Some((_, g)) => g.push(value),
+            None => groups.push((key, vec![value]))
+        }
+        index += 1;
+    }
+
+    let obj = JObject::new();
+    obj.set_prototype(std::ptr::null_mut());
+    for (key, values) in groups{
+        obj.define_own(&key, Array::new(JObject::new(), &values));
+    }
+    JValue::Object(obj)
+}
+
+/// `Object.prototype.hasOwnProperty(key)`
+fn has_own_property(this:JValue, key:JValue) -> bool{
+    let key = key.to_property_key();
+    require_object_coercible(this);
+    has_own(this, key.as_key())
+}
+
+/// `Object.prototype.isPrototypeOf(value)`
+fn is_prototype_of(this:JValue, value:JValue) -> bool{
+    let mut p = match value.object(){
+        Some(o) => o.prototype(),
+        None => return false
+    };
+    require_object_coercible(this);
+    let this = match this.object(){
+        Some(o) => o as *const JObject,
+        None => return false
+    };
+    while let Some(o) = p{
+        if std::ptr::eq(&*o as *const JObject, this){
+            return true
+        }
+        p = o.prototype();
     }
-}
\ No newline at end of file
+    false
+}
+
+/// `Object.prototype.propertyIsEnumerable(key)`
+fn property_is_enumerable(this:JValue, key:JValue) -> bool{
+    let key = key.to_property_key();
+    require_object_coercible(this);
+    match this.unbox(){
+        JValueEnum::Object(o) => unsafe{&*o}.get_own_property(&key).and_then(|d|d.enumerable) == Some(true),
+        // the characters of a string are enumerable, its length is not
+        JValueEnum::String(_) => key.as_key() != Key::String("length") && has_own(this, key.as_key()),
+        _ => false
+    }
+}
+
+/// `Object.prototype.toString()`
+fn to_string(this:JValue) -> String{
+    let tag = match this.unbox(){
+        JValueEnum::Undefined => "Undefined",
+        JValueEnum::Null => "Null",
+        JValueEnum::Boolean(_) => "Boolean",
+        JValueEnum::Number(_) => "Number",
+        JValueEnum::String(_) => "String",
+        JValueEnum::Object(o) => match &unsafe{&*o}.inner{
+            JObjectInnerEnum::Array(_) => "Array",
+            JObjectInnerEnum::Function(_) => "Function",
+            JObjectInnerEnum::Error(_) => "Error",
+            JObjectInnerEnum::Boolean(_) => "Boolean",
+            JObjectInnerEnum::Number(_) => "Number",
+            JObjectInnerEnum::String(_) => "String",
+            JObjectInnerEnum::Date() => "Date",
+            JObjectInnerEnum::RegExp() => "RegExp",
+            _ => "Object"
+        },
+        _ => "Object"
+    };
+    format!("[object {}]", tag)
+}
+
+/// `Object.prototype.valueOf()`
+fn value_of(this:JValue) -> JValue{
+    require_object_coercible(this);
+    constructor(JValue::Undefined, this)
+}
+
+/// `Object.defineProperty(obj, key, attributes)`
+fn define_property(this:JValue, obj:JValue, key:JValue, attributes:JValue) -> JValue{
+    let o = object_arg(obj, "defineProperty");
+    let key = key.to_property_key();
+    let desc = PropertyDescriptor::from_value(attributes);
+    if !o.define_property(&key, &desc){
+        operator::throw(Error::newTypeError(format!("Cannot redefine property: {}", key.as_key())))
+    }
+    obj
+}
+
+/// `Object.defineProperties(obj, props)`, every descriptor is read before any is defined
+fn define_properties(this:JValue, obj:JValue, props:JValue) -> JValue{
+    let o = object_arg(obj, "defineProperties");
+    let props = match props.object(){
+        Some(p) => p,
+        None if props.is_nullish() => operator::throw(Error::newTypeError("Cannot convert undefined or null to object")),
+        None => return obj
+    };
+
+    let descs = props.own_keys(true).into_iter()
+        .map(|k|{
+            let desc = PropertyDescriptor::from_value(props.member_str(&k));
+            (k, desc)
+        })
+        .collect::<Vec<_>>();
+
+    for (key, desc) in descs{
+        if !o.define_property(&key, &desc){
+            operator::throw(Error::newTypeError(format!("Cannot redefine property: {}", key.as_key())))
+        }
+    }
+    obj
+}
+
+/// `Object.getOwnPropertyDescriptor(obj, key)`
+fn get_own_property_descriptor(this:JValue, obj:JValue, key:JValue) -> JValue{
+    if obj.is_nullish(){
+        operator::throw(Error::newTypeError("Cannot convert undefined or null to object"))
+    }
+    match obj.object().and_then(|o|o.get_own_property(&key.to_property_key())){
+        Some(desc) => desc.to_value(),
+        None => JValue::Undefined
+    }
+}
+
+/// `Object.getOwnPropertyDescriptors(obj)`
+fn get_own_property_descriptors(this:JValue, obj:JValue) -> JValue{
+    if obj.is_nullish(){
+        operator::throw(Error::newTypeError("Cannot convert undefined or null to object"))
+    }
+    let re = JObject::new();
+    if let Some(o) = obj.object(){
+        for key in o.own_keys(false){
+            if let Some(desc) = o.get_own_property(&key){
+                re.define_own(&key, desc.to_value());
+            }
+        }
+    }
+    JValue::Object(re)
+}
+
+fn freeze(this:JValue, obj:JValue) -> JValue{
+    if let Some(o) = obj.object(){
+        o.freeze();
+    }
+    obj
+}
+
+fn seal(this:JValue, obj:JValue) -> JValue{
+    if let Some(o) = obj.object(){
+        o.seal();
+    }
+    obj
+}
+
+fn prevent_extensions(this:JValue, obj:JValue) -> JValue{
+    if let Some(o) = obj.object(){
+        o.prevent_extensions();
+    }
+    obj
+}
+
+/// primitives are frozen
+fn is_frozen(this:JValue, obj:JValue) -> bool{
+    obj.object().map(|o|o.is_frozen()).unwrap_or(true)
+}
+
+fn is_sealed(this:JValue, obj:JValue) -> bool{
+    obj.object().map(|o|o.is_sealed()).unwrap_or(true)
+}
+
+fn is_extensible(this:JValue, obj:JValue) -> bool{
+    obj.object().map(|o|o.is_extensible()).unwrap_or(false)
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let prototype = realm.prototype(PrototypeKind::Object).as_mut().unwrap();
+    let constructor = Function::native(constructor).object().unwrap();
+
+    let to_string = Function::native(to_string);
+    realm.object_to_string = to_string;
+    prototype.builtin_member("toString", to_string);
+    prototype.builtin_member("hasOwnProperty", Function::native(has_own_property));
+    prototype.builtin_member("isPrototypeOf", Function::native(is_prototype_of));
+    prototype.builtin_member("propertyIsEnumerable", Function::native(property_is_enumerable));
+    prototype.builtin_member("valueOf", Function::native(value_of));
+
+    constructor.builtin_member("keys", Function::native(keys));
+    constructor.builtin_member("values", Function::native(values));
+    constructor.builtin_member("entries", Function::native(entries));
+    constructor.builtin_member("fromEntries", Function::native(from_entries));
+    constructor.builtin_member("assign", Function::native(assign));
+    constructor.builtin_member("create", Function::native(create));
+    constructor.builtin_member("getPrototypeOf", Function::native(get_prototype_of));
+    constructor.builtin_member("setPrototypeOf", Function::native(set_prototype_of));
+    constructor.builtin_member("is", Function::native(is));
+    constructor.builtin_member("hasOwn", Function::native(has_own_static));
+    constructor.builtin_member("groupBy", Function::native(group_by));
+
+    constructor.builtin_member("defineProperty", Function::native(define_property));
+    constructor.builtin_member("defineProperties", Function::native(define_properties));
+    constructor.builtin_member("getOwnPropertyDescriptor", Function::native(get_own_property_descriptor));
+    constructor.builtin_member("getOwnPropertyDescriptors", Function::native(get_own_property_descriptors));
+    constructor.builtin_member("freeze", Function::native(freeze));
+    constructor.builtin_member("seal", Function::native(seal));
+    constructor.builtin_member("preventExtensions", Function::native(prevent_extensions));
+    constructor.builtin_member("isFrozen", Function::native(is_frozen));
+    constructor.builtin_member("isSealed", Function::native(is_sealed));
+    constructor.builtin_member("isExtensible", Function::native(is_extensible));
+
+    realm.add_constructor("Object", constructor, Some(PrototypeKind::Object));
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    #[test]
+    fn own_keys_order(){
+        let _runtime = Runtime::new();
+        let obj = JObject::new();
+
+        let symbol = Symbol::new("s").to_property_key();
+        obj.set_member_str("b", 1);
+        obj.set_member_str(&symbol, 1);
+        obj.set_member_str("10", 1);
+        obj.set_member_str("a", 1);
+        obj.set_member_str("2", 1);
+        // not an array index
+        obj.set_member_str("01", 1);
+        obj.builtin_member("hidden", 1);
+
+        let keys = |keys:Vec<PropertyKey>|keys.iter().map(|k|k.as_key().to_string()).collect::<Vec<_>>();
+        assert_eq!(keys(obj.own_keys(false)), ["2", "10", "b", "a", "01", "hidden", "Symbol(s)"]);
+        assert_eq!(keys(obj.own_keys(true)), ["2", "10", "b", "a", "01", "Symbol(s)"]);
+        assert_eq!(obj.keys(), ["2", "10", "b", "a", "01"]);
+
+        // a key deleted and added again moves to the end
+        obj.delete_property("b");
+        obj.set_member_str("b", 1);
+        assert_eq!(obj.keys(), ["2", "10", "a", "01", "b"]);
+    }
+
+    #[test]
+    fn symbol_keys_do_not_alias_strings(){
+        let _runtime = Runtime::new();
+        let obj = JObject::new();
+
+        let symbol = Symbol::new("s");
+        let JValueEnum::Symbol(s) = symbol.unbox() else { unreachable!() };
+        let alias = format!("\0@@{}", s.id);
+        obj.set_member_str(&symbol.to_property_key(), 1);
+        obj.set_member_str(alias.as_str(), 2);
+
+        assert_eq!(obj.member_str(&symbol.to_property_key()).to_string(), "1");
+        assert_eq!(obj.member_str(alias.as_str()).to_string(), "2");
+        assert_eq!(obj.keys().len(), 1);
+    }
+
+    #[test]
+    fn array_keys_order(){
+        let _runtime = Runtime::new();
+        let array = Array::new(JObject::new(), &[JValue::Null, JValue::Null]);
+        let obj = array.object().unwrap();
+        obj.set_member_str("x", 1);
+
+        let keys = obj.own_keys(false).iter().map(|k|k.as_key().to_string()).collect::<Vec<_>>();
+        assert_eq!(keys, ["0", "1", "length", "x"]);
+        assert_eq!(obj.keys(), ["0", "1", "x"]);
+    }
+
+    #[test]
+    fn object_keys_from_script(){
+        let runtime = Runtime::new();
+        let re = runtime.exec("test.js", "let k = Object.keys({b:1, 2:1, a:1, 1:1}); k[0] + ',' + k[1] + ',' + k[2] + ',' + k[3]").unwrap();
+        assert_eq!(re.value.string().as_deref(), Some("1,2,b,a"));
+    }
+
+    #[test]
+    fn symbol_keys_are_copied(){
+        let runtime = Runtime::new();
+        let re = runtime.exec("test.js", "let s = Symbol('k'); let a = {[s]: 1, x: 2}; let {x, ...r} = a; let b = Object.assign({}, a); '' + r[s] + b[s] + Object.keys(b).length").unwrap();
+        assert_eq!(re.value.string().as_deref(), Some("111"));
+    }
+
+    #[test]
+    fn descriptors_from_script(){
+        let cases = [
+            ("let o = { a:1 }; Object.freeze(o); o.a = 2; o.b = 3; '' + o.a + o.b + Object.isFrozen(o)", "1undefinedtrue"),
+            ("let o = Object.seal({ a:1 }); o.a = 2; delete o.a; o.b = 3; '' + o.a + o.b + Object.isSealed(o) + Object.isFrozen(o)", "2undefinedtruefalse"),
+            ("let o = Object.preventExtensions({ a:1 }); o.b = 2; o.a = 3; '' + o.a + o.b + Object.isExtensible(o)", "3undefinedfalse"),
+            ("let o = Object.freeze([1, 2]); o[0] = 5; o[2] = 3; '' + o[0] + o.length", "12"),
+            ("let o = {}; Object.defineProperty(o, 'x', { get(){ return this.y * 2 }, set(v){ this.y = v }, configurable:true }); o.x = 4; o.x", "8"),
+            ("let p = { set x(v){ this.y = v * 3 } }; let o = Object.create(p); o.x = 2; '' + o.y + Object.keys(o).length + o.hasOwnProperty('x')", "61false"),
+            ("let p = {}; Object.defineProperty(p, 'x', { value:1 }); let o = Object.create(p); o.x = 2; '' + o.x + o.hasOwnProperty('x')", "1false"),
+            ("let o = {}; Object.defineProperty(o, 'x', { value:1 }); let d = Object.getOwnPropertyDescriptor(o, 'x'); '' + d.writable + d.enumerable + d.configurable + Object.keys(o).length", "falsefalsefalse0"),
+            ("let o = {}; Object.defineProperty(o, 'x', { value:1 }); let r; try { Object.defineProperty(o, 'x', { value:2 }) } catch(e) { r = e instanceof TypeError } r", "true"),
+            ("let d = Object.getOwnPropertyDescriptors({ a:1, get b(){ return 2 } }); '' + d.a.value + d.a.writable + typeof d.b.get + typeof d.b.set", "1truefunctionundefined"),
+            ("let o = Object.defineProperties({}, { a:{ value:1, enumerable:true }, b:{ value:2 } }); Object.keys(o).length + o.b", "3"),
+        ];
+        for mode in [ExecutionMode::Jit, ExecutionMode::Interpreter]{
+            for (script, expected) in cases{
+                let runtime = Runtime::new();
+                runtime.set_execution_mode(mode);
+                let re = runtime.clone().exec("test.js", script).unwrap();
+                assert_eq!(re.value.to_string(), expected, "{:?} {}", mode, script);
+            }
+        }
+    }
+}
@@ -1,66 +1,601 @@
+use std::cell::{Cell, RefCell};
 use std::future::Future;
-use std::pin::Pin;
+use std::rc::Rc;
 use std::sync::Arc;
-use std::task::Poll;
 
-use futures::FutureExt;
-use tokio::task::{JoinHandle, JoinError};
+use tokio::task::JoinError;
 
+use crate::runtime::RUNTIME;
+use crate::utils::ToMutable;
 use crate::value::JValue;
+use crate::vm::VmContext;
 use crate::operator;
 
-use super::object::JObjectInner;
+use super::generator::{Generator, ResumeMode};
+use super::object::JObjectInnerEnum;
+use super::{JObject, Function, Array, Error};
+use super::prototypes::{resolve_prototype, PrototypeKind};
+use super::realm::Realm;
 
-pub enum Promise{
-    AsyncNative(JoinHandle<JValue>),
-    Resolved(JValue),
+#[derive(Clone, Copy)]
+pub(crate) enum PromiseState{
+    Pending,
+    Fulfilled(JValue),
+    Rejected(JValue),
+}
+
+/// what to do once a promise is settled
+#[derive(Clone, Copy)]
+pub(crate) enum Reaction{
+    /// a handler passed to `then`, the derived promise is settled with its result
+    Then{
+        on_fulfilled:JValue,
+        on_rejected:JValue,
+        derived:Option<*mut JObject>,
+    },
+    /// resume an async function suspended on `await`
+    Await(*mut JObject),
+}
+
+/// a job in the microtask queue of the runtime
+pub(crate) enum Job{
+    /// run a reaction with the settled value, `rejected` tells which handler to run
+    Reaction{
+        reaction:Reaction,
+        value:JValue,
+        rejected:bool,
+    },
+    /// resolve `promise` by calling the `then` method of a thenable
+    ResolveThenable{
+        promise:*mut JObject,
+        thenable:JValue,
+        then:JValue,
+    },
+}
+
+pub struct Promise{
+    pub(crate) state:PromiseState,
+
+    /// reactions added while the promise is pending
+    reactions:Vec<Reaction>,
+
+    /// true once a reaction is added, a rejected promise without handler is reported to the runtime
+    pub(crate) handled:bool,
 }
 
 impl Promise{
-    pub fn native<F>(future:F) -> Arc<dyn JObjectInner> where F:Future<Output = JValue> + Send + 'static{
+    /// create a pending promise object
+    pub(crate) fn new_object() -> &'static mut JObject{
+        let obj = JObject::new();
+        obj.set_prototype(Self::prototype());
+        obj.inner = JObjectInnerEnum::Promise(Promise{
+            state:PromiseState::Pending,
+            reactions:Vec::new(),
+            handled:false,
+        });
+        obj
+    }
+
+    /// %Promise.prototype% of the current realm
+    fn prototype() -> *mut JObject{
+        resolve_prototype(PrototypeKind::Promise)
+    }
+
+    /// spawn a rust future and return a promise settled with its output.
+    ///
+    /// the future is polled by `Runtime::run_event_loop`, 
+    /// the promise is rejected if the future panics or is cancelled.
+    pub fn native<F>(future:F) -> JValue where F:Future<Output = JValue> + Send + 'static{
         let handle = tokio::spawn(future);
-        return Arc::new(Promise::AsyncNative(handle))
+        let obj = Self::new_object();
+
+        RUNTIME.with(|runtime|{
+            runtime.to_mut().pending_futures.push((obj as *mut JObject, handle));
+        });
+        JValue::Object(obj)
     }
-}
 
-impl JObjectInner for Promise{
-    fn call(&mut self, vmctx:&mut crate::vm::VmContext, this:JValue, args:&[JValue]) -> JValue {
-        operator::throw(JValue::Null)
+    pub(crate) fn from_value(value:JValue) -> Option<&'static mut Promise>{
+        if let Some(o) = value.object(){
+            if let JObjectInnerEnum::Promise(p) = &o.inner{
+                return Some(p.to_mut())
+            }
+        }
+        None
+    }
+
+    fn inner(obj:*mut JObject) -> &'static mut Promise{
+        match unsafe{&obj.as_ref().unwrap().inner}{
+            JObjectInnerEnum::Promise(p) => p.to_mut(),
+            _ => unreachable!()
+        }
+    }
+
+    /// PromiseResolve: return `value` if it is already a promise,
+    /// otherwise a new promise resolved with `value`.
+    pub(crate) fn promise_resolve(value:JValue) -> *mut JObject{
+        if Self::from_value(value).is_some(){
+            return value.object().unwrap() as *const JObject as *mut JObject
+        }
+        let obj = Self::new_object();
+        Self::resolve(obj, value);
+        obj
+    }
+
+    /// the promise resolve functions.
+    ///
+    /// a thenable is adopted by a job calling its `then` method.
+    pub(crate) fn resolve(promise:*mut JObject, value:JValue){
+        if let Some(o) = value.object(){
+            if o as *const JObject == promise as *const JObject{
+                return Self::reject(promise, Error::newTypeError("Chaining cycle detected for promise"))
+            }
+
+            let (then, ok) = operator::catch(||value.member_str("then"));
+            if !ok{
+                return Self::reject(promise, then)
+            }
+
+            if then.is_function(){
+                return enqueue_job(Job::ResolveThenable {
+                    promise,
+                    thenable: value,
+                    then
+                })
+            }
+        }
+        Self::fulfill(promise, value)
+    }
+
+    pub(crate) fn fulfill(promise:*mut JObject, value:JValue){
+        Self::settle(promise, PromiseState::Fulfilled(value))
+    }
+
+    pub(crate) fn reject(promise:*mut JObject, reason:JValue){
+        Self::settle(promise, PromiseState::Rejected(reason))
+    }
+
+    fn settle(promise:*mut JObject, state:PromiseState){
+        let p = Self::inner(promise);
+        if !matches!(p.state, PromiseState::Pending){
+            return
+        }
+        p.state = state;
+
+        if let PromiseState::Rejected(_) = state{
+            if !p.handled{
+                RUNTIME.with(|runtime|{
+                    runtime.to_mut().pending_rejections.push(promise);
+                });
+            }
+        }
+
+        for reaction in std::mem::take(&mut p.reactions){
+            p.trigger(reaction);
+        }
+    }
+
+    /// PerformPromiseThen: run the reaction once the promise is settled
+    pub(crate) fn add_reaction(promise:*mut JObject, reaction:Reaction){
+        let p = Self::inner(promise);
+
+        if !p.handled{
+            p.handled = true;
+            // a rejected promise may have been reported as unhandled
+            if let PromiseState::Rejected(_) = p.state{
+                RUNTIME.with(|runtime|{
+                    runtime.to_mut().rejection_handled(promise);
+                });
+            }
+        }
+
+        match p.state{
+            PromiseState::Pending => p.reactions.push(reaction),
+            _ => p.trigger(reaction)
+        }
+    }
+
+    fn trigger(&self, reaction:Reaction){
+        let (value, rejected) = match self.state{
+            PromiseState::Fulfilled(v) => (v, false),
+            PromiseState::Rejected(v) => (v, true),
+            PromiseState::Pending => return
+        };
+        enqueue_job(Job::Reaction {
+            reaction,
+            value,
+            rejected
+        })
+    }
+
+    /// settle the promise of a native future with its output
+    pub(crate) fn settle_native(promise:*mut JObject, re:Result<JValue, JoinError>){
+        match re{
+            Ok(v) => Self::resolve(promise, v),
+            Err(e) => {
+                let reason = if e.is_panic(){
+                    let err = e.into_panic();
+
+                    // a value thrown by the future
+                    if let Some(v) = err.downcast_ref::<JValue>(){
+                        *v
+                    } else if let Some(s) = err.downcast_ref::<&str>(){
+                        Error::newError(format!("native future panicked: {}", s))
+                    } else if let Some(s) = err.downcast_ref::<String>(){
+                        Error::newError(format!("native future panicked: {}", s))
+                    } else{
+                        Error::newError("native future panicked")
+                    }
+                } else{
+                    Error::newError("native future was cancelled")
+                };
+                Self::reject(promise, reason)
+            }
+        }
+    }
+
+    /// create a resolve and reject function pair for `promise`,
+    /// only the first call to either of them has an effect.
+    pub(crate) fn resolving_functions(promise:*mut JObject) -> (JValue, JValue){
+        let already_resolved = Rc::new(Cell::new(false));
+
+        let resolved = already_resolved.clone();
+        let resolve = Function::from_object(JObject::new(), Arc::new(move |_vmctx:&mut VmContext, _this:JValue, args:&[JValue]|{
+            if !resolved.replace(true){
+                Promise::resolve(promise, args.get(0).copied().unwrap_or(JValue::Undefined));
+            }
+            JValue::Undefined
+        }), false, false);
+
+        let resolved = already_resolved;
+        let reject = Function::from_object(JObject::new(), Arc::new(move |_vmctx:&mut VmContext, _this:JValue, args:&[JValue]|{
+            if !resolved.replace(true){
+                Promise::reject(promise, args.get(0).copied().unwrap_or(JValue::Undefined));
+            }
+            JValue::Undefined
+        }), false, false);
+
+        (resolve, reject)
+    }
+
+    /// PerformPromiseThen with a derived promise
+    fn perform_then(promise:*mut JObject, on_fulfilled:JValue, on_rejected:JValue) -> JValue{
+        let derived = Self::new_object();
+        Self::add_reaction(promise, Reaction::Then { 
+            on_fulfilled, 
+            on_rejected, 
+            derived: Some(derived) 
+        });
+        JValue::Object(derived)
+    }
+
+    /// invoke the `then` method of `value`, it may not be a builtin promise
+    fn invoke_then(value:JValue, on_fulfilled:JValue, on_rejected:JValue) -> JValue{
+        let then = value.member_str("then");
+        if !then.is_function(){
+            operator::throw(Error::newTypeError(format!("{} is not a function", then.to_string())))
+        }
+        match then.call(value, &[on_fulfilled, on_rejected]){
+            Ok(v) => v,
+            Err(e) => operator::throw(e)
+        }
+    }
+
+    fn then(this:JValue, on_fulfilled:JValue, on_rejected:JValue) -> JValue{
+        if Self::from_value(this).is_none(){
+            operator::throw(Error::newTypeError("Promise.prototype.then called on incompatible receiver"))
+        }
+        let promise = this.object().unwrap() as *mut JObject;
+        Self::perform_then(promise, on_fulfilled, on_rejected)
+    }
+
+    fn catch(this:JValue, on_rejected:JValue) -> JValue{
+        Self::invoke_then(this, JValue::Undefined, on_rejected)
+    }
+
+    /// the callback is called without arguments and the result of `this` is passed through,
+    /// unless the callback throws or returns a rejected promise.
+    fn finally(this:JValue, on_finally:JValue) -> JValue{
+        if !on_finally.is_function(){
+            return Self::invoke_then(this, on_finally, on_finally)
+        }
+
+        let then_finally = closure(move |value|{
+            let re = call_function(on_finally, &[])?;
+            let value_thunk = closure(move |_|Ok(value));
+            Ok(Self::invoke_then(JValue::Object(Self::promise_resolve(re)), value_thunk, JValue::Undefined))
+        });
+
+        let catch_finally = closure(move |reason|{
+            let re = call_function(on_finally, &[])?;
+            let thrower = closure(move |_|Err(reason));
+            Ok(Self::invoke_then(JValue::Object(Self::promise_resolve(re)), thrower, JValue::Undefined))
+        });
+
+        Self::invoke_then(this, then_finally, catch_finally)
+    }
+
+    /// `new Promise(executor)`, the prototype of `this` is the one of the subclass `new` was applied to
+    fn constructor(vmctx:&mut VmContext, this:JValue, args:&[JValue]) -> JValue{
+        let executor = args.first().copied().unwrap_or(JValue::Undefined);
+        let obj = match this.object(){
+            Some(o) if vmctx.new_target.is_some() && matches!(o.inner, JObjectInnerEnum::None) => o,
+            _ => operator::throw(Error::newTypeError("Promise constructor cannot be invoked without 'new'"))
+        };
+        if !executor.is_function(){
+            operator::throw(Error::newTypeError(format!("Promise resolver {} is not a function", executor.to_string())))
+        }
+
+        obj.inner = JObjectInnerEnum::Promise(Promise{
+            state:PromiseState::Pending,
+            reactions:Vec::new(),
+            handled:false,
+        });
+
+        let promise = obj as *mut JObject;
+        let (resolve, reject) = Self::resolving_functions(promise);
+        if let Err(e) = executor.call(JValue::Undefined, &[resolve, reject]){
+            let _ = reject.call(JValue::Undefined, &[e]);
+        }
+        this
+    }
+
+    fn resolve_static(this:JValue, value:JValue) -> JValue{
+        JValue::Object(Self::promise_resolve(value))
+    }
+
+    fn reject_static(this:JValue, reason:JValue) -> JValue{
+        let promise = Self::new_object();
+        Self::reject(promise, reason);
+        JValue::Object(promise)
+    }
+
+    fn with_resolvers(this:JValue, args:&[JValue]) -> JValue{
+        let promise = Self::new_object();
+        let (resolve, reject) = Self::resolving_functions(promise);
+
+        let obj = JObject::new();
+        obj.set_member_str("promise", JValue::Object(promise));
+        obj.set_member_str("resolve", resolve);
+        obj.set_member_str("reject", reject);
+        JValue::Object(obj)
+    }
+
+    /// run `f` for every value of the iterable with the promise resolved from it.
+    /// 
+    /// return the combined promise, rejected if iterating throws.
+    fn combinator<F>(iterable:JValue, f:F) -> JValue where F:FnOnce(*mut JObject, Vec<*mut JObject>){
+        let promise = Self::new_object();
+
+        let (re, ok) = operator::catch(||{
+            let promises = operator::IteratorCollect(iterable).into_iter().map(Self::promise_resolve).collect();
+            f(promise, promises);
+            JValue::Undefined
+        });
+        if !ok{
+            Self::reject(promise, re);
+        }
+        JValue::Object(promise)
     }
-}
 
-impl Future for Promise{
-    type Output = JValue;
-    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
-        match self.get_mut(){
-            Promise::AsyncNative(h) => { 
-                let re = h.poll_unpin(cx);
-                match re{
-                    Poll::Pending => return Poll::Pending,
-                    Poll::Ready(v) => {
-                        match v{
-                            Ok(v) => return Poll::Ready(v),
-                            Err(e) => {
-                                if e.is_panic(){
-                                    let err = e.into_panic();
-
-                                    if let Some(v) = err.downcast_ref::<JValue>(){
-                                        operator::throw(*v)
-                                    } else{
-                                        println!("async error: {:#?}", err);
-                                        operator::throw(JValue::Undefined)
-                                    }
-                                } else{
-                                    println!("tokio join cancel error");
-                                    operator::throw(JValue::Undefined)
-                                }
-                            }
+    fn all(this:JValue, iterable:JValue) -> JValue{
+        Self::combinator(iterable, |promise, promises|{
+            let values = Rc::new(RefCell::new(vec![JValue::Undefined; promises.len()]));
+            let remaining = Rc::new(Cell::new(promises.len()));
+
+            if promises.is_empty(){
+                return Self::resolve(promise, Array::new(JObject::new(), &[]))
+            }
+
+            for (i, p) in promises.into_iter().enumerate(){
+                let (values, remaining) = (values.clone(), remaining.clone());
+                let on_fulfilled = closure(move |v|{
+                    values.borrow_mut()[i] = v;
+                    remaining.set(remaining.get() - 1);
+                    if remaining.get() == 0{
+                        Self::resolve(promise, Array::new(JObject::new(), &values.borrow()));
+                    }
+                    Ok(JValue::Undefined)
+                });
+                let on_rejected = closure(move |reason|{
+                    Self::reject(promise, reason);
+                    Ok(JValue::Undefined)
+                });
+                Self::invoke_then(JValue::Object(p), on_fulfilled, on_rejected);
+            }
+        })
+    }
+
+    fn all_settled(this:JValue, iterable:JValue) -> JValue{
+        Self::combinator(iterable, |promise, promises|{
+            let values = Rc::new(RefCell::new(vec![JValue::Undefined; promises.len()]));
+            let remaining = Rc::new(Cell::new(promises.len()));
+
+            if promises.is_empty(){
+                return Self::resolve(promise, Array::new(JObject::new(), &[]))
+            }
+
+            for (i, p) in promises.into_iter().enumerate(){
+                let settled = |status:&'static str, key:&'static str|{
+                    let (values, remaining) = (values.clone(), remaining.clone());
+                    closure(move |v|{
+                        let obj = JObject::new();
+                        obj.set_member_str("status", status);
+                        obj.set_member_str(key, v);
+
+                        values.borrow_mut()[i] = JValue::Object(obj);
+                        remaining.set(remaining.get() - 1);
+                        if remaining.get() == 0{
+                            Self::resolve(promise, Array::new(JObject::new(), &values.borrow()));
                         }
+                        Ok(JValue::Undefined)
+                    })
+                };
+                Self::invoke_then(JValue::Object(p), settled("fulfilled", "value"), settled("rejected", "reason"));
+            }
+        })
+    }
+
+    fn any(this:JValue, iterable:JValue) -> JValue{
+        Self::combinator(iterable, |promise, promises|{
+            let errors = Rc::new(RefCell::new(vec![JValue::Undefined; promises.len()]));
+            let remaining = Rc::new(Cell::new(promises.len()));
+
+            if promises.is_empty(){
+                return Self::reject(promise, Error::newAggregateError(&[], "All promises were rejected"))
+            }
+
+            for (i, p) in promises.into_iter().enumerate(){
+                let (errors, remaining) = (errors.clone(), remaining.clone());
+                let on_fulfilled = closure(move |v|{
+                    Self::resolve(promise, v);
+                    Ok(JValue::Undefined)
+                });
+                let on_rejected = closure(move |reason|{
+                    errors.borrow_mut()[i] = reason;
+                    remaining.set(remaining.get() - 1);
+                    if remaining.get() == 0{
+                        Self::reject(promise, Error::newAggregateError(&errors.borrow(), "All promises were rejected"));
+                    }
+                    Ok(JValue::Undefined)
+                });
+                Self::invoke_then(JValue::Object(p), on_fulfilled, on_rejected);
+            }
+        })
+    }
+
+    fn race(this:JValue, iterable:JValue) -> JValue{
+        Self::combinator(iterable, |promise, promises|{
+            for p in promises{
+                let on_fulfilled = closure(move |v|{
+                    Self::resolve(promise, v);
+                    Ok(JValue::Undefined)
+                });
+                let on_rejected = closure(move |reason|{
+                    Self::reject(promise, reason);
+                    Ok(JValue::Undefined)
+                });
+                Self::invoke_then(JValue::Object(p), on_fulfilled, on_rejected);
+            }
+        })
+    }
+}
+
+/// create a function from a closure taking the first argument, Err is thrown
+fn closure<F>(f:F) -> JValue where F:Fn(JValue) -> Result<JValue, JValue> + 'static{
+    Function::from_object(JObject::new(), Arc::new(move |_vmctx:&mut VmContext, _this:JValue, args:&[JValue]|{
+        match f(args.get(0).copied().unwrap_or(JValue::Undefined)){
+            Ok(v) => v,
+            Err(e) => operator::throw(e)
+        }
+    }), false, false)
+}
+
+fn call_function(func:JValue, args:&[JValue]) -> Result<JValue, JValue>{
+    func.call(JValue::Undefined, args)
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let constructor = Function::from_object(JObject::new(), Arc::new(Promise::constructor), false, false).object().unwrap();
+    let prototype = realm.prototype(PrototypeKind::Promise).as_mut().unwrap();
+
+    prototype.builtin_member("then", Function::native(Promise::then));
+    prototype.builtin_member("catch", Function::native(Promise::catch));
+    prototype.builtin_member("finally", Function::native(Promise::finally));
+
+    constructor.builtin_member("resolve", Function::native(Promise::resolve_static));
+    constructor.builtin_member("reject", Function::native(Promise::reject_static));
+    constructor.builtin_member("withResolvers", Function::native(Promise::with_resolvers));
+    constructor.builtin_member("all", Function::native(Promise::all));
+    constructor.builtin_member("allSettled", Function::native(Promise::all_settled));
+    constructor.builtin_member("any", Function::native(Promise::any));
+    constructor.builtin_member("race", Function::native(Promise::race));
+
+    realm.add_constructor("Promise", constructor, Some(PrototypeKind::Promise));
+}
+
+impl Job{
+    pub(crate) fn run(self, vmctx:&mut VmContext){
+        match self{
+            Job::Reaction { reaction:Reaction::Then { on_fulfilled, on_rejected, derived }, value, rejected } => {
+                let handler = if rejected {on_rejected} else {on_fulfilled};
+
+                // a missing handler passes the result through
+                let (re, ok) = if handler.is_function(){
+                    unsafe{handler.call_raw(vmctx, JValue::Undefined, &value, 1, false)}
+                } else{
+                    (value, !rejected)
+                };
+
+                if let Some(derived) = derived{
+                    if ok{
+                        Promise::resolve(derived, re)
+                    } else{
+                        Promise::reject(derived, re)
                     }
                 }
             },
+            Job::Reaction { reaction:Reaction::Await(generator), value, rejected } => {
+                let mode = if rejected {ResumeMode::Throw} else {ResumeMode::Next};
+                Generator::async_step(generator, mode, value);
+            },
+            Job::ResolveThenable { promise, thenable, then } => {
+                let (resolve, reject) = Promise::resolving_functions(promise);
+                let args = [resolve, reject];
+                let (re, ok) = unsafe{then.call_raw(vmctx, thenable, args.as_ptr(), 2, false)};
+                if !ok{
+                    let _ = reject.call(JValue::Undefined, &[re]);
+                }
+            },
+        }
+    }
+}
+
+/// add a job to the microtask queue of the current runtime
+pub(crate) fn enqueue_job(job:Job){
+    RUNTIME.with(|runtime|{
+        runtime.to_mut().jobs.push_back(job);
+    })
+}
 
-            Promise::Resolved(v) => return Poll::Ready(*v),
+#[cfg(test)]
+mod tests{
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    const MODES:[ExecutionMode; 4] = [ExecutionMode::Interpreter, ExecutionMode::Bytecode, ExecutionMode::Tiered, ExecutionMode::Jit];
+
+    /// run `script`, then read `result` once the jobs it queued ran
+    fn settle(mode:ExecutionMode, script:&str, result:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        runtime.clone().exec("test.js", script).unwrap();
+        runtime.run_jobs();
+        runtime.clone().exec("result.js", result).unwrap().value.to_string()
+    }
+
+    #[test]
+    fn async_functions_resume_after_the_script(){
+        let script = "var log = ''; async function f(){ log += 1; await null; log += 3; return 4 } f().then(v => log += v); log += 2;";
+        let rejected = "var r; async function h(){ try { await Promise.reject(5) } catch(e) { return e + 1 } } h().then(v => r = v);";
+        for mode in MODES{
+            assert_eq!(settle(mode, script, "log"), "1234", "{:?}", mode);
+            assert_eq!(settle(mode, rejected, "r"), "6", "{:?}", mode);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn combinators_and_finally(){
+        let cases = [
+            ("var r; Promise.all([1, Promise.resolve(2), new Promise(res => res(3))]).then(v => r = v[0] + v[1] + v[2]);", "6"),
+            ("var r; Promise.race([new Promise(() => {}), Promise.resolve('b')]).then(v => r = v);", "b"),
+            ("var r; Promise.allSettled([Promise.reject(1), 2]).then(v => r = v[0].status + v[1].value);", "rejected2"),
+            ("var r; Promise.any([Promise.reject(1), Promise.resolve(2)]).then(v => r = v);", "2"),
+            ("var r = ''; Promise.reject(1).finally(() => r += 'f').catch(e => r += e);", "f1"),
+        ];
+        for mode in MODES{
+            for (script, expected) in cases{
+                assert_eq!(settle(mode, script, "r"), expected, "{:?} {}", mode, script);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+
+use crate::operator;
+use crate::value::JValue;
+
+use super::{JObject, Error, Symbol};
+
+/// the key of a property, a symbol is its id and never equals a string key
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum PropertyKey{
+    String(String),
+    Symbol(u32),
+}
+
+/// a borrowed `PropertyKey`, the maps keyed by `PropertyKey` are looked up without allocating
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Key<'a>{
+    String(&'a str),
+    Symbol(u32),
+}
+
+impl PropertyKey{
+    pub(crate) fn as_key(&self) -> Key<'_>{
+        match self{
+            PropertyKey::String(s) => Key::String(s),
+            PropertyKey::Symbol(id) => Key::Symbol(*id)
+        }
+    }
+
+    /// the string or symbol value of the key
+    pub(crate) fn to_value(&self) -> JValue{
+        self.as_key().to_value()
+    }
+}
+
+impl<'a> Key<'a>{
+    pub(crate) fn to_owned(self) -> PropertyKey{
+        match self{
+            Key::String(s) => PropertyKey::String(s.to_string()),
+            Key::Symbol(id) => PropertyKey::Symbol(id)
+        }
+    }
+
+    /// the string of a string key
+    pub(crate) fn string(self) -> Option<&'a str>{
+        match self{
+            Key::String(s) => Some(s),
+            Key::Symbol(_) => None
+        }
+    }
+
+    pub(crate) fn to_value(self) -> JValue{
+        match self{
+            Key::String(s) => s.into(),
+            Key::Symbol(id) => JValue::Symbol(Symbol{id})
+        }
+    }
+}
+
+/// the name of the key in error messages
+impl std::fmt::Display for Key<'_>{
+    fn fmt(&self, f:&mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        match self{
+            Key::String(s) => f.write_str(s),
+            Key::Symbol(id) => write!(f, "Symbol({})", Symbol{id:*id}.as_ref())
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Key<'a>{
+    fn from(s:&'a str) -> Self{
+        Key::String(s)
+    }
+}
+
+impl<'a> From<&'a String> for Key<'a>{
+    fn from(s:&'a String) -> Self{
+        Key::String(s)
+    }
+}
+
+impl<'a> From<&'a PropertyKey> for Key<'a>{
+    fn from(k:&'a PropertyKey) -> Self{
+        k.as_key()
+    }
+}
+
+/// a key borrowed from a `PropertyKey` or a `Key`
+pub(crate) trait AsKey{
+    fn key(&self) -> Key<'_>;
+}
+
+impl AsKey for PropertyKey{
+    fn key(&self) -> Key<'_>{
+        self.as_key()
+    }
+}
+
+impl AsKey for Key<'_>{
+    fn key(&self) -> Key<'_>{
+        *self
+    }
+}
+
+// a `PropertyKey` hashes as its `Key` so that maps can be looked up by `&dyn AsKey`
+impl Hash for PropertyKey{
+    fn hash<H:Hasher>(&self, state:&mut H){
+        self.as_key().hash(state)
+    }
+}
+
+impl Hash for dyn AsKey + '_{
+    fn hash<H:Hasher>(&self, state:&mut H){
+        self.key().hash(state)
+    }
+}
+
+impl PartialEq for dyn AsKey + '_{
+    fn eq(&self, other:&Self) -> bool{
+        self.key() == other.key()
+    }
+}
+
+impl Eq for dyn AsKey + '_{}
+
+impl<'a> Borrow<dyn AsKey + 'a> for PropertyKey{
+    fn borrow(&self) -> &(dyn AsKey + 'a){
+        self
+    }
+}
+
+/// the attributes of an own property, stored in the shape of the object
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Attributes(u8);
+
+impl Attributes{
+    pub(crate) const WRITABLE:u8 = 1;
+    pub(crate) const ENUMERABLE:u8 = 2;
+    pub(crate) const CONFIGURABLE:u8 = 4;
+    /// the value is held by the accessors of the object instead of its slot
+    pub(crate) const ACCESSOR:u8 = 8;
+
+    /// the attributes of a property created by assignment
+    pub(crate) const DEFAULT:Attributes = Attributes(Self::WRITABLE | Self::ENUMERABLE | Self::CONFIGURABLE);
+    /// the attributes of a property of a builtin object
+    pub(crate) const BUILTIN:Attributes = Attributes(Self::WRITABLE | Self::CONFIGURABLE);
+
+    pub(crate) const fn new(bits:u8) -> Attributes{
+        Attributes(bits)
+    }
+
+    pub(crate) fn writable(self) -> bool{
+        self.0 & Self::WRITABLE != 0
+    }
+
+    pub(crate) fn enumerable(self) -> bool{
+        self.0 & Self::ENUMERABLE != 0
+    }
+
+    pub(crate) fn configurable(self) -> bool{
+        self.0 & Self::CONFIGURABLE != 0
+    }
+
+    pub(crate) fn is_accessor(self) -> bool{
+        self.0 & Self::ACCESSOR != 0
+    }
+
+    /// set or clear the bits of `flag`
+    pub(crate) fn with(self, flag:u8, set:bool) -> Attributes{
+        if set{
+            Attributes(self.0 | flag)
+        } else{
+            Attributes(self.0 & !flag)
+        }
+    }
+}
+
+/// a property descriptor, absent fields are none.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct PropertyDescriptor{
+    pub(crate) value:Option<JValue>,
+    pub(crate) writable:Option<bool>,
+    pub(crate) get:Option<JValue>,
+    pub(crate) set:Option<JValue>,
+    pub(crate) enumerable:Option<bool>,
+    pub(crate) configurable:Option<bool>,
+}
+
+impl PropertyDescriptor{
+    pub(crate) fn is_accessor(&self) -> bool{
+        self.get.is_some() || self.set.is_some()
+    }
+
+    pub(crate) fn is_data(&self) -> bool{
+        self.value.is_some() || self.writable.is_some()
+    }
+
+    /// ToPropertyDescriptor
+    pub(crate) fn from_value(value:JValue) -> PropertyDescriptor{
+        let obj = match value.object(){
+            Some(o) => o,
+            None => operator::throw(Error::newTypeError(format!("Property description must be an object: {}", value.to_string())))
+        };
+        let mut field = |name:&str| if obj.has_property(name){
+            Some(obj.member_str(name))
+        } else{
+            None
+        };
+
+        let desc = PropertyDescriptor{
+            enumerable:field("enumerable").map(|v|v.to_bool()),
+            configurable:field("configurable").map(|v|v.to_bool()),
+            value:field("value"),
+            writable:field("writable").map(|v|v.to_bool()),
+            get:field("get"),
+            set:field("set"),
+        };
+
+        for (f, name) in [(desc.get, "Getter"), (desc.set, "Setter")]{
+            if let Some(f) = f{
+                if !f.is_undefined() && !f.is_function(){
+                    operator::throw(Error::newTypeError(format!("{} must be a function: {}", name, f.to_string())))
+                }
+            }
+        }
+        if desc.is_accessor() && desc.is_data(){
+            operator::throw(Error::newTypeError("Invalid property descriptor. Cannot both specify accessors and a value or writable attribute"))
+        }
+        desc
+    }
+
+    /// FromPropertyDescriptor
+    pub(crate) fn to_value(&self) -> JValue{
+        let obj = JObject::new();
+        if let Some(v) = self.value{
+            obj.set_member_str("value", v);
+        }
+        if let Some(w) = self.writable{
+            obj.set_member_str("writable", w);
+        }
+        if let Some(g) = self.get{
+            obj.set_member_str("get", g);
+        }
+        if let Some(s) = self.set{
+            obj.set_member_str("set", s);
+        }
+        if let Some(e) = self.enumerable{
+            obj.set_member_str("enumerable", e);
+        }
+        if let Some(c) = self.configurable{
+            obj.set_member_str("configurable", c);
+        }
+        JValue::Object(obj)
+    }
+}
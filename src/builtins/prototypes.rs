@@ -1,28 +1,34 @@
-
-
-use std::any::TypeId;
-
 use crate::runtime::RUNTIME;
 use super::object::JObject;
 
+/// the intrinsic prototypes held by a realm
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub(crate) enum PrototypeKind{
     Object,
+    Function,
     Array,
-    Funtion,
+    Error,
+    TypeError,
+    RangeError,
+    ReferenceError,
+    SyntaxError,
+    AggregateError,
     String,
     Number,
+    Boolean,
     BigInt,
     Symbol,
     Promise,
+    Generator,
+    ShadowRealm,
 }
 
-pub(crate) fn resolve_prototype(typeid:TypeId) -> *mut JObject{
-    todo!();
-    RUNTIME.with(|runtime|{
-
-    });
-    if typeid == TypeId::of::<JObject>(){
+impl PrototypeKind{
+    pub(crate) const COUNT:usize = PrototypeKind::ShadowRealm as usize + 1;
+}
 
-    }
-    return 0 as _
-}
\ No newline at end of file
+/// the prototype of objects of `kind` in the current realm,
+/// null while the realm is initialized and the prototype is not allocated yet.
+pub(crate) fn resolve_prototype(kind:PrototypeKind) -> *mut JObject{
+    RUNTIME.with(|runtime|runtime.realm().prototype(kind))
+}
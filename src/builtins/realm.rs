@@ -0,0 +1,222 @@
+//! realms, the intrinsic objects and the global object scripts run against.
+//!
+//! the objects allocated by the runtime read their prototype from the current realm,
+//! a realm is created empty and its intrinsics are allocated by `Realm::init`
+//! once it is the current realm of the runtime.
+//!
+//! a runtime may hold several realms sharing the heap and the compiled code,
+//! a function runs in the realm it was created in whichever realm calls it.
+
+use crate::runtime::RUNTIME;
+use crate::value::JValue;
+use crate::vm::VmContext;
+use crate::jit::scope::ScriptBindings;
+
+use super::property::PropertyDescriptor;
+use super::prototypes::PrototypeKind;
+use super::{JObject, Function};
+
+pub struct Realm{
+    /// the intrinsic prototypes indexed by kind, null until allocated
+    prototypes:[*mut JObject; PrototypeKind::COUNT],
+    /// the intrinsic constructors and their global names, in creation order
+    constructors:Vec<(&'static str, *mut JObject)>,
+    /// %Object.prototype.toString%, skipped by `ToPrimitive` for builtin objects
+    pub(crate) object_to_string:JValue,
+    /// the global object, null until allocated by `init`
+    global:*mut JObject,
+    /// the script context, holding the top level declarations of the scripts run in the realm
+    pub(crate) context:VmContext,
+    /// the indexes of the top level bindings in the record of the script context, used when compiling
+    pub(crate) script_bindings:ScriptBindings,
+}
+
+/// %Function.prototype% is a function accepting any arguments and returning undefined
+fn empty(this:JValue, args:&[JValue]){}
+
+impl Realm{
+    /// a realm without intrinsics, the objects are allocated by `init`
+    pub(crate) fn new() -> &'static mut Realm{
+        let realm = Box::leak(Box::new(Realm{
+            prototypes:[std::ptr::null_mut(); PrototypeKind::COUNT],
+            constructors:Vec::new(),
+            object_to_string:JValue::Undefined,
+            // allocating an object reads the current realm
+            global:std::ptr::null_mut(),
+            context:VmContext::new(),
+            script_bindings:ScriptBindings::default(),
+        }));
+        let ptr = realm as *mut Realm;
+        realm.context.runtime = RUNTIME.with(|runtime|unsafe{std::mem::transmute_copy(runtime)});
+        realm.context.realm = ptr;
+        realm
+    }
+
+    pub(crate) fn global(&self) -> &'static mut JObject{
+        unsafe{self.global.as_mut().expect("the realm is not initialized")}
+    }
+
+    pub(crate) fn prototype(&self, kind:PrototypeKind) -> *mut JObject{
+        self.prototypes[kind as usize]
+    }
+
+    fn set_prototype(&mut self, kind:PrototypeKind, proto:*mut JObject){
+        self.prototypes[kind as usize] = proto;
+    }
+
+    /// register an intrinsic constructor, it is defined on the global object by `init`.
+    ///
+    /// the constructor and the prototype of `kind` are linked by their
+    /// `prototype` and `constructor` properties.
+    pub(crate) fn add_constructor(&mut self, name:&'static str, constructor:&'static mut JObject, kind:Option<PrototypeKind>){
+        let ptr = constructor as *mut JObject;
+        if let Some(proto) = kind.and_then(|k|unsafe{self.prototype(k).as_mut()}){
+            constructor.builtin_member("prototype", JValue::Object(proto));
+            proto.builtin_member("constructor", JValue::Object(ptr));
+        }
+        self.constructors.push((name, ptr));
+    }
+
+    /// allocate the intrinsics and populate the global object.
+    ///
+    /// the realm must be the current realm of the runtime,
+    /// the objects created here inherit from the prototypes already allocated.
+    pub(crate) unsafe fn init(&mut self){
+        // %Object.prototype% is allocated first and has a null prototype
+        let object = JObject::new();
+        self.set_prototype(PrototypeKind::Object, object);
+        self.global = JObject::new();
+
+        // %Function.prototype% is a function itself,
+        // it is created without a prototype since it does not exist yet.
+        let function = Function::native(empty).object().unwrap();
+        function.set_prototype(object);
+        self.set_prototype(PrototypeKind::Function, function);
+
+        for kind in [
+            PrototypeKind::Array, PrototypeKind::Error, PrototypeKind::String, PrototypeKind::Number,
+            PrototypeKind::Boolean, PrototypeKind::BigInt, PrototypeKind::Symbol, PrototypeKind::Promise,
+            PrototypeKind::Generator, PrototypeKind::ShadowRealm
+        ]{
+            self.set_prototype(kind, JObject::new());
+        }
+
+        // the native errors inherit from %Error.prototype%
+        for kind in [
+            PrototypeKind::TypeError, PrototypeKind::RangeError, PrototypeKind::ReferenceError,
+            PrototypeKind::SyntaxError, PrototypeKind::AggregateError
+        ]{
+            let proto = JObject::new();
+            proto.set_prototype(self.prototype(PrototypeKind::Error));
+            self.set_prototype(kind, proto);
+        }
+
+        super::object::init(self);
+        super::array::init(self);
+        super::error::init(self);
+        super::symbol::init(self);
+        super::string::init(self);
+        super::bigint::init(self);
+        super::promise::init(self);
+        super::generator::init(self);
+        super::shadow_realm::init(self);
+
+        let global = self.global();
+        for (name, constructor) in &self.constructors{
+            global.builtin_member(*name, JValue::Object(*constructor));
+        }
+        global.builtin_member("globalThis", JValue::Object(self.global));
+
+        // the value properties of the global object are read only
+        for (name, value) in [("undefined", JValue::Undefined), ("NaN", JValue::Number(f64::NAN)), ("Infinity", JValue::Number(f64::INFINITY))]{
+            global.define_property(name, &PropertyDescriptor{
+                value:Some(value),
+                writable:Some(false),
+                enumerable:Some(false),
+                configurable:Some(false),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// the realm of the running code
+pub(crate) fn current() -> *mut Realm{
+    RUNTIME.with(|runtime|runtime.realm)
+}
+
+/// the current realm of the runtime is replaced until the scope is dropped,
+/// the previous realm is restored even if a value is thrown.
+pub(crate) struct RealmScope{
+    previous:*mut Realm,
+}
+
+impl RealmScope{
+    pub(crate) fn enter(realm:*mut Realm) -> RealmScope{
+        let previous = current();
+        RUNTIME.with(|runtime|{
+            runtime.to_mut().realm = realm;
+        });
+        RealmScope{previous}
+    }
+}
+
+impl Drop for RealmScope{
+    fn drop(&mut self) {
+        RUNTIME.with(|runtime|{
+            runtime.to_mut().realm = self.previous;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    #[test]
+    fn realms_have_their_own_globals(){
+        let runtime = Runtime::new();
+        let realm = runtime.create_realm();
+
+        runtime.clone().exec("main.js", "var x = 1; Array.prototype.tag = 'main'").unwrap();
+        let re = runtime.clone().exec_in_realm(realm, "other.js", "typeof x + ',' + typeof [].tag").unwrap();
+        assert_eq!(re.value.string().as_deref(), Some("undefined,undefined"));
+
+        let re = runtime.clone().exec("main.js", "x + ',' + [].tag").unwrap();
+        assert_eq!(re.value.string().as_deref(), Some("1,main"));
+    }
+
+    #[test]
+    fn foreign_realms_are_rejected(){
+        let first = Runtime::new();
+        let realm = first.create_realm();
+
+        let second = Runtime::new();
+        assert!(matches!(second.exec_in_realm(realm, "test.js", "1"), Err(Error::ForeignRealm)));
+    }
+
+    #[test]
+    fn intrinsic_prototypes(){
+        let scripts = [
+            "Object.getPrototypeOf([]) === Array.prototype && Object.getPrototypeOf(Array.prototype) === Object.prototype",
+            "Object.getPrototypeOf(Object.prototype) === null",
+            "let f = function(){}; Object.getPrototypeOf(f) === Object.getPrototypeOf(Object) && Object.getPrototypeOf(Object.getPrototypeOf(f)) === Object.prototype",
+            "Object.getPrototypeOf({}) === Object.prototype && ({}).constructor === Object && [].constructor === Array",
+            "Object.getPrototypeOf(new TypeError('x')) === TypeError.prototype && Object.getPrototypeOf(TypeError.prototype) === Error.prototype",
+            "Object.getPrototypeOf(Promise.resolve(1)) === Promise.prototype",
+            "Object.getPrototypeOf('s') === String.prototype && Object.getPrototypeOf(1n) === BigInt.prototype",
+            "globalThis.Array === Array && globalThis.globalThis === globalThis",
+            "typeof undefined == 'undefined' && NaN !== NaN && Infinity === 1 / 0",
+            "NaN = 1; undefined = 2; NaN !== NaN && undefined === void 0 && !Object.getOwnPropertyDescriptor(globalThis, 'Infinity').writable",
+        ];
+        for mode in [ExecutionMode::Jit, ExecutionMode::Interpreter]{
+            for s in scripts{
+                let runtime = Runtime::new();
+                runtime.set_execution_mode(mode);
+                let re = runtime.clone().exec("test.js", s).unwrap();
+                assert_eq!(re.value.to_string(), "true", "{:?} {}", mode, s);
+            }
+        }
+    }
+}
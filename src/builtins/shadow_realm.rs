@@ -0,0 +1,143 @@
+//! the `ShadowRealm` constructor, evaluating code in a separate realm.
+//!
+//! only primitives and callables cross the boundary of a shadow realm,
+//! a callable is wrapped by a function of the receiving realm which wraps
+//! its arguments and its result in turn. an exception thrown across the boundary
+//! becomes a TypeError of the calling realm.
+//!
+//! `importValue` is not defined, the runtime has no module loader.
+
+use std::sync::Arc;
+
+use crate::operator;
+use crate::runtime::RUNTIME;
+use crate::value::JValue;
+use crate::vm::VmContext;
+
+use super::object::JObjectInnerEnum;
+use super::prototypes::PrototypeKind;
+use super::realm::{self, Realm, RealmScope};
+use super::{JObject, Function, Error};
+
+/// `new ShadowRealm()`
+fn constructor(vmctx:&mut VmContext, this:JValue, _args:&[JValue]) -> JValue{
+    let obj = match this.object(){
+        Some(o) if vmctx.new_target.is_some() && matches!(o.inner, JObjectInnerEnum::None) => o,
+        _ => operator::throw(Error::newTypeError("Constructor ShadowRealm requires 'new'"))
+    };
+    let realm = RUNTIME.with(|runtime|runtime.new_realm());
+    obj.inner = JObjectInnerEnum::ShadowRealm(realm);
+    this
+}
+
+fn this_realm(this:JValue, method:&str) -> *mut Realm{
+    match this.object().map(|o|&o.inner){
+        Some(JObjectInnerEnum::ShadowRealm(r)) => *r,
+        _ => operator::throw(Error::newTypeError(format!("ShadowRealm.prototype.{} called on incompatible receiver", method)))
+    }
+}
+
+/// GetWrappedValue, `value` as seen by the code of `realm`
+fn get_wrapped_value(realm:*mut Realm, value:JValue) -> JValue{
+    if value.is_function(){
+        return wrap(realm, value)
+    }
+    if value.is_object(){
+        operator::throw(Error::newTypeError("Cannot pass an object across a ShadowRealm boundary, only primitives and callables"))
+    }
+    value
+}
+
+/// WrappedFunctionCreate, a function of `realm` calling `target` in the realm of `target`
+fn wrap(realm:*mut Realm, target:JValue) -> JValue{
+    let target_realm = target.object().unwrap().inner.function().unwrap().realm();
+
+    // the wrapper belongs to the realm it is passed to
+    let _scope = RealmScope::enter(realm);
+    Function::from_object(JObject::new(), Arc::new(move |vmctx:&mut VmContext, this:JValue, args:&[JValue]|{
+        // calls without a receiver pass the global object of the caller, which stands for undefined
+        let this = if this.object().is_some_and(|o|std::ptr::eq(o, unsafe{&*realm}.global())){
+            JValue::Undefined
+        } else{
+            get_wrapped_value(target_realm, this)
+        };
+        let args = args.iter().map(|a|get_wrapped_value(target_realm, *a)).collect::<Vec<_>>();
+
+        let (re, ok) = unsafe{target.call_raw(vmctx, this, args.as_ptr(), args.len() as i64, false)};
+        if !ok{
+            operator::throw(Error::newTypeError(format!("Wrapped function threw: {}", re.to_string())))
+        }
+        get_wrapped_value(realm, re)
+    }), false, false)
+}
+
+/// `ShadowRealm.prototype.evaluate(sourceText)`
+fn evaluate(this:JValue, source:JValue) -> JValue{
+    let realm = this_realm(this, "evaluate");
+    let source = match source.string(){
+        Some(s) => s.to_string(),
+        None => operator::throw(Error::newTypeError("ShadowRealm.prototype.evaluate: sourceText must be a string"))
+    };
+
+    let caller = realm::current();
+    let re = RUNTIME.with(|runtime|runtime.arc().eval(realm, "ShadowRealm", &source));
+
+    match re{
+        Ok(v) => get_wrapped_value(caller, v),
+        // a script that does not compile throws a SyntaxError of the calling realm
+        Err(e @ crate::error::Error::ParseError(_)) |
+        Err(e @ crate::error::Error::SyntaxError(_)) |
+        Err(e @ crate::error::Error::IllegalBreakStatement(_)) |
+        Err(e @ crate::error::Error::IllegalContinueStatment(_)) |
+        Err(e @ crate::error::Error::UndefinedLabel(_, _)) => operator::throw(Error::newSyntaxError(e.to_string())),
+        Err(e) => operator::throw(Error::newTypeError(format!("ShadowRealm.prototype.evaluate: {}", e)))
+    }
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let constructor = Function::from_object(JObject::new(), Arc::new(constructor), false, false).object().unwrap();
+    let prototype = realm.prototype(PrototypeKind::ShadowRealm).as_mut().unwrap();
+
+    prototype.builtin_member("evaluate", Function::native(evaluate));
+
+    realm.add_constructor("ShadowRealm", constructor, Some(PrototypeKind::ShadowRealm));
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    fn run(mode:ExecutionMode, script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    #[test]
+    fn evaluate(){
+        for mode in [ExecutionMode::Jit, ExecutionMode::Tiered, ExecutionMode::Bytecode, ExecutionMode::Interpreter]{
+            assert_eq!(run(mode, "let r = new ShadowRealm(); r.evaluate('1 + 2')"), "3");
+            assert_eq!(run(mode, "let r = new ShadowRealm(); r.evaluate('globalThis.x = 5'); typeof x"), "undefined");
+            assert_eq!(run(mode, "let r = new ShadowRealm(); r.evaluate('var y = 2'); r.evaluate('y')"), "2");
+            assert_eq!(run(mode, "let r = new ShadowRealm(); try { r.evaluate('1 +') } catch(e) { e instanceof SyntaxError }"), "true");
+            assert_eq!(run(mode, "let r = new ShadowRealm(); try { r.evaluate('({})') } catch(e) { e instanceof TypeError }"), "true");
+        }
+    }
+
+    #[test]
+    fn wrapped_functions(){
+        for mode in [ExecutionMode::Jit, ExecutionMode::Tiered, ExecutionMode::Bytecode, ExecutionMode::Interpreter]{
+            assert_eq!(run(mode, "let r = new ShadowRealm(); let f = r.evaluate('(a) => a * 2'); f(21)"), "42");
+            assert_eq!(run(mode, "let r = new ShadowRealm(); let f = r.evaluate('(g) => g(1) + 1'); f(x => x * 10)"), "11");
+            // errors become TypeErrors of the calling realm
+            assert_eq!(run(mode, "let r = new ShadowRealm(); let f = r.evaluate('() => { throw 1 }'); try { f() } catch(e) { e instanceof TypeError }"), "true");
+            assert_eq!(run(mode, "let r = new ShadowRealm(); let f = r.evaluate('(o) => 1'); try { f({}) } catch(e) { e instanceof TypeError }"), "true");
+            assert_eq!(run(mode, "let r = new ShadowRealm(); let f = r.evaluate('() => []'); try { f() } catch(e) { e instanceof TypeError }"), "true");
+        }
+    }
+}
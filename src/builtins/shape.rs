@@ -0,0 +1,188 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::mem::ManuallyDrop;
+
+use crate::value::JValue;
+
+use super::property::{AsKey, Attributes, Key, PropertyKey};
+
+/// properties added beyond this count move an object to a dictionary shape
+const MAX_FAST_PROPERTIES:usize = 128;
+
+/// an own property of a shape
+#[derive(Clone, Copy)]
+pub(crate) struct Property{
+    pub(crate) slot:u32,
+    pub(crate) attributes:Attributes,
+}
+
+/// a hidden class describing the own properties of an object.
+///
+/// objects that add the same properties with the same attributes in the same order share a shape,
+/// the shapes form a transition tree rooted at the empty shape.
+/// a dictionary shape is owned by a single object and is changed in place,
+/// it is never shared nor cached by compiled code.
+pub(crate) struct Shape{
+    properties:HashMap<PropertyKey, Property>,
+    /// the shapes reached by adding a property
+    transitions:RefCell<HashMap<(PropertyKey, Attributes), *mut Shape>>,
+    dictionary:bool,
+}
+
+thread_local!{
+    static ROOT:Cell<*mut Shape> = Cell::new(0 as *mut Shape);
+}
+
+impl Shape{
+    /// the shape of an object without properties
+    pub(crate) fn root() -> *mut Shape{
+        ROOT.with(|r|{
+            if r.get().is_null(){
+                r.set(Shape::alloc(HashMap::default(), false));
+            }
+            r.get()
+        })
+    }
+
+    // shapes live as long as the runtime, like the objects
+    fn alloc(properties:HashMap<PropertyKey, Property>, dictionary:bool) -> *mut Shape{
+        Box::into_raw(Box::new(Shape{
+            properties,
+            transitions:RefCell::default(),
+            dictionary
+        }))
+    }
+
+    pub(crate) fn property(&self, key:Key) -> Option<Property>{
+        self.properties.get(&key as &dyn AsKey).copied()
+    }
+
+    pub(crate) fn len(&self) -> usize{
+        self.properties.len()
+    }
+
+    pub(crate) fn is_dictionary(&self) -> bool{
+        self.dictionary
+    }
+
+    /// the properties in the order they were added
+    pub(crate) fn properties(&self) -> Vec<(Key<'_>, Property)>{
+        let mut props = self.properties.iter().map(|(k, p)|(k.as_key(), *p)).collect::<Vec<_>>();
+        props.sort_unstable_by_key(|(_, p)|p.slot);
+        props
+    }
+
+    /// the property keys in the order they were added
+    pub(crate) fn keys(&self) -> Vec<Key<'_>>{
+        self.properties().into_iter().map(|(k, _)|k).collect()
+    }
+
+    /// the shape with `key` added,
+    /// a dictionary shape adds the property in place.
+    pub(crate) fn add(this:*mut Shape, key:Key, prop:Property) -> *mut Shape{
+        let shape = unsafe{&mut *this};
+        if shape.dictionary{
+            shape.properties.insert(key.to_owned(), prop);
+            return this
+        }
+        if shape.properties.len() >= MAX_FAST_PROPERTIES{
+            let dict = Shape::dictionary(this);
+            return Shape::add(dict, key, prop)
+        }
+
+        let transition = (key.to_owned(), prop.attributes);
+        if let Some(next) = shape.transitions.borrow().get(&transition){
+            return *next
+        }
+
+        let mut properties = shape.properties.clone();
+        properties.insert(key.to_owned(), prop);
+        let next = Shape::alloc(properties, false);
+        shape.transitions.borrow_mut().insert(transition, next);
+        next
+    }
+
+    /// a dictionary shape with the properties of `this`,
+    /// a dictionary shape is returned unchanged.
+    pub(crate) fn dictionary(this:*mut Shape) -> *mut Shape{
+        let shape = unsafe{&*this};
+        if shape.dictionary{
+            return this
+        }
+        Shape::alloc(shape.properties.clone(), true)
+    }
+
+    /// remove `key` from a dictionary shape
+    pub(crate) fn remove(&mut self, key:Key) -> Option<Property>{
+        debug_assert!(self.dictionary);
+        self.properties.remove(&key as &dyn AsKey)
+    }
+
+    /// change the attributes of `key` in a dictionary shape
+    pub(crate) fn set_attributes(&mut self, key:Key, attributes:Attributes){
+        debug_assert!(self.dictionary);
+        if let Some(p) = self.properties.get_mut(&key as &dyn AsKey){
+            p.attributes = attributes;
+        }
+    }
+}
+
+/// the slot indexed property storage of an object.
+///
+/// the layout is read by compiled code, `ptr` addresses `len` values.
+#[repr(C)]
+pub(crate) struct Slots{
+    ptr:*mut JValue,
+    len:usize,
+    cap:usize,
+}
+
+impl Slots{
+    /// the offset of the value pointer
+    pub(crate) const PTR_OFFSET:usize = std::mem::offset_of!(Slots, ptr);
+
+    pub(crate) fn new() -> Slots{
+        Slots::from_vec(Vec::new())
+    }
+
+    fn from_vec(v:Vec<JValue>) -> Slots{
+        let mut v = ManuallyDrop::new(v);
+        Slots{
+            ptr:v.as_mut_ptr(),
+            len:v.len(),
+            cap:v.capacity()
+        }
+    }
+
+    pub(crate) fn len(&self) -> u32{
+        self.len as u32
+    }
+
+    pub(crate) fn get(&self, slot:u32) -> JValue{
+        assert!((slot as usize) < self.len);
+        unsafe{*self.ptr.add(slot as usize)}
+    }
+
+    pub(crate) fn set(&mut self, slot:u32, value:JValue){
+        assert!((slot as usize) < self.len);
+        unsafe{*self.ptr.add(slot as usize) = value}
+    }
+
+    /// append a value, return its slot
+    pub(crate) fn push(&mut self, value:JValue) -> u32{
+        let slot = self.len as u32;
+        let mut v = unsafe{Vec::from_raw_parts(self.ptr, self.len, self.cap)};
+        v.push(value);
+        // the old parts are moved into the vec and must not be dropped
+        unsafe{std::ptr::write(self, Slots::from_vec(v))};
+        slot
+    }
+}
+
+impl Drop for Slots{
+    fn drop(&mut self) {
+        if self.cap != 0{
+            unsafe{drop(Vec::from_raw_parts(self.ptr, self.len, self.cap))}
+        }
+    }
+}
@@ -3,20 +3,48 @@ use std::hash::Hash;
 
 
 use crate::value::JValue;
+use crate::operator;
 use crate::string_allocator::STRING_ALLOCATOR;
 use crate::allocator::DataMarker;
 
+use super::{Function, Error};
+use super::prototypes::PrototypeKind;
+use super::realm::Realm;
+
 /// JString is a string allocated on a runtime local allocator.
-#[repr(packed)]
+/// 
+/// the pointer addresses a header holding the length followed by the bytes,
+/// a string fits the payload of a `JValue`.
 #[derive(Clone, Copy)]
+#[repr(transparent)]
 pub struct JString{
-    len:u32,
     ptr:*const u8,
 }
 
+/// the size of the length header
+const HEADER:usize = std::mem::size_of::<u32>();
+
 impl JString{
+    /// allocate a string of `len` bytes, the bytes are written by `write`.
+    fn alloc(len:usize, write:impl FnOnce(*mut u8)) -> JString{
+        unsafe{
+            let ptr = STRING_ALLOCATOR.lock().alloc(std::alloc::Layout::from_size_align(HEADER + len, HEADER).unwrap());
+            (ptr as *mut u32).write_unaligned(len as u32);
+            write(ptr.add(HEADER));
+            JString{ptr}
+        }
+    }
+
+    pub(crate) fn from_ptr(ptr:*const u8) -> JString{
+        JString{ptr}
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const u8{
+        self.ptr
+    }
+
     pub fn len(&self) -> usize{
-        self.len as usize
+        unsafe{(self.ptr as *const u32).read_unaligned() as usize}
     }
 
     pub fn as_str(&self) -> &str{
@@ -27,23 +55,16 @@ impl JString{
 impl Add<JString> for &str{
     type Output = JString;
     fn add(self, rhs: JString) -> Self::Output {
-        unsafe{
-            let ptr = STRING_ALLOCATOR.lock().alloc(std::alloc::Layout::array::<u8>(self.len()+rhs.len()).unwrap());
+        JString::alloc(self.len() + rhs.len(), |ptr|unsafe{
             std::ptr::copy_nonoverlapping(self.as_ptr(), ptr, self.len());
-            std::ptr::copy_nonoverlapping(rhs.ptr, ptr.add(self.len()), rhs.len());
-
-            JString{
-                ptr:ptr,
-                len:self.len() as u32 + rhs.len
-            }
-        }
+            std::ptr::copy_nonoverlapping(rhs.as_str().as_ptr(), ptr.add(self.len()), rhs.len());
+        })
     }
 }
 
 impl AsRef<str> for JString{
     fn as_ref(&self) -> &str {
-
-        unsafe{std::mem::transmute(std::slice::from_raw_parts::<'static,u8>(self.ptr, self.len()))}
+        unsafe{std::mem::transmute(std::slice::from_raw_parts::<'static,u8>(self.ptr.add(HEADER), self.len()))}
     }
 }
 
@@ -62,15 +83,9 @@ impl Hash for JString{
 
 impl From<&str> for JValue{
     fn from(s: &str) -> Self {
-        unsafe{
-            let ptr = STRING_ALLOCATOR.lock().alloc(std::alloc::Layout::array::<u8>(s.len()).unwrap());
+        JValue::String(JString::alloc(s.len(), |ptr|unsafe{
             std::ptr::copy_nonoverlapping(s.as_ptr(), ptr, s.len());
-
-            return JValue::String(JString{
-                ptr:ptr,
-                len:s.len() as u32
-            })
-        }
+        }))
     }
 }
 
@@ -78,4 +93,47 @@ impl From<String> for JValue{
     fn from(s: String) -> Self {
         Self::from(s.as_str())
     }
-}
\ No newline at end of file
+}
+/// `String(value)`, symbols are converted to their description
+fn constructor(this:JValue, args:&[JValue]) -> JValue{
+    match args.first(){
+        None => "".into(),
+        Some(v) if v.is_symbol() => v.to_string().into(),
+        Some(v) => v.to_js_string().into()
+    }
+}
+
+/// `String.raw(template, ...substitutions)`
+fn raw(this:JValue, args:&[JValue]) -> JValue{
+    let template = args.first().copied().unwrap_or(JValue::Undefined);
+    if template.is_nullish(){
+        operator::throw(Error::newTypeError("Cannot convert undefined or null to object"))
+    }
+
+    let raw = template.member_str("raw");
+    if raw.is_nullish(){
+        operator::throw(Error::newTypeError("Cannot convert undefined or null to object"))
+    }
+
+    let len = raw.member_str("length").to_float();
+    let len = if len.is_nan() || len < 0.0 {0} else {len as usize};
+
+    let mut s = String::new();
+    for i in 0..len{
+        s.push_str(&raw.member(JValue::Number(i as f64)).to_js_string());
+        if i + 1 < len{
+            if let Some(v) = args.get(i + 1){
+                s.push_str(&v.to_js_string());
+            }
+        }
+    }
+    s.into()
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let constructor = Function::native(constructor).object().unwrap();
+
+    constructor.builtin_member("raw", Function::native(raw));
+
+    realm.add_constructor("String", constructor, Some(PrototypeKind::String));
+}
@@ -1,59 +1,84 @@
-use std::sync::atomic::{AtomicUsize, AtomicU32};
+use std::sync::atomic::AtomicUsize;
 use std::ops::Deref;
 
 use string_interner::{
     StringInterner,
+    DefaultBackend,
     symbol::SymbolU32,
 };
 
+type Interner = StringInterner<DefaultBackend<SymbolU32>>;
+
 use parking_lot::RwLock;
 
 use crate::value::JValue;
 
+use super::Function;
+use super::prototypes::PrototypeKind;
+use super::realm::Realm;
+
 lazy_static::lazy_static!{
-    static ref INTERNER:StringInterner = StringInterner::new();
+    static ref INTERNER:Interner = Interner::new();
+    /// the interned description of every symbol, indexed by id
+    static ref DESCRIPTIONS:RwLock<Vec<SymbolU32>> = RwLock::new(Vec::new());
 
     pub static ref Iterator:JValue = Symbol::new("iterator");
+    pub static ref ToPrimitive:JValue = Symbol::new("toPrimitive");
 }
 
-static SYMBOL_COUNT:AtomicU32 = AtomicU32::new(0);
-
+/// a symbol is its id, the description is looked up in `DESCRIPTIONS`
 #[derive(Clone, Copy)]
 pub struct Symbol{
     pub(crate) id:u32,
-    pub(crate) intern:SymbolU32,
 }
 
 impl Symbol{
     pub fn new(s:&str) -> JValue{
-        let id = SYMBOL_COUNT.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let i = unsafe{(INTERNER.deref() as *const _ as *mut StringInterner).as_mut().unwrap()};
+        let i = unsafe{(INTERNER.deref() as *const _ as *mut Interner).as_mut().unwrap()};
         let intern = i.get_or_intern(s);
+
+        let mut descriptions = DESCRIPTIONS.write();
+        let id = descriptions.len() as u32;
+        descriptions.push(intern);
         return JValue::Symbol(Symbol{
-            id,
-            intern
+            id
         })
     }
 
+    fn description(&self) -> &'static str{
+        let intern = DESCRIPTIONS.read()[self.id as usize];
+        INTERNER.resolve(intern).unwrap()
+    }
+
+    /// create the key of a private name, 
+    /// every call returns a distinct key even for the same name.
     pub fn new_private(s:&str) -> JValue{
-        let i = unsafe{(INTERNER.deref() as *const _ as *mut StringInterner).as_mut().unwrap()};
-        let intern = i.get_or_intern(s);
-        return JValue::Symbol(Symbol{
-            id:0,
-            intern
-        })
+        Self::new(s)
     }
 }
 
+fn constructor(this:JValue, description:Option<String>) -> JValue{
+    Symbol::new(&description.unwrap_or_default())
+}
+
+pub unsafe fn init(realm:&mut Realm){
+    let constructor = Function::native(constructor).object().unwrap();
+
+    constructor.builtin_member("iterator", *Iterator);
+    constructor.builtin_member("toPrimitive", *ToPrimitive);
+
+    realm.add_constructor("Symbol", constructor, Some(PrototypeKind::Symbol));
+}
+
 impl Deref for Symbol{
     type Target = str;
     fn deref(&self) -> &Self::Target {
-        INTERNER.resolve(self.intern).unwrap()
+        self.description()
     }
 }
 
 impl AsRef<str> for Symbol{
     fn as_ref(&self) -> &str {
-        INTERNER.resolve(self.intern).unwrap()
+        self.description()
     }
 }
\ No newline at end of file
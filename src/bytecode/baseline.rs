@@ -0,0 +1,754 @@
+//! the baseline tier of the bytecode, lowering the code blocks called often to Cranelift.
+//!
+//! every instruction is translated to the runtime helper the interpreter runs for it
+//! and the registers of the frame live in a stack slot, the compiled code saves the
+//! dispatch of the interpreter but does not specialize on the types of the values.
+//! the optimizing compiler of `ExecutionMode::Jit` still lowers the syntax tree.
+//!
+//! generators and async functions keep their frame in the generator between resumes,
+//! they always run in the interpreter.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cranelift::codegen::ir::{FuncRef, StackSlot};
+use cranelift::prelude::*;
+use cranelift_module::Module;
+
+use crate::error::Error;
+use crate::operator;
+use crate::runtime::Runtime;
+use crate::value::JValue;
+use crate::vm::{self, VmContext};
+
+use super::interpreter::{array_values, create_function};
+use super::{BinOp, CodeBlock, Constant, Linked, Op, Reg, UnOp, NO_REG};
+
+/// the tier of a code block in a runtime
+pub(crate) enum Tier{
+    /// the calls and loop iterations counted so far
+    Interpreted(u32),
+    Compiled(*mut u8),
+    /// the code block cannot be compiled, it is not counted anymore
+    Failed,
+}
+
+/// count a call of `code`, return the compiled code once the code block is hot.
+pub(super) fn tier_up(vmctx:&mut VmContext, code:&CodeBlock) -> Option<*mut u8>{
+    count(vmctx, code);
+    match code.link(vmctx.runtime).tier{
+        Tier::Compiled(mem) => Some(mem),
+        _ => None
+    }
+}
+
+/// count an iteration of a loop of `code`.
+///
+/// there is no on stack replacement, the running call finishes in the interpreter.
+pub(super) fn back_edge(vmctx:&mut VmContext, code:&CodeBlock){
+    count(vmctx, code)
+}
+
+fn count(vmctx:&mut VmContext, code:&CodeBlock){
+    let threshold = vmctx.runtime.tier_up_threshold();
+    let runtime = vmctx.runtime.arc();
+    let mut linked = code.link(vmctx.runtime);
+
+    let count = match linked.tier{
+        Tier::Interpreted(n) => n.saturating_add(1),
+        _ => return
+    };
+    if count < threshold{
+        linked.tier = Tier::Interpreted(count);
+        return
+    }
+
+    linked.tier = match compile(runtime, code, &linked){
+        Ok(mem) => Tier::Compiled(mem),
+        Err(_) => Tier::Failed
+    };
+}
+
+/// call the code compiled from a code block
+pub(super) fn run(mem:*mut u8, vmctx:&mut VmContext, this:JValue, args:&[JValue]) -> (JValue, bool){
+    let code:fn(*mut VmContext, JValue, *const JValue, i64) -> JValue = unsafe{std::mem::transmute(mem)};
+    let re = code(vmctx, this, args.as_ptr(), args.len() as i64);
+
+    // the compiled code returns normally with the exception set
+    match vmctx.exception.take(){
+        Some(e) => (e, false),
+        None => (re, true)
+    }
+}
+
+/// compile the code block of a function to the calling convention of compiled functions,
+/// `fn(*mut VmContext, this:JValue, argv:*const JValue, argc:i64) -> JValue`.
+pub(crate) fn compile(runtime:Arc<Runtime>, code:&CodeBlock, linked:&Linked) -> Result<*mut u8, Error>{
+    if code.is_async || code.is_generator{
+        return Err(Error::Unimplemented("baseline compilation of generators and async functions."))
+    }
+
+    let module = runtime.module.clone();
+    let pointer_type = module.isa().pointer_type();
+    let mut ctx = module.make_context();
+    let mut func_ctx = FunctionBuilderContext::new();
+
+    ctx.func.signature = Signature{
+        params:vec![
+            AbiParam::new(pointer_type),
+            AbiParam::new(JValue::TYPE),
+            AbiParam::new(pointer_type),
+            AbiParam::new(types::I64)
+        ],
+        returns:vec![AbiParam::new(JValue::TYPE)],
+        call_conv:module.target_config().default_call_conv
+    };
+
+    let re = Baseline{
+        runtime:&runtime,
+        builder:FunctionBuilder::new(&mut ctx.func, &mut func_ctx),
+        code,
+        linked,
+        pointer_type,
+        helpers:HashMap::new(),
+        blocks:Vec::new(),
+        registers:None,
+        vmctx:None,
+        this:None,
+        argv:None,
+        argc:None,
+    }.translate();
+
+    if let Err(e) = re{
+        ctx.clear();
+        return Err(e)
+    }
+    runtime.compile_function(&mut ctx)
+}
+
+/// the innermost context of the frame, a pointer
+fn scope_var() -> Variable{
+    Variable::with_u32(0)
+}
+
+/// the number of scopes pushed by `PushScope`, an i64
+fn depth_var() -> Variable{
+    Variable::with_u32(1)
+}
+
+struct Baseline<'a>{
+    runtime:&'a Arc<Runtime>,
+    builder:FunctionBuilder<'a>,
+    code:&'a CodeBlock,
+    linked:&'a Linked,
+    pointer_type:Type,
+
+    /// the runtime helpers imported by the function, declared on first use
+    helpers:HashMap<&'static str, FuncRef>,
+    /// the block of every instruction, the last block returns undefined
+    blocks:Vec<Block>,
+    registers:Option<StackSlot>,
+
+    vmctx:Option<Value>,
+    this:Option<Value>,
+    argv:Option<Value>,
+    argc:Option<Value>,
+}
+
+impl<'a> Baseline<'a>{
+    fn translate(mut self) -> Result<(), Error>{
+        let entry = self.builder.create_block();
+        self.builder.append_block_params_for_function_params(entry);
+        self.builder.switch_to_block(entry);
+
+        let params = self.builder.block_params(entry).to_vec();
+        self.vmctx = Some(params[0]);
+        self.this = Some(params[1]);
+        self.argv = Some(params[2]);
+        self.argc = Some(params[3]);
+
+        self.builder.declare_var(scope_var(), self.pointer_type);
+        self.builder.declare_var(depth_var(), types::I64);
+        self.builder.def_var(scope_var(), params[0]);
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        self.builder.def_var(depth_var(), zero);
+
+        // the registers start undefined
+        self.registers = Some(self.builder.create_stack_slot(StackSlotData{
+            kind:StackSlotKind::ExplicitSlot,
+            size:self.code.registers.max(1) * 8,
+        }));
+        let undefined = self.undefined();
+        for r in 0..self.code.registers{
+            self.store(r, undefined);
+        }
+
+        self.blocks = (0..=self.code.code.len()).map(|_|self.builder.create_block()).collect();
+        self.builder.ins().jump(self.blocks[0], &[]);
+
+        let code = self.code;
+        for (pc, op) in code.code.iter().enumerate(){
+            self.builder.switch_to_block(self.blocks[pc]);
+            if !self.translate_op(pc as u32, *op)?{
+                self.builder.ins().jump(self.blocks[pc + 1], &[]);
+            }
+        }
+
+        // the end of the code returns undefined
+        self.builder.switch_to_block(self.blocks[code.code.len()]);
+        let undefined = self.undefined();
+        self.builder.ins().return_(&[undefined]);
+
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+        Ok(())
+    }
+
+    /// translate the instruction at `pc`, return true if it ends the block
+    fn translate_op(&mut self, pc:u32, op:Op) -> Result<bool, Error>{
+        match op{
+            Op::LoadConst{dst, index} => {
+                let code = self.code;
+                let v = match &code.constants[index as usize]{
+                    // a regular expression literal creates a new object every time
+                    Constant::RegExp{..} | Constant::Template{..} => self.constant(index),
+                    _ => {
+                        let bits = self.linked.constants[index as usize].to_bits();
+                        self.builder.ins().iconst(JValue::TYPE, bits as i64)
+                    }
+                };
+                self.store(dst, v);
+            },
+            Op::LoadUndefined{dst} => {
+                let v = self.undefined();
+                self.store(dst, v);
+            },
+            Op::LoadThis{dst} => self.store(dst, self.this.unwrap()),
+            Op::Move{dst, src} => {
+                let v = self.load(src);
+                self.store(dst, v);
+            },
+            Op::LoadArg{dst, index} => {
+                let index = self.builder.ins().iconst(types::I64, index as i64);
+                let v = self.call("resolve_argument", &[self.argv.unwrap(), self.argc.unwrap(), index])[0];
+                self.store(dst, v);
+            },
+            Op::RestArgs{dst, index} => {
+                let index = self.builder.ins().iconst(types::I64, index as i64);
+                let v = self.call("rest_arguments", &[self.argv.unwrap(), self.argc.unwrap(), index])[0];
+                self.store(dst, v);
+            },
+
+            Op::GetName{dst, name} => {
+                let (scope, name) = (self.builder.use_var(scope_var()), self.name(name));
+                let v = self.call_checked(pc, "resolve_var", &[scope, name]);
+                self.store(dst, v);
+            },
+            Op::SetName{name, src} => {
+                let (scope, name) = (self.builder.use_var(scope_var()), self.name(name));
+                let v = self.load(src);
+                self.call_checked(pc, "set_var", &[scope, name, v]);
+            },
+            Op::DeclareName{name, src, kind} => {
+                let (scope, name) = (self.builder.use_var(scope_var()), self.name(name));
+                let v = self.load(src);
+                let kind = self.builder.ins().iconst(types::I8, kind as i64);
+                self.call("declare_var", &[scope, name, v, kind]);
+            },
+            Op::DeclareUninitialized{name, kind} => {
+                let (scope, name) = (self.builder.use_var(scope_var()), self.name(name));
+                let v = self.builder.ins().iconst(JValue::TYPE, vm::uninitialized().to_bits() as i64);
+                let kind = self.builder.ins().iconst(types::I8, kind as i64);
+                self.call("declare_var", &[scope, name, v, kind]);
+            },
+            Op::InitName{name, src} => {
+                let (scope, name) = (self.builder.use_var(scope_var()), self.name(name));
+                let v = self.load(src);
+                self.call("init_var", &[scope, name, v]);
+            },
+            Op::ThrowConstAssignment{..} => {
+                let scope = self.builder.use_var(scope_var());
+                let e = self.call("const_error", &[scope])[0];
+                self.throw(pc, e);
+                return Ok(true)
+            },
+            Op::TypeofName{dst, name} => {
+                let (scope, name) = (self.builder.use_var(scope_var()), self.name(name));
+                let v = self.call_checked(pc, "typeof_var", &[scope, name]);
+                self.store(dst, v);
+            },
+            Op::PushScope{} => {
+                let scope = self.builder.use_var(scope_var());
+                let scope = self.call("baseline_push_scope", &[scope])[0];
+                self.builder.def_var(scope_var(), scope);
+                let depth = self.builder.use_var(depth_var());
+                let depth = self.builder.ins().iadd_imm(depth, 1);
+                self.builder.def_var(depth_var(), depth);
+            },
+            Op::PopScope{} => {
+                let scope = self.builder.use_var(scope_var());
+                let one = self.builder.ins().iconst(types::I64, 1);
+                let scope = self.call("baseline_pop_scopes", &[scope, one])[0];
+                self.builder.def_var(scope_var(), scope);
+                let depth = self.builder.use_var(depth_var());
+                let depth = self.builder.ins().iadd_imm(depth, -1);
+                self.builder.def_var(depth_var(), depth);
+            },
+
+            Op::Binary{op, dst, lhs, rhs} => {
+                let helper = match BinOp::from_u32(op){
+                    Some(op) => binary_helper(op),
+                    None => return Err(Error::SyntaxError(format!("invalid binary operator {}", op)))
+                };
+                let (lhs, rhs) = (self.load(lhs), self.load(rhs));
+                let v = self.call_checked(pc, helper, &[lhs, rhs]);
+                self.store(dst, v);
+            },
+            Op::Unary{op, dst, src} => {
+                let v = self.load(src);
+                let v = match UnOp::from_u32(op){
+                    Some(UnOp::Not) => self.call("not", &[v])[0],
+                    Some(UnOp::TypeOf) => self.call("typeof", &[v])[0],
+                    Some(UnOp::Neg) => self.call_checked(pc, "neg", &[v]),
+                    Some(UnOp::Plus) => self.call_checked(pc, "pos", &[v]),
+                    Some(UnOp::BitNot) => self.call_checked(pc, "bitNot", &[v]),
+                    Some(UnOp::Void) => self.undefined(),
+                    None => return Err(Error::SyntaxError(format!("invalid unary operator {}", op)))
+                };
+                self.store(dst, v);
+            },
+            Op::Increment{dst, src} => {
+                let v = self.load(src);
+                let v = self.call_checked(pc, "increment", &[v]);
+                self.store(dst, v);
+            },
+            Op::Decrement{dst, src} => {
+                let v = self.load(src);
+                let v = self.call_checked(pc, "decrement", &[v]);
+                self.store(dst, v);
+            },
+            Op::ToNumeric{dst, src} => {
+                let v = self.load(src);
+                let v = self.call_checked(pc, "to_numeric", &[v]);
+                self.store(dst, v);
+            },
+
+            Op::Jump{target} => {
+                self.builder.ins().jump(self.blocks[target as usize], &[]);
+                return Ok(true)
+            },
+            Op::JumpIfTrue{src, target} => {
+                let v = self.load(src);
+                let c = self.call("to_bool", &[v])[0];
+                self.branch(pc, c, target, true);
+                return Ok(true)
+            },
+            Op::JumpIfFalse{src, target} => {
+                let v = self.load(src);
+                let c = self.call("to_bool", &[v])[0];
+                self.branch(pc, c, target, false);
+                return Ok(true)
+            },
+            Op::JumpIfNullish{src, target} => {
+                let v = self.load(src);
+                let c = self.call("is_nullish", &[v])[0];
+                self.branch(pc, c, target, true);
+                return Ok(true)
+            },
+            Op::JumpIfNotNullish{src, target} => {
+                let v = self.load(src);
+                let c = self.call("is_nullish", &[v])[0];
+                self.branch(pc, c, target, false);
+                return Ok(true)
+            },
+            Op::JumpIfNotUndefined{src, target} => {
+                let v = self.load(src);
+                let c = self.builder.ins().icmp_imm(IntCC::NotEqual, v, JValue::Undefined.to_bits() as i64);
+                self.branch(pc, c, target, true);
+                return Ok(true)
+            },
+            Op::Return{src} => {
+                let v = self.load(src);
+                self.builder.ins().return_(&[v]);
+                return Ok(true)
+            },
+            Op::Throw{src} => {
+                let v = self.load(src);
+                self.throw(pc, v);
+                return Ok(true)
+            },
+
+            Op::NewObject{dst} => {
+                let v = self.call("new_object", &[])[0];
+                self.store(dst, v);
+            },
+            Op::NewArray{dst} => {
+                let argv = self.builder.ins().iconst(self.pointer_type, 0);
+                let argc = self.builder.ins().iconst(types::I64, 0);
+                let spread = self.builder.ins().iconst(types::I8, 0);
+                let v = self.call_checked(pc, "array_new", &[argv, argc, spread]);
+                self.store(dst, v);
+            },
+            Op::ArrayPush{array, src} => {
+                let (array, v) = (self.load(array), self.load(src));
+                self.call("array_push", &[array, v]);
+            },
+            Op::ArraySpread{array, src} => {
+                let (array, v) = (self.load(array), self.load(src));
+                self.call_checked(pc, "array_spread", &[array, v]);
+            },
+            Op::GetMember{dst, obj, key} => {
+                let (obj, key) = (self.load(obj), self.load(key));
+                let v = self.call_checked(pc, "member", &[obj, key]);
+                self.store(dst, v);
+            },
+            Op::SetMember{obj, key, src} => {
+                let (obj, key, v) = (self.load(obj), self.load(key), self.load(src));
+                self.call_checked(pc, "set_member", &[obj, key, v]);
+            },
+            Op::DeleteMember{dst, obj, key} => {
+                let (obj, key) = (self.load(obj), self.load(key));
+                let v = self.call_checked(pc, "delete_member", &[obj, key]);
+                self.store(dst, v);
+            },
+            Op::SpreadObject{obj, src} => {
+                let (obj, v) = (self.load(obj), self.load(src));
+                self.call_checked(pc, "set_member_spread", &[obj, v]);
+            },
+            Op::DefineMethod{obj, key, func, kind} => {
+                let (obj, key, func) = (self.load(obj), self.load(key), self.load(func));
+                let kind = self.builder.ins().iconst(types::I8, kind as i64);
+                self.call_checked(pc, "define_method", &[obj, key, func, kind]);
+            },
+            Op::Template{dst, index} => {
+                let v = self.constant(index);
+                self.store(dst, v);
+            },
+            Op::TemplateString{dst, args, argc} => {
+                let argv = self.register_addr(args);
+                let argc = self.builder.ins().iconst(types::I64, argc as i64);
+                let v = self.call_checked(pc, "tpl_new", &[argv, argc]);
+                self.store(dst, v);
+            },
+
+            Op::Closure{dst, index} => {
+                let code = self.builder.ins().iconst(self.pointer_type, self.code as *const CodeBlock as i64);
+                let scope = self.builder.use_var(scope_var());
+                let index = self.builder.ins().iconst(types::I64, index as i64);
+                let v = self.call("baseline_closure", &[code, scope, index, self.this.unwrap()])[0];
+                self.store(dst, v);
+            },
+            Op::Call{dst, callee, this, args, argc} => {
+                let scope = self.builder.use_var(scope_var());
+                let (callee, this) = (self.load(callee), self.load(this));
+                let argv = self.register_addr(args);
+                let argc = self.builder.ins().iconst(types::I64, argc as i64);
+                let spread = self.builder.ins().iconst(types::I8, 0);
+                let v = self.call_checked(pc, "call", &[callee, scope, this, argv, argc, spread]);
+                self.store(dst, v);
+            },
+            Op::CallSpread{dst, callee, this, array} => {
+                let scope = self.builder.use_var(scope_var());
+                let (callee, this, array) = (self.load(callee), self.load(this), self.load(array));
+                let v = self.call_checked(pc, "baseline_call_spread", &[callee, scope, this, array]);
+                self.store(dst, v);
+            },
+            Op::New{dst, callee, args, argc} => {
+                let scope = self.builder.use_var(scope_var());
+                let callee = self.load(callee);
+                let argv = self.register_addr(args);
+                let argc = self.builder.ins().iconst(types::I64, argc as i64);
+                let spread = self.builder.ins().iconst(types::I8, 0);
+                let v = self.call_checked(pc, "construct", &[callee, scope, argv, argc, spread]);
+                self.store(dst, v);
+            },
+            Op::NewSpread{dst, callee, array} => {
+                let scope = self.builder.use_var(scope_var());
+                let (callee, array) = (self.load(callee), self.load(array));
+                let v = self.call_checked(pc, "baseline_new_spread", &[callee, scope, array]);
+                self.store(dst, v);
+            },
+
+            Op::GetIterator{dst, src} => {
+                let v = self.load(src);
+                let v = self.call_checked(pc, "get_iterator", &[v]);
+                self.store(dst, v);
+            },
+            Op::ForInIterator{dst, src} => {
+                let v = self.load(src);
+                let v = self.call("for_in_iterator", &[v])[0];
+                self.store(dst, v);
+            },
+            Op::IteratorNext{dst, iterator, done} => {
+                let iterator = self.load(iterator);
+                let re = self.call("iterator_next", &[iterator]);
+                let (v, status) = (re[0], re[1]);
+
+                // status 0 for a value, 1 once done and 2 if `next` threw
+                let throw_block = self.builder.create_block();
+                let value_block = self.builder.create_block();
+                let not_done = self.builder.create_block();
+
+                let is_done = self.builder.ins().icmp_imm(IntCC::Equal, status, 1);
+                self.builder.ins().brnz(is_done, self.blocks[done as usize], &[]);
+                self.builder.ins().jump(not_done, &[]);
+
+                self.builder.switch_to_block(not_done);
+                let threw = self.builder.ins().icmp_imm(IntCC::Equal, status, 2);
+                self.builder.ins().brnz(threw, throw_block, &[]);
+                self.builder.ins().jump(value_block, &[]);
+
+                self.builder.switch_to_block(throw_block);
+                self.throw(pc, v);
+
+                self.builder.switch_to_block(value_block);
+                self.store(dst, v);
+            },
+            Op::IteratorStep{dst, iterator, done} => {
+                let iterator = self.load(iterator);
+                let done = self.register_addr(done);
+                let v = self.call_checked(pc, "baseline_iterator_step", &[iterator, done]);
+                self.store(dst, v);
+            },
+            Op::IteratorRest{dst, iterator, done} => {
+                let iterator = self.load(iterator);
+                let d = self.load(done);
+                let d = self.call("to_bool", &[d])[0];
+                let t = self.builder.ins().iconst(JValue::TYPE, JValue::Boolean(true).to_bits() as i64);
+                self.store(done, t);
+
+                let v = self.call_checked(pc, "iterator_rest", &[iterator, d]);
+                self.store(dst, v);
+            },
+            Op::IteratorClose{iterator, done, quiet} => {
+                let close_block = self.builder.create_block();
+                let next = self.builder.create_block();
+
+                if done == NO_REG{
+                    self.builder.ins().jump(close_block, &[]);
+                } else{
+                    let d = self.load(done);
+                    let d = self.call("to_bool", &[d])[0];
+                    self.builder.ins().brnz(d, next, &[]);
+                    self.builder.ins().jump(close_block, &[]);
+                }
+
+                self.builder.switch_to_block(close_block);
+                let iterator = self.load(iterator);
+                if quiet == 1{
+                    self.call("iterator_close", &[iterator]);
+                } else{
+                    self.call_checked(pc, "iterator_close", &[iterator]);
+                }
+                self.builder.ins().jump(next, &[]);
+                self.builder.switch_to_block(next);
+            },
+            Op::RequireObjectCoercible{src} => {
+                let v = self.load(src);
+                self.call_checked(pc, "require_object_coercible", &[v]);
+            },
+            Op::ObjectRest{dst, src, keys, count} => {
+                let v = self.load(src);
+                let keys = self.register_addr(keys);
+                let count = self.builder.ins().iconst(types::I64, count as i64);
+                let v = self.call_checked(pc, "object_rest", &[v, keys, count]);
+                self.store(dst, v);
+            },
+
+            Op::Yield{..} |
+            Op::Resume{..} |
+            Op::ResumeRaw{..} |
+            Op::JumpIfMode{..} |
+            Op::Delegate{..} => return Err(Error::Unimplemented("baseline compilation of generators and async functions."))
+        }
+        Ok(false)
+    }
+
+    fn undefined(&mut self) -> Value{
+        self.builder.ins().iconst(JValue::TYPE, JValue::Undefined.to_bits() as i64)
+    }
+
+    fn load(&mut self, r:Reg) -> Value{
+        self.builder.ins().stack_load(JValue::TYPE, self.registers.unwrap(), (r * 8) as i32)
+    }
+
+    fn store(&mut self, r:Reg, v:Value){
+        self.builder.ins().stack_store(v, self.registers.unwrap(), (r * 8) as i32);
+    }
+
+    /// the address of the register `r`, the registers from `r` are passed as arguments
+    fn register_addr(&mut self, r:Reg) -> Value{
+        self.builder.ins().stack_addr(self.pointer_type, self.registers.unwrap(), (r * 8) as i32)
+    }
+
+    /// the variable id of the name `index`, resolved when the code block was linked
+    fn name(&mut self, index:u32) -> Value{
+        self.builder.ins().iconst(types::I64, self.linked.names[index as usize] as i64)
+    }
+
+    /// the constants created when evaluated, regular expressions and template objects
+    fn constant(&mut self, index:u32) -> Value{
+        let code = self.builder.ins().iconst(self.pointer_type, self.code as *const CodeBlock as i64);
+        let scope = self.builder.use_var(scope_var());
+        let index = self.builder.ins().iconst(types::I64, index as i64);
+        self.call("baseline_constant", &[code, scope, index])[0]
+    }
+
+    fn call(&mut self, helper:&'static str, args:&[Value]) -> Vec<Value>{
+        let f = match self.helpers.get(helper){
+            Some(f) => *f,
+            None => {
+                let f = self.runtime.module.declare_func_in_func(self.runtime.builtin_functions[helper], self.builder.func);
+                self.helpers.insert(helper, f);
+                f
+            }
+        };
+        let inst = self.builder.ins().call(f, args);
+        self.builder.inst_results(inst).to_vec()
+    }
+
+    /// call a helper returning `(JValue, ok:bool)`, throw the value if not ok
+    fn call_checked(&mut self, pc:u32, helper:&'static str, args:&[Value]) -> Value{
+        let re = self.call(helper, args);
+
+        let throw_block = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.append_block_param(throw_block, JValue::TYPE);
+
+        self.builder.ins().brz(re[1], throw_block, &[re[0]]);
+        self.builder.ins().jump(next, &[]);
+
+        self.builder.switch_to_block(throw_block);
+        let e = self.builder.block_params(throw_block)[0];
+        self.throw(pc, e);
+
+        self.builder.switch_to_block(next);
+        re[0]
+    }
+
+    /// jump to `target` if `c` is `expect`, else continue with the next instruction
+    fn branch(&mut self, pc:u32, c:Value, target:u32, expect:bool){
+        let (target, next) = (self.blocks[target as usize], self.blocks[pc as usize + 1]);
+        if expect{
+            self.builder.ins().brnz(c, target, &[]);
+        } else{
+            self.builder.ins().brz(c, target, &[]);
+        }
+        self.builder.ins().jump(next, &[]);
+    }
+
+    /// throw `e` from the instruction `pc`.
+    ///
+    /// an instruction inside a protected range jumps to its handler with the scopes
+    /// of the range, other exceptions leave every scope and are returned to the caller.
+    fn throw(&mut self, pc:u32, e:Value){
+        let scope = self.builder.use_var(scope_var());
+        let depth = self.builder.use_var(depth_var());
+
+        match self.code.find_handler(pc){
+            Some(h) => {
+                let count = self.builder.ins().iadd_imm(depth, -(h.scopes as i64));
+                let scope = self.call("baseline_pop_scopes", &[scope, count])[0];
+                self.builder.def_var(scope_var(), scope);
+                let depth = self.builder.ins().iconst(types::I64, h.scopes as i64);
+                self.builder.def_var(depth_var(), depth);
+
+                self.store(h.register, e);
+                self.builder.ins().jump(self.blocks[h.target as usize], &[]);
+            },
+            None => {
+                self.call("baseline_pop_scopes", &[scope, depth]);
+                self.call("set_exception", &[self.vmctx.unwrap(), e]);
+                let undefined = self.undefined();
+                self.builder.ins().return_(&[undefined]);
+            }
+        }
+    }
+}
+
+/// the runtime helper of a binary operator
+fn binary_helper(op:BinOp) -> &'static str{
+    match op{
+        BinOp::Add => "add",
+        BinOp::Sub => "sub",
+        BinOp::Mul => "mul",
+        BinOp::Div => "div",
+        BinOp::Mod => "mod",
+        BinOp::Exp => "exp",
+        BinOp::BitAnd => "bitAnd",
+        BinOp::BitOr => "bitOr",
+        BinOp::BitXor => "bitXor",
+        BinOp::Shl => "lshift",
+        BinOp::Shr => "rshift",
+        BinOp::UShr => "unsignedRShift",
+        BinOp::EqEq => "eqeq",
+        BinOp::NotEq => "noteq",
+        BinOp::EqEqEq => "eqeqeq",
+        BinOp::NotEqEq => "noteqeq",
+        BinOp::Lt => "lt",
+        BinOp::LtEq => "lteq",
+        BinOp::Gt => "gt",
+        BinOp::GtEq => "gteq",
+        BinOp::In => "in",
+        BinOp::InstanceOf => "instanceof",
+    }
+}
+
+/// the value of a constant created when evaluated
+pub(crate) unsafe fn constant(code:*const CodeBlock, vmctx:*mut VmContext, index:i64) -> JValue{
+    let code = &*code;
+    let mut linked = code.link((*vmctx).runtime);
+    code.constant(&mut linked, index as u32)
+}
+
+/// `Closure`, create the function `index` of the code block
+pub(crate) unsafe fn closure(code:*const CodeBlock, vmctx:*mut VmContext, index:i64, this:JValue) -> JValue{
+    let code = &*code;
+    create_function(&mut *vmctx, code.functions[index as usize].clone(), this)
+}
+
+/// `PushScope`, return the new innermost context
+pub(crate) unsafe fn push_scope(vmctx:*mut VmContext) -> *mut VmContext{
+    (*vmctx).new_child()
+}
+
+/// leave `count` scopes pushed by `PushScope`, return the innermost context left
+pub(crate) unsafe fn pop_scopes(vmctx:*mut VmContext, count:i64) -> *mut VmContext{
+    let mut ctx = vmctx;
+    for _ in 0..count{
+        let parent = match &mut (*ctx).parent{
+            Some(p) => &mut **p as *mut VmContext,
+            None => unreachable!("popping the context of the function")
+        };
+        (*ctx).done();
+        ctx = parent;
+    }
+    ctx
+}
+
+pub(crate) unsafe fn call_spread(callee:JValue, vmctx:*mut VmContext, this:JValue, array:JValue) -> (JValue, bool){
+    let args = array_values(array).clone();
+    callee.call_raw(&mut *vmctx, this, args.as_ptr(), args.len() as i64, false)
+}
+
+pub(crate) unsafe fn new_spread(callee:JValue, vmctx:*mut VmContext, array:JValue) -> (JValue, bool){
+    let args = array_values(array).clone();
+    callee.new_raw(&mut *vmctx, args.as_ptr(), args.len() as i64, false)
+}
+
+/// `IteratorStep`, the next value of a destructured iterator or undefined once done
+pub(crate) unsafe fn iterator_step(iterator:JValue, done:*mut JValue) -> (JValue, bool){
+    if (*done).to_bool(){
+        return (JValue::Undefined, true)
+    }
+    // an iterator throwing from `next` is not closed
+    *done = JValue::Boolean(true);
+    match operator::iterator_next_raw(iterator){
+        (v, 0) => {
+            *done = JValue::Boolean(false);
+            (v, true)
+        },
+        (_, 1) => (JValue::Undefined, true),
+        (e, _) => (e, false)
+    }
+}
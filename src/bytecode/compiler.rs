@@ -0,0 +1,1866 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use swc_ecma_ast::*;
+
+use crate::builtins::bigint;
+use crate::error::{Error, Position};
+
+use super::scope::{self, captured_names, captured_names_in_function, pat_names, var_names};
+use super::{BinOp, CodeBlock, Constant, Handler, Label, Op, Reg, UnOp, NO_REG};
+
+/// compile a script into a code block.
+///
+/// the bindings declared at the top level of the script are global,
+/// they are declared in the context of the runtime like compiled scripts do.
+pub(crate) fn compile_script(module:&Module) -> Result<CodeBlock, Error>{
+    let mut stmts = Vec::new();
+    for item in &module.body{
+        match item{
+            ModuleItem::Stmt(s) => stmts.push(s),
+            ModuleItem::ModuleDecl(_) => return Err(Error::Unimplemented("module declarations in Runtime::exec."))
+        }
+    }
+
+    let mut c = FunctionCompiler::new(captured_names(stmts.iter().copied()), true, false, false);
+    c.completion = c.alloc();
+    c.emit(Op::LoadUndefined{dst:c.completion});
+
+    let mut names:Vec<(String, u32)> = var_names(stmts.iter().copied()).into_iter().map(|n|(n, 0)).collect();
+    names.extend(lexical_names(stmts.iter().copied(), true));
+    c.enter_scope(names, true);
+    c.hoist_functions(stmts.iter().copied())?;
+
+    for s in stmts{
+        c.stmt(s, None)?;
+    }
+    c.emit(Op::Return{src:c.completion});
+
+    Ok(c.finish(None, 0, false))
+}
+
+/// where a binding lives
+#[derive(Clone, Copy)]
+enum Binding{
+    Register(Reg),
+    /// declared in the context, the index of the name
+    Named(u32),
+}
+
+/// a lexical scope of the function being compiled
+struct Scope{
+    bindings:HashMap<String, Binding>,
+    /// the `const` bindings, assigning them throws
+    constants:HashSet<String>,
+    /// true if the scope pushed a context for its named bindings
+    pushed:bool,
+    /// the first register of the scope, released when the scope exits
+    mark:Reg,
+}
+
+/// what must be undone by a jump out of a statement
+#[derive(Clone, Copy)]
+enum Unwind<'a>{
+    /// a context pushed by a scope
+    Scope,
+    /// the finalizer of a `try` statement, compiled inline on every exit
+    Finally(&'a BlockStmt),
+    /// the iterator of a `for of` loop, closed on exit
+    Iterator(Reg),
+    /// an open protected range, the index in `handlers`
+    Handler(usize),
+}
+
+/// the target of a break or continue
+struct JumpTarget{
+    label:Option<String>,
+    break_label:Label,
+    /// the unwind depth of break
+    break_unwind:usize,
+    continue_label:Option<Label>,
+    continue_unwind:usize,
+    /// a labeled statement that is not a loop, only exited by a labeled break
+    labeled_only:bool,
+}
+
+/// a protected range being compiled, split around the code jumping out of it
+struct OpenHandler{
+    start:u32,
+    target:Label,
+    register:Reg,
+    scopes:u32,
+}
+
+/// the body of a function, arrow functions may have a single expression
+#[derive(Clone, Copy)]
+enum Body<'a>{
+    Stmts(&'a [Stmt]),
+    Expr(&'a Expr),
+}
+
+struct FunctionCompiler<'a>{
+    block:CodeBlock,
+    /// the positions of the labels, u32::MAX until bound
+    labels:Vec<u32>,
+
+    next_reg:Reg,
+    scopes:Vec<Scope>,
+    unwind:Vec<Unwind<'a>>,
+    jumps:Vec<JumpTarget>,
+    handlers:Vec<OpenHandler>,
+
+    /// the names referenced by the inner functions, their bindings are named
+    captured:HashSet<String>,
+    /// the names resolved outside of the function
+    free:Vec<u32>,
+
+    name_indices:HashMap<String, u32>,
+    string_constants:HashMap<String, u32>,
+    number_constants:HashMap<u64, u32>,
+
+    is_script:bool,
+    is_async:bool,
+    is_generator:bool,
+    /// the completion value of a script
+    completion:Reg,
+    /// true while binding the names of a declaration, the bindings are initialized instead of assigned
+    init:bool,
+}
+
+impl<'a> FunctionCompiler<'a>{
+    fn new(captured:HashSet<String>, is_script:bool, is_async:bool, is_generator:bool) -> Self{
+        let mut block = CodeBlock::new();
+        block.is_async = is_async;
+        block.is_generator = is_generator;
+
+        Self{
+            block,
+            labels:Vec::new(),
+
+            next_reg:0,
+            scopes:Vec::new(),
+            unwind:Vec::new(),
+            jumps:Vec::new(),
+            handlers:Vec::new(),
+
+            captured,
+            free:Vec::new(),
+
+            name_indices:HashMap::new(),
+            string_constants:HashMap::new(),
+            number_constants:HashMap::new(),
+
+            is_script,
+            is_async,
+            is_generator,
+            completion:NO_REG,
+            init:false,
+        }
+    }
+
+    /// resolve the labels and return the code block
+    fn finish(mut self, name:Option<&str>, length:u32, is_arrow:bool) -> CodeBlock{
+        let mut block = std::mem::replace(&mut self.block, CodeBlock::new());
+        block.resolve_labels(&self.labels);
+        block.name = name.map(|n|n.to_string());
+        block.length = length;
+        block.is_arrow = is_arrow;
+        block.captures = self.free.clone();
+        block
+    }
+
+    fn emit(&mut self, op:Op){
+        self.block.code.push(op);
+    }
+
+    fn pc(&self) -> u32{
+        self.block.code.len() as u32
+    }
+
+    fn new_label(&mut self) -> Label{
+        self.labels.push(u32::MAX);
+        (self.labels.len() - 1) as Label
+    }
+
+    fn bind_label(&mut self, label:Label){
+        self.labels[label as usize] = self.pc();
+    }
+
+    fn alloc(&mut self) -> Reg{
+        let r = self.next_reg;
+        self.next_reg += 1;
+        self.block.registers = self.block.registers.max(self.next_reg);
+        r
+    }
+
+    /// allocate `n` consecutive registers
+    fn alloc_n(&mut self, n:u32) -> Reg{
+        let r = self.next_reg;
+        self.next_reg += n;
+        self.block.registers = self.block.registers.max(self.next_reg);
+        r
+    }
+
+    fn name(&mut self, name:&str) -> u32{
+        if let Some(i) = self.name_indices.get(name){
+            return *i
+        }
+        self.block.names.push(name.to_string());
+        let i = (self.block.names.len() - 1) as u32;
+        self.name_indices.insert(name.to_string(), i);
+        i
+    }
+
+    fn constant(&mut self, c:Constant) -> u32{
+        // strings and numbers are shared, templates must keep their own identity
+        match &c{
+            Constant::String(s) => {
+                if let Some(i) = self.string_constants.get(s){
+                    return *i
+                }
+            },
+            Constant::Number(n) => {
+                if let Some(i) = self.number_constants.get(&n.to_bits()){
+                    return *i
+                }
+            },
+            _ => {}
+        }
+        let i = self.block.constants.len() as u32;
+        match &c{
+            Constant::String(s) => {
+                self.string_constants.insert(s.clone(), i);
+            },
+            Constant::Number(n) => {
+                self.number_constants.insert(n.to_bits(), i);
+            },
+            _ => {}
+        }
+        self.block.constants.push(c);
+        i
+    }
+
+    fn load_const(&mut self, dst:Reg, c:Constant){
+        let index = self.constant(c);
+        self.emit(Op::LoadConst{dst, index});
+    }
+
+    fn load_str(&mut self, dst:Reg, s:&str){
+        self.load_const(dst, Constant::String(s.to_string()));
+    }
+
+    /// the number of contexts pushed by the enclosing scopes
+    fn scope_depth(&self) -> u32{
+        self.unwind.iter().filter(|u|matches!(u, Unwind::Scope)).count() as u32
+    }
+
+    /// declare the bindings of a new scope.
+    ///
+    /// bindings captured by inner functions and the bindings of the script are declared
+    /// in the context, the others get a register. `base` scopes declare their named bindings
+    /// in the context the code runs in, the others push a context.
+    fn enter_scope(&mut self, names:Vec<(String, u32)>, base:bool){
+        let mark = self.next_reg;
+        let named = names.iter().any(|(n, _)|self.is_script && self.scopes.is_empty() || self.captured.contains(n));
+
+        let pushed = named && !base;
+        if pushed{
+            self.emit(Op::PushScope{});
+            self.unwind.push(Unwind::Scope);
+        }
+
+        let mut bindings = HashMap::new();
+        let mut constants = HashSet::new();
+        let undefined = if named {self.alloc()} else {NO_REG};
+        if named{
+            self.emit(Op::LoadUndefined{dst:undefined});
+        }
+
+        for (name, kind) in names{
+            if bindings.contains_key(&name){
+                continue;
+            }
+            if kind == 2{
+                constants.insert(name.clone());
+            }
+            let global = self.is_script && self.scopes.is_empty();
+            let b = if global || self.captured.contains(&name){
+                let index = self.name(&name);
+                if kind == 0{
+                    self.emit(Op::DeclareName{name:index, src:undefined, kind});
+                } else{
+                    // `let` and `const` are not readable before their declaration runs
+                    self.emit(Op::DeclareUninitialized{name:index, kind});
+                }
+                Binding::Named(index)
+            } else{
+                let r = self.alloc();
+                self.emit(Op::LoadUndefined{dst:r});
+                Binding::Register(r)
+            };
+            bindings.insert(name, b);
+        }
+
+        self.scopes.push(Scope{bindings, constants, pushed, mark});
+    }
+
+    fn exit_scope(&mut self){
+        let scope = self.scopes.pop().unwrap();
+        if scope.pushed{
+            self.emit(Op::PopScope{});
+            self.unwind.pop();
+        }
+        self.next_reg = scope.mark;
+    }
+
+    fn resolve(&mut self, name:&str) -> Binding{
+        for s in self.scopes.iter().rev(){
+            if let Some(b) = s.bindings.get(name){
+                return *b
+            }
+        }
+        // resolved from the enclosing functions or the global object
+        let index = self.name(name);
+        if !self.free.contains(&index){
+            self.free.push(index);
+        }
+        Binding::Named(index)
+    }
+
+    fn load_ident(&mut self, name:&str, dst:Reg){
+        match self.resolve(name){
+            Binding::Register(r) => self.emit(Op::Move{dst, src:r}),
+            Binding::Named(index) => self.emit(Op::GetName{dst, name:index}),
+        }
+    }
+
+    fn store(&mut self, name:&str, src:Reg){
+        let init = self.init;
+        self.store_binding(name, src, init)
+    }
+
+    /// assign `src` to `name`, or run its declaration if `init` is true
+    fn store_binding(&mut self, name:&str, src:Reg, init:bool){
+        match self.resolve(name){
+            Binding::Register(_) if !init && self.is_const(name) => {
+                let index = self.name(name);
+                self.emit(Op::ThrowConstAssignment{name:index});
+            },
+            Binding::Register(r) => self.emit(Op::Move{dst:r, src}),
+            Binding::Named(index) if init => self.emit(Op::InitName{name:index, src}),
+            Binding::Named(index) => self.emit(Op::SetName{name:index, src}),
+        }
+    }
+
+    /// true if the innermost binding of `name` is a `const`
+    fn is_const(&self, name:&str) -> bool{
+        for s in self.scopes.iter().rev(){
+            if s.bindings.contains_key(name){
+                return s.constants.contains(name)
+            }
+        }
+        false
+    }
+
+    /// create the function declarations of a scope before its statements run
+    fn hoist_functions<I:Iterator<Item = &'a Stmt>>(&mut self, stmts:I) -> Result<(), Error>{
+        for s in stmts{
+            if let Stmt::Decl(Decl::Fn(f)) = s{
+                let mark = self.next_reg;
+                let r = self.alloc();
+                self.function(&f.function, Some(&f.ident.sym), r)?;
+                self.store_binding(&f.ident.sym, r, true);
+                self.next_reg = mark;
+            }
+        }
+        Ok(())
+    }
+
+    /// open a protected range jumping to `target` with the exception in `register`
+    fn open_handler(&mut self, target:Label, register:Reg){
+        let scopes = self.scope_depth();
+        self.handlers.push(OpenHandler{start:self.pc(), target, register, scopes});
+        self.unwind.push(Unwind::Handler(self.handlers.len() - 1));
+    }
+
+    /// close the innermost protected range
+    fn close_handler(&mut self){
+        match self.unwind.pop(){
+            Some(Unwind::Handler(_)) => {},
+            _ => unreachable!("unbalanced protected range")
+        }
+        let h = self.handlers.pop().unwrap();
+        self.push_handler_range(&h);
+    }
+
+    fn push_handler_range(&mut self, h:&OpenHandler){
+        let end = self.pc();
+        if h.start < end{
+            self.block.handlers.push(Handler{
+                start:h.start,
+                end,
+                target:h.target,
+                register:h.register,
+                scopes:h.scopes,
+            });
+        }
+    }
+
+    /// emit the code leaving the statements above `depth` in the unwind stack.
+    ///
+    /// the protected ranges left are suspended, they are reopened after the jump.
+    fn emit_unwind(&mut self, depth:usize) -> Result<Vec<usize>, Error>{
+        let mut suspended = Vec::new();
+        let mut i = self.unwind.len();
+        while i > depth{
+            i -= 1;
+            match self.unwind[i]{
+                Unwind::Scope => self.emit(Op::PopScope{}),
+                Unwind::Iterator(r) => self.emit(Op::IteratorClose{iterator:r, done:NO_REG, quiet:0}),
+                Unwind::Handler(h) => {
+                    let range = OpenHandler{
+                        start:self.handlers[h].start,
+                        target:self.handlers[h].target,
+                        register:self.handlers[h].register,
+                        scopes:self.handlers[h].scopes
+                    };
+                    self.push_handler_range(&range);
+                    suspended.push(h);
+                },
+                Unwind::Finally(f) => {
+                    // the finalizer runs outside of the statements it protects
+                    let tail = self.unwind.split_off(i);
+                    let re = self.block_stmts(&f.stmts);
+                    self.unwind.extend(tail);
+                    re?;
+                }
+            }
+        }
+        Ok(suspended)
+    }
+
+    fn resume_handlers(&mut self, suspended:Vec<usize>){
+        let pc = self.pc();
+        for h in suspended{
+            self.handlers[h].start = pc;
+        }
+    }
+
+    /// jump to `target` out of the statements above `depth`
+    fn jump_out(&mut self, target:Label, depth:usize) -> Result<(), Error>{
+        let suspended = self.emit_unwind(depth)?;
+        self.emit(Op::Jump{target});
+        self.resume_handlers(suspended);
+        Ok(())
+    }
+
+    /// return the register from the function, running the finalizers
+    fn emit_return(&mut self, src:Reg) -> Result<(), Error>{
+        let suspended = self.emit_unwind(0)?;
+        self.emit(Op::Return{src});
+        self.resume_handlers(suspended);
+        Ok(())
+    }
+
+    fn find_jump(&self, label:Option<&str>, is_continue:bool, position:Position) -> Result<(Label, usize), Error>{
+        let target = match label{
+            Some(l) => match self.jumps.iter().rev().find(|j|j.label.as_deref() == Some(l)){
+                Some(j) => j,
+                None => return Err(Error::UndefinedLabel(l.to_string(), position))
+            },
+            None if is_continue => match self.jumps.iter().rev().find(|j|j.continue_label.is_some()){
+                Some(j) => j,
+                None => return Err(Error::IllegalContinueStatment(position))
+            },
+            None => match self.jumps.iter().rev().find(|j|!j.labeled_only){
+                Some(j) => j,
+                None => return Err(Error::IllegalBreakStatement(position))
+            }
+        };
+
+        if !is_continue{
+            return Ok((target.break_label, target.break_unwind))
+        }
+        match target.continue_label{
+            Some(l) => Ok((l, target.continue_unwind)),
+            None => Err(Error::IllegalContinueStatment(position))
+        }
+    }
+
+    fn block_stmts(&mut self, stmts:&'a [Stmt]) -> Result<(), Error>{
+        let names = lexical_names(stmts.iter(), false);
+        self.enter_scope(names, false);
+        self.hoist_functions(stmts.iter())?;
+        for s in stmts{
+            self.stmt(s, None)?;
+        }
+        self.exit_scope();
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt:&'a Stmt, label:Option<String>) -> Result<(), Error>{
+        let mark = self.next_reg;
+        let re = self.translate_stmt(stmt, label);
+        self.next_reg = mark;
+        re
+    }
+
+    fn translate_stmt(&mut self, stmt:&'a Stmt, label:Option<String>) -> Result<(), Error>{
+        match stmt{
+            Stmt::Block(b) => self.block_stmts(&b.stmts)?,
+
+            Stmt::Break(b) => {
+                let label = b.label.as_ref().map(|l|l.sym.to_string());
+                let (target, depth) = self.find_jump(label.as_deref(), false, Position::new(b.span.lo.0))?;
+                self.jump_out(target, depth)?;
+            },
+
+            Stmt::Continue(c) => {
+                let label = c.label.as_ref().map(|l|l.sym.to_string());
+                let (target, depth) = self.find_jump(label.as_deref(), true, Position::new(c.span.lo.0))?;
+                self.jump_out(target, depth)?;
+            },
+
+            Stmt::Debugger(_) | Stmt::Empty(_) => {},
+
+            Stmt::Decl(d) => match d{
+                // created when the scope is entered
+                Decl::Fn(_) => {},
+                Decl::Var(v) => self.var_decl(v)?,
+                Decl::Class(_) => return Err(Error::Unimplemented("classes in bytecode.")),
+                _ => return Err(Error::Unimplemented("typescript declarations."))
+            },
+
+            Stmt::Expr(e) => {
+                if self.is_script{
+                    let dst = self.completion;
+                    self.expr(&e.expr, dst)?;
+                } else{
+                    let dst = self.alloc();
+                    self.expr(&e.expr, dst)?;
+                }
+            },
+
+            Stmt::If(i) => {
+                let (alt, end) = (self.new_label(), self.new_label());
+                let test = self.alloc();
+                self.expr(&i.test, test)?;
+                self.emit(Op::JumpIfFalse{src:test, target:alt});
+                self.scoped_stmt(&i.cons)?;
+                self.emit(Op::Jump{target:end});
+                self.bind_label(alt);
+                if let Some(a) = &i.alt{
+                    self.scoped_stmt(a)?;
+                }
+                self.bind_label(end);
+            },
+
+            Stmt::Labeled(l) => {
+                let name = l.label.sym.to_string();
+                if self.jumps.iter().any(|j|j.label.as_ref() == Some(&name)){
+                    return Err(Error::SyntaxError(format!("Label '{}' has already been declared", name)))
+                }
+
+                match l.body.as_ref(){
+                    // loops and switch statements take the label
+                    Stmt::For(_) | Stmt::ForIn(_) | Stmt::ForOf(_) |
+                    Stmt::While(_) | Stmt::DoWhile(_) | Stmt::Switch(_) => {
+                        return self.stmt(&l.body, Some(name))
+                    },
+                    _ => {}
+                };
+
+                let end = self.new_label();
+                self.jumps.push(JumpTarget{
+                    label:Some(name),
+                    break_label:end,
+                    break_unwind:self.unwind.len(),
+                    continue_label:None,
+                    continue_unwind:0,
+                    labeled_only:true
+                });
+                let re = self.scoped_stmt(&l.body);
+                self.jumps.pop();
+                re?;
+                self.bind_label(end);
+            },
+
+            Stmt::Return(r) => {
+                let src = self.alloc();
+                match &r.arg{
+                    Some(e) => self.expr(e, src)?,
+                    None => self.emit(Op::LoadUndefined{dst:src})
+                };
+                self.emit_return(src)?;
+            },
+
+            Stmt::Throw(t) => {
+                let src = self.alloc();
+                self.expr(&t.arg, src)?;
+                self.emit(Op::Throw{src});
+            },
+
+            Stmt::Switch(s) => self.switch(s, label)?,
+            Stmt::Try(t) => self.try_stmt(t)?,
+
+            Stmt::While(w) => {
+                let (start, end) = (self.new_label(), self.new_label());
+                self.push_loop(label, end, start);
+                self.bind_label(start);
+
+                let test = self.alloc();
+                let re = self.expr(&w.test, test).and_then(|_|{
+                    self.emit(Op::JumpIfFalse{src:test, target:end});
+                    self.scoped_stmt(&w.body)
+                });
+                self.jumps.pop();
+                re?;
+
+                self.emit(Op::Jump{target:start});
+                self.bind_label(end);
+            },
+
+            Stmt::DoWhile(d) => {
+                let (start, test_label, end) = (self.new_label(), self.new_label(), self.new_label());
+                self.push_loop(label, end, test_label);
+                self.bind_label(start);
+                let re = self.scoped_stmt(&d.body);
+                self.jumps.pop();
+                re?;
+
+                self.bind_label(test_label);
+                let test = self.alloc();
+                self.expr(&d.test, test)?;
+                self.emit(Op::JumpIfTrue{src:test, target:start});
+                self.bind_label(end);
+            },
+
+            Stmt::For(f) => {
+                // the scope of the declarations in the initializer
+                let names = match &f.init{
+                    Some(VarDeclOrExpr::VarDecl(v)) if v.kind != VarDeclKind::Var => decl_names(v),
+                    _ => Vec::new()
+                };
+                self.enter_scope(names, false);
+
+                match &f.init{
+                    Some(VarDeclOrExpr::VarDecl(v)) => self.var_decl(v)?,
+                    Some(VarDeclOrExpr::Expr(e)) => {
+                        let r = self.alloc();
+                        self.expr(e, r)?;
+                    },
+                    None => {}
+                };
+
+                let (start, update, end) = (self.new_label(), self.new_label(), self.new_label());
+                self.push_loop(label, end, update);
+                self.bind_label(start);
+
+                let re = (||{
+                    if let Some(t) = &f.test{
+                        let test = self.alloc();
+                        self.expr(t, test)?;
+                        self.emit(Op::JumpIfFalse{src:test, target:end});
+                    }
+                    self.scoped_stmt(&f.body)
+                })();
+                self.jumps.pop();
+                re?;
+
+                self.bind_label(update);
+                // every iteration has its own `let` bindings, 
+                // the functions created by the previous iteration keep theirs.
+                if let Some(VarDeclOrExpr::VarDecl(v)) = &f.init{
+                    if v.kind == VarDeclKind::Let{
+                        for (name, kind) in decl_names(v){
+                            if let Binding::Named(index) = self.resolve(&name){
+                                let r = self.alloc();
+                                self.emit(Op::GetName{dst:r, name:index});
+                                self.emit(Op::DeclareName{name:index, src:r, kind});
+                            }
+                        }
+                    }
+                }
+                if let Some(u) = &f.update{
+                    let r = self.alloc();
+                    self.expr(u, r)?;
+                }
+                self.emit(Op::Jump{target:start});
+                self.bind_label(end);
+
+                self.exit_scope();
+            },
+
+            Stmt::ForIn(f) => {
+                let right = self.alloc();
+                self.expr(&f.right, right)?;
+                let iterator = self.alloc();
+                self.emit(Op::ForInIterator{dst:iterator, src:right});
+                self.iteration(&f.left, iterator, &f.body, label, false)?;
+            },
+
+            Stmt::ForOf(f) => {
+                if f.await_token.is_some(){
+                    return Err(Error::Unimplemented("for await of statement."))
+                }
+                let right = self.alloc();
+                self.expr(&f.right, right)?;
+                let iterator = self.alloc();
+                self.emit(Op::GetIterator{dst:iterator, src:right});
+                self.iteration(&f.left, iterator, &f.body, label, true)?;
+            },
+
+            Stmt::With(_) => {
+                return Err(Error::Deprecated("`with` statment is deprecated and not supported."))
+            }
+        }
+        Ok(())
+    }
+
+    /// a statement in its own scope, the body of a loop or branch
+    fn scoped_stmt(&mut self, stmt:&'a Stmt) -> Result<(), Error>{
+        match stmt{
+            Stmt::Block(b) => self.block_stmts(&b.stmts),
+            s => self.block_stmts(std::slice::from_ref(s)),
+        }
+    }
+
+    fn push_loop(&mut self, label:Option<String>, break_label:Label, continue_label:Label){
+        let depth = self.unwind.len();
+        self.jumps.push(JumpTarget{
+            label,
+            break_label,
+            break_unwind:depth,
+            continue_label:Some(continue_label),
+            continue_unwind:depth,
+            labeled_only:false
+        });
+    }
+
+    /// run the body for every value of the iterator.
+    ///
+    /// if `close` is true, the iterator is closed by exits from the body.
+    fn iteration(&mut self, left:&'a VarDeclOrPat, iterator:Reg, body:&'a Stmt, label:Option<String>, close:bool) -> Result<(), Error>{
+        let (next, end) = (self.new_label(), self.new_label());
+        let break_unwind = self.unwind.len();
+
+        let value = self.alloc();
+        let exception = self.alloc();
+        let on_throw = self.new_label();
+
+        self.bind_label(next);
+        // the iterator is not closed if `next` throws
+        self.emit(Op::IteratorNext{dst:value, iterator, done:end});
+
+        if close{
+            self.unwind.push(Unwind::Iterator(iterator));
+            self.open_handler(on_throw, exception);
+        }
+        self.jumps.push(JumpTarget{
+            label,
+            break_label:end,
+            break_unwind,
+            continue_label:Some(next),
+            continue_unwind:self.unwind.len(),
+            labeled_only:false
+        });
+
+        let re = (||{
+            // a new binding is declared for every iteration
+            let names = match left{
+                VarDeclOrPat::VarDecl(v) if v.kind != VarDeclKind::Var => decl_names(v),
+                _ => Vec::new()
+            };
+            self.enter_scope(names, false);
+            match left{
+                VarDeclOrPat::VarDecl(v) => self.bind_decl(&v.decls[0].name, value)?,
+                VarDeclOrPat::Pat(p) => self.bind_pat(p, value)?
+            }
+            self.scoped_stmt(body)?;
+            self.exit_scope();
+            Ok(())
+        })();
+        self.jumps.pop();
+        re?;
+
+        self.emit(Op::Jump{target:next});
+
+        if close{
+            self.close_handler();
+            self.unwind.pop();
+
+            // close the iterator and rethrow
+            let after = self.new_label();
+            self.emit(Op::Jump{target:after});
+            self.bind_label(on_throw);
+            self.emit(Op::IteratorClose{iterator, done:NO_REG, quiet:1});
+            self.emit(Op::Throw{src:exception});
+            self.bind_label(after);
+        }
+        self.bind_label(end);
+        Ok(())
+    }
+
+    fn switch(&mut self, s:&'a SwitchStmt, label:Option<String>) -> Result<(), Error>{
+        let discrim = self.alloc();
+        self.expr(&s.discriminant, discrim)?;
+
+        // the cases share one scope
+        let names = lexical_names(s.cases.iter().flat_map(|c|&c.cons), false);
+        self.enter_scope(names, false);
+        self.hoist_functions(s.cases.iter().flat_map(|c|&c.cons))?;
+
+        let labels:Vec<Label> = s.cases.iter().map(|_|self.new_label()).collect();
+        let end = self.new_label();
+
+        // the cases are tested in source order,
+        // the default case is selected only if none matches wherever it is placed
+        let test = self.alloc();
+        for (i, c) in s.cases.iter().enumerate(){
+            if let Some(t) = &c.test{
+                self.expr(t, test)?;
+                self.emit(Op::Binary{op:BinOp::EqEqEq as u32, dst:test, lhs:discrim, rhs:test});
+                self.emit(Op::JumpIfTrue{src:test, target:labels[i]});
+            }
+        }
+        let default = s.cases.iter().position(|c|c.test.is_none());
+        self.emit(Op::Jump{target:default.map(|i|labels[i]).unwrap_or(end)});
+
+        self.jumps.push(JumpTarget{
+            label,
+            break_label:end,
+            break_unwind:self.unwind.len(),
+            continue_label:None,
+            continue_unwind:0,
+            labeled_only:false
+        });
+        let re = (||{
+            // the case bodies fall through to the next case
+            for (i, c) in s.cases.iter().enumerate(){
+                self.bind_label(labels[i]);
+                for s in &c.cons{
+                    self.stmt(s, None)?;
+                }
+            }
+            Ok(())
+        })();
+        self.jumps.pop();
+        re?;
+
+        self.bind_label(end);
+        self.exit_scope();
+        Ok(())
+    }
+
+    fn try_stmt(&mut self, t:&'a TryStmt) -> Result<(), Error>{
+        let exception = self.alloc();
+        let (normal, on_catch, on_finally_throw) = (self.new_label(), self.new_label(), self.new_label());
+
+        if let Some(f) = &t.finalizer{
+            self.unwind.push(Unwind::Finally(f));
+        }
+
+        // the block throws to the catch clause or to the finalizer
+        let target = if t.handler.is_some() {on_catch} else {on_finally_throw};
+        self.open_handler(target, exception);
+        self.block_stmts(&t.block.stmts)?;
+        self.close_handler();
+        self.emit(Op::Jump{target:normal});
+
+        if let Some(clause) = &t.handler{
+            self.bind_label(on_catch);
+
+            // a throw inside the catch clause is not caught by it
+            if t.finalizer.is_some(){
+                self.open_handler(on_finally_throw, exception);
+            }
+
+            let names = match &clause.param{
+                Some(p) => {
+                    let mut names = Vec::new();
+                    pat_names(p, &mut names);
+                    names.into_iter().map(|n|(n, 1)).collect()
+                },
+                None => Vec::new()
+            };
+            self.enter_scope(names, false);
+            if let Some(p) = &clause.param{
+                self.bind_decl(p, exception)?;
+            }
+            self.block_stmts(&clause.body.stmts)?;
+            self.exit_scope();
+
+            if t.finalizer.is_some(){
+                self.close_handler();
+            }
+        }
+
+        self.bind_label(normal);
+
+        if let Some(f) = &t.finalizer{
+            self.unwind.pop();
+
+            // the finalizer runs on every completion,
+            // its own abrupt completion replaces the completion of the try statement
+            let end = self.new_label();
+            self.block_stmts(&f.stmts)?;
+            self.emit(Op::Jump{target:end});
+
+            self.bind_label(on_finally_throw);
+            self.block_stmts(&f.stmts)?;
+            self.emit(Op::Throw{src:exception});
+            self.bind_label(end);
+        }
+        Ok(())
+    }
+
+    fn var_decl(&mut self, decl:&'a VarDecl) -> Result<(), Error>{
+        for d in &decl.decls{
+            // a `var` without initializer keeps its value
+            if d.init.is_none() && decl.kind == VarDeclKind::Var{
+                continue;
+            }
+            let mark = self.next_reg;
+            let value = self.alloc();
+            match &d.init{
+                Some(e) => self.expr(e, value)?,
+                None => self.emit(Op::LoadUndefined{dst:value})
+            };
+            self.bind_decl(&d.name, value)?;
+            self.next_reg = mark;
+        }
+        Ok(())
+    }
+
+    /// run the declaration of the names of the pattern with the value of `src`
+    fn bind_decl(&mut self, p:&'a Pat, src:Reg) -> Result<(), Error>{
+        let init = std::mem::replace(&mut self.init, true);
+        let re = self.bind_pat(p, src);
+        self.init = init;
+        re
+    }
+
+    /// evaluate an expression nested in a pattern, its assignments are not declarations
+    fn pat_expr(&mut self, e:&'a Expr, dst:Reg) -> Result<(), Error>{
+        let init = std::mem::replace(&mut self.init, false);
+        let re = self.expr(e, dst);
+        self.init = init;
+        re
+    }
+
+    /// bind the value of `src` to the pattern, the bindings are already declared.
+    fn bind_pat(&mut self, p:&'a Pat, src:Reg) -> Result<(), Error>{
+        let mark = self.next_reg;
+        let re = self.translate_pat(p, src);
+        self.next_reg = mark;
+        re
+    }
+
+    fn translate_pat(&mut self, p:&'a Pat, src:Reg) -> Result<(), Error>{
+        match p{
+            Pat::Ident(i) => self.store(&i.id.sym, src),
+
+            // the default is only evaluated if the value is undefined
+            Pat::Assign(a) => {
+                let value = self.alloc();
+                let skip = self.new_label();
+                self.emit(Op::Move{dst:value, src});
+                self.emit(Op::JumpIfNotUndefined{src:value, target:skip});
+                self.pat_expr(&a.right, value)?;
+                self.bind_label(skip);
+                self.bind_pat(&a.left, value)?;
+            },
+
+            Pat::Array(a) => {
+                let iterator = self.alloc();
+                let done = self.alloc();
+                let exception = self.alloc();
+                let value = self.alloc();
+                let (on_throw, end) = (self.new_label(), self.new_label());
+
+                self.emit(Op::GetIterator{dst:iterator, src});
+                self.load_const(done, Constant::Bool(false));
+
+                // the iterator is closed by exceptions thrown while binding the elements
+                self.open_handler(on_throw, exception);
+                for elem in &a.elems{
+                    match elem{
+                        Some(Pat::Rest(r)) => {
+                            self.emit(Op::IteratorRest{dst:value, iterator, done});
+                            self.bind_pat(&r.arg, value)?;
+                        },
+                        Some(p) => {
+                            self.emit(Op::IteratorStep{dst:value, iterator, done});
+                            self.bind_pat(p, value)?;
+                        },
+                        None => self.emit(Op::IteratorStep{dst:value, iterator, done})
+                    }
+                }
+                self.close_handler();
+
+                self.emit(Op::IteratorClose{iterator, done, quiet:0});
+                self.emit(Op::Jump{target:end});
+                self.bind_label(on_throw);
+                self.emit(Op::IteratorClose{iterator, done, quiet:1});
+                self.emit(Op::Throw{src:exception});
+                self.bind_label(end);
+            },
+
+            Pat::Object(o) => {
+                self.emit(Op::RequireObjectCoercible{src});
+
+                // the keys already bound are excluded from the rest element
+                let count = o.props.iter().filter(|p|!matches!(p, ObjectPatProp::Rest(_))).count() as u32;
+                let keys = self.alloc_n(count);
+                let value = self.alloc();
+                let mut i = 0;
+
+                for prop in &o.props{
+                    match prop{
+                        ObjectPatProp::KeyValue(kv) => {
+                            self.prop_name(&kv.key, keys + i)?;
+                            self.emit(Op::GetMember{dst:value, obj:src, key:keys + i});
+                            i += 1;
+                            self.bind_pat(&kv.value, value)?;
+                        },
+                        ObjectPatProp::Assign(a) => {
+                            self.load_str(keys + i, &a.key.sym);
+                            self.emit(Op::GetMember{dst:value, obj:src, key:keys + i});
+                            i += 1;
+                            if let Some(default) = &a.value{
+                                let skip = self.new_label();
+                                self.emit(Op::JumpIfNotUndefined{src:value, target:skip});
+                                self.pat_expr(default, value)?;
+                                self.bind_label(skip);
+                            }
+                            self.store(&a.key.sym, value);
+                        },
+                        ObjectPatProp::Rest(r) => {
+                            self.emit(Op::ObjectRest{dst:value, src, keys, count:i});
+                            self.bind_pat(&r.arg, value)?;
+                        }
+                    }
+                }
+            },
+
+            // an assignment target
+            Pat::Expr(e) => self.assign_target(e, src)?,
+
+            Pat::Rest(_) => return Err(Error::SyntaxError("Rest element must be last element".to_string())),
+            Pat::Invalid(_) => return Err(Error::SyntaxError("Invalid destructuring assignment target".to_string())),
+        }
+        Ok(())
+    }
+
+    /// assign to an identifier or a member expression
+    fn assign_target(&mut self, target:&'a Expr, src:Reg) -> Result<(), Error>{
+        match unparen(target){
+            Expr::Ident(i) => self.store(&i.sym, src),
+            Expr::Member(m) => {
+                let (obj, key) = (self.alloc(), self.alloc());
+                self.reference(m, obj, key)?;
+                self.emit(Op::SetMember{obj, key, src});
+            },
+            _ => return Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+        }
+        Ok(())
+    }
+
+    /// evaluate the object and key of a member expression
+    fn reference(&mut self, m:&'a MemberExpr, obj:Reg, key:Reg) -> Result<(), Error>{
+        match &m.obj{
+            ExprOrSuper::Expr(e) => self.expr(e, obj)?,
+            ExprOrSuper::Super(_) => return Err(Error::Unimplemented("super in bytecode."))
+        };
+        self.prop(&m.prop, m.computed, key)
+    }
+
+    fn prop(&mut self, prop:&'a Expr, computed:bool, dst:Reg) -> Result<(), Error>{
+        if computed{
+            return self.expr(prop, dst)
+        }
+        match prop{
+            Expr::Ident(i) => {
+                self.load_str(dst, &i.sym);
+                Ok(())
+            },
+            Expr::PrivateName(_) => Err(Error::Unimplemented("private names in bytecode.")),
+            _ => Err(Error::Unimplemented("non computed property expression."))
+        }
+    }
+
+    fn prop_name(&mut self, key:&'a PropName, dst:Reg) -> Result<(), Error>{
+        match key{
+            PropName::Ident(i) => self.load_str(dst, &i.sym),
+            PropName::Str(s) => self.load_str(dst, &s.value),
+            PropName::Num(n) => self.load_str(dst, &crate::value::JValue::Number(n.value).to_string()),
+            PropName::BigInt(b) => self.load_str(dst, &b.value.to_string()),
+            PropName::Computed(c) => self.expr(&c.expr, dst)?,
+        }
+        Ok(())
+    }
+
+    /// evaluate the expression into `dst`
+    fn expr(&mut self, expr:&'a Expr, dst:Reg) -> Result<(), Error>{
+        let mark = self.next_reg;
+        let re = self.translate_expr(expr, dst);
+        self.next_reg = mark;
+        re
+    }
+
+    fn translate_expr(&mut self, expr:&'a Expr, dst:Reg) -> Result<(), Error>{
+        match expr{
+            Expr::Array(a) => {
+                let array = self.alloc();
+                let value = self.alloc();
+                self.emit(Op::NewArray{dst:array});
+                for e in &a.elems{
+                    match e{
+                        Some(e) => {
+                            self.expr(&e.expr, value)?;
+                            if e.spread.is_some(){
+                                self.emit(Op::ArraySpread{array, src:value});
+                            } else{
+                                self.emit(Op::ArrayPush{array, src:value});
+                            }
+                        },
+                        None => {
+                            self.emit(Op::LoadUndefined{dst:value});
+                            self.emit(Op::ArrayPush{array, src:value});
+                        }
+                    }
+                }
+                self.emit(Op::Move{dst, src:array});
+            },
+
+            Expr::Arrow(a) => {
+                let params:Vec<&Pat> = a.params.iter().collect();
+                let body = match &a.body{
+                    BlockStmtOrExpr::BlockStmt(b) => Body::Stmts(&b.stmts),
+                    BlockStmtOrExpr::Expr(e) => Body::Expr(e)
+                };
+                self.closure(&params, body, a.is_async, a.is_generator, true, None, dst)?;
+            },
+
+            Expr::Assign(a) => self.assign(a, dst)?,
+
+            Expr::Await(a) => {
+                if !self.is_async{
+                    return Err(Error::SyntaxError("await is only valid in async functions".to_string()))
+                }
+                let value = self.alloc();
+                let mode = self.alloc();
+                self.expr(&a.arg, value)?;
+                // the async function is resumed with the settled value, a rejection is thrown by `Resume`
+                self.emit(Op::Yield{src:value});
+                self.emit(Op::Resume{dst, mode});
+            },
+
+            Expr::Bin(b) => {
+                if let Expr::PrivateName(_) = b.left.as_ref(){
+                    return Err(Error::Unimplemented("private names in bytecode."))
+                }
+
+                // the right operand is only evaluated if the left does not short circuit
+                let short_circuit = match b.op{
+                    BinaryOp::LogicalAnd => Some(0),
+                    BinaryOp::LogicalOr => Some(1),
+                    BinaryOp::NullishCoalescing => Some(2),
+                    _ => None
+                };
+                if let Some(kind) = short_circuit{
+                    let end = self.new_label();
+                    let left = self.alloc();
+                    self.expr(&b.left, left)?;
+                    self.emit(Op::Move{dst, src:left});
+                    self.emit(match kind{
+                        0 => Op::JumpIfFalse{src:left, target:end},
+                        1 => Op::JumpIfTrue{src:left, target:end},
+                        _ => Op::JumpIfNotNullish{src:left, target:end},
+                    });
+                    self.expr(&b.right, dst)?;
+                    self.bind_label(end);
+                    return Ok(())
+                }
+
+                let (lhs, rhs) = (self.alloc(), self.alloc());
+                self.expr(&b.left, lhs)?;
+                self.expr(&b.right, rhs)?;
+                let op = binary_op(b.op).unwrap();
+                self.emit(Op::Binary{op:op as u32, dst, lhs, rhs});
+            },
+
+            Expr::Call(_) | Expr::Member(_) | Expr::OptChain(_) => {
+                // a short circuited chain is undefined
+                let mut exit = None;
+                self.chain(expr, dst, &mut exit)?;
+                if let Some(exit) = exit{
+                    let end = self.new_label();
+                    self.emit(Op::Jump{target:end});
+                    self.bind_label(exit);
+                    self.emit(Op::LoadUndefined{dst});
+                    self.bind_label(end);
+                }
+            },
+
+            Expr::Class(_) => return Err(Error::Unimplemented("classes in bytecode.")),
+
+            Expr::Cond(c) => {
+                let (alt, end) = (self.new_label(), self.new_label());
+                let test = self.alloc();
+                self.expr(&c.test, test)?;
+                self.emit(Op::JumpIfFalse{src:test, target:alt});
+                self.expr(&c.cons, dst)?;
+                self.emit(Op::Jump{target:end});
+                self.bind_label(alt);
+                self.expr(&c.alt, dst)?;
+                self.bind_label(end);
+            },
+
+            Expr::Fn(f) => match &f.ident{
+                // the name of a function expression is bound in its own scope
+                Some(i) => {
+                    self.captured.insert(i.sym.to_string());
+                    self.enter_scope(vec![(i.sym.to_string(), 2)], false);
+                    self.function(&f.function, Some(&i.sym), dst)?;
+                    self.store_binding(&i.sym, dst, true);
+                    self.exit_scope();
+                },
+                None => self.function(&f.function, None, dst)?
+            },
+
+            Expr::Ident(i) => self.load_ident(&i.sym, dst),
+
+            Expr::Invalid(_) => self.emit(Op::LoadUndefined{dst}),
+
+            Expr::Lit(l) => match l{
+                Lit::BigInt(b) => self.load_const(dst, Constant::BigInt(bigint::from_literal(b))),
+                Lit::Bool(b) => self.load_const(dst, Constant::Bool(b.value)),
+                Lit::Null(_) => self.load_const(dst, Constant::Null),
+                Lit::Num(n) => self.load_const(dst, Constant::Number(n.value)),
+                Lit::Regex(r) => self.load_const(dst, Constant::RegExp{exp:r.exp.to_string(), flags:r.flags.to_string()}),
+                Lit::Str(s) => self.load_str(dst, &s.value),
+                Lit::JSXText(_) => return Err(Error::Unimplemented("jsx."))
+            },
+
+            Expr::MetaProp(_) => return Err(Error::Unimplemented("meta properties.")),
+
+            Expr::New(n) => {
+                let callee = self.alloc();
+                self.expr(&n.callee, callee)?;
+                let args = n.args.as_deref().unwrap_or(&[]);
+                if args.iter().any(|a|a.spread.is_some()){
+                    let array = self.spread_args(args)?;
+                    self.emit(Op::NewSpread{dst, callee, array});
+                } else{
+                    let (args, argc) = self.args(args)?;
+                    self.emit(Op::New{dst, callee, args, argc});
+                }
+            },
+
+            Expr::Object(o) => {
+                let obj = self.alloc();
+                let (key, value) = (self.alloc(), self.alloc());
+                self.emit(Op::NewObject{dst:obj});
+
+                for p in &o.props{
+                    match p{
+                        PropOrSpread::Spread(s) => {
+                            self.expr(&s.expr, value)?;
+                            self.emit(Op::SpreadObject{obj, src:value});
+                        },
+                        PropOrSpread::Prop(p) => match p.as_ref(){
+                            Prop::Shorthand(i) => {
+                                self.load_str(key, &i.sym);
+                                self.load_ident(&i.sym, value);
+                                self.emit(Op::SetMember{obj, key, src:value});
+                            },
+                            Prop::KeyValue(kv) => {
+                                self.prop_name(&kv.key, key)?;
+                                self.expr(&kv.value, value)?;
+                                self.emit(Op::SetMember{obj, key, src:value});
+                            },
+                            Prop::Method(m) => {
+                                self.prop_name(&m.key, key)?;
+                                self.function(&m.function, prop_name_str(&m.key), value)?;
+                                self.emit(Op::DefineMethod{obj, key, func:value, kind:0});
+                            },
+                            Prop::Getter(g) => {
+                                self.prop_name(&g.key, key)?;
+                                let body = match &g.body{
+                                    Some(b) => Body::Stmts(&b.stmts),
+                                    None => Body::Stmts(&[])
+                                };
+                                self.closure(&[], body, false, false, false, prop_name_str(&g.key), value)?;
+                                self.emit(Op::DefineMethod{obj, key, func:value, kind:1});
+                            },
+                            Prop::Setter(s) => {
+                                self.prop_name(&s.key, key)?;
+                                let body = match &s.body{
+                                    Some(b) => Body::Stmts(&b.stmts),
+                                    None => Body::Stmts(&[])
+                                };
+                                self.closure(&[&s.param], body, false, false, false, prop_name_str(&s.key), value)?;
+                                self.emit(Op::DefineMethod{obj, key, func:value, kind:2});
+                            },
+                            Prop::Assign(_) => {
+                                return Err(Error::SyntaxError("Invalid shorthand property initializer".to_string()))
+                            }
+                        }
+                    }
+                }
+                self.emit(Op::Move{dst, src:obj});
+            },
+
+            Expr::Paren(p) => self.expr(&p.expr, dst)?,
+
+            Expr::PrivateName(p) => {
+                return Err(Error::SyntaxError(format!("Unexpected private field '#{}'", p.id.sym)))
+            },
+
+            Expr::Seq(s) => {
+                for e in &s.exprs{
+                    self.expr(e, dst)?;
+                }
+            },
+
+            Expr::TaggedTpl(t) => {
+                let (callee, this) = (self.alloc(), self.alloc());
+                match t.tag.as_ref(){
+                    Expr::Member(m) => {
+                        let key = self.alloc();
+                        self.reference(m, this, key)?;
+                        self.emit(Op::GetMember{dst:callee, obj:this, key});
+                    },
+                    tag => {
+                        self.expr(tag, callee)?;
+                        self.emit(Op::LoadThis{dst:this});
+                    }
+                };
+
+                // the template object is created once per call site
+                let cooked = t.tpl.quasis.iter().map(|q|q.cooked.as_ref().map(|c|c.value.to_string())).collect();
+                let raw = t.tpl.quasis.iter().map(|q|q.raw.value.to_string()).collect();
+                let index = self.block.constants.len() as u32;
+                self.block.constants.push(Constant::Template{cooked, raw});
+
+                let args = self.alloc_n(t.tpl.exprs.len() as u32 + 1);
+                self.emit(Op::Template{dst:args, index});
+                for (i, e) in t.tpl.exprs.iter().enumerate(){
+                    self.expr(e, args + 1 + i as u32)?;
+                }
+                self.emit(Op::Call{dst, callee, this, args, argc:t.tpl.exprs.len() as u32 + 1});
+            },
+
+            Expr::This(_) => self.emit(Op::LoadThis{dst}),
+
+            Expr::Tpl(t) => {
+                let argc = (t.quasis.len() + t.exprs.len()) as u32;
+                let args = self.alloc_n(argc);
+                let mut i = 0;
+                for (n, q) in t.quasis.iter().enumerate(){
+                    let cooked = match &q.cooked{
+                        Some(c) => c.value.to_string(),
+                        None => return Err(Error::SyntaxError("Invalid escape sequence in template".to_string()))
+                    };
+                    self.load_str(args + i, &cooked);
+                    i += 1;
+                    if !q.tail{
+                        self.expr(&t.exprs[n], args + i)?;
+                        i += 1;
+                    }
+                }
+                self.emit(Op::TemplateString{dst, args, argc:i});
+            },
+
+            Expr::Unary(u) => match (u.op, u.arg.as_ref()){
+                // `typeof x` does not throw if x is not declared
+                (UnaryOp::TypeOf, Expr::Ident(i)) => match self.resolve(&i.sym){
+                    Binding::Register(r) => self.emit(Op::Unary{op:UnOp::TypeOf as u32, dst, src:r}),
+                    Binding::Named(name) => self.emit(Op::TypeofName{dst, name}),
+                },
+
+                (UnaryOp::Delete, Expr::Member(m)) => {
+                    if let Expr::PrivateName(_) = m.prop.as_ref(){
+                        return Err(Error::SyntaxError("Private fields can not be deleted".to_string()))
+                    }
+                    let (obj, key) = (self.alloc(), self.alloc());
+                    self.reference(m, obj, key)?;
+                    self.emit(Op::DeleteMember{dst, obj, key});
+                },
+
+                // declared bindings cannot be deleted
+                (UnaryOp::Delete, Expr::Ident(_)) => self.load_const(dst, Constant::Bool(false)),
+
+                (UnaryOp::Delete, e) => {
+                    self.expr(e, dst)?;
+                    self.load_const(dst, Constant::Bool(true));
+                },
+
+                (op, e) => {
+                    let src = self.alloc();
+                    self.expr(e, src)?;
+                    let op = match op{
+                        UnaryOp::Bang => UnOp::Not,
+                        UnaryOp::Minus => UnOp::Neg,
+                        UnaryOp::Plus => UnOp::Plus,
+                        UnaryOp::Tilde => UnOp::BitNot,
+                        UnaryOp::TypeOf => UnOp::TypeOf,
+                        UnaryOp::Void => UnOp::Void,
+                        UnaryOp::Delete => unreachable!()
+                    };
+                    self.emit(Op::Unary{op:op as u32, dst, src});
+                }
+            },
+
+            Expr::Update(u) => {
+                let (old, new) = (self.alloc(), self.alloc());
+                let update = |old, new| match u.op{
+                    UpdateOp::PlusPlus => Op::Increment{dst:new, src:old},
+                    UpdateOp::MinusMinus => Op::Decrement{dst:new, src:old},
+                };
+
+                match unparen(&u.arg){
+                    Expr::Ident(i) => {
+                        self.load_ident(&i.sym, old);
+                        self.emit(Op::ToNumeric{dst:old, src:old});
+                        self.emit(update(old, new));
+                        self.store(&i.sym, new);
+                    },
+                    Expr::Member(m) => {
+                        let (obj, key) = (self.alloc(), self.alloc());
+                        self.reference(m, obj, key)?;
+                        self.emit(Op::GetMember{dst:old, obj, key});
+                        self.emit(Op::ToNumeric{dst:old, src:old});
+                        self.emit(update(old, new));
+                        self.emit(Op::SetMember{obj, key, src:new});
+                    },
+                    _ => return Err(Error::SyntaxError(format!("Invalid left-hand side expression in {} operation", if u.prefix {"prefix"} else {"postfix"})))
+                }
+                self.emit(Op::Move{dst, src:if u.prefix {new} else {old}});
+            },
+
+            Expr::Yield(y) => {
+                if !self.is_generator{
+                    return Err(Error::SyntaxError("yield is only valid in generator functions".to_string()))
+                }
+
+                let value = self.alloc();
+                match &y.arg{
+                    Some(a) => self.expr(a, value)?,
+                    None => self.emit(Op::LoadUndefined{dst:value})
+                };
+
+                if y.delegate{
+                    return self.yield_delegate(value, dst)
+                }
+
+                let mode = self.alloc();
+                let next = self.new_label();
+                self.emit(Op::Yield{src:value});
+                // generator.throw(value) is thrown by `Resume`
+                self.emit(Op::Resume{dst, mode});
+                self.emit(Op::JumpIfMode{mode, expect:0, target:next});
+                // generator.return(value)
+                self.emit_return(dst)?;
+                self.bind_label(next);
+            },
+
+            Expr::TsAs(_) |
+            Expr::TsConstAssertion(_) |
+            Expr::TsNonNull(_) |
+            Expr::TsTypeAssertion(_) => return Err(Error::Unimplemented("typescript expressions.")),
+
+            Expr::JSXElement(_) |
+            Expr::JSXEmpty(_) |
+            Expr::JSXFragment(_) |
+            Expr::JSXMember(_) |
+            Expr::JSXNamespacedName(_) => return Err(Error::Unimplemented("jsx."))
+        }
+        Ok(())
+    }
+
+    /// `yield* iterable`, forwarding every resumption to the inner iterator
+    fn yield_delegate(&mut self, iterable:Reg, dst:Reg) -> Result<(), Error>{
+        let (iterator, sent, mode, value) = (self.alloc(), self.alloc(), self.alloc(), self.alloc());
+        let (next, done, ret) = (self.new_label(), self.new_label(), self.new_label());
+
+        self.emit(Op::GetIterator{dst:iterator, src:iterable});
+        self.emit(Op::LoadUndefined{dst:sent});
+        self.load_const(mode, Constant::Number(0.0));
+
+        self.bind_label(next);
+        self.emit(Op::Delegate{dst:value, iterator, sent, mode, done, ret});
+        self.emit(Op::Yield{src:value});
+        self.emit(Op::ResumeRaw{dst:sent, mode});
+        self.emit(Op::Jump{target:next});
+
+        self.bind_label(ret);
+        self.emit_return(value)?;
+
+        self.bind_label(done);
+        self.emit(Op::Move{dst, src:value});
+        Ok(())
+    }
+
+    /// evaluate an element of an optional chain.
+    ///
+    /// `exit` is the label a short circuited `?.` jumps to, created by the first one.
+    fn chain(&mut self, expr:&'a Expr, dst:Reg, exit:&mut Option<Label>) -> Result<(), Error>{
+        match expr{
+            Expr::Member(m) => self.member(m, dst, false, exit),
+            Expr::Call(c) => self.call(c, dst, false, exit),
+            Expr::OptChain(o) => match o.expr.as_ref(){
+                Expr::Member(m) => self.member(m, dst, true, exit),
+                Expr::Call(c) => self.call(c, dst, true, exit),
+                _ => Err(Error::SyntaxError("Invalid optional chain".to_string()))
+            },
+            e => self.expr(e, dst)
+        }
+    }
+
+    fn chain_exit(&mut self, exit:&mut Option<Label>) -> Label{
+        match exit{
+            Some(l) => *l,
+            None => {
+                let l = self.new_label();
+                *exit = Some(l);
+                l
+            }
+        }
+    }
+
+    /// `obj.prop`, `obj?.prop` if optional
+    fn member(&mut self, m:&'a MemberExpr, dst:Reg, optional:bool, exit:&mut Option<Label>) -> Result<(), Error>{
+        let mark = self.next_reg;
+        let (obj, key) = (self.alloc(), self.alloc());
+        self.member_object(m, obj, optional, exit)?;
+        self.prop(&m.prop, m.computed, key)?;
+        self.emit(Op::GetMember{dst, obj, key});
+        self.next_reg = mark;
+        Ok(())
+    }
+
+    fn member_object(&mut self, m:&'a MemberExpr, obj:Reg, optional:bool, exit:&mut Option<Label>) -> Result<(), Error>{
+        match &m.obj{
+            ExprOrSuper::Expr(e) => self.chain(e, obj, exit)?,
+            ExprOrSuper::Super(_) => return Err(Error::Unimplemented("super in bytecode."))
+        };
+        if optional{
+            let target = self.chain_exit(exit);
+            self.emit(Op::JumpIfNullish{src:obj, target});
+        }
+        Ok(())
+    }
+
+    /// `callee(args)`, `callee?.(args)` if optional.
+    ///
+    /// member calls pass the object as this, other calls the `this` of the caller like compiled code.
+    fn call(&mut self, c:&'a CallExpr, dst:Reg, optional:bool, exit:&mut Option<Label>) -> Result<(), Error>{
+        let mark = self.next_reg;
+        let callee = match &c.callee{
+            ExprOrSuper::Expr(e) => e.as_ref(),
+            ExprOrSuper::Super(_) => return Err(Error::Unimplemented("super in bytecode."))
+        };
+
+        let (func, this) = (self.alloc(), self.alloc());
+        let member = match callee{
+            Expr::Member(m) => Some((m, false)),
+            Expr::OptChain(o) => match o.expr.as_ref(){
+                Expr::Member(m) => Some((m, true)),
+                _ => None
+            },
+            _ => None
+        };
+
+        match member{
+            Some((m, optional_member)) => {
+                let key = self.alloc();
+                self.member_object(m, this, optional_member, exit)?;
+                self.prop(&m.prop, m.computed, key)?;
+                self.emit(Op::GetMember{dst:func, obj:this, key});
+            },
+            None => {
+                self.chain(callee, func, exit)?;
+                self.emit(Op::LoadThis{dst:this});
+            }
+        }
+
+        if optional{
+            let target = self.chain_exit(exit);
+            self.emit(Op::JumpIfNullish{src:func, target});
+        }
+
+        if c.args.iter().any(|a|a.spread.is_some()){
+            let array = self.spread_args(&c.args)?;
+            self.emit(Op::CallSpread{dst, callee:func, this, array});
+        } else{
+            let (args, argc) = self.args(&c.args)?;
+            self.emit(Op::Call{dst, callee:func, this, args, argc});
+        }
+        self.next_reg = mark;
+        Ok(())
+    }
+
+    /// evaluate the arguments into consecutive registers
+    fn args(&mut self, args:&'a [ExprOrSpread]) -> Result<(Reg, u32), Error>{
+        let start = self.alloc_n(args.len() as u32);
+        for (i, a) in args.iter().enumerate(){
+            self.expr(&a.expr, start + i as u32)?;
+        }
+        Ok((start, args.len() as u32))
+    }
+
+    /// collect arguments with spread elements into an array
+    fn spread_args(&mut self, args:&'a [ExprOrSpread]) -> Result<Reg, Error>{
+        let (array, value) = (self.alloc(), self.alloc());
+        self.emit(Op::NewArray{dst:array});
+        for a in args{
+            self.expr(&a.expr, value)?;
+            if a.spread.is_some(){
+                self.emit(Op::ArraySpread{array, src:value});
+            } else{
+                self.emit(Op::ArrayPush{array, src:value});
+            }
+        }
+        Ok(array)
+    }
+
+    fn assign(&mut self, a:&'a AssignExpr, dst:Reg) -> Result<(), Error>{
+        let target = match &a.left{
+            PatOrExpr::Expr(e) => unparen(e),
+            PatOrExpr::Pat(p) => match p.as_ref(){
+                Pat::Expr(e) => unparen(e),
+                Pat::Ident(i) => return self.assign_ident(a, &i.id.sym, dst),
+                p => {
+                    if a.op != AssignOp::Assign{
+                        return Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+                    }
+                    self.expr(&a.right, dst)?;
+                    return self.bind_pat(p, dst)
+                }
+            }
+        };
+
+        match target{
+            Expr::Ident(i) => self.assign_ident(a, &i.sym, dst),
+            Expr::Member(m) => {
+                // the reference is evaluated before the value
+                let (obj, key) = (self.alloc(), self.alloc());
+                self.reference(m, obj, key)?;
+
+                if a.op == AssignOp::Assign{
+                    self.expr(&a.right, dst)?;
+                    self.emit(Op::SetMember{obj, key, src:dst});
+                    return Ok(())
+                }
+
+                self.emit(Op::GetMember{dst, obj, key});
+                let end = self.new_label();
+                self.assign_op(a, dst, end)?;
+                self.emit(Op::SetMember{obj, key, src:dst});
+                self.bind_label(end);
+                Ok(())
+            },
+            _ => Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+        }
+    }
+
+    fn assign_ident(&mut self, a:&'a AssignExpr, name:&str, dst:Reg) -> Result<(), Error>{
+        if a.op == AssignOp::Assign{
+            self.expr(&a.right, dst)?;
+            self.store(name, dst);
+            return Ok(())
+        }
+
+        self.load_ident(name, dst);
+        let end = self.new_label();
+        self.assign_op(a, dst, end)?;
+        self.store(name, dst);
+        self.bind_label(end);
+        Ok(())
+    }
+
+    /// `dst = dst op value` of a compound assignment.
+    ///
+    /// logical assignments jump to `end` with `dst` unchanged if they short circuit,
+    /// the value is only evaluated if they do not.
+    fn assign_op(&mut self, a:&'a AssignExpr, dst:Reg, end:Label) -> Result<(), Error>{
+        match a.op{
+            AssignOp::AndAssign => self.emit(Op::JumpIfFalse{src:dst, target:end}),
+            AssignOp::OrAssign => self.emit(Op::JumpIfTrue{src:dst, target:end}),
+            AssignOp::NullishAssign => self.emit(Op::JumpIfNotNullish{src:dst, target:end}),
+            _ => {}
+        };
+
+        let value = self.alloc();
+        self.expr(&a.right, value)?;
+
+        let op = match a.op{
+            AssignOp::AndAssign | AssignOp::OrAssign | AssignOp::NullishAssign => {
+                self.emit(Op::Move{dst, src:value});
+                return Ok(())
+            },
+            AssignOp::AddAssign => BinOp::Add,
+            AssignOp::SubAssign => BinOp::Sub,
+            AssignOp::MulAssign => BinOp::Mul,
+            AssignOp::DivAssign => BinOp::Div,
+            AssignOp::ModAssign => BinOp::Mod,
+            AssignOp::ExpAssign => BinOp::Exp,
+            AssignOp::BitAndAssign => BinOp::BitAnd,
+            AssignOp::BitOrAssign => BinOp::BitOr,
+            AssignOp::BitXorAssign => BinOp::BitXor,
+            AssignOp::LShiftAssign => BinOp::Shl,
+            AssignOp::RShiftAssign => BinOp::Shr,
+            AssignOp::ZeroFillRShiftAssign => BinOp::UShr,
+            AssignOp::Assign => unreachable!()
+        };
+        self.emit(Op::Binary{op:op as u32, dst, lhs:dst, rhs:value});
+        Ok(())
+    }
+
+    fn function(&mut self, func:&'a Function, name:Option<&str>, dst:Reg) -> Result<(), Error>{
+        let params:Vec<&Pat> = func.params.iter().map(|p|&p.pat).collect();
+        let body = match &func.body{
+            Some(b) => Body::Stmts(&b.stmts),
+            None => Body::Stmts(&[])
+        };
+        self.closure(&params, body, func.is_async, func.is_generator, false, name, dst)
+    }
+
+    /// compile a function into a code block of this one and create it into `dst`
+    fn closure(&mut self, params:&[&'a Pat], body:Body<'a>, is_async:bool, is_generator:bool, is_arrow:bool, name:Option<&str>, dst:Reg) -> Result<(), Error>{
+        if is_async && is_generator{
+            return Err(Error::Unimplemented("async generator functions."))
+        }
+
+        let (stmts, expr) = match body{
+            Body::Stmts(s) => (s, None),
+            Body::Expr(e) => (&[][..], Some(e))
+        };
+
+        let captured = captured_names_in_function(params, stmts, expr);
+        let mut f = FunctionCompiler::new(captured, false, is_async, is_generator);
+
+        // parameters, `var` and the top level declarations of the body share the function scope
+        let mut names = Vec::new();
+        for p in params{
+            let mut n = Vec::new();
+            pat_names(p, &mut n);
+            names.extend(n.into_iter().map(|n|(n, 0)));
+        }
+        names.extend(var_names(stmts.iter()).into_iter().map(|n|(n, 0)));
+        names.extend(lexical_names(stmts.iter(), true));
+        f.enter_scope(names, true);
+
+        let mark = f.next_reg;
+        let arg = f.alloc();
+        for (i, p) in params.iter().copied().enumerate(){
+            // `...args` collects the remaining arguments
+            let p = match p{
+                Pat::Rest(r) => {
+                    f.emit(Op::RestArgs{dst:arg, index:i as u32});
+                    r.arg.as_ref()
+                },
+                p => {
+                    f.emit(Op::LoadArg{dst:arg, index:i as u32});
+                    p
+                }
+            };
+            f.bind_decl(p, arg)?;
+        }
+        f.next_reg = mark;
+
+        f.hoist_functions(stmts.iter())?;
+        for s in stmts{
+            f.stmt(s, None)?;
+        }
+
+        let re = f.alloc();
+        match expr{
+            Some(e) => f.expr(e, re)?,
+            None => f.emit(Op::LoadUndefined{dst:re})
+        };
+        f.emit(Op::Return{src:re});
+
+        // the free names of the inner function are captured from this one
+        for index in f.free.clone(){
+            let name = f.block.names[index as usize].clone();
+            self.resolve(&name);
+        }
+
+        let block = f.finish(name, params.len() as u32, is_arrow);
+        self.block.functions.push(Arc::new(block));
+        let index = (self.block.functions.len() - 1) as u32;
+        self.emit(Op::Closure{dst, index});
+        Ok(())
+    }
+}
+
+/// the lexical declarations of a list of statements with the kind passed to `DeclareName`
+fn lexical_names<'a, I:Iterator<Item = &'a Stmt>>(stmts:I, top_level:bool) -> Vec<(String, u32)>{
+    scope::lexical_names(stmts, top_level).into_iter().map(|(n, k)|(n, kind_of(k))).collect()
+}
+
+/// the names declared by a declaration with the kind passed to `DeclareName`
+fn decl_names(v:&VarDecl) -> Vec<(String, u32)>{
+    let mut names = Vec::new();
+    for d in &v.decls{
+        pat_names(&d.name, &mut names);
+    }
+    names.into_iter().map(|n|(n, kind_of(v.kind))).collect()
+}
+
+fn kind_of(kind:VarDeclKind) -> u32{
+    match kind{
+        VarDeclKind::Var => 0,
+        VarDeclKind::Let => 1,
+        VarDeclKind::Const => 2,
+    }
+}
+
+fn binary_op(op:BinaryOp) -> Option<BinOp>{
+    Some(match op{
+        BinaryOp::Add => BinOp::Add,
+        BinaryOp::Sub => BinOp::Sub,
+        BinaryOp::Mul => BinOp::Mul,
+        BinaryOp::Div => BinOp::Div,
+        BinaryOp::Mod => BinOp::Mod,
+        BinaryOp::Exp => BinOp::Exp,
+        BinaryOp::BitAnd => BinOp::BitAnd,
+        BinaryOp::BitOr => BinOp::BitOr,
+        BinaryOp::BitXor => BinOp::BitXor,
+        BinaryOp::LShift => BinOp::Shl,
+        BinaryOp::RShift => BinOp::Shr,
+        BinaryOp::ZeroFillRShift => BinOp::UShr,
+        BinaryOp::EqEq => BinOp::EqEq,
+        BinaryOp::NotEq => BinOp::NotEq,
+        BinaryOp::EqEqEq => BinOp::EqEqEq,
+        BinaryOp::NotEqEq => BinOp::NotEqEq,
+        BinaryOp::Lt => BinOp::Lt,
+        BinaryOp::LtEq => BinOp::LtEq,
+        BinaryOp::Gt => BinOp::Gt,
+        BinaryOp::GtEq => BinOp::GtEq,
+        BinaryOp::In => BinOp::In,
+        BinaryOp::InstanceOf => BinOp::InstanceOf,
+        BinaryOp::LogicalAnd |
+        BinaryOp::LogicalOr |
+        BinaryOp::NullishCoalescing => return None
+    })
+}
+
+fn prop_name_str(key:&PropName) -> Option<&str>{
+    match key{
+        PropName::Ident(i) => Some(&i.sym),
+        PropName::Str(s) => Some(&s.value),
+        _ => None
+    }
+}
+
+fn unparen(e:&Expr) -> &Expr{
+    let mut e = e;
+    while let Expr::Paren(p) = e{
+        e = &p.expr;
+    }
+    e
+}
@@ -0,0 +1,430 @@
+use std::sync::Arc;
+
+use crate::builtins::generator::Generator;
+use crate::builtins::{self, Array, JObject};
+use crate::error::Error;
+use crate::operator;
+use crate::utils::ToMutable;
+use crate::value::{JValue, JValueEnum};
+use crate::vm::{self, VmContext};
+
+use super::{baseline, BinOp, CodeBlock, Op, UnOp, NO_REG};
+
+/// the state of a running code block, kept by generators between resumes
+struct Frame{
+    code:Arc<CodeBlock>,
+    registers:Vec<JValue>,
+    pc:u32,
+    this:JValue,
+    args:Vec<JValue>,
+    /// the contexts pushed by `PushScope`, innermost last
+    scopes:Vec<*mut VmContext>,
+    /// loop iterations are counted by the baseline tier, false for scripts and generators
+    tiered:bool,
+}
+
+/// how the dispatch loop stopped
+enum Exit{
+    Return(JValue),
+    /// the generator or async function is suspended with the value
+    Yield(JValue),
+}
+
+impl Frame{
+    fn new(code:Arc<CodeBlock>, this:JValue, args:&[JValue]) -> Self{
+        Self{
+            registers:vec![JValue::Undefined;code.registers as usize],
+            code,
+            pc:0,
+            this,
+            args:args.to_vec(),
+            scopes:Vec::new(),
+            tiered:false,
+        }
+    }
+
+    /// the innermost context of the frame
+    fn context(&self, base:*mut VmContext) -> &'static mut VmContext{
+        let ctx = self.scopes.last().copied().unwrap_or(base);
+        unsafe{ctx.as_mut().unwrap()}
+    }
+
+    /// leave the scopes pushed above `depth`
+    fn pop_scopes(&mut self, depth:usize){
+        while self.scopes.len() > depth{
+            let ctx = self.scopes.pop().unwrap();
+            unsafe{ctx.as_mut().unwrap().done()};
+        }
+    }
+}
+
+/// run a script compiled by `compile_script` in `vmctx`, return the completion value
+pub(crate) fn execute_script(vmctx:&mut VmContext, code:&Arc<CodeBlock>, this:JValue) -> Result<JValue, Error>{
+    let mut frame = Frame::new(code.clone(), this, &[]);
+    let (re, ok) = operator::catch(||match run(vmctx, &mut frame){
+        Exit::Return(v) => v,
+        Exit::Yield(_) => unreachable!("yield outside of a generator")
+    });
+
+    if ok{
+        Ok(re)
+    } else{
+        Err(Error::Value(re))
+    }
+}
+
+/// call a function created by `Closure`.
+///
+/// functions called often run the code compiled by the baseline tier.
+/// generators and async functions are called on every resume,
+/// the frame suspended by the last `Yield` is restored from the generator.
+fn call(vmctx:&mut VmContext, code:&Arc<CodeBlock>, this:JValue, args:&[JValue]) -> (JValue, bool){
+    if !(code.is_async || code.is_generator){
+        if let Some(mem) = baseline::tier_up(vmctx, code){
+            return baseline::run(mem, vmctx, this, args)
+        }
+
+        let mut frame = Frame::new(code.clone(), this, args);
+        frame.tiered = true;
+        return operator::catch(||match run(vmctx, &mut frame){
+            Exit::Return(v) => v,
+            Exit::Yield(_) => unreachable!("yield outside of a generator")
+        })
+    }
+
+    let g = vmctx.generator();
+    let mut frame = match g.frame.take().and_then(|f|f.downcast::<Frame>().ok()){
+        Some(f) => *f,
+        None => Frame::new(code.clone(), this, args)
+    };
+
+    let mut yielded = None;
+    let (re, ok) = operator::catch(||match run(vmctx, &mut frame){
+        Exit::Return(v) => v,
+        Exit::Yield(v) => {
+            yielded = Some(v);
+            JValue::Undefined
+        }
+    });
+    if let Some(v) = yielded{
+        g.frame = Some(Box::new(frame));
+        Generator::yield_raw(vmctx, std::ptr::null_mut(), v, 1);
+    }
+    (re, ok)
+}
+
+/// run the frame until it returns or yields.
+///
+/// an exception thrown by an instruction inside a protected range jumps to its handler,
+/// other exceptions leave the scopes of the frame and are thrown to the caller.
+fn run(vmctx:&mut VmContext, frame:&mut Frame) -> Exit{
+    let base = vmctx as *mut VmContext;
+    loop{
+        let mut exit = None;
+        let (e, ok) = operator::catch(||{
+            exit = Some(dispatch(base, frame));
+            JValue::Undefined
+        });
+        if ok{
+            return exit.unwrap()
+        }
+
+        match frame.code.find_handler(frame.pc - 1){
+            Some(h) => {
+                frame.pop_scopes(h.scopes as usize);
+                frame.registers[h.register as usize] = e;
+                frame.pc = h.target;
+            },
+            None => {
+                frame.pop_scopes(0);
+                operator::throw(e)
+            }
+        }
+    }
+}
+
+fn dispatch(base:*mut VmContext, frame:&mut Frame) -> Exit{
+    let code = frame.code.clone();
+
+    macro_rules! reg {
+        ($r:expr) => {
+            frame.registers[$r as usize]
+        };
+    }
+
+    loop{
+        let pc = frame.pc;
+        let op = code.code[pc as usize];
+        frame.pc += 1;
+
+        match op{
+            Op::LoadConst{dst, index} => {
+                let ctx = frame.context(base);
+                let mut linked = code.link(ctx.runtime);
+                reg!(dst) = code.constant(&mut linked, index);
+            },
+            Op::LoadUndefined{dst} => reg!(dst) = JValue::Undefined,
+            Op::LoadThis{dst} => reg!(dst) = frame.this,
+            Op::Move{dst, src} => reg!(dst) = reg!(src),
+            Op::LoadArg{dst, index} => reg!(dst) = frame.args.get(index as usize).copied().unwrap_or(JValue::Undefined),
+            Op::RestArgs{dst, index} => {
+                reg!(dst) = Array::new(JObject::new(), frame.args.get(index as usize..).unwrap_or(&[]));
+            },
+
+            Op::GetName{dst, name} => {
+                let ctx = frame.context(base);
+                let name = name_id(&code, ctx, name);
+                reg!(dst) = ctx.get_variable(name);
+            },
+            Op::SetName{name, src} => {
+                let ctx = frame.context(base);
+                let name = name_id(&code, ctx, name);
+                ctx.set_variable(name, reg!(src));
+            },
+            Op::DeclareName{name, src, kind} => {
+                let ctx = frame.context(base);
+                let name = name_id(&code, ctx, name);
+                ctx.declare_variable(name, reg!(src), kind as i8);
+            },
+            Op::DeclareUninitialized{name, kind} => {
+                let ctx = frame.context(base);
+                let name = name_id(&code, ctx, name);
+                ctx.declare_variable(name, vm::uninitialized(), kind as i8);
+            },
+            Op::InitName{name, src} => {
+                let ctx = frame.context(base);
+                let name = name_id(&code, ctx, name);
+                ctx.initialize_variable(name, reg!(src));
+            },
+            Op::ThrowConstAssignment{..} => operator::throw(frame.context(base).const_error()),
+            Op::TypeofName{dst, name} => {
+                let ctx = frame.context(base);
+                let name = name_id(&code, ctx, name);
+                reg!(dst) = ctx.typeof_variable(name);
+            },
+            Op::PushScope{} => {
+                let ctx = frame.context(base).new_child();
+                frame.scopes.push(ctx);
+            },
+            Op::PopScope{} => {
+                let depth = frame.scopes.len() - 1;
+                frame.pop_scopes(depth);
+            },
+
+            Op::Binary{op, dst, lhs, rhs} => {
+                reg!(dst) = BinOp::from_u32(op).unwrap().apply(reg!(lhs), reg!(rhs));
+            },
+            Op::Unary{op, dst, src} => reg!(dst) = UnOp::from_u32(op).unwrap().apply(reg!(src)),
+            Op::Increment{dst, src} => reg!(dst) = reg!(src).increment(),
+            Op::Decrement{dst, src} => reg!(dst) = reg!(src).decrement(),
+            Op::ToNumeric{dst, src} => reg!(dst) = reg!(src).to_numeric(),
+
+            Op::Jump{target} => frame.pc = target,
+            Op::JumpIfTrue{src, target} => if reg!(src).to_bool(){
+                frame.pc = target;
+            },
+            Op::JumpIfFalse{src, target} => if !reg!(src).to_bool(){
+                frame.pc = target;
+            },
+            Op::JumpIfNullish{src, target} => if reg!(src).is_nullish(){
+                frame.pc = target;
+            },
+            Op::JumpIfNotNullish{src, target} => if !reg!(src).is_nullish(){
+                frame.pc = target;
+            },
+            Op::JumpIfNotUndefined{src, target} => if !reg!(src).is_undefined(){
+                frame.pc = target;
+            },
+            Op::Return{src} => return Exit::Return(reg!(src)),
+            Op::Throw{src} => operator::throw(reg!(src)),
+
+            Op::NewObject{dst} => reg!(dst) = JValue::Object(JObject::new()),
+            Op::NewArray{dst} => reg!(dst) = Array::new(JObject::new(), &[]),
+            Op::ArrayPush{array, src} => array_values(reg!(array)).push(reg!(src)),
+            Op::ArraySpread{array, src} => {
+                let values = operator::IteratorCollect(reg!(src));
+                array_values(reg!(array)).extend(values);
+            },
+            Op::GetMember{dst, obj, key} => reg!(dst) = reg!(obj).member(reg!(key)),
+            Op::SetMember{obj, key, src} => reg!(obj).set_member(reg!(key), reg!(src)),
+            Op::DeleteMember{dst, obj, key} => reg!(dst) = reg!(obj).delete_member(reg!(key)),
+            Op::SpreadObject{obj, src} => reg!(obj).set_member_spread(reg!(src)),
+            Op::DefineMethod{obj, key, func, kind} => {
+                builtins::Function::define_method(reg!(obj), reg!(key), reg!(func), kind as i8);
+            },
+            Op::Template{dst, index} => {
+                let ctx = frame.context(base);
+                let mut linked = code.link(ctx.runtime);
+                reg!(dst) = code.constant(&mut linked, index);
+            },
+            Op::TemplateString{dst, args, argc} => {
+                reg!(dst) = unsafe{operator::tpl_new(frame.registers.as_ptr().add(args as usize), argc as i64)};
+            },
+
+            Op::Closure{dst, index} => {
+                let ctx = frame.context(base);
+                reg!(dst) = create_function(ctx, code.functions[index as usize].clone(), frame.this);
+            },
+            Op::Call{dst, callee, this, args, argc} => {
+                let ctx = frame.context(base);
+                let argv = unsafe{frame.registers.as_ptr().add(args as usize)};
+                reg!(dst) = check(unsafe{reg!(callee).call_raw(ctx, reg!(this), argv, argc as i64, false)});
+            },
+            Op::CallSpread{dst, callee, this, array} => {
+                let ctx = frame.context(base);
+                let args = array_values(reg!(array)).clone();
+                reg!(dst) = check(unsafe{reg!(callee).call_raw(ctx, reg!(this), args.as_ptr(), args.len() as i64, false)});
+            },
+            Op::New{dst, callee, args, argc} => {
+                let ctx = frame.context(base);
+                let argv = unsafe{frame.registers.as_ptr().add(args as usize)};
+                reg!(dst) = check(unsafe{reg!(callee).new_raw(ctx, argv, argc as i64, false)});
+            },
+            Op::NewSpread{dst, callee, array} => {
+                let ctx = frame.context(base);
+                let args = array_values(reg!(array)).clone();
+                reg!(dst) = check(unsafe{reg!(callee).new_raw(ctx, args.as_ptr(), args.len() as i64, false)});
+            },
+
+            Op::GetIterator{dst, src} => reg!(dst) = operator::get_iterator(reg!(src)),
+            Op::ForInIterator{dst, src} => reg!(dst) = operator::for_in_iterator(reg!(src)),
+            Op::IteratorNext{dst, iterator, done} => {
+                let (v, d) = operator::iterator_next(reg!(iterator));
+                if d{
+                    frame.pc = done;
+                } else{
+                    reg!(dst) = v;
+                }
+            },
+            Op::IteratorStep{dst, iterator, done} => {
+                if reg!(done).to_bool(){
+                    reg!(dst) = JValue::Undefined;
+                } else{
+                    // an iterator throwing from `next` is not closed
+                    reg!(done) = JValue::Boolean(true);
+                    let (v, d) = operator::iterator_next(reg!(iterator));
+                    if d{
+                        reg!(dst) = JValue::Undefined;
+                    } else{
+                        reg!(done) = JValue::Boolean(false);
+                        reg!(dst) = v;
+                    }
+                }
+            },
+            Op::IteratorRest{dst, iterator, done} => {
+                let d = reg!(done).to_bool();
+                reg!(done) = JValue::Boolean(true);
+                reg!(dst) = check(operator::iterator_rest_raw(reg!(iterator), d));
+            },
+            Op::IteratorClose{iterator, done, quiet} => {
+                if done == NO_REG || !reg!(done).to_bool(){
+                    if quiet == 1{
+                        operator::iterator_close_raw(reg!(iterator));
+                    } else{
+                        operator::iterator_close(reg!(iterator));
+                    }
+                }
+            },
+            Op::RequireObjectCoercible{src} => {
+                check(operator::require_object_coercible_raw(reg!(src)));
+            },
+            Op::ObjectRest{dst, src, keys, count} => {
+                let keys = unsafe{frame.registers.as_ptr().add(keys as usize)};
+                reg!(dst) = check(unsafe{operator::object_rest_raw(reg!(src), keys, count as i64)});
+            },
+
+            Op::Yield{src} => return Exit::Yield(reg!(src)),
+            Op::Resume{dst, mode} => {
+                let (v, m) = Generator::resume_raw(unsafe{base.as_mut().unwrap()});
+                reg!(mode) = JValue::Number(m as f64);
+                // generator.throw and rejected awaits throw at the suspension point
+                if m == 1{
+                    operator::throw(v)
+                }
+                reg!(dst) = v;
+            },
+            Op::ResumeRaw{dst, mode} => {
+                let (v, m) = Generator::resume_raw(unsafe{base.as_mut().unwrap()});
+                reg!(mode) = JValue::Number(m as f64);
+                reg!(dst) = v;
+            },
+            Op::JumpIfMode{mode, expect, target} => if mode_of(reg!(mode)) == expect as i8{
+                frame.pc = target;
+            },
+            Op::Delegate{dst, iterator, sent, mode, done, ret} => {
+                let ctx = frame.context(base);
+                let (v, status) = Generator::delegate_raw(ctx, reg!(iterator), reg!(sent), mode_of(reg!(mode)));
+                reg!(dst) = v;
+                match status{
+                    1 => frame.pc = done,
+                    2 => frame.pc = ret,
+                    3 => operator::throw(v),
+                    _ => {}
+                }
+            },
+        }
+
+        // a jump backward is an iteration of a loop
+        if frame.tiered && frame.pc <= pc{
+            baseline::back_edge(frame.context(base), &code);
+        }
+    }
+}
+
+/// create a function running the code block
+pub(super) fn create_function(vmctx:&mut VmContext, code:Arc<CodeBlock>, this:JValue) -> JValue{
+    let (is_async, is_generator) = (code.is_async, code.is_generator);
+
+    let body = code.clone();
+    let func = builtins::Function::from_code(JObject::new(), Arc::new(move |vmctx:&mut VmContext, this:JValue, args:&[JValue]|{
+        call(vmctx, &body, this, args)
+    }), is_async, is_generator);
+
+    // the names resolved outside of the function are captured when it is created
+    let names:Vec<u64> = {
+        let linked = code.link(vmctx.runtime);
+        code.captures.iter().map(|n|linked.names[*n as usize]).collect()
+    };
+    if let Some(f) = func.object().unwrap().inner.function(){
+        f.to_mut().try_capture(unsafe{&mut *(vmctx as *mut VmContext)}, &names);
+    }
+
+    // arrow functions use the `this` of the enclosing function
+    if code.is_arrow{
+        builtins::Function::bind_this(func, vmctx, this);
+    }
+
+    func.set_member("length".into(), JValue::Number(code.length as f64));
+    if let Some(name) = &code.name{
+        func.set_member("name".into(), name.as_str().into());
+    }
+    func
+}
+
+/// the variable id of the name `index` of the code block
+fn name_id(code:&CodeBlock, vmctx:&mut VmContext, index:u32) -> u64{
+    code.link(vmctx.runtime).names[index as usize]
+}
+
+/// the values of an array created by `NewArray`
+pub(super) fn array_values(array:JValue) -> &'static mut Vec<JValue>{
+    match array.object().and_then(|o|o.inner.array()){
+        Some(a) => &mut a.to_mut().values,
+        None => unreachable!("not an array")
+    }
+}
+
+fn mode_of(v:JValue) -> i8{
+    match v.unbox(){
+        JValueEnum::Number(n) => n as i8,
+        _ => 0
+    }
+}
+
+/// throw the value of a raw helper that failed
+fn check((value, ok):(JValue, bool)) -> JValue{
+    if !ok{
+        operator::throw(value)
+    }
+    value
+}
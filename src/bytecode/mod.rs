@@ -0,0 +1,803 @@
+//! a register based bytecode between the syntax tree and the execution tiers.
+//!
+//! the compiler resolves the scopes of a script once: bindings that are not
+//! captured by any inner function live in registers, the others are declared
+//! in the `VmContext` and accessed by name like compiled code does.
+//!
+//! a `CodeBlock` owns no syntax tree, it can be encoded to bytes and cached.
+//!
+//! the bytecode feeds `ExecutionMode::Bytecode` and its baseline tier only.
+//! `ExecutionMode::Jit` still lowers the syntax tree with `BuilderContext`:
+//! the compiler does not cover classes, `super` and private names yet and
+//! the baseline tier does not compile generators and async functions,
+//! the JIT moves onto code blocks once both do.
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use num_bigint::BigInt;
+
+use crate::builtins::regexp::RegExp;
+use crate::builtins::{Array, JObject};
+use crate::error::Error;
+use crate::runtime::Runtime;
+use crate::value::JValue;
+
+pub(crate) mod baseline;
+mod compiler;
+mod interpreter;
+pub(crate) mod scope;
+
+pub(crate) use compiler::compile_script;
+pub(crate) use interpreter::execute_script;
+
+/// a register of the running frame
+pub(crate) type Reg = u32;
+/// a jump target, an index into the code once the code block is finished
+pub(crate) type Label = u32;
+
+/// an operand that is not set
+pub(crate) const NO_REG:Reg = u32::MAX;
+
+/// a binary operator, the logical operators are compiled into jumps
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub(crate) enum BinOp{
+    Add, Sub, Mul, Div, Mod, Exp,
+    BitAnd, BitOr, BitXor, Shl, Shr, UShr,
+    EqEq, NotEq, EqEqEq, NotEqEq,
+    Lt, LtEq, Gt, GtEq,
+    In, InstanceOf,
+}
+
+impl BinOp{
+    const ALL:[BinOp;22] = [
+        BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div, BinOp::Mod, BinOp::Exp,
+        BinOp::BitAnd, BinOp::BitOr, BinOp::BitXor, BinOp::Shl, BinOp::Shr, BinOp::UShr,
+        BinOp::EqEq, BinOp::NotEq, BinOp::EqEqEq, BinOp::NotEqEq,
+        BinOp::Lt, BinOp::LtEq, BinOp::Gt, BinOp::GtEq,
+        BinOp::In, BinOp::InstanceOf,
+    ];
+
+    pub(crate) fn from_u32(v:u32) -> Option<Self>{
+        Self::ALL.get(v as usize).copied()
+    }
+
+    pub(crate) fn apply(self, lhs:JValue, rhs:JValue) -> JValue{
+        match self{
+            BinOp::Add => lhs + rhs,
+            BinOp::Sub => lhs - rhs,
+            BinOp::Mul => lhs * rhs,
+            BinOp::Div => lhs / rhs,
+            BinOp::Mod => lhs % rhs,
+            BinOp::Exp => lhs.exp(rhs),
+            BinOp::BitAnd => lhs & rhs,
+            BinOp::BitOr => lhs | rhs,
+            BinOp::BitXor => lhs ^ rhs,
+            BinOp::Shl => lhs << rhs,
+            BinOp::Shr => lhs >> rhs,
+            BinOp::UShr => lhs.unsignedRShift(rhs),
+            BinOp::EqEq => lhs.eqeq(rhs),
+            BinOp::NotEq => lhs.noteq(rhs),
+            BinOp::EqEqEq => lhs.eqeqeq(rhs),
+            BinOp::NotEqEq => lhs.noteqeq(rhs),
+            BinOp::Lt => lhs.lt(rhs),
+            BinOp::LtEq => lhs.lteq(rhs),
+            BinOp::Gt => lhs.gt(rhs),
+            BinOp::GtEq => lhs.gteq(rhs),
+            BinOp::In => lhs.In(rhs),
+            BinOp::InstanceOf => lhs.instanceof(rhs),
+        }
+    }
+}
+
+/// a unary operator, `delete` is compiled into `DeleteMember`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub(crate) enum UnOp{
+    Not, Neg, Plus, BitNot, TypeOf, Void,
+}
+
+impl UnOp{
+    const ALL:[UnOp;6] = [UnOp::Not, UnOp::Neg, UnOp::Plus, UnOp::BitNot, UnOp::TypeOf, UnOp::Void];
+
+    pub(crate) fn from_u32(v:u32) -> Option<Self>{
+        Self::ALL.get(v as usize).copied()
+    }
+
+    pub(crate) fn apply(self, v:JValue) -> JValue{
+        match self{
+            UnOp::Not => v.not(),
+            UnOp::Neg => v.neg(),
+            UnOp::Plus => v.to_number(),
+            UnOp::BitNot => v.bitNot(),
+            UnOp::TypeOf => v.typeOf(),
+            UnOp::Void => JValue::Undefined,
+        }
+    }
+}
+
+/// define the instructions with their encoding.
+///
+/// every operand is a u32: a register, a label, an index into a table of the code block or a flag.
+macro_rules! define_ops {
+    ($( $(#[$doc:meta])* $name:ident { $( $field:ident ),* } = $tag:literal ),* $(,)?) => {
+        #[derive(Clone, Copy, Debug)]
+        pub(crate) enum Op{
+            $( $(#[$doc])* $name{ $( $field:u32 ),* } ),*
+        }
+
+        impl Op{
+            fn encode(&self, w:&mut Writer){
+                match self{
+                    $( Op::$name{ $( $field ),* } => {
+                        w.u8($tag);
+                        $( w.u32(*$field); )*
+                    } ),*
+                }
+            }
+
+            fn decode(r:&mut Reader) -> Result<Self, Error>{
+                match r.u8()?{
+                    $( $tag => Ok(Op::$name{ $( $field:r.u32()? ),* }), )*
+                    t => Err(Error::SyntaxError(format!("invalid bytecode instruction {}", t)))
+                }
+            }
+        }
+    };
+}
+
+define_ops!{
+    /// load the constant `index` of the code block
+    LoadConst{dst, index} = 0,
+    LoadUndefined{dst} = 1,
+    LoadThis{dst} = 2,
+    Move{dst, src} = 3,
+    /// the argument `index`, undefined if not passed
+    LoadArg{dst, index} = 4,
+    /// the arguments from `index` as an array
+    RestArgs{dst, index} = 5,
+
+    /// read the variable `name` from the context, for captured and free bindings
+    GetName{dst, name} = 10,
+    SetName{name, src} = 11,
+    /// declare `name` in the innermost scope, kind 0 var, 1 let, 2 const
+    DeclareName{name, src, kind} = 12,
+    /// `typeof name`, does not throw if the variable is not declared
+    TypeofName{dst, name} = 13,
+    /// enter a scope declaring captured bindings
+    PushScope{} = 14,
+    PopScope{} = 15,
+    /// declare the `let` or `const` `name` in the innermost scope, reading it throws until initialized
+    DeclareUninitialized{name, kind} = 16,
+    /// run the declaration of `name`, the binding is assigned even if `const`
+    InitName{name, src} = 17,
+    /// throw the TypeError of an assignment to the `const` `name`
+    ThrowConstAssignment{name} = 18,
+
+    Binary{op, dst, lhs, rhs} = 20,
+    Unary{op, dst, src} = 21,
+    Increment{dst, src} = 22,
+    Decrement{dst, src} = 23,
+    ToNumeric{dst, src} = 24,
+
+    Jump{target} = 30,
+    JumpIfTrue{src, target} = 31,
+    JumpIfFalse{src, target} = 32,
+    JumpIfNullish{src, target} = 33,
+    JumpIfNotNullish{src, target} = 34,
+    JumpIfNotUndefined{src, target} = 35,
+    Return{src} = 36,
+    Throw{src} = 37,
+
+    NewObject{dst} = 40,
+    NewArray{dst} = 41,
+    ArrayPush{array, src} = 42,
+    /// push the values of the iterable `src`
+    ArraySpread{array, src} = 43,
+    GetMember{dst, obj, key} = 44,
+    SetMember{obj, key, src} = 45,
+    DeleteMember{dst, obj, key} = 46,
+    /// copy the own properties of `src`
+    SpreadObject{obj, src} = 47,
+    /// kind 0 method, 1 getter, 2 setter
+    DefineMethod{obj, key, func, kind} = 48,
+    /// the template object of the constant `index`, created once per code block
+    Template{dst, index} = 49,
+    /// concat `argc` registers from `args`
+    TemplateString{dst, args, argc} = 50,
+
+    /// create the function `index` of the code block
+    Closure{dst, index} = 60,
+    /// call with the `argc` registers from `args`
+    Call{dst, callee, this, args, argc} = 61,
+    /// call with the values of the array `array`
+    CallSpread{dst, callee, this, array} = 62,
+    New{dst, callee, args, argc} = 63,
+    NewSpread{dst, callee, array} = 64,
+
+    GetIterator{dst, src} = 70,
+    ForInIterator{dst, src} = 71,
+    /// the next value of the iterator, jump to `done` once the iterator is done
+    IteratorNext{dst, iterator, done} = 72,
+    /// the next value of a destructured iterator, undefined once the register `done` is true
+    IteratorStep{dst, iterator, done} = 73,
+    /// the remaining values of a destructured iterator as an array
+    IteratorRest{dst, iterator, done} = 74,
+    /// close the iterator unless the register `done` is true or unset,
+    /// errors are ignored if `quiet` is 1.
+    IteratorClose{iterator, done, quiet} = 75,
+    RequireObjectCoercible{src} = 76,
+    /// copy the own properties of `src` except the `count` keys from `keys`
+    ObjectRest{dst, src, keys, count} = 77,
+
+    /// suspend the generator or async function with the value
+    Yield{src} = 80,
+    /// the value sent to the resumed generator, throws on `throw` and sets the mode
+    Resume{dst, mode} = 81,
+    /// like `Resume` but does not throw, used by `yield*`
+    ResumeRaw{dst, mode} = 82,
+    JumpIfMode{mode, expect, target} = 83,
+    /// one step of `yield*`: continue to yield the value,
+    /// jump to `done` with the result or to `ret` if the generator should return.
+    Delegate{dst, iterator, sent, mode, done, ret} = 84,
+}
+
+impl Op{
+    /// replace the labels of the instruction by code positions
+    fn resolve_labels(&mut self, positions:&[u32]){
+        match self{
+            Op::Jump{target} |
+            Op::JumpIfTrue{target, ..} |
+            Op::JumpIfFalse{target, ..} |
+            Op::JumpIfNullish{target, ..} |
+            Op::JumpIfNotNullish{target, ..} |
+            Op::JumpIfNotUndefined{target, ..} |
+            Op::JumpIfMode{target, ..} => *target = positions[*target as usize],
+            Op::IteratorNext{done, ..} => *done = positions[*done as usize],
+            Op::Delegate{done, ret, ..} => {
+                *done = positions[*done as usize];
+                *ret = positions[*ret as usize];
+            },
+            _ => {}
+        }
+    }
+}
+
+/// a constant of a code block
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Constant{
+    Null,
+    Bool(bool),
+    Number(f64),
+    BigInt(BigInt),
+    String(String),
+    /// a regular expression literal, a new object is created by every evaluation
+    RegExp{exp:String, flags:String},
+    /// the strings of a tagged template, the cooked string is none for invalid escapes
+    Template{cooked:Vec<Option<String>>, raw:Vec<String>},
+}
+
+/// a protected range of code, an exception thrown inside jumps to `target`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Handler{
+    pub(crate) start:u32,
+    pub(crate) end:u32,
+    pub(crate) target:u32,
+    /// the register receiving the exception
+    pub(crate) register:Reg,
+    /// the number of scopes pushed when the range is entered
+    pub(crate) scopes:u32,
+}
+
+/// the code of a script or function
+pub struct CodeBlock{
+    pub(crate) name:Option<String>,
+
+    pub(crate) code:Vec<Op>,
+    pub(crate) constants:Vec<Constant>,
+    /// the names of the variables accessed through the context
+    pub(crate) names:Vec<String>,
+    pub(crate) functions:Vec<Arc<CodeBlock>>,
+    /// innermost handlers first
+    pub(crate) handlers:Vec<Handler>,
+
+    pub(crate) registers:u32,
+    /// the `length` of the function
+    pub(crate) length:u32,
+    pub(crate) is_arrow:bool,
+    pub(crate) is_async:bool,
+    pub(crate) is_generator:bool,
+    /// the names captured by the function when it is created
+    pub(crate) captures:Vec<u32>,
+
+    linked:RefCell<Option<Linked>>,
+}
+
+/// the tables of a code block resolved for a runtime
+pub(crate) struct Linked{
+    runtime:usize,
+    pub(crate) names:Vec<u64>,
+    pub(crate) constants:Vec<JValue>,
+    pub(crate) templates:Vec<Option<JValue>>,
+    /// the calls counted until the code block is compiled by the baseline tier, then its code
+    pub(crate) tier:baseline::Tier,
+}
+
+const MAGIC:&[u8;4] = b"JSBC";
+const VERSION:u32 = 2;
+
+impl CodeBlock{
+    pub(crate) fn new() -> Self{
+        Self{
+            name:None,
+            code:Vec::new(),
+            constants:Vec::new(),
+            names:Vec::new(),
+            functions:Vec::new(),
+            handlers:Vec::new(),
+            registers:0,
+            length:0,
+            is_arrow:false,
+            is_async:false,
+            is_generator:false,
+            captures:Vec::new(),
+            linked:RefCell::new(None),
+        }
+    }
+
+    /// replace the labels of the code by positions once the code is complete
+    pub(crate) fn resolve_labels(&mut self, positions:&[u32]){
+        for op in &mut self.code{
+            op.resolve_labels(positions);
+        }
+        for h in &mut self.handlers{
+            h.target = positions[h.target as usize];
+        }
+    }
+
+    /// the handler of an exception thrown by the instruction at `pc`
+    pub(crate) fn find_handler(&self, pc:u32) -> Option<Handler>{
+        self.handlers.iter().find(|h|h.start <= pc && pc < h.end).copied()
+    }
+
+    /// intern the names and create the constants for the runtime.
+    ///
+    /// a code block is linked again if it runs in another runtime.
+    pub(crate) fn link(&self, runtime:&mut Runtime) -> std::cell::RefMut<'_, Linked>{
+        let id = runtime as *mut Runtime as usize;
+        let mut linked = self.linked.borrow_mut();
+
+        if linked.as_ref().map(|l|l.runtime) != Some(id){
+            let names = self.names.iter().map(|n|runtime.new_variable_name(n) as u64).collect();
+            let constants = self.constants.iter().map(|c|{
+                let v = match c{
+                    Constant::Null => JValue::Null,
+                    Constant::Bool(b) => JValue::Boolean(*b),
+                    Constant::Number(n) => JValue::Number(*n),
+                    Constant::BigInt(b) => JValue::BigInt(b.clone()),
+                    Constant::String(s) => s.as_str().into(),
+                    Constant::RegExp{..} | Constant::Template{..} => JValue::Undefined
+                };
+                v.keep_alive(true);
+                v
+            }).collect();
+
+            *linked = Some(Linked{
+                runtime:id,
+                names,
+                constants,
+                templates:vec![None;self.constants.len()],
+                tier:baseline::Tier::Interpreted(0),
+            });
+        }
+
+        std::cell::RefMut::map(linked, |l|l.as_mut().unwrap())
+    }
+
+    /// the value of the constant `index`
+    pub(crate) fn constant(&self, linked:&mut Linked, index:u32) -> JValue{
+        match &self.constants[index as usize]{
+            Constant::RegExp{exp, flags} => RegExp::from_str(exp, flags),
+            Constant::Template{cooked, raw} => {
+                // the template object of a call site is created once
+                if let Some(v) = linked.templates[index as usize]{
+                    return v
+                }
+                let cooked:Vec<JValue> = cooked.iter().map(|c|match c{
+                    Some(c) => c.as_str().into(),
+                    None => JValue::Undefined
+                }).collect();
+                let raw:Vec<JValue> = raw.iter().map(|r|r.as_str().into()).collect();
+
+                let raw = Array::new(JObject::new(), &raw);
+                raw.object().unwrap().freeze();
+
+                let strings = Array::new(JObject::new(), &cooked);
+                let obj = strings.object().unwrap();
+                obj.define_own("raw", raw);
+                obj.freeze();
+
+                strings.keep_alive(true);
+                linked.templates[index as usize] = Some(strings);
+                strings
+            },
+            _ => linked.constants[index as usize]
+        }
+    }
+
+    /// encode the code block and its functions
+    pub fn encode(&self) -> Vec<u8>{
+        let mut w = Writer(Vec::new());
+        w.0.extend_from_slice(MAGIC);
+        w.u32(VERSION);
+        self.write(&mut w);
+        w.0
+    }
+
+    /// decode a code block encoded by `encode`
+    pub fn decode(bytes:&[u8]) -> Result<Self, Error>{
+        let mut r = Reader{bytes, pos:0};
+        if r.take(4)? != MAGIC{
+            return Err(Error::SyntaxError("invalid bytecode header".to_string()))
+        }
+        let version = r.u32()?;
+        if version != VERSION{
+            return Err(Error::SyntaxError(format!("unsupported bytecode version {}", version)))
+        }
+        Self::read(&mut r)
+    }
+
+    fn write(&self, w:&mut Writer){
+        match &self.name{
+            Some(n) => {
+                w.u8(1);
+                w.str(n);
+            },
+            None => w.u8(0)
+        }
+
+        w.u32(self.code.len() as u32);
+        for op in &self.code{
+            op.encode(w);
+        }
+
+        w.u32(self.constants.len() as u32);
+        for c in &self.constants{
+            match c{
+                Constant::Null => w.u8(0),
+                Constant::Bool(b) => {
+                    w.u8(1);
+                    w.u8(*b as u8);
+                },
+                Constant::Number(n) => {
+                    w.u8(2);
+                    w.0.extend_from_slice(&n.to_bits().to_le_bytes());
+                },
+                Constant::BigInt(b) => {
+                    w.u8(3);
+                    let bytes = b.to_signed_bytes_le();
+                    w.u32(bytes.len() as u32);
+                    w.0.extend_from_slice(&bytes);
+                },
+                Constant::String(s) => {
+                    w.u8(4);
+                    w.str(s);
+                },
+                Constant::RegExp{exp, flags} => {
+                    w.u8(5);
+                    w.str(exp);
+                    w.str(flags);
+                },
+                Constant::Template{cooked, raw} => {
+                    w.u8(6);
+                    w.u32(cooked.len() as u32);
+                    for (c, r) in cooked.iter().zip(raw){
+                        match c{
+                            Some(c) => {
+                                w.u8(1);
+                                w.str(c);
+                            },
+                            None => w.u8(0)
+                        }
+                        w.str(r);
+                    }
+                }
+            }
+        }
+
+        w.u32(self.names.len() as u32);
+        for n in &self.names{
+            w.str(n);
+        }
+
+        w.u32(self.functions.len() as u32);
+        for f in &self.functions{
+            f.write(w);
+        }
+
+        w.u32(self.handlers.len() as u32);
+        for h in &self.handlers{
+            w.u32(h.start);
+            w.u32(h.end);
+            w.u32(h.target);
+            w.u32(h.register);
+            w.u32(h.scopes);
+        }
+
+        w.u32(self.registers);
+        w.u32(self.length);
+        w.u8(self.is_arrow as u8 | (self.is_async as u8) << 1 | (self.is_generator as u8) << 2);
+
+        w.u32(self.captures.len() as u32);
+        for c in &self.captures{
+            w.u32(*c);
+        }
+    }
+
+    fn read(r:&mut Reader) -> Result<Self, Error>{
+        let mut block = Self::new();
+
+        if r.u8()? == 1{
+            block.name = Some(r.str()?);
+        }
+
+        for _ in 0..r.u32()?{
+            block.code.push(Op::decode(r)?);
+        }
+
+        for _ in 0..r.u32()?{
+            let c = match r.u8()?{
+                0 => Constant::Null,
+                1 => Constant::Bool(r.u8()? != 0),
+                2 => Constant::Number(f64::from_bits(u64::from_le_bytes(r.take(8)?.try_into().unwrap()))),
+                3 => {
+                    let len = r.u32()? as usize;
+                    Constant::BigInt(BigInt::from_signed_bytes_le(r.take(len)?))
+                },
+                4 => Constant::String(r.str()?),
+                5 => Constant::RegExp{exp:r.str()?, flags:r.str()?},
+                6 => {
+                    let (mut cooked, mut raw) = (Vec::new(), Vec::new());
+                    for _ in 0..r.u32()?{
+                        cooked.push(if r.u8()? == 1 {Some(r.str()?)} else {None});
+                        raw.push(r.str()?);
+                    }
+                    Constant::Template{cooked, raw}
+                },
+                t => return Err(Error::SyntaxError(format!("invalid bytecode constant {}", t)))
+            };
+            block.constants.push(c);
+        }
+
+        for _ in 0..r.u32()?{
+            block.names.push(r.str()?);
+        }
+
+        for _ in 0..r.u32()?{
+            block.functions.push(Arc::new(Self::read(r)?));
+        }
+
+        for _ in 0..r.u32()?{
+            block.handlers.push(Handler{
+                start:r.u32()?,
+                end:r.u32()?,
+                target:r.u32()?,
+                register:r.u32()?,
+                scopes:r.u32()?,
+            });
+        }
+
+        block.registers = r.u32()?;
+        block.length = r.u32()?;
+        let flags = r.u8()?;
+        block.is_arrow = flags & 1 != 0;
+        block.is_async = flags & 2 != 0;
+        block.is_generator = flags & 4 != 0;
+
+        for _ in 0..r.u32()?{
+            block.captures.push(r.u32()?);
+        }
+
+        block.validate()?;
+        Ok(block)
+    }
+
+    /// check that the operands of decoded code are in bound,
+    /// the interpreter indexes the tables without checks.
+    fn validate(&self) -> Result<(), Error>{
+        let invalid = ||Error::SyntaxError("invalid bytecode operand".to_string());
+        let len = self.code.len() as u32;
+
+        let reg = |r:u32| r < self.registers || r == NO_REG;
+        let target = |t:u32| t <= len;
+
+        for op in &self.code{
+            let ok = match *op{
+                Op::LoadConst{dst, index} | Op::Template{dst, index} => reg(dst) && (index as usize) < self.constants.len(),
+                Op::GetName{dst, name} | Op::TypeofName{dst, name} => reg(dst) && (name as usize) < self.names.len(),
+                Op::SetName{name, src} | Op::DeclareName{name, src, ..} | Op::InitName{name, src} => reg(src) && (name as usize) < self.names.len(),
+                Op::DeclareUninitialized{name, ..} | Op::ThrowConstAssignment{name} => (name as usize) < self.names.len(),
+                Op::Closure{dst, index} => reg(dst) && (index as usize) < self.functions.len(),
+                Op::Binary{op, dst, lhs, rhs} => BinOp::from_u32(op).is_some() && reg(dst) && reg(lhs) && reg(rhs),
+                Op::Unary{op, dst, src} => UnOp::from_u32(op).is_some() && reg(dst) && reg(src),
+                Op::Jump{target:t} => target(t),
+                Op::JumpIfTrue{src, target:t} | Op::JumpIfFalse{src, target:t} |
+                Op::JumpIfNullish{src, target:t} | Op::JumpIfNotNullish{src, target:t} |
+                Op::JumpIfNotUndefined{src, target:t} => reg(src) && target(t),
+                Op::JumpIfMode{mode, target:t, ..} => reg(mode) && target(t),
+                Op::IteratorNext{dst, iterator, done} => reg(dst) && reg(iterator) && target(done),
+                Op::Delegate{dst, iterator, sent, mode, done, ret} => reg(dst) && reg(iterator) && reg(sent) && reg(mode) && target(done) && target(ret),
+                Op::Call{dst, callee, this, args, argc} => reg(dst) && reg(callee) && reg(this) && (argc == 0 || (args as u64 + argc as u64) <= self.registers as u64),
+                Op::New{dst, callee, args, argc} => reg(dst) && reg(callee) && (argc == 0 || (args as u64 + argc as u64) <= self.registers as u64),
+                Op::TemplateString{dst, args, argc} => reg(dst) && (argc == 0 || (args as u64 + argc as u64) <= self.registers as u64),
+                Op::ObjectRest{dst, src, keys, count} => reg(dst) && reg(src) && (count == 0 || (keys as u64 + count as u64) <= self.registers as u64),
+                Op::LoadUndefined{dst} | Op::LoadThis{dst} | Op::NewObject{dst} | Op::NewArray{dst} |
+                Op::LoadArg{dst, ..} | Op::RestArgs{dst, ..} => reg(dst),
+                Op::Move{dst, src} | Op::Increment{dst, src} | Op::Decrement{dst, src} | Op::ToNumeric{dst, src} |
+                Op::GetIterator{dst, src} | Op::ForInIterator{dst, src} => reg(dst) && reg(src),
+                Op::Return{src} | Op::Throw{src} | Op::Yield{src} | Op::RequireObjectCoercible{src} => reg(src),
+                Op::ArrayPush{array, src} | Op::ArraySpread{array, src} => reg(array) && reg(src),
+                Op::SpreadObject{obj, src} => reg(obj) && reg(src),
+                Op::GetMember{dst, obj, key} | Op::DeleteMember{dst, obj, key} => reg(dst) && reg(obj) && reg(key),
+                Op::SetMember{obj, key, src} => reg(obj) && reg(key) && reg(src),
+                Op::DefineMethod{obj, key, func, ..} => reg(obj) && reg(key) && reg(func),
+                Op::CallSpread{dst, callee, this, array} => reg(dst) && reg(callee) && reg(this) && reg(array),
+                Op::NewSpread{dst, callee, array} => reg(dst) && reg(callee) && reg(array),
+                Op::IteratorStep{dst, iterator, done} | Op::IteratorRest{dst, iterator, done} => reg(dst) && reg(iterator) && reg(done),
+                Op::IteratorClose{iterator, done, ..} => reg(iterator) && reg(done),
+                Op::Resume{dst, mode} | Op::ResumeRaw{dst, mode} => reg(dst) && reg(mode),
+                Op::PushScope{} | Op::PopScope{} => true,
+            };
+            if !ok{
+                return Err(invalid())
+            }
+        }
+
+        for h in &self.handlers{
+            if h.start > h.end || h.end > len || !target(h.target) || !reg(h.register){
+                return Err(invalid())
+            }
+        }
+        if self.captures.iter().any(|c|*c as usize >= self.names.len()){
+            return Err(invalid())
+        }
+        Ok(())
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer{
+    fn u8(&mut self, v:u8){
+        self.0.push(v);
+    }
+
+    fn u32(&mut self, v:u32){
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn str(&mut self, s:&str){
+        self.u32(s.len() as u32);
+        self.0.extend_from_slice(s.as_bytes());
+    }
+}
+
+struct Reader<'a>{
+    bytes:&'a [u8],
+    pos:usize,
+}
+
+impl<'a> Reader<'a>{
+    fn take(&mut self, len:usize) -> Result<&'a [u8], Error>{
+        match self.bytes.get(self.pos..self.pos + len){
+            Some(b) => {
+                self.pos += len;
+                Ok(b)
+            },
+            None => Err(Error::SyntaxError("unexpected end of bytecode".to_string()))
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, Error>{
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error>{
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<String, Error>{
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_|Error::SyntaxError("invalid string in bytecode".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::parse::parse_ecma;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    const SCRIPT:&str = r#"
+        function f(a, ...rest){
+            let s = `${a}:${rest.length}`;
+            try{
+                throw new Error(s);
+            } catch(e){
+                return [e.message, 10n, /x+/g, null, true, 1.5];
+            }
+        }
+        const g = function*(){ yield* [1, 2]; };
+        f(1, 2, 3);
+    "#;
+
+    fn compile(script:&str) -> CodeBlock{
+        compile_script(&parse_ecma("test.js", script).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn encode_round_trip(){
+        let bytes = compile(SCRIPT).encode();
+        let decoded = CodeBlock::decode(&bytes).unwrap();
+        assert_eq!(decoded.encode(), bytes);
+        assert!(!decoded.functions.is_empty());
+    }
+
+    #[test]
+    fn truncated_bytecode_is_rejected(){
+        let bytes = compile(SCRIPT).encode();
+        for len in 0..bytes.len(){
+            assert!(CodeBlock::decode(&bytes[..len]).is_err(), "decoded {} of {} bytes", len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn invalid_bytecode_is_rejected(){
+        let mut bytes = compile("1 + 2").encode();
+        bytes[0] = b'X';
+        assert!(CodeBlock::decode(&bytes).is_err());
+
+        let mut bytes = compile("1 + 2").encode();
+        bytes[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(CodeBlock::decode(&bytes).is_err());
+
+        // the constant is out of the table
+        let mut block = CodeBlock::new();
+        block.registers = 1;
+        block.code.push(Op::LoadConst{dst:0, index:3});
+        assert!(CodeBlock::decode(&block.encode()).is_err());
+    }
+
+    #[test]
+    fn hot_functions_are_compiled(){
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(ExecutionMode::Bytecode);
+        runtime.set_tier_up_threshold(2);
+
+        let re = runtime.clone().exec("test.js", r#"
+            function f(n){
+                let s = 0;
+                for (let i = 0; i < n; i++){
+                    try{
+                        if (i % 3 == 0) throw i;
+                        s += i;
+                    } catch(e){
+                        s -= e;
+                    }
+                }
+                return s;
+            }
+            let total = 0;
+            for (let i = 0; i < 10; i++){
+                total += f(i);
+            }
+            total
+        "#).unwrap();
+
+        // the same sum computed by the first calls in the interpreter
+        let expected:f64 = (0..10).map(|n|(0..n).map(|i|if i % 3 == 0 {-i} else {i}).sum::<i32>() as f64).sum();
+        assert_eq!(re.value.value.number(), Some(expected));
+    }
+}
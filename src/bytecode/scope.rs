@@ -0,0 +1,594 @@
+use std::collections::HashSet;
+
+use swc_ecma_ast::*;
+
+/// the names referenced by the functions and classes nested in `stmts`.
+///
+/// the bindings of the enclosing function with these names must live in
+/// the context instead of registers. the set is conservative: every identifier
+/// inside a nested function is included, shadowed or not.
+pub(crate) fn captured_names<'a, I:IntoIterator<Item = &'a Stmt>>(stmts:I) -> HashSet<String>{
+    let mut c = Collector{nested:0, names:HashSet::new(), targets:false, labels:Vec::new(), detached:true};
+    for s in stmts{
+        c.stmt(s);
+    }
+    c.names
+}
+
+/// the names captured by a function from its parameters and body
+pub(crate) fn captured_names_in_function(params:&[&Pat], stmts:&[Stmt], expr:Option<&Expr>) -> HashSet<String>{
+    let mut c = Collector{nested:0, names:HashSet::new(), targets:false, labels:Vec::new(), detached:true};
+    for p in params{
+        c.pat(p);
+    }
+    for s in stmts{
+        c.stmt(s);
+    }
+    if let Some(e) = expr{
+        c.expr(e);
+    }
+    c.names
+}
+
+/// the names assigned, updated or declared in `stmts` and `exprs`, nested functions included
+pub(crate) fn assigned_names<'a, I:IntoIterator<Item = &'a Stmt>>(stmts:I, exprs:&[&Expr]) -> HashSet<String>{
+    let mut c = Collector{nested:0, names:HashSet::new(), targets:true, labels:Vec::new(), detached:true};
+    for s in stmts{
+        c.stmt(s);
+    }
+    for e in exprs{
+        c.expr(e);
+    }
+    c.names
+}
+
+/// true if the loop `stmt` labeled `label` can run detached from its function,
+/// in the body of a function called in its place.
+///
+/// the loop must not return, yield, await or jump past itself, declare `var` bindings
+/// or functions of the enclosing function, or use `arguments`, `new.target` or `super`.
+pub(crate) fn runs_detached(stmt:&Stmt, label:Option<&str>) -> bool{
+    let mut c = Collector{nested:0, names:HashSet::new(), targets:false, labels:label.into_iter().map(String::from).collect(), detached:true};
+    c.stmt(stmt);
+    c.detached
+}
+
+/// the names declared by `var` in `stmts`, not entering nested functions
+pub(crate) fn var_names<'a, I:IntoIterator<Item = &'a Stmt>>(stmts:I) -> Vec<String>{
+    let mut names = Vec::new();
+    for s in stmts{
+        stmt_var_names(s, &mut names);
+    }
+    names
+}
+
+fn stmt_var_names(s:&Stmt, names:&mut Vec<String>){
+    match s{
+        Stmt::Block(b) => b.stmts.iter().for_each(|s|stmt_var_names(s, names)),
+        Stmt::Decl(Decl::Var(v)) => var_decl_names(v, names),
+        Stmt::DoWhile(d) => stmt_var_names(&d.body, names),
+        Stmt::While(w) => stmt_var_names(&w.body, names),
+        Stmt::For(f) => {
+            if let Some(VarDeclOrExpr::VarDecl(v)) = &f.init{
+                var_decl_names(v, names);
+            }
+            stmt_var_names(&f.body, names);
+        },
+        Stmt::ForIn(f) => {
+            if let VarDeclOrPat::VarDecl(v) = &f.left{
+                var_decl_names(v, names);
+            }
+            stmt_var_names(&f.body, names);
+        },
+        Stmt::ForOf(f) => {
+            if let VarDeclOrPat::VarDecl(v) = &f.left{
+                var_decl_names(v, names);
+            }
+            stmt_var_names(&f.body, names);
+        },
+        Stmt::If(i) => {
+            stmt_var_names(&i.cons, names);
+            if let Some(alt) = &i.alt{
+                stmt_var_names(alt, names);
+            }
+        },
+        Stmt::Labeled(l) => stmt_var_names(&l.body, names),
+        Stmt::Switch(s) => s.cases.iter().flat_map(|c|&c.cons).for_each(|s|stmt_var_names(s, names)),
+        Stmt::Try(t) => {
+            t.block.stmts.iter().for_each(|s|stmt_var_names(s, names));
+            if let Some(h) = &t.handler{
+                h.body.stmts.iter().for_each(|s|stmt_var_names(s, names));
+            }
+            if let Some(f) = &t.finalizer{
+                f.stmts.iter().for_each(|s|stmt_var_names(s, names));
+            }
+        },
+        _ => {}
+    }
+}
+
+fn var_decl_names(v:&VarDecl, names:&mut Vec<String>){
+    if v.kind != VarDeclKind::Var{
+        return
+    }
+    for d in &v.decls{
+        pat_names(&d.name, names);
+    }
+}
+
+/// the names declared by `let`, `const`, classes and functions at the top level of `stmts`.
+///
+/// functions are declared like `var` at the top level of a function or script and like `let` in blocks.
+pub(crate) fn lexical_names<'a, I:IntoIterator<Item = &'a Stmt>>(stmts:I, top_level:bool) -> Vec<(String, VarDeclKind)>{
+    let mut names = Vec::new();
+    for s in stmts{
+        match s{
+            Stmt::Decl(Decl::Var(v)) if v.kind != VarDeclKind::Var => {
+                let mut n = Vec::new();
+                for d in &v.decls{
+                    pat_names(&d.name, &mut n);
+                }
+                names.extend(n.into_iter().map(|n|(n, v.kind)));
+            },
+            Stmt::Decl(Decl::Fn(f)) => {
+                let kind = if top_level {VarDeclKind::Var} else {VarDeclKind::Let};
+                names.push((f.ident.sym.to_string(), kind));
+            },
+            Stmt::Decl(Decl::Class(c)) => names.push((c.ident.sym.to_string(), VarDeclKind::Let)),
+            _ => {}
+        }
+    }
+    names
+}
+
+/// the names bound by a pattern
+pub(crate) fn pat_names(p:&Pat, names:&mut Vec<String>){
+    match p{
+        Pat::Ident(i) => {
+            let name = i.id.sym.to_string();
+            if !names.contains(&name){
+                names.push(name);
+            }
+        },
+        Pat::Array(a) => a.elems.iter().flatten().for_each(|p|pat_names(p, names)),
+        Pat::Rest(r) => pat_names(&r.arg, names),
+        Pat::Object(o) => {
+            for prop in &o.props{
+                match prop{
+                    ObjectPatProp::KeyValue(kv) => pat_names(&kv.value, names),
+                    ObjectPatProp::Assign(a) => {
+                        let name = a.key.sym.to_string();
+                        if !names.contains(&name){
+                            names.push(name);
+                        }
+                    },
+                    ObjectPatProp::Rest(r) => pat_names(&r.arg, names),
+                }
+            }
+        },
+        Pat::Assign(a) => pat_names(&a.left, names),
+        Pat::Invalid(_) | Pat::Expr(_) => {}
+    }
+}
+
+struct Collector{
+    /// the depth of nested functions, identifiers are recorded inside functions only
+    nested:u32,
+    names:HashSet<String>,
+    /// record the identifiers written to instead of the captured ones
+    targets:bool,
+    /// the labels of the enclosing statements
+    labels:Vec<String>,
+    /// cleared by the code that cannot run detached from its function, see `runs_detached`
+    detached:bool,
+}
+
+impl Collector{
+    fn ident(&mut self, i:&Ident){
+        // arrow functions use the `arguments` of the enclosing function
+        if &*i.sym == "arguments"{
+            self.detached = false;
+        }
+        if self.nested > 0 && !self.targets{
+            self.names.insert(i.sym.to_string());
+        }
+    }
+
+    /// an identifier bound by a pattern or written by an assignment
+    fn target(&mut self, i:&Ident){
+        if self.targets{
+            self.names.insert(i.sym.to_string());
+        } else{
+            self.ident(i);
+        }
+    }
+
+    /// the identifier assigned by `e`, if any
+    fn target_expr(&mut self, e:&Expr){
+        match e{
+            Expr::Ident(i) => self.target(i),
+            Expr::Paren(p) => self.target_expr(&p.expr),
+            _ => self.expr(e)
+        }
+    }
+
+    fn nested<F:FnOnce(&mut Self)>(&mut self, f:F){
+        self.nested += 1;
+        f(self);
+        self.nested -= 1;
+    }
+
+    fn stmts(&mut self, stmts:&[Stmt]){
+        for s in stmts{
+            self.stmt(s);
+        }
+    }
+
+    fn stmt(&mut self, s:&Stmt){
+        match s{
+            Stmt::Block(b) => self.stmts(&b.stmts),
+            Stmt::Empty(_) | Stmt::Debugger(_) => {},
+            Stmt::Break(BreakStmt{label, ..}) | Stmt::Continue(ContinueStmt{label, ..}) => {
+                if let Some(l) = label{
+                    if self.nested == 0 && !self.labels.iter().any(|n|*n == *l.sym){
+                        self.detached = false;
+                    }
+                }
+            },
+            Stmt::With(w) => {
+                self.expr(&w.obj);
+                self.stmt(&w.body);
+            },
+            Stmt::Return(r) => {
+                if self.nested == 0{
+                    self.detached = false;
+                }
+                if let Some(e) = &r.arg{
+                    self.expr(e);
+                }
+            },
+            Stmt::Labeled(l) => {
+                self.labels.push(l.label.sym.to_string());
+                self.stmt(&l.body);
+                self.labels.pop();
+            },
+            Stmt::If(i) => {
+                self.expr(&i.test);
+                self.stmt(&i.cons);
+                if let Some(alt) = &i.alt{
+                    self.stmt(alt);
+                }
+            },
+            Stmt::Switch(s) => {
+                self.expr(&s.discriminant);
+                for c in &s.cases{
+                    if let Some(t) = &c.test{
+                        self.expr(t);
+                    }
+                    self.stmts(&c.cons);
+                }
+            },
+            Stmt::Throw(t) => self.expr(&t.arg),
+            Stmt::Try(t) => {
+                self.stmts(&t.block.stmts);
+                if let Some(h) = &t.handler{
+                    if let Some(p) = &h.param{
+                        self.pat(p);
+                    }
+                    self.stmts(&h.body.stmts);
+                }
+                if let Some(f) = &t.finalizer{
+                    self.stmts(&f.stmts);
+                }
+            },
+            Stmt::While(w) => {
+                self.expr(&w.test);
+                self.stmt(&w.body);
+            },
+            Stmt::DoWhile(d) => {
+                self.stmt(&d.body);
+                self.expr(&d.test);
+            },
+            Stmt::For(f) => {
+                match &f.init{
+                    Some(VarDeclOrExpr::VarDecl(v)) => self.var_decl(v),
+                    Some(VarDeclOrExpr::Expr(e)) => self.expr(e),
+                    None => {}
+                }
+                if let Some(t) = &f.test{
+                    self.expr(t);
+                }
+                if let Some(u) = &f.update{
+                    self.expr(u);
+                }
+                self.stmt(&f.body);
+            },
+            Stmt::ForIn(f) => {
+                self.var_decl_or_pat(&f.left);
+                self.expr(&f.right);
+                self.stmt(&f.body);
+            },
+            Stmt::ForOf(f) => {
+                self.var_decl_or_pat(&f.left);
+                self.expr(&f.right);
+                self.stmt(&f.body);
+            },
+            Stmt::Decl(d) => self.decl(d),
+            Stmt::Expr(e) => self.expr(&e.expr),
+        }
+    }
+
+    fn var_decl_or_pat(&mut self, v:&VarDeclOrPat){
+        match v{
+            VarDeclOrPat::VarDecl(v) => self.var_decl(v),
+            VarDeclOrPat::Pat(p) => self.pat(p),
+        }
+    }
+
+    fn decl(&mut self, d:&Decl){
+        match d{
+            Decl::Class(c) => {
+                self.ident(&c.ident);
+                self.class(&c.class);
+            },
+            Decl::Fn(f) => {
+                if self.nested == 0{
+                    self.detached = false;
+                }
+                self.ident(&f.ident);
+                self.function(&f.function);
+            },
+            Decl::Var(v) => self.var_decl(v),
+            _ => {}
+        }
+    }
+
+    fn var_decl(&mut self, v:&VarDecl){
+        if v.kind == VarDeclKind::Var && self.nested == 0{
+            self.detached = false;
+        }
+        for d in &v.decls{
+            self.pat(&d.name);
+            if let Some(e) = &d.init{
+                self.expr(e);
+            }
+        }
+    }
+
+    fn function(&mut self, f:&Function){
+        self.nested(|c|{
+            for p in &f.params{
+                c.pat(&p.pat);
+            }
+            if let Some(b) = &f.body{
+                c.stmts(&b.stmts);
+            }
+        });
+    }
+
+    fn class(&mut self, class:&Class){
+        if let Some(s) = &class.super_class{
+            self.expr(s);
+        }
+        // methods, field initializers and static blocks are functions
+        self.nested(|c|{
+            for m in &class.body{
+                match m{
+                    ClassMember::Constructor(ctor) => {
+                        for p in &ctor.params{
+                            if let ParamOrTsParamProp::Param(p) = p{
+                                c.pat(&p.pat);
+                            }
+                        }
+                        if let Some(b) = &ctor.body{
+                            c.stmts(&b.stmts);
+                        }
+                    },
+                    ClassMember::Method(m) => {
+                        c.prop_name(&m.key);
+                        c.function(&m.function);
+                    },
+                    ClassMember::PrivateMethod(m) => c.function(&m.function),
+                    ClassMember::ClassProp(p) => {
+                        c.prop_name(&p.key);
+                        if let Some(v) = &p.value{
+                            c.expr(v);
+                        }
+                    },
+                    ClassMember::PrivateProp(p) => {
+                        if let Some(v) = &p.value{
+                            c.expr(v);
+                        }
+                    },
+                    ClassMember::StaticBlock(b) => c.stmts(&b.body.stmts),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    fn prop_name(&mut self, p:&PropName){
+        if let PropName::Computed(c) = p{
+            self.expr(&c.expr);
+        }
+    }
+
+    fn pat(&mut self, p:&Pat){
+        match p{
+            Pat::Ident(i) => self.target(&i.id),
+            Pat::Array(a) => {
+                for e in a.elems.iter().flatten(){
+                    self.pat(e);
+                }
+            },
+            Pat::Rest(r) => self.pat(&r.arg),
+            Pat::Object(o) => {
+                for prop in &o.props{
+                    match prop{
+                        ObjectPatProp::KeyValue(kv) => {
+                            self.prop_name(&kv.key);
+                            self.pat(&kv.value);
+                        },
+                        ObjectPatProp::Assign(a) => {
+                            self.target(&a.key);
+                            if let Some(v) = &a.value{
+                                self.expr(v);
+                            }
+                        },
+                        ObjectPatProp::Rest(r) => self.pat(&r.arg),
+                    }
+                }
+            },
+            Pat::Assign(a) => {
+                self.pat(&a.left);
+                self.expr(&a.right);
+            },
+            Pat::Expr(e) => self.target_expr(e),
+            Pat::Invalid(_) => {}
+        }
+    }
+
+    fn expr_or_super(&mut self, e:&ExprOrSuper){
+        match e{
+            ExprOrSuper::Expr(e) => self.expr(e),
+            ExprOrSuper::Super(_) => self.detached = false
+        }
+    }
+
+    fn expr(&mut self, e:&Expr){
+        match e{
+            Expr::This(_) | Expr::Lit(_) | Expr::PrivateName(_) | Expr::Invalid(_) => {},
+            Expr::MetaProp(_) => self.detached = false,
+            Expr::Array(a) => {
+                for e in a.elems.iter().flatten(){
+                    self.expr(&e.expr);
+                }
+            },
+            Expr::Object(o) => {
+                for p in &o.props{
+                    match p{
+                        PropOrSpread::Spread(s) => self.expr(&s.expr),
+                        PropOrSpread::Prop(p) => match p.as_ref(){
+                            Prop::Shorthand(i) => self.ident(i),
+                            Prop::KeyValue(kv) => {
+                                self.prop_name(&kv.key);
+                                self.expr(&kv.value);
+                            },
+                            Prop::Assign(a) => {
+                                self.ident(&a.key);
+                                self.expr(&a.value);
+                            },
+                            Prop::Getter(g) => {
+                                self.prop_name(&g.key);
+                                if let Some(b) = &g.body{
+                                    self.nested(|c|c.stmts(&b.stmts));
+                                }
+                            },
+                            Prop::Setter(s) => {
+                                self.prop_name(&s.key);
+                                self.nested(|c|{
+                                    c.pat(&s.param);
+                                    if let Some(b) = &s.body{
+                                        c.stmts(&b.stmts);
+                                    }
+                                });
+                            },
+                            Prop::Method(m) => {
+                                self.prop_name(&m.key);
+                                self.function(&m.function);
+                            },
+                        }
+                    }
+                }
+            },
+            Expr::Fn(f) => self.function(&f.function),
+            Expr::Unary(u) => self.expr(&u.arg),
+            Expr::Update(u) => self.target_expr(&u.arg),
+            Expr::Bin(b) => {
+                self.expr(&b.left);
+                self.expr(&b.right);
+            },
+            Expr::Assign(a) => {
+                match &a.left{
+                    PatOrExpr::Expr(e) => self.target_expr(e),
+                    PatOrExpr::Pat(p) => self.pat(p),
+                }
+                self.expr(&a.right);
+            },
+            Expr::Member(m) => {
+                self.expr_or_super(&m.obj);
+                if m.computed{
+                    self.expr(&m.prop);
+                }
+            },
+            Expr::Cond(c) => {
+                self.expr(&c.test);
+                self.expr(&c.cons);
+                self.expr(&c.alt);
+            },
+            Expr::Call(c) => {
+                self.expr_or_super(&c.callee);
+                for a in &c.args{
+                    self.expr(&a.expr);
+                }
+            },
+            Expr::New(n) => {
+                self.expr(&n.callee);
+                for a in n.args.iter().flatten(){
+                    self.expr(&a.expr);
+                }
+            },
+            Expr::Seq(s) => {
+                for e in &s.exprs{
+                    self.expr(e);
+                }
+            },
+            Expr::Ident(i) => self.ident(i),
+            Expr::Tpl(t) => {
+                for e in &t.exprs{
+                    self.expr(e);
+                }
+            },
+            Expr::TaggedTpl(t) => {
+                self.expr(&t.tag);
+                for e in &t.tpl.exprs{
+                    self.expr(e);
+                }
+            },
+            Expr::Arrow(a) => {
+                self.nested(|c|{
+                    for p in &a.params{
+                        c.pat(p);
+                    }
+                    match &a.body{
+                        BlockStmtOrExpr::BlockStmt(b) => c.stmts(&b.stmts),
+                        BlockStmtOrExpr::Expr(e) => c.expr(e),
+                    }
+                });
+            },
+            Expr::Class(c) => {
+                if let Some(i) = &c.ident{
+                    self.ident(i);
+                }
+                self.class(&c.class);
+            },
+            Expr::Yield(y) => {
+                if self.nested == 0{
+                    self.detached = false;
+                }
+                if let Some(a) = &y.arg{
+                    self.expr(a);
+                }
+            },
+            Expr::Await(a) => {
+                if self.nested == 0{
+                    self.detached = false;
+                }
+                self.expr(&a.arg);
+            },
+            Expr::Paren(p) => self.expr(&p.expr),
+            Expr::OptChain(o) => self.expr(&o.expr),
+            _ => {}
+        }
+    }
+}
@@ -1,17 +1,61 @@
 use std::{fmt::{Debug, Display, Write}, sync::Arc};
 
 use cranelift::codegen::CodegenError;
+use cranelift_module::ModuleError;
 
 use crate::value::JValue;
 
 
 
+/// the source position of a compile time error.
+///
+/// the builder only knows the byte offset of a statement,
+/// the line and column are filled by `Error::locate` once the source is known.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Position{
+    pub offset:u32,
+    /// 1 based line, 0 if not located
+    pub line:u32,
+    /// 1 based column
+    pub column:u32,
+}
+
+impl Position{
+    pub fn new(offset:u32) -> Self{
+        Self { offset, line: 0, column: 0 }
+    }
+
+    /// compute the line and column of the offset in `source`
+    pub fn locate(&mut self, source:&str){
+        let offset = (self.offset as usize).min(source.len());
+        let before = match source.get(..offset){
+            Some(s) => s,
+            None => return
+        };
+        self.line = before.matches('\n').count() as u32 + 1;
+        let line_start = before.rfind('\n').map(|i|i + 1).unwrap_or(0);
+        self.column = before[line_start..].chars().count() as u32 + 1;
+    }
+}
+
+impl Display for Position{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0{
+            f.write_fmt(format_args!("offset {}", self.offset))
+        } else{
+            f.write_fmt(format_args!("{}:{}", self.line, self.column))
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Error{
-    IllegalBreakStatement,
-    IllegalContinueStatment,
+    IllegalBreakStatement(Position),
+    IllegalContinueStatment(Position),
 
-    UndefinedLabel(String),
+    UndefinedLabel(String, Position),
+
+    SyntaxError(String),
 
     Break(Option<String>),
     Continue(Option<String>),
@@ -20,7 +64,11 @@ pub enum Error{
     Deprecated(&'static str),
     Unimplemented(&'static str),
 
+    /// a realm passed to a runtime that did not create it
+    ForeignRealm,
+
     CodegenError(Arc<CodegenError>),
+    ModuleError(Arc<ModuleError>),
 
     ParseError(swc_ecma_parser::error::Error),
 
@@ -28,7 +76,26 @@ pub enum Error{
 }
 
 impl Error{
+    /// fill the line and column of the position carried by a compile time error
+    pub fn locate(mut self, source:&str) -> Self{
+        match &mut self{
+            Error::IllegalBreakStatement(p) |
+            Error::IllegalContinueStatment(p) |
+            Error::UndefinedLabel(_, p) => p.locate(source),
+            _ => {}
+        };
+        self
+    }
 
+    /// the source position of a compile time error
+    pub fn position(&self) -> Option<Position>{
+        match self{
+            Error::IllegalBreakStatement(p) |
+            Error::IllegalContinueStatment(p) |
+            Error::UndefinedLabel(_, p) => Some(*p),
+            _ => None
+        }
+    }
 }
 
 impl std::error::Error for Error{
@@ -47,14 +114,64 @@ impl Display for Error{
             Error::Break(_) => f.write_str("Illegal break statment."),
             Error::Continue(_) => f.write_str("Illegal continue statment."),
             Error::Return(_) => f.write_str("Illegal return statment."),
-            Error::UndefinedLabel(l) => f.write_fmt(format_args!("Undefined label: {}.", l)),
-            Error::IllegalBreakStatement => f.write_str("Illegal break statment."),
-            Error::IllegalContinueStatment => f.write_str("Illegal continue statment."),
+            Error::UndefinedLabel(l, p) => f.write_fmt(format_args!("Undefined label: {} at {}.", l, p)),
+            Error::SyntaxError(s) => f.write_fmt(format_args!("SyntaxError: {}", s)),
+            Error::IllegalBreakStatement(p) => f.write_fmt(format_args!("Illegal break statment at {}.", p)),
+            Error::IllegalContinueStatment(p) => f.write_fmt(format_args!("Illegal continue statment at {}.", p)),
             Error::Deprecated(s) => f.write_fmt(format_args!("Deprecated: {}", s)),
             Error::Unimplemented(s) => f.write_fmt(format_args!("Unimplemented: {}", s)),
+            Error::ForeignRealm => f.write_str("The realm was created by another runtime."),
             Error::CodegenError(c) => Display::fmt(c, f),
+            Error::ModuleError(m) => Display::fmt(m, f),
             Error::ParseError(p) => Display::fmt(&p.kind().msg(), f),
             Error::Value(v) => f.write_str(v.to_string().as_str()),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn locate(source:&str, offset:u32) -> (u32, u32){
+        let mut p = Position::new(offset);
+        p.locate(source);
+        (p.line, p.column)
+    }
+
+    #[test]
+    fn locate_lines_and_columns(){
+        let source = "let a;\n  break;\n\nx";
+        assert_eq!(locate(source, 0), (1, 1));
+        assert_eq!(locate(source, 4), (1, 5));
+        // the newline ends its line
+        assert_eq!(locate(source, 6), (1, 7));
+        assert_eq!(locate(source, 7), (2, 1));
+        assert_eq!(locate(source, 9), (2, 3));
+        assert_eq!(locate(source, 17), (4, 1));
+    }
+
+    #[test]
+    fn locate_counts_characters(){
+        // the offset is in bytes, the column in characters
+        let source = "é = 'ü'; break;";
+        let offset = source.find("break").unwrap() as u32;
+        assert_eq!(locate(source, offset), (1, 10));
+
+        // an offset inside a character is not located
+        assert_eq!(locate(source, 1), (0, 0));
+    }
+
+    #[test]
+    fn locate_past_the_end(){
+        assert_eq!(locate("a\nb", 100), (2, 2));
+        assert_eq!(locate("", 3), (1, 1));
+    }
+
+    #[test]
+    fn error_locate(){
+        let e = Error::UndefinedLabel("outer".to_string(), Position::new(9)).locate("while(1)\n{ continue outer; }");
+        assert_eq!(e.position(), Some(Position{offset:9, line:2, column:1}));
+
+        assert_eq!(Error::SyntaxError("x".to_string()).locate("").position(), None);
+    }
+}
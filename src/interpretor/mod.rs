@@ -1,652 +1,1908 @@
-use std::panic::panic_any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::{resume_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
 use std::{sync::Arc, cell::RefCell};
 
 use swc_ecma_ast::*;
 
 use async_recursion::async_recursion;
-
-use crate::builtins::promise::Promise;
+use futures::FutureExt;
+use futures::task::noop_waker_ref;
+
+use crate::builtins::generator::{Generator, ResumeMode};
+use crate::bytecode::scope::pat_names;
+use crate::builtins::regexp::RegExp;
+use crate::builtins::{Array, JObject};
+use crate::jit::builder::{is_private, method_kind, prop_name_str, template_object};
+use crate::utils::ToMutable;
 use crate::{builtins, operator};
-use crate::builtins::object::JObject;
 use crate::value::JValue;
-use crate::{runtime::Runtime, vm::VmContext};
-use crate::error::Error;
-
+use crate::vm::VmContext;
+use crate::error::{Error, Position};
+use crate::runtime::ExecutionMode;
+
+mod tiering;
+use tiering::{LoopProfiles, Profile};
+
+/// a tree walking interpreter over the syntax tree, an alternative to the jit.
+///
+/// it shares the contexts, builtins and runtime helpers with compiled code
+/// and follows the same semantics, so both can be checked against each other.
+///
+/// every evaluation is a future: the body of a generator or async function
+/// is suspended at `yield` and `await` and kept by the generator across resumes,
+/// other code never suspends and is run to completion by a single poll.
 #[derive(Clone)]
 pub struct Interpreter{
-    runtime:Arc<&'static Runtime>,
+    /// the script being run, kept alive by the functions it creates
+    script:Arc<Script>,
+
+    this:JValue,
+    is_async:bool,
+    is_generator:bool,
+
+    /// the enclosing statements targeted by `break` and `continue`
+    jumps:Vec<Jump>,
+    /// the private names of the enclosing classes
+    private_names:Vec<HashMap<String, JValue>>,
+    /// the template objects of tagged templates, created once per call site
+    templates:Arc<RefCell<HashMap<*const Tpl, JValue>>>,
+
+    /// the value of the last expression statement
+    completion:JValue,
+
+    /// the profile of the function being run in `ExecutionMode::Tiered`
+    profile:Option<Arc<Profile>>,
+    /// the loops of the script entered on stack in `ExecutionMode::Tiered`
+    loops:Arc<LoopProfiles>,
 }
 
-impl Interpreter{
+/// an enclosing statement targeted by `break` or `continue`
+#[derive(Clone)]
+struct Jump{
+    label:Option<String>,
+    /// loops can be continued
+    is_loop:bool,
+    /// a labeled statement that is not a loop, only exited by a labeled break
+    labeled_only:bool,
+}
 
-    #[async_recursion]
-    pub async fn translate_stmt(&mut self, vmctx:&mut VmContext, stmt:&'static Stmt, label:Option<String>) -> Result<(), Error>{
-        match stmt{
-            Stmt::Block(b) => {
-                let ctx = vmctx.new_child();
+enum LoopControl{
+    Next,
+    Break,
+}
 
-                for i in &b.stmts{
-                    self.translate_stmt(ctx, i, None).await?;
-                }
-                ctx.done();
-            },
-            Stmt::Break(b) => {
-                if let Some(l) = &b.label{
-                    return Err(Error::Break(Some(l.sym.to_string())))
-                } else{
-                    return Err(Error::Break(None))
-                }
-            },
-            Stmt::Continue(c) => {
-                if let Some(l) = &c.label{
-                    return Err(Error::Continue(Some(l.sym.to_string())))
-                } else{
-                    return Err(Error::Continue(None))
-                }
-            },
-            Stmt::Debugger(d) => {
-                todo!()
-            },
-            Stmt::Decl(d) => {
-                self.translate_decl(vmctx, d).await?;
-            },
-            Stmt::DoWhile(d) => {
-                loop{
-                    let ctx = vmctx.new_child();
-                    let re = self.translate_stmt(ctx, &d.body, None).await;
-                    if let Err(e) = &re{
-                        match e{
-                            Error::Break(b) => {
-                                if b.is_some() && label.is_some(){
-                                    // match the labels
-                                    if b.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // label matched
-                                        ctx.done();
-                                        break;
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re;
-                                    }
-                                } else if b.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    ctx.done();
-                                    break
-                                }
-                            },
-                            Error::Continue(c) => {
-
-                                if c.is_some() && label.is_some(){
-                                    // check if label matches
-                                    if c.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // go to next loop
-                                        ctx.done();
-                                        continue;
-
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re
-                                    }
-                                } else if c.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    // go to next loop
-                                    ctx.done();
-                                    continue;
-                                }
-                            }
-                            _ => return re,
-                        };
-                    };
+/// the source and the syntax tree of a script run by the interpreter.
+///
+/// the interpreter holds `'static` references into the tree, they are valid
+/// as long as an interpreter holding the script is alive.
+pub struct Script{
+    source:String,
+    module:Module,
+}
 
-                    let test = self.translate_expr(ctx, &d.test).await?;
-                    ctx.done();
+impl Script{
+    pub fn parse(filename:&str, source:&str) -> Result<Arc<Script>, Error>{
+        let module = crate::parse::parse_ecma(filename, source)?;
+        Ok(Arc::new(Script{source:source.to_string(), module}))
+    }
 
-                    if !test.to_bool(){
-                        break
-                    }
-                }
-            },
+    /// the source, used to locate errors
+    fn source(&self) -> &str{
+        &self.source
+    }
 
-            Stmt::Empty(e) => {},
-            Stmt::Expr(e) => {
-                self.translate_expr(vmctx, &e.expr).await?;
-            },
+    fn items(&self) -> &'static [ModuleItem]{
+        // the tree is not moved or dropped while the interpreter holds the script
+        unsafe{&*(self.module.body.as_slice() as *const [ModuleItem])}
+    }
+}
 
-            Stmt::For(f) => {
-                let ctx = vmctx.new_child();
+/// the body of a function, arrow functions may have a single expression
+#[derive(Clone, Copy)]
+enum Body{
+    Stmts(&'static [Stmt]),
+    Expr(&'static Expr),
+}
 
-                if let Some(i) = &f.init{
-                    
-                    match i{
-                        VarDeclOrExpr::Expr(e) => {
-                            self.translate_expr(ctx, &e).await?;
-                        },
-                        VarDeclOrExpr::VarDecl(d) => {
-                            self.translate_var_decl(ctx, d).await?;
-                        }
-                    }
-                }
+/// an element evaluated on `this` by a class initializer,
+/// the keys are resolved when the class is defined.
+enum ClassElement{
+    Field(JValue, Option<&'static Expr>),
+    PrivateField(JValue, Option<&'static Expr>),
+    PrivateMethod(JValue, JValue, i8),
+    StaticBlock(&'static BlockStmt),
+}
 
-                loop{
-                    let ctx = ctx.new_child();
+impl Interpreter{
+    pub fn new(script:Arc<Script>) -> Self{
+        Self{
+            script,
 
-                    if let Some(e) = &f.test{
-                        self.translate_expr(ctx, &e).await?;
-                    }
+            this:JValue::Undefined,
+            is_async:false,
+            is_generator:false,
 
-                    let re = self.translate_stmt(ctx, &f.body, None).await;
-
-                    // break or continue
-                    if let Err(e) = &re{
-                        match e{
-                            Error::Break(b) => {
-                                if b.is_some() && label.is_some(){
-                                    // match the labels
-                                    if b.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // label matched
-                                        ctx.done();
-                                        break;
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re;
-                                    }
-                                } else if b.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    ctx.done();
-                                    break
-                                }
-                            },
-                            Error::Continue(c) => {
-
-                                if c.is_some() && label.is_some(){
-                                    // check if label matches
-                                    if c.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // go to next loop
-                                        ctx.done();
-                                        continue;
-
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re
-                                    }
-                                } else if c.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    // go to next loop
-                                    ctx.done();
-                                    continue;
-                                }
-                            }
-                            _ => return re,
-                        };
-                    };
+            jumps:Vec::new(),
+            private_names:Vec::new(),
+            templates:Default::default(),
 
-                    // update
-                    if let Some(e) = &f.update{
-                        self.translate_expr(ctx, &e).await?;
-                    }
-                    ctx.done();
+            completion:JValue::Undefined,
+
+            profile:None,
+            loops:Default::default(),
+        }
+    }
+
+    /// run the items of the script, return the completion value
+    pub fn exec(&mut self, vmctx:&mut VmContext, this:JValue) -> Result<JValue, Error>{
+        self.this = this;
+        let items = self.script.items();
+
+        let re = run_sync(catch_thrown(async{
+            self.hoist_functions(vmctx, items.iter().filter_map(|item|match item{
+                ModuleItem::Stmt(s) => Some(s),
+                ModuleItem::ModuleDecl(_) => None
+            }))?;
+            for item in items{
+                match item{
+                    ModuleItem::Stmt(s) if is_function_decl(s) => {},
+                    ModuleItem::Stmt(s) => self.translate_stmt(vmctx, s, None).await?,
+                    ModuleItem::ModuleDecl(_) => return Err(Error::Unimplemented("module declarations in Runtime::exec."))
                 }
+            }
+            Ok(self.completion)
+        }));
 
-                ctx.done();
-            },
+        match re{
+            Err(Error::Return(v)) => Ok(v),
+            re => re
+        }
+    }
 
-            Stmt::ForIn(f) => {
-                
-                let right = self.translate_expr(vmctx, &f.right).await?;
-                let keys = right.owned_keys();
+    /// the interpreter running the body of a function created by this one
+    fn function_interpreter(&self, is_async:bool, is_generator:bool) -> Self{
+        Self{
+            script:self.script.clone(),
 
-                for key in keys{
+            this:JValue::Undefined,
+            is_async,
+            is_generator,
 
-                    let ctx = vmctx.new_child();
+            jumps:Vec::new(),
+            private_names:self.private_names.clone(),
+            templates:self.templates.clone(),
 
-                    match &f.left{
-                        VarDeclOrPat::Pat(p) => self.translate_pat(ctx, p, key.into()).await?,
-                        VarDeclOrPat::VarDecl(d) => {
-                            let decl = d.decls.first().unwrap();
-                            self.translate_pat(ctx, &decl.name, key.into()).await?;
-                        },
-                    };
+            completion:JValue::Undefined,
 
-                    let re = self.translate_stmt(ctx, &f.body, None).await;
-
-                    // break or continue
-                    if let Err(e) = &re{
-                        match e{
-                            Error::Break(b) => {
-                                if b.is_some() && label.is_some(){
-                                    // match the labels
-                                    if b.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // label matched
-                                        ctx.done();
-                                        break;
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re;
-                                    }
-                                } else if b.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    ctx.done();
-                                    break
-                                }
-                            },
-                            Error::Continue(c) => {
-
-                                if c.is_some() && label.is_some(){
-                                    // check if label matches
-                                    if c.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // go to next loop
-                                        ctx.done();
-                                        continue;
-
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re
-                                    }
-                                } else if c.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    // go to next loop
-                                    ctx.done();
-                                    continue;
-                                }
-                            }
-                            _ => return re,
-                        };
-                    };
+            profile:None,
+            loops:self.loops.clone(),
+        }
+    }
 
-                    ctx.done();
-                }
-                
+    #[async_recursion(?Send)]
+    pub async fn translate_stmt(&mut self, vmctx:&mut VmContext, stmt:&'static Stmt, label:Option<String>) -> Result<(), Error>{
+        match stmt{
+            Stmt::Block(b) => {
+                self.translate_block(vmctx, &b.stmts).await?;
             },
 
-            Stmt::ForOf(f) => {
-                let right = self.translate_expr(vmctx, &f.right).await?;
-                let iter = right.member(*builtins::symbol::Iterator);
+            Stmt::Break(b) => {
+                let label = b.label.as_ref().map(|l|l.sym.to_string());
+                self.check_jump(label.as_deref(), false, Position::new(b.span.lo.0))?;
+                return Err(Error::Break(label))
+            },
 
-                let mut done = false;
+            Stmt::Continue(c) => {
+                let label = c.label.as_ref().map(|l|l.sym.to_string());
+                self.check_jump(label.as_deref(), true, Position::new(c.span.lo.0))?;
+                return Err(Error::Continue(label))
+            },
 
-                loop{
+            Stmt::Debugger(_) => {},
 
-                    let next = iter.member_str("next");
+            Stmt::Decl(d) => {
+                self.translate_decl(vmctx, d).await?;
+            },
 
-                    if done{
-                        break;
-                    }
+            Stmt::DoWhile(d) => {
+                self.jumps.push(Jump{label:label.clone(), is_loop:true, labeled_only:false});
+                let re = self.translate_while(vmctx, stmt, &d.test, &d.body, &label, false).await;
+                self.jumps.pop();
+                re?;
+            },
 
-                    done = next.member_str("done").to_bool();
-                    let value = next.member_str("value");
+            Stmt::Empty(_) => {},
 
-                    let ctx = vmctx.new_child();
+            Stmt::Expr(e) => {
+                self.completion = self.translate_expr(vmctx, &e.expr).await?;
+            },
 
-                    match &f.left{
-                        VarDeclOrPat::Pat(p) => self.translate_pat(ctx, p, value).await?,
-                        VarDeclOrPat::VarDecl(d) => {
-                            let decl = d.decls.first().unwrap();
-                            self.translate_pat(ctx, &decl.name, value).await?;
-                        },
-                    };
+            Stmt::For(f) => {
+                // the scope of the declarations in the initializer
+                let ctx = vmctx.new_child();
+                self.jumps.push(Jump{label:label.clone(), is_loop:true, labeled_only:false});
+                let re = self.translate_for(ctx, stmt, f, &label).await;
+                self.jumps.pop();
+                ctx.done();
+                re?;
+            },
 
-                    let re = self.translate_stmt(ctx, &f.body, None).await;
-
-                    // break or continue
-                    if let Err(e) = &re{
-                        match e{
-                            Error::Break(b) => {
-                                if b.is_some() && label.is_some(){
-                                    // match the labels
-                                    if b.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // label matched
-                                        ctx.done();
-                                        break;
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re;
-                                    }
-                                } else if b.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    ctx.done();
-                                    break
-                                }
-                            },
-                            Error::Continue(c) => {
-
-                                if c.is_some() && label.is_some(){
-                                    // check if label matches
-                                    if c.as_ref().unwrap().as_str() == label.as_ref().unwrap().as_str(){
-                                        // go to next loop
-                                        ctx.done();
-                                        continue;
-
-                                    } else{
-                                        // label does not match
-                                        ctx.done();
-                                        return re
-                                    }
-                                } else if c.is_some(){
-                                    ctx.done();
-                                    return re
-                                } else{
-                                    // go to next loop
-                                    ctx.done();
-                                    continue;
-                                }
-                            }
-                            _ => return re,
-                        };
-                    };
+            Stmt::ForIn(f) => {
+                let right = self.translate_expr(vmctx, &f.right).await?;
+                let iterator = operator::for_in_iterator(right);
+                self.translate_iteration(vmctx, &f.left, iterator, &f.body, label, false).await?;
+            },
 
-                    ctx.done();
+            Stmt::ForOf(f) => {
+                if f.await_token.is_some(){
+                    return Err(Error::Unimplemented("for await of statement."))
                 }
+                let right = self.translate_expr(vmctx, &f.right).await?;
+                let iterator = check_raw(operator::get_iterator_raw(right))?;
+                self.translate_iteration(vmctx, &f.left, iterator, &f.body, label, true).await?;
             },
 
             Stmt::If(i) => {
+                let test = self.translate_expr(vmctx, &i.test).await?.to_bool();
 
-                if self.translate_expr(vmctx, &i.test).await?.to_bool(){
-                    let ctx = vmctx.new_child();
-                    self.translate_stmt(ctx, &i.cons, None).await?;
-                    ctx.done();
+                let ctx = vmctx.new_child();
+                let re = if test{
+                    self.translate_stmt(ctx, &i.cons, None).await
+                } else if let Some(alt) = &i.alt{
+                    self.translate_stmt(ctx, alt, None).await
                 } else{
-                    if let Some(alt) = &i.alt{
-                        let ctx = vmctx.new_child();
-                        self.translate_stmt(ctx, &alt, None).await?;
-                        ctx.done();
-                    }
-                }
+                    Ok(())
+                };
+                ctx.done();
+                re?;
             },
 
             Stmt::Labeled(l) => {
-                self.translate_stmt(vmctx, &l.body, Some(l.label.sym.to_string())).await?;
-            },
-
-            Stmt::Return(r) => {
-                if let Some(arg) = &r.arg{
-                    return Err(Error::Return(self.translate_expr(vmctx, &arg).await?))
-                } else{
-                    return Err(Error::Return(JValue::Undefined))
+                let name = l.label.sym.to_string();
+                if self.jumps.iter().any(|j|j.label.as_ref() == Some(&name)){
+                    return Err(Error::SyntaxError(format!("Label '{}' has already been declared", name)))
                 }
-            },
 
-            Stmt::Switch(s) => {
-                let target = self.translate_expr(vmctx, &s.discriminant).await?;
+                match l.body.as_ref(){
+                    // loops and switch statements take the label
+                    Stmt::For(_) | Stmt::ForIn(_) | Stmt::ForOf(_) |
+                    Stmt::While(_) | Stmt::DoWhile(_) | Stmt::Switch(_) => {
+                        return self.translate_stmt(vmctx, &l.body, Some(name)).await
+                    },
+                    _ => {}
+                };
 
-                for i in &s.cases{
+                // a labeled block is exited by `break label`
+                self.jumps.push(Jump{label:Some(name.clone()), is_loop:false, labeled_only:true});
+                let re = self.translate_stmt(vmctx, &l.body, None).await;
+                self.jumps.pop();
 
-                    let ctx = vmctx.new_child();
+                match re{
+                    Err(Error::Break(Some(l))) if l == name => {},
+                    re => re?
+                }
+            },
 
-                    if let Some(test) = &i.test{
-                        if self.translate_expr(ctx, &test).await?.eqeqeq(target).to_bool(){
+            Stmt::Return(r) => {
+                let value = match &r.arg{
+                    Some(e) => self.translate_expr(vmctx, e).await?,
+                    None => JValue::Undefined
+                };
+                return Err(Error::Return(value))
+            },
 
-                            for stmt in &i.cons{
-                                self.translate_stmt(ctx, stmt, None).await?;
-                            }
-                            ctx.done();
+            Stmt::Switch(s) => {
+                let discrim = self.translate_expr(vmctx, &s.discriminant).await?;
+
+                // the cases are tested in source order,
+                // the default case is selected only if none matches wherever it is placed
+                let mut start = None;
+                for (i, cas) in s.cases.iter().enumerate(){
+                    if let Some(test) = &cas.test{
+                        let value = self.translate_expr(vmctx, test).await?;
+                        if discrim.eqeqeq(value).to_bool(){
+                            start = Some(i);
                             break;
                         }
-                    } else{
-                        for stmt in &i.cons{
-                            self.translate_stmt(ctx, stmt, None).await?;
-                        }
-                        ctx.done();
-                        break;
                     }
+                }
+                let start = start.or_else(||s.cases.iter().position(|c|c.test.is_none()));
 
+                if let Some(start) = start{
+                    self.jumps.push(Jump{label:label.clone(), is_loop:false, labeled_only:false});
+
+                    // the case bodies fall through to the next case
+                    let ctx = vmctx.new_child();
+                    let re = self.translate_cases(ctx, &s.cases[start..]).await;
                     ctx.done();
+                    self.jumps.pop();
+
+                    match re{
+                        Err(Error::Break(l)) if l.is_none() || l == label => {},
+                        re => re?
+                    }
                 }
             },
 
             Stmt::Throw(t) => {
-                let arg = self.translate_expr(vmctx, &t.arg).await?;
-                return Err(Error::Value(arg))
+                let value = self.translate_expr(vmctx, &t.arg).await?;
+                return Err(Error::Value(value))
             },
 
             Stmt::Try(t) => {
-                let mut ctx = vmctx.new_child();
+                let depth = self.jumps.len();
 
-                let mut re = Ok(());
-                for stmt in &t.block.stmts{
-                    re = self.translate_stmt(ctx, stmt, None).await;
-                    if re.is_err(){
-                        break;
-                    }
-                }
+                let ctx = vmctx.new_child();
+                let mut re = catch_thrown(self.translate_stmts(ctx, &t.block.stmts)).await;
                 ctx.done();
+                self.jumps.truncate(depth);
 
-                if let Err(err) = re{
-
-                    if let Error::Value(err) = err{
-                        if let Some(h) = &t.handler{
-                            let ctx = vmctx.new_child();
-                    
-                            if let Some(p) = &h.param{
-                                self.translate_pat(ctx, p, err).await?;
-                            }
-                            
-                            for stmt in &h.body.stmts{
-                                self.translate_stmt(ctx, stmt, None).await?;
-                            }
-                            ctx.done()
-                        }
+                let thrown = match &re{
+                    Err(Error::Value(e)) => Some(*e),
+                    _ => None
+                };
 
-                    } else{
-                        // not a runtime error
-                        return Err(err)
-                    }
+                // a throw inside the catch block is not caught by it
+                if let (Some(e), Some(clause)) = (thrown, &t.handler){
+                    let ctx = vmctx.new_child();
+                    re = catch_thrown(self.translate_catch(ctx, clause, e)).await;
+                    ctx.done();
+                    self.jumps.truncate(depth);
                 }
 
+                // the finalizer runs on every completion,
+                // its own abrupt completion replaces the completion of the try statement
                 if let Some(f) = &t.finalizer{
-                    let ctx = vmctx.new_child();
-
-                    for stmt in &f.stmts{
-                        self.translate_stmt(ctx, stmt, None).await?;
-                    }
-
-                    ctx.done();
+                    self.translate_block(vmctx, &f.stmts).await?;
                 }
+                re?;
             },
 
             Stmt::While(w) => {
-                loop{
-                    if self.translate_expr(vmctx, &w.test).await?.to_bool(){
-                        let ctx = vmctx.new_child();
-                        self.translate_stmt(ctx, &w.body, None);
-                        ctx.done();
-                    } else{
-                        break;
-                    }
-                }
+                self.jumps.push(Jump{label:label.clone(), is_loop:true, labeled_only:false});
+                let re = self.translate_while(vmctx, stmt, &w.test, &w.body, &label, true).await;
+                self.jumps.pop();
+                re?;
             },
 
-            Stmt::With(w) => {
-                return Err(Error::Deprecated("with statment is deprecated and no longer supported."))
+            Stmt::With(_) => {
+                return Err(Error::Deprecated("`with` statment is deprecated and not supported."))
             }
         }
         Ok(())
     }
 
+    async fn translate_stmts(&mut self, vmctx:&mut VmContext, stmts:&'static [Stmt]) -> Result<(), Error>{
+        self.hoist_functions(vmctx, stmts.iter())?;
+        for s in stmts{
+            if !is_function_decl(s){
+                self.translate_stmt(vmctx, s, None).await?;
+            }
+        }
+        Ok(())
+    }
 
-    #[async_recursion]
-    pub async fn translate_expr(&mut self, vmctx:&mut VmContext, expr:&'static Expr) -> Result<JValue, Error>{
-        match expr{
-            Expr::Array(a) => {
-                todo!()
-            },
-            Expr::Arrow(a) => {
-
-                let vm = self.clone();
-                
-                // not a generator or async function
-                if !a.is_async && !a.is_generator{
+    /// create the function declarations of a statement list before its statements run
+    fn hoist_functions(&mut self, vmctx:&mut VmContext, stmts:impl Iterator<Item = &'static Stmt>) -> Result<(), Error>{
+        for s in stmts{
+            if let Stmt::Decl(Decl::Fn(f)) = s{
+                let func = self.translate_func(vmctx, &f.function, Some(&*f.ident.sym))?;
+                self.bind_ident(vmctx, &f.ident, func, Some(VarDeclKind::Var));
+            }
+        }
+        Ok(())
+    }
 
-                    // create a clousure
-                    Ok(JObject::fromInner(builtins::function::Function::native(move |vmctx, this, args|{
+    /// run the statements in a new scope
+    async fn translate_block(&mut self, vmctx:&mut VmContext, stmts:&'static [Stmt]) -> Result<(), Error>{
+        let ctx = vmctx.new_child();
+        let re = self.translate_stmts(ctx, stmts).await;
+        ctx.done();
+        re
+    }
 
-                        let mut vm = vm.clone();
+    async fn translate_cases(&mut self, vmctx:&mut VmContext, cases:&'static [SwitchCase]) -> Result<(), Error>{
+        for cas in cases{
+            self.translate_stmts(vmctx, &cas.cons).await?;
+        }
+        Ok(())
+    }
 
-                        let mut i = 0;
-                        let mut err = Ok(());
+    async fn translate_catch(&mut self, vmctx:&mut VmContext, clause:&'static CatchClause, thrown:JValue) -> Result<(), Error>{
+        if let Some(p) = &clause.param{
+            self.translate_pat(vmctx, p, thrown, Some(VarDeclKind::Var)).await?;
+        }
+        self.translate_stmts(vmctx, &clause.body.stmts).await
+    }
 
-                        // asign all the params
-                        for p in &a.params{
-                            if let Some(v) = args.get(i){
-                                err = futures::executor::block_on(vm.translate_pat(vmctx, p, *v));
-                            } else{
-                                err = futures::executor::block_on(vm.translate_pat(vmctx, p, JValue::Undefined));
-                            }
-                            check_err(&err);
-                            i+=1;
-                        }
+    /// check the target of a break or continue with the same rules as compiled code.
+    ///
+    /// an unlabeled break exits the nearest loop or switch,
+    /// an unlabeled continue the nearest loop.
+    fn check_jump(&self, label:Option<&str>, is_continue:bool, position:Position) -> Result<(), Error>{
+        let jump = match label{
+            Some(l) => match self.jumps.iter().rev().find(|j|j.label.as_deref() == Some(l)){
+                Some(j) => j,
+                None => return Err(Error::UndefinedLabel(l.to_string(), position))
+            },
+            None if is_continue => match self.jumps.iter().rev().find(|j|j.is_loop){
+                Some(j) => j,
+                None => return Err(Error::IllegalContinueStatment(position))
+            },
+            None => match self.jumps.iter().rev().find(|j|!j.labeled_only){
+                Some(j) => j,
+                None => return Err(Error::IllegalBreakStatement(position))
+            }
+        };
 
-                        // body
-                        match &a.body{
-                            BlockStmtOrExpr::Expr(e) => {
-                                let re = futures::executor::block_on(vm.translate_expr(vmctx, &e));
-                                if let Ok(v) = re{
-                                    return v
-                                } else{
-                                    check_err(&Err(re.err().unwrap()))
-                                }
-                            },
-                            BlockStmtOrExpr::BlockStmt(b) => {
-                                for stmt in &b.stmts{
-                                    err = futures::executor::block_on(vm.translate_stmt(vmctx, stmt, None));
-
-                                    if let Err(Error::Return(v)) = err{
-                                        return v;
-                                    } else{
-                                        check_err(&err)
-                                    }
-                                }
-                            }
-                        }
+        if is_continue && !jump.is_loop{
+            return Err(Error::IllegalContinueStatment(position))
+        }
+        Ok(())
+    }
 
-                        JValue::Undefined
-                    })).into())
-                    
-                } else if a.is_async && !a.is_generator{
+    /// count an iteration of a loop for tiering
+    fn back_edge(&self, vmctx:&mut VmContext){
+        if let Some(p) = &self.profile{
+            p.back_edge(vmctx);
+        }
+    }
 
-                    // is async but not generator
-                    return Ok(JObject::fromInner(builtins::function::Function::native(move |vmctx, this, args|{
-                        let mut vm = vm.clone();
+    /// count an iteration of the loop `stmt` at its header and continue a hot loop in compiled code.
+    ///
+    /// return true once the compiled loop completed, the loop is done.
+    fn enter_compiled_loop(&mut self, vmctx:&mut VmContext, stmt:&'static Stmt, label:&Option<String>) -> Result<bool, Error>{
+        if vmctx.runtime.execution_mode() != ExecutionMode::Tiered{
+            return Ok(false)
+        }
+        let code = match self.loops.back_edge(vmctx, stmt, label){
+            Some(code) => code,
+            None => return Ok(false)
+        };
+
+        // the compiled loop declares its bindings in a context of its own
+        let ctx = vmctx.new_child();
+        let (re, ok) = code(ctx, self.this, &[self.completion]);
+        ctx.done();
+
+        if !ok{
+            return Err(Error::Value(re))
+        }
+        self.completion = re;
+        Ok(true)
+    }
 
-                        let mut i = 0;
-                        let mut err = Ok(());
+    /// how a loop goes on after its body completed with `re`
+    fn loop_control(re:Result<(), Error>, label:&Option<String>) -> Result<LoopControl, Error>{
+        match re{
+            Ok(()) => Ok(LoopControl::Next),
+            Err(Error::Break(l)) if l.is_none() || l == *label => Ok(LoopControl::Break),
+            Err(Error::Continue(l)) if l.is_none() || l == *label => Ok(LoopControl::Next),
+            Err(e) => Err(e)
+        }
+    }
 
-                        let vmctx = vmctx.new_child();
+    /// `while` and `do while` loops, the body of a `do while` runs before the first test
+    async fn translate_while(&mut self, vmctx:&mut VmContext, stmt:&'static Stmt, test:&'static Expr, body:&'static Stmt, label:&Option<String>, test_first:bool) -> Result<(), Error>{
+        let mut first = true;
+        loop{
+            // the compiled loop starts before the test of a `while` and before the body of a `do while`
+            if test_first && self.enter_compiled_loop(vmctx, stmt, label)?{
+                break;
+            }
+            if test_first || !first{
+                if !self.translate_expr(vmctx, test).await?.to_bool(){
+                    break;
+                }
+            }
+            if !(test_first || first) && self.enter_compiled_loop(vmctx, stmt, label)?{
+                break;
+            }
+            first = false;
+            self.back_edge(vmctx);
 
-                        // asign params
-                        for p in &a.params{
-                            if let Some(v) = args.get(i){
-                                err = futures::executor::block_on(vm.translate_pat(vmctx, p, *v));
-                            } else{
-                                err = futures::executor::block_on(vm.translate_pat(vmctx, p, JValue::Undefined));
-                            }
-                            check_err(&err);
-                            i+=1;
-                        }
+            let ctx = vmctx.new_child();
+            let re = self.translate_stmt(ctx, body, None).await;
+            ctx.done();
 
-                        // return and spawn a promise
-                        return JObject::fromInner(Promise::native(async move{
-                            // the async part
-                            
-                            // body
-                            match &a.body{
-                                BlockStmtOrExpr::Expr(e) => {
-                                    let re = vm.translate_expr(vmctx, &e).await;
-                                    if let Ok(v) = re{
-                                        vmctx.done();
-                                        return v
-                                    } else{
-                                        check_err(&Err(re.err().unwrap()))
-                                    }
-                                },
-                                BlockStmtOrExpr::BlockStmt(b) => {
-                                    for stmt in &b.stmts{
-                                        err = futures::executor::block_on(vm.translate_stmt(vmctx, stmt, None));
-
-                                        if let Err(Error::Return(v)) = err{
-                                            vmctx.done();
-                                            return v;
-                                        } else{
-                                            check_err(&err)
-                                        }
-                                    }
-                                }
-                            }
-                            vmctx.done();
-                            JValue::Undefined
-                        })).into()
-                    })).into());
+            if let LoopControl::Break = Self::loop_control(re, label)?{
+                break;
+            }
+        }
+        Ok(())
+    }
 
-                } else{
-                    todo!()
+    async fn translate_for(&mut self, vmctx:&mut VmContext, stmt:&'static Stmt, f:&'static ForStmt, label:&Option<String>) -> Result<(), Error>{
+        // the `let` bindings of the initializer, copied for every iteration
+        let mut names = Vec::new();
+        if let Some(init) = &f.init{
+            match init{
+                VarDeclOrExpr::Expr(e) => {
+                    self.translate_expr(vmctx, e).await?;
+                },
+                VarDeclOrExpr::VarDecl(d) => {
+                    self.translate_var_decl(vmctx, d).await?;
+                    if d.kind == VarDeclKind::Let{
+                        d.decls.iter().for_each(|d|pat_names(&d.name, &mut names));
+                    }
                 }
-                
-            },
+            }
+        }
+        let names = names.iter().map(|n|variable_id(vmctx, n)).collect::<Vec<u64>>();
 
-            Expr::Assign(a) => {
-                let right = self.translate_expr(vmctx, &a.right).await?;
-                match &a.op{
-                    AssignOp::Assign => {
-                        Ok(right)
-                    },
-                    _ => todo!()
+        loop{
+            if self.enter_compiled_loop(vmctx, stmt, label)?{
+                break;
+            }
+            if let Some(test) = &f.test{
+                if !self.translate_expr(vmctx, test).await?.to_bool(){
+                    break;
                 }
-            },
+            }
 
-            Expr::Await(a) => {
-                let right = self.translate_expr(vmctx, &a.arg).await?;
-                if let Some(o) = right.object(){
-                    if let Some(i) = &o.inner{
-                        if let Some(v) = i.downcast_ref::<Promise>(){
-                            return Ok(v.await)
-                        }
-                    }
-                }
-                Ok(right)
+            self.back_edge(vmctx);
+
+            let ctx = vmctx.new_child();
+            let re = self.translate_stmt(ctx, &f.body, None).await;
+            ctx.done();
+
+            if let LoopControl::Break = Self::loop_control(re, label)?{
+                break;
             }
 
-            _ => todo!()
-        }
-    }
+            // the closures created by the body keep the bindings of their iteration
+            for id in &names{
+                let value = vmctx.get_variable(*id);
+                vmctx.declare_variable(*id, value, 1);
+            }
 
-    pub async fn translate_decl(&mut self, vmctx:&mut VmContext, decl:&Decl) -> Result<(), Error>{
+            // continue runs the update before the test
+            if let Some(update) = &f.update{
+                self.translate_expr(vmctx, update).await?;
+            }
+        }
         Ok(())
     }
 
-    pub async fn translate_var_decl(&mut self, vmctx:&mut VmContext, decl:&VarDecl) -> Result<(), Error>{
-        todo!()
+    /// run the body for every value of the iterator.
+    ///
+    /// if `close` is true, the iterator is closed by exits from the body.
+    async fn translate_iteration(&mut self, vmctx:&mut VmContext, left:&'static VarDeclOrPat, iterator:JValue, body:&'static Stmt, label:Option<String>, close:bool) -> Result<(), Error>{
+        self.jumps.push(Jump{label:label.clone(), is_loop:true, labeled_only:false});
+
+        let re = loop{
+            let value = match operator::iterator_next_raw(iterator){
+                (v, 0) => v,
+                (_, 1) => break Ok(()),
+                // the iterator is not closed if `next` throws
+                (e, _) => break Err(Error::Value(e))
+            };
+
+            self.back_edge(vmctx);
+
+            // a new binding is declared for every iteration
+            let ctx = vmctx.new_child();
+            let re = catch_thrown(self.translate_iteration_body(ctx, left, value, body)).await;
+            ctx.done();
+
+            match Self::loop_control(re, &label){
+                Ok(LoopControl::Next) => {},
+                Ok(LoopControl::Break) => {
+                    if close{
+                        if let Err(e) = check_raw(operator::iterator_close_raw(iterator)){
+                            break Err(e)
+                        }
+                    }
+                    break Ok(())
+                },
+                Err(e) => {
+                    if close{
+                        let (re, ok) = operator::iterator_close_raw(iterator);
+                        // errors from closing are ignored while an exception propagates
+                        if !ok && !matches!(e, Error::Value(_)){
+                            break Err(Error::Value(re))
+                        }
+                    }
+                    break Err(e)
+                }
+            }
+        };
+
+        self.jumps.pop();
+        re
     }
 
-    pub async fn translate_pat(&mut self, vmctx:&mut VmContext, p:&Pat, value:JValue) -> Result<(), Error>{
-        todo!()
+    async fn translate_iteration_body(&mut self, vmctx:&mut VmContext, left:&'static VarDeclOrPat, value:JValue, body:&'static Stmt) -> Result<(), Error>{
+        match left{
+            VarDeclOrPat::VarDecl(d) => {
+                self.translate_pat(vmctx, &d.decls[0].name, value, Some(d.kind)).await?;
+            },
+            VarDeclOrPat::Pat(p) => {
+                self.translate_pat(vmctx, p, value, None).await?;
+            }
+        };
+        self.translate_stmt(vmctx, body, None).await
     }
-}
 
-pub fn check_err(err:&Result<(), Error>){
-    if let Err(e) = err{
-        if let Error::Value(v) = e{
-            operator::throw(*v)
-        } else{
-            panic_any(e.clone());
+    #[async_recursion(?Send)]
+    pub async fn translate_expr(&mut self, vmctx:&mut VmContext, expr:&'static Expr) -> Result<JValue, Error>{
+        match expr{
+            Expr::Array(a) => {
+                let mut values = Vec::with_capacity(a.elems.len());
+                for e in &a.elems{
+                    match e{
+                        Some(e) => {
+                            let v = self.translate_expr(vmctx, &e.expr).await?;
+                            if e.spread.is_some(){
+                                values.extend(operator::IteratorCollect(v));
+                            } else{
+                                values.push(v);
+                            }
+                        },
+                        None => values.push(JValue::Undefined)
+                    }
+                }
+                Ok(Array::new(JObject::new(), &values))
+            },
+
+            Expr::Arrow(a) => {
+                let params = a.params.iter().collect::<Vec<&Pat>>();
+                let body = match &a.body{
+                    BlockStmtOrExpr::BlockStmt(b) => Body::Stmts(&b.stmts),
+                    BlockStmtOrExpr::Expr(e) => Body::Expr(e)
+                };
+                self.create_function(vmctx, params, body, a.is_async, a.is_generator, true, None)
+            },
+
+            Expr::Assign(a) => {
+                if a.op != AssignOp::Assign{
+                    return self.translate_compound_assign(vmctx, a).await
+                }
+
+                // the target is evaluated after the value
+                let value = self.translate_expr(vmctx, &a.right).await?;
+                match &a.left{
+                    PatOrExpr::Expr(e) => self.assign_target(vmctx, e, value).await?,
+                    PatOrExpr::Pat(p) => self.translate_pat(vmctx, p, value, None).await?
+                }
+                Ok(value)
+            },
+
+            Expr::Await(a) => {
+                if !self.is_async{
+                    return Err(Error::SyntaxError("await is only valid in async functions".to_string()))
+                }
+                let value = self.translate_expr(vmctx, &a.arg).await?;
+
+                // the async function is resumed with the settled value
+                let (value, mode) = Suspend::new(vmctx, value).await;
+                if mode == ResumeMode::Throw as i8{
+                    return Err(Error::Value(value))
+                }
+                Ok(value)
+            },
+
+            Expr::Bin(b) => {
+                // `#x in obj`
+                if let (BinaryOp::In, Expr::PrivateName(p)) = (b.op, b.left.as_ref()){
+                    let key = self.resolve_private(&p.id.sym)?;
+                    let obj = self.translate_expr(vmctx, &b.right).await?;
+                    return Ok(obj.private_in(key))
+                }
+
+                let left = self.translate_expr(vmctx, &b.left).await?;
+
+                // the right operand is only evaluated if the left does not short circuit
+                let short_circuit = match b.op{
+                    BinaryOp::LogicalAnd => Some(!left.to_bool()),
+                    BinaryOp::LogicalOr => Some(left.to_bool()),
+                    BinaryOp::NullishCoalescing => Some(!left.is_nullish()),
+                    _ => None
+                };
+                match short_circuit{
+                    Some(true) => return Ok(left),
+                    Some(false) => return self.translate_expr(vmctx, &b.right).await,
+                    None => {}
+                };
+
+                let right = self.translate_expr(vmctx, &b.right).await?;
+
+                Ok(match b.op{
+                    BinaryOp::Add => left + right,
+                    BinaryOp::BitAnd => left & right,
+                    BinaryOp::BitOr => left | right,
+                    BinaryOp::BitXor => left ^ right,
+                    BinaryOp::Div => left / right,
+                    BinaryOp::EqEq => left.eqeq(right),
+                    BinaryOp::EqEqEq => left.eqeqeq(right),
+                    BinaryOp::Exp => left.exp(right),
+                    BinaryOp::Gt => left.gt(right),
+                    BinaryOp::GtEq => left.gteq(right),
+                    BinaryOp::In => left.In(right),
+                    BinaryOp::InstanceOf => left.instanceof(right),
+                    BinaryOp::LShift => left << right,
+                    BinaryOp::LogicalAnd |
+                    BinaryOp::LogicalOr |
+                    BinaryOp::NullishCoalescing => unreachable!(),
+                    BinaryOp::Lt => left.lt(right),
+                    BinaryOp::LtEq => left.lteq(right),
+                    BinaryOp::Mod => left % right,
+                    BinaryOp::Mul => left * right,
+                    BinaryOp::NotEq => left.noteq(right),
+                    BinaryOp::NotEqEq => left.noteqeq(right),
+                    BinaryOp::RShift => left >> right,
+                    BinaryOp::Sub => left - right,
+                    BinaryOp::ZeroFillRShift => left.unsignedRShift(right),
+                })
+            },
+
+            Expr::Call(_) | Expr::Member(_) | Expr::OptChain(_) => {
+                // a short circuited chain is undefined
+                Ok(self.translate_chain(vmctx, expr).await?.unwrap_or(JValue::Undefined))
+            },
+
+            Expr::Class(c) => {
+                let name = c.ident.as_ref().map(|i|&*i.sym);
+                self.translate_class(vmctx, &c.class, name).await
+            },
+
+            Expr::Cond(c) => {
+                if self.translate_expr(vmctx, &c.test).await?.to_bool(){
+                    self.translate_expr(vmctx, &c.cons).await
+                } else{
+                    self.translate_expr(vmctx, &c.alt).await
+                }
+            },
+
+            Expr::Fn(f) => {
+                let name = match &f.ident{
+                    Some(i) => &*i.sym,
+                    None => return self.translate_func(vmctx, &f.function, None)
+                };
+                // the name is bound in a scope of its own, visible from the body
+                let ctx = vmctx.new_child();
+                self.bind_name(ctx, name, JValue::Undefined, Some(VarDeclKind::Const));
+                let re = self.translate_func(ctx, &f.function, Some(name));
+                if let Ok(func) = re{
+                    let id = variable_id(ctx, name);
+                    ctx.initialize_variable(id, func);
+                }
+                ctx.done();
+                re
+            },
+
+            Expr::Ident(i) => {
+                self.resolve_ident(vmctx, &i.sym)
+            },
+
+            Expr::Invalid(_) => {
+                Ok(JValue::Undefined)
+            },
+
+            Expr::Lit(l) => {
+                match l{
+                    Lit::BigInt(b) => Ok(JValue::BigInt(builtins::bigint::from_literal(b))),
+                    Lit::Bool(b) => Ok(JValue::Boolean(b.value)),
+                    Lit::Null(_) => Ok(JValue::Null),
+                    Lit::Num(n) => Ok(JValue::Number(n.value)),
+                    Lit::Regex(r) => Ok(RegExp::from_str(&r.exp, &r.flags)),
+                    Lit::Str(s) => Ok(s.value.as_ref().into()),
+                    Lit::JSXText(_) => Err(Error::Unimplemented("jsx."))
+                }
+            },
+
+            Expr::MetaProp(_) => {
+                Err(Error::Unimplemented("meta properties."))
+            },
+
+            Expr::New(n) => {
+                let callee = self.translate_expr(vmctx, &n.callee).await?;
+                let args = match &n.args{
+                    Some(a) => self.translate_args(vmctx, a).await?,
+                    None => Vec::new()
+                };
+                check_raw(unsafe{callee.new_raw(vmctx, args.as_ptr(), args.len() as i64, false)})
+            },
+
+            Expr::Object(o) => {
+                let obj = unsafe{JObject::construct()};
+                for p in &o.props{
+                    match p{
+                        PropOrSpread::Spread(s) => {
+                            let spread = self.translate_expr(vmctx, &s.expr).await?;
+                            obj.set_member_spread(spread);
+                        },
+                        PropOrSpread::Prop(p) => self.translate_object_prop(vmctx, obj, p).await?
+                    }
+                }
+                Ok(obj)
+            },
+
+            Expr::Paren(p) => {
+                self.translate_expr(vmctx, &p.expr).await
+            },
+
+            Expr::PrivateName(p) => {
+                Err(Error::SyntaxError(format!("Unexpected private field '#{}'", p.id.sym)))
+            },
+
+            Expr::Seq(s) => {
+                let mut v = JValue::Undefined;
+                for e in &s.exprs{
+                    v = self.translate_expr(vmctx, e).await?;
+                }
+                Ok(v)
+            },
+
+            Expr::TaggedTpl(t) => {
+                // the template object is created once per call site
+                let strings = *self.templates.borrow_mut()
+                    .entry(&t.tpl as *const Tpl)
+                    .or_insert_with(||template_object(&t.tpl));
+
+                let (callee, this) = match t.tag.as_ref(){
+                    Expr::Member(m) if !matches!(m.obj, ExprOrSuper::Super(_)) => {
+                        let (obj, prop) = self.translate_reference(vmctx, m).await?;
+                        (self.get_reference(vmctx, m, obj, prop), obj)
+                    },
+                    tag => (self.translate_expr(vmctx, tag).await?, self.this)
+                };
+
+                let mut args = vec![strings];
+                for e in &t.tpl.exprs{
+                    args.push(self.translate_expr(vmctx, e).await?);
+                }
+                check_raw(unsafe{callee.call_raw(vmctx, this, args.as_ptr(), args.len() as i64, false)})
+            },
+
+            Expr::This(_) => {
+                Ok(self.this)
+            },
+
+            Expr::Tpl(t) => {
+                let mut values:Vec<JValue> = Vec::new();
+                for (i, q) in t.quasis.iter().enumerate(){
+                    let cooked = match &q.cooked{
+                        Some(c) => c.value.as_ref(),
+                        None => return Err(Error::SyntaxError("Invalid escape sequence in template".to_string()))
+                    };
+                    values.push(cooked.into());
+
+                    if !q.tail{
+                        values.push(self.translate_expr(vmctx, &t.exprs[i]).await?);
+                    }
+                }
+                Ok(unsafe{operator::tpl_new(values.as_ptr(), values.len() as i64)})
+            },
+
+            Expr::Unary(u) => {
+                match (u.op, u.arg.as_ref()){
+                    // `typeof x` does not throw if x is not declared
+                    (UnaryOp::TypeOf, Expr::Ident(i)) => {
+                        let id = variable_id(vmctx, &i.sym);
+                        Ok(vmctx.typeof_variable(id))
+                    },
+
+                    (UnaryOp::Delete, Expr::Member(m)) => {
+                        if is_private(&m.prop){
+                            return Err(Error::SyntaxError("Private fields can not be deleted".to_string()))
+                        }
+                        let obj = match &m.obj{
+                            ExprOrSuper::Expr(e) => self.translate_expr(vmctx, e).await?,
+                            ExprOrSuper::Super(_) => return Err(Error::Unimplemented("delete of a super property."))
+                        };
+                        let prop = self.translate_prop(vmctx, &m.prop, m.computed).await?;
+                        Ok(obj.delete_member(prop))
+                    },
+
+                    // declared bindings cannot be deleted
+                    (UnaryOp::Delete, Expr::Ident(_)) => {
+                        Ok(JValue::Boolean(false))
+                    },
+
+                    (UnaryOp::Delete, e) => {
+                        self.translate_expr(vmctx, e).await?;
+                        Ok(JValue::Boolean(true))
+                    },
+
+                    (op, e) => {
+                        let v = self.translate_expr(vmctx, e).await?;
+                        Ok(match op{
+                            UnaryOp::Void => JValue::Undefined,
+                            UnaryOp::TypeOf => v.typeOf(),
+                            UnaryOp::Bang => v.not(),
+                            UnaryOp::Minus => v.neg(),
+                            UnaryOp::Plus => v.to_number(),
+                            UnaryOp::Tilde => v.bitNot(),
+                            UnaryOp::Delete => unreachable!()
+                        })
+                    }
+                }
+            },
+
+            Expr::Update(u) => {
+                let op:fn(JValue) -> JValue = match u.op{
+                    UpdateOp::PlusPlus => JValue::increment,
+                    UpdateOp::MinusMinus => JValue::decrement,
+                };
+
+                match unparen(&u.arg){
+                    Expr::Ident(i) => {
+                        let old = self.resolve_ident(vmctx, &i.sym)?.to_numeric();
+                        let new = op(old);
+                        self.bind_ident(vmctx, i, new, None);
+                        Ok(if u.prefix {new} else {old})
+                    },
+                    Expr::Member(m) => {
+                        let (obj, prop) = self.translate_reference(vmctx, m).await?;
+                        let old = self.get_reference(vmctx, m, obj, prop).to_numeric();
+                        let new = op(old);
+                        self.set_reference(m, obj, prop, new);
+                        Ok(if u.prefix {new} else {old})
+                    },
+                    _ => Err(Error::SyntaxError(format!("Invalid left-hand side expression in {} operation", if u.prefix {"prefix"} else {"postfix"})))
+                }
+            },
+
+            Expr::Yield(y) => {
+                if !self.is_generator{
+                    return Err(Error::SyntaxError("yield is only valid in generator functions".to_string()))
+                }
+
+                let value = match &y.arg{
+                    Some(a) => self.translate_expr(vmctx, a).await?,
+                    None => JValue::Undefined
+                };
+
+                if y.delegate{
+                    return self.translate_yield_delegate(vmctx, value).await
+                }
+
+                let (sent, mode) = Suspend::new(vmctx, value).await;
+                match mode{
+                    // generator.return(value)
+                    m if m == ResumeMode::Return as i8 => Err(Error::Return(sent)),
+                    // generator.throw(value)
+                    m if m == ResumeMode::Throw as i8 => Err(Error::Value(sent)),
+                    _ => Ok(sent)
+                }
+            },
+
+            Expr::TsAs(_) |
+            Expr::TsConstAssertion(_) |
+            Expr::TsNonNull(_) |
+            Expr::TsTypeAssertion(_) => Err(Error::Unimplemented("typescript expressions.")),
+
+            Expr::JSXElement(_) |
+            Expr::JSXEmpty(_) |
+            Expr::JSXFragment(_) |
+            Expr::JSXMember(_) |
+            Expr::JSXNamespacedName(_) => Err(Error::Unimplemented("jsx."))
+        }
+    }
+
+    /// evaluate an element of an optional chain, none if the chain short circuits
+    #[async_recursion(?Send)]
+    async fn translate_chain(&mut self, vmctx:&mut VmContext, expr:&'static Expr) -> Result<Option<JValue>, Error>{
+        match expr{
+            Expr::Member(m) => self.translate_member(vmctx, m, false).await,
+            Expr::Call(c) => self.translate_call(vmctx, c, false).await,
+            Expr::OptChain(o) => match o.expr.as_ref(){
+                Expr::Member(m) => self.translate_member(vmctx, m, true).await,
+                Expr::Call(c) => self.translate_call(vmctx, c, true).await,
+                _ => Err(Error::SyntaxError("Invalid optional chain".to_string()))
+            },
+            e => Ok(Some(self.translate_expr(vmctx, e).await?))
+        }
+    }
+
+    /// `obj.prop`, `obj?.prop` if optional
+    async fn translate_member(&mut self, vmctx:&mut VmContext, m:&'static MemberExpr, optional:bool) -> Result<Option<JValue>, Error>{
+        let obj = match &m.obj{
+            ExprOrSuper::Expr(e) => match self.translate_chain(vmctx, e).await?{
+                Some(obj) if !(optional && obj.is_nullish()) => obj,
+                _ => return Ok(None)
+            },
+            // `super.prop` is read from the home object with `this` as receiver
+            ExprOrSuper::Super(_) => self.this
+        };
+        let prop = self.translate_prop(vmctx, &m.prop, m.computed).await?;
+        Ok(Some(self.get_reference(vmctx, m, obj, prop)))
+    }
+
+    /// `callee(args)`, `callee?.(args)` if optional.
+    ///
+    /// the callee is evaluated before the arguments.
+    async fn translate_call(&mut self, vmctx:&mut VmContext, c:&'static CallExpr, optional:bool) -> Result<Option<JValue>, Error>{
+        let callee = match &c.callee{
+            ExprOrSuper::Expr(e) => e.as_ref(),
+            ExprOrSuper::Super(_) => {
+                let args = self.translate_args(vmctx, &c.args).await?;
+                let re = unsafe{builtins::Function::super_call_raw(vmctx, self.this, args.as_ptr(), args.len() as i64, false)};
+                return check_raw(re).map(Some)
+            }
+        };
+
+        // member calls pass the object as this, `a?.b()` is a member call too
+        let member = match callee{
+            Expr::Member(m) => Some((m, false)),
+            Expr::OptChain(o) => match o.expr.as_ref(){
+                Expr::Member(m) => Some((m, true)),
+                _ => None
+            },
+            _ => None
+        };
+
+        let (callee, this) = match member{
+            Some((m, optional_member)) => {
+                let obj = match &m.obj{
+                    ExprOrSuper::Expr(e) => match self.translate_chain(vmctx, e).await?{
+                        Some(obj) if !(optional_member && obj.is_nullish()) => obj,
+                        _ => return Ok(None)
+                    },
+                    ExprOrSuper::Super(_) => self.this
+                };
+                let prop = self.translate_prop(vmctx, &m.prop, m.computed).await?;
+                (self.get_reference(vmctx, m, obj, prop), obj)
+            },
+            // this is a function call
+            None => match self.translate_chain(vmctx, callee).await?{
+                Some(callee) => (callee, self.this),
+                None => return Ok(None)
+            }
+        };
+
+        if optional && callee.is_nullish(){
+            return Ok(None)
+        }
+
+        let args = self.translate_args(vmctx, &c.args).await?;
+        check_raw(unsafe{callee.call_raw(vmctx, this, args.as_ptr(), args.len() as i64, false)}).map(Some)
+    }
+
+    /// evaluate the arguments of a call, spread arguments are expanded
+    async fn translate_args(&mut self, vmctx:&mut VmContext, args:&'static [ExprOrSpread]) -> Result<Vec<JValue>, Error>{
+        let mut values = Vec::with_capacity(args.len());
+        for a in args{
+            let v = self.translate_expr(vmctx, &a.expr).await?;
+            if a.spread.is_some(){
+                values.extend(operator::IteratorCollect(v));
+            } else{
+                values.push(v);
+            }
+        }
+        Ok(values)
+    }
+
+    async fn translate_object_prop(&mut self, vmctx:&mut VmContext, obj:JValue, prop:&'static Prop) -> Result<(), Error>{
+        match prop{
+            Prop::Shorthand(i) => {
+                let v = self.resolve_ident(vmctx, &i.sym)?;
+                obj.set_member(i.sym.as_ref().into(), v);
+            },
+            Prop::KeyValue(kv) => {
+                let key = self.translate_prop_name(vmctx, &kv.key).await?;
+                let v = self.translate_expr(vmctx, &kv.value).await?;
+                obj.set_member(key, v);
+            },
+            Prop::Method(m) => {
+                let key = self.translate_prop_name(vmctx, &m.key).await?;
+                let func = self.translate_func(vmctx, &m.function, prop_name_str(&m.key))?;
+                builtins::Function::define_method(obj, key, func, 0);
+            },
+            Prop::Getter(g) => {
+                let key = self.translate_prop_name(vmctx, &g.key).await?;
+                let body = match &g.body{
+                    Some(b) => Body::Stmts(&b.stmts),
+                    None => Body::Stmts(&[])
+                };
+                let func = self.create_function(vmctx, Vec::new(), body, false, false, false, prop_name_str(&g.key))?;
+                builtins::Function::define_method(obj, key, func, 1);
+            },
+            Prop::Setter(s) => {
+                let key = self.translate_prop_name(vmctx, &s.key).await?;
+                let param:&'static Pat = &s.param;
+                let body = match &s.body{
+                    Some(b) => Body::Stmts(&b.stmts),
+                    None => Body::Stmts(&[])
+                };
+                let func = self.create_function(vmctx, vec![param], body, false, false, false, prop_name_str(&s.key))?;
+                builtins::Function::define_method(obj, key, func, 2);
+            },
+            Prop::Assign(_) => {
+                return Err(Error::SyntaxError("Invalid shorthand property initializer".to_string()))
+            }
+        }
+        Ok(())
+    }
+
+    /// `target op= value`, the target is evaluated once before the value.
+    async fn translate_compound_assign(&mut self, vmctx:&mut VmContext, a:&'static AssignExpr) -> Result<JValue, Error>{
+        let target = match &a.left{
+            PatOrExpr::Expr(e) => unparen(e),
+            PatOrExpr::Pat(p) => match p.as_ref(){
+                Pat::Expr(e) => unparen(e),
+                Pat::Ident(i) => return self.assign_ident_op(vmctx, a, &i.id).await,
+                _ => return Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+            }
+        };
+
+        match target{
+            Expr::Ident(i) => self.assign_ident_op(vmctx, a, i).await,
+            Expr::Member(m) => {
+                let (obj, prop) = self.translate_reference(vmctx, m).await?;
+                let old = self.get_reference(vmctx, m, obj, prop);
+                match self.assign_op(vmctx, a, old).await?{
+                    Some(v) => {
+                        self.set_reference(m, obj, prop, v);
+                        Ok(v)
+                    },
+                    None => Ok(old)
+                }
+            },
+            _ => Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+        }
+    }
+
+    async fn assign_ident_op(&mut self, vmctx:&mut VmContext, a:&'static AssignExpr, ident:&'static Ident) -> Result<JValue, Error>{
+        let old = self.resolve_ident(vmctx, &ident.sym)?;
+        match self.assign_op(vmctx, a, old).await?{
+            Some(v) => {
+                self.bind_ident(vmctx, ident, v, None);
+                Ok(v)
+            },
+            None => Ok(old)
+        }
+    }
+
+    /// `old op value` of a compound assignment, none if a logical assignment short circuits.
+    ///
+    /// the value is only evaluated if the assignment does not short circuit.
+    async fn assign_op(&mut self, vmctx:&mut VmContext, a:&'static AssignExpr, old:JValue) -> Result<Option<JValue>, Error>{
+        let short_circuit = match a.op{
+            AssignOp::AndAssign => !old.to_bool(),
+            AssignOp::OrAssign => old.to_bool(),
+            AssignOp::NullishAssign => !old.is_nullish(),
+            _ => false
+        };
+        if short_circuit{
+            return Ok(None)
+        }
+        let value = self.translate_expr(vmctx, &a.right).await?;
+        Ok(old.apply_assign_op(value, a.op))
+    }
+
+    /// assign to an identifier or a member expression
+    async fn assign_target(&mut self, vmctx:&mut VmContext, target:&'static Expr, value:JValue) -> Result<(), Error>{
+        match unparen(target){
+            Expr::Ident(i) => self.bind_ident(vmctx, i, value, None),
+            Expr::Member(m) => {
+                let (obj, prop) = self.translate_reference(vmctx, m).await?;
+                self.set_reference(m, obj, prop, value);
+            },
+            _ => return Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+        }
+        Ok(())
+    }
+
+    /// the object and key of a member expression, the object is `this` for super properties
+    async fn translate_reference(&mut self, vmctx:&mut VmContext, m:&'static MemberExpr) -> Result<(JValue, JValue), Error>{
+        let obj = match &m.obj{
+            ExprOrSuper::Expr(e) => self.translate_expr(vmctx, e).await?,
+            ExprOrSuper::Super(_) => self.this
+        };
+        let prop = self.translate_prop(vmctx, &m.prop, m.computed).await?;
+        Ok((obj, prop))
+    }
+
+    /// read the member resolved by `translate_reference`,
+    /// super properties are read from the home object.
+    fn get_reference(&self, vmctx:&mut VmContext, m:&MemberExpr, obj:JValue, prop:JValue) -> JValue{
+        if is_private(&m.prop){
+            obj.private_get(prop)
+        } else if let ExprOrSuper::Super(_) = &m.obj{
+            JValue::superMember(vmctx, obj, prop)
+        } else{
+            obj.member(prop)
+        }
+    }
+
+    /// write the member resolved by `translate_reference`, `super.x = v` assigns to `this`
+    fn set_reference(&self, m:&MemberExpr, obj:JValue, prop:JValue, value:JValue){
+        if is_private(&m.prop){
+            obj.private_set(prop, value, AssignOp::Assign as i8);
+        } else{
+            obj.set_member(prop, value);
+        }
+    }
+
+    /// `yield* iterable`, forwarding every resumption to the inner iterator
+    async fn translate_yield_delegate(&mut self, vmctx:&mut VmContext, iterable:JValue) -> Result<JValue, Error>{
+        let iterator = check_raw(operator::get_iterator_raw(iterable))?;
+
+        let (mut sent, mut mode) = (JValue::Undefined, ResumeMode::Next as i8);
+        loop{
+            let (value, status) = Generator::delegate_raw(vmctx, iterator, sent, mode);
+            match status{
+                0 => {
+                    let (s, m) = Suspend::new(vmctx, value).await;
+                    sent = s;
+                    mode = m;
+                },
+                1 => return Ok(value),
+                2 => return Err(Error::Return(value)),
+                _ => return Err(Error::Value(value))
+            }
+        }
+    }
+
+    pub async fn translate_decl(&mut self, vmctx:&mut VmContext, decl:&'static Decl) -> Result<(), Error>{
+        match decl{
+            Decl::Class(c) => {
+                let class = self.translate_class(vmctx, &c.class, Some(&*c.ident.sym)).await?;
+                self.bind_ident(vmctx, &c.ident, class, Some(VarDeclKind::Let));
+            },
+            Decl::Fn(f) => {
+                let func = self.translate_func(vmctx, &f.function, Some(&*f.ident.sym))?;
+                self.bind_ident(vmctx, &f.ident, func, Some(VarDeclKind::Var));
+            },
+            Decl::Var(v) => {
+                self.translate_var_decl(vmctx, v).await?;
+            },
+            Decl::TsEnum(_) |
+            Decl::TsInterface(_) |
+            Decl::TsModule(_) |
+            Decl::TsTypeAlias(_) => return Err(Error::Unimplemented("typescript declarations."))
+        }
+        Ok(())
+    }
+
+    pub async fn translate_var_decl(&mut self, vmctx:&mut VmContext, decl:&'static VarDecl) -> Result<(), Error>{
+        for dec in &decl.decls{
+            let value = match &dec.init{
+                Some(e) => self.translate_expr(vmctx, e).await?,
+                None => JValue::Undefined
+            };
+            self.translate_pat(vmctx, &dec.name, value, Some(decl.kind)).await?;
         }
-    };
-}
\ No newline at end of file
+        Ok(())
+    }
+
+    pub fn translate_func(&mut self, vmctx:&mut VmContext, func:&'static Function, name:Option<&str>) -> Result<JValue, Error>{
+        let params = func.params.iter().map(|p|&p.pat).collect::<Vec<&Pat>>();
+        let body = match &func.body{
+            Some(b) => Body::Stmts(&b.stmts),
+            None => Body::Stmts(&[])
+        };
+        self.create_function(vmctx, params, body, func.is_async, func.is_generator, false, name)
+    }
+
+    /// create a function object running `body` with a new interpreter.
+    fn create_function(&mut self, vmctx:&mut VmContext, params:Vec<&'static Pat>, body:Body, is_async:bool, is_generator:bool, is_arrow:bool, name:Option<&str>) -> Result<JValue, Error>{
+        if is_async && is_generator{
+            return Err(Error::Unimplemented("async generator functions."))
+        }
+
+        let length = params.len();
+        let mut interpreter = self.function_interpreter(is_async, is_generator);
+
+        // in tiered mode hot functions are compiled by the jit
+        let profile = match vmctx.runtime.execution_mode(){
+            ExecutionMode::Tiered => Some(Arc::new(Profile::new(params.clone(), body, is_async, is_generator))),
+            _ => None
+        };
+        interpreter.profile = profile.clone();
+
+        let code = move |vmctx:&mut VmContext, this:JValue, args:&[JValue]|{
+            if let Some(p) = &interpreter.profile{
+                // a generator is only counted when it starts, 
+                // a started generator keeps running its interpreted body.
+                if !(is_async || is_generator) || vmctx.generator().body.is_none(){
+                    p.enter(vmctx);
+                }
+            }
+
+            let mut interpreter = interpreter.clone();
+            interpreter.this = this;
+
+            if is_async || is_generator{
+                return interpreter.resume_body(vmctx, params.clone(), body, args)
+            }
+            match run_sync(interpreter.call_body(vmctx, &params, body, args)){
+                Ok(v) => v,
+                Err(e) => throw_error(e, interpreter.script.source())
+            }
+        };
+
+        let func = builtins::Function::from_object(JObject::new(), Arc::new(code), is_async, is_generator);
+        if let Some(p) = profile{
+            p.set_function(func);
+        }
+        self.init_function(vmctx, func, is_arrow, length, name);
+        Ok(func)
+    }
+
+    /// capture the variables visible from `vmctx` and set the `length` and `name` of a new function
+    fn init_function(&self, vmctx:&mut VmContext, func:JValue, is_arrow:bool, length:usize, name:Option<&str>){
+        // the free variables of the body are not resolved, every visible variable is captured
+        let names = vmctx.visible_names();
+        if let Some(f) = func.object().unwrap().inner.function(){
+            f.to_mut().try_capture(unsafe{&mut *(vmctx as *mut VmContext)}, &names);
+        }
+
+        // arrow functions use the `this` of the enclosing function
+        if is_arrow{
+            builtins::Function::bind_this(func, vmctx, self.this);
+        }
+
+        func.set_member("length".into(), JValue::Number(length as f64));
+        if let Some(name) = name{
+            func.set_member("name".into(), name.into());
+        }
+    }
+
+    /// bind the arguments to the parameters and run the body of a function
+    async fn call_body(&mut self, vmctx:&mut VmContext, params:&[&'static Pat], body:Body, args:&[JValue]) -> Result<JValue, Error>{
+        for (i, p) in params.iter().copied().enumerate(){
+            // `...args` collects the remaining arguments
+            let (p, v) = match p{
+                Pat::Rest(r) => (r.arg.as_ref(), Array::new(JObject::new(), args.get(i..).unwrap_or(&[]))),
+                p => (p, args.get(i).copied().unwrap_or(JValue::Undefined))
+            };
+            self.translate_pat(vmctx, p, v, Some(VarDeclKind::Var)).await?;
+        }
+
+        match body{
+            Body::Stmts(stmts) => match self.translate_stmts(vmctx, stmts).await{
+                Ok(()) => Ok(JValue::Undefined),
+                Err(Error::Return(v)) => Ok(v),
+                Err(e) => Err(e)
+            },
+            Body::Expr(e) => self.translate_expr(vmctx, e).await
+        }
+    }
+
+    /// run the body of a generator or async function until it suspends or completes.
+    ///
+    /// the body is started by the first resume and kept by the generator,
+    /// the value of a suspension is saved in the generator by `Suspend`.
+    fn resume_body(self, vmctx:&mut VmContext, params:Vec<&'static Pat>, body:Body, args:&[JValue]) -> JValue{
+        let script = self.script.clone();
+        let g = vmctx.generator();
+
+        if g.body.is_none(){
+            let mut interpreter = self;
+            let args = args.to_vec();
+            // the context is owned by the generator and outlives the body
+            let vmctx = unsafe{&mut *(vmctx as *mut VmContext)};
+
+            g.body = Some(Box::pin(async move{
+                interpreter.call_body(vmctx, &params, body, &args).await
+            }));
+        }
+
+        let mut cx = TaskContext::from_waker(noop_waker_ref());
+        let re = match g.body.as_mut().unwrap().as_mut().poll(&mut cx){
+            Poll::Pending => return JValue::Undefined,
+            Poll::Ready(re) => re
+        };
+        g.body = None;
+
+        match re{
+            Ok(v) => v,
+            Err(e) => throw_error(e, script.source())
+        }
+    }
+
+    /// evaluate a class and return its constructor
+    async fn translate_class(&mut self, vmctx:&mut VmContext, class:&'static Class, name:Option<&'static str>) -> Result<JValue, Error>{
+        // the class scope
+        let ctx = vmctx.new_child();
+
+        // every evaluation of the class creates new private names
+        let mut names = HashMap::new();
+        for m in &class.body{
+            let key = match m{
+                ClassMember::PrivateMethod(p) => &p.key,
+                ClassMember::PrivateProp(p) => &p.key,
+                _ => continue
+            };
+            // a getter and setter pair shares the name
+            names.entry(key.id.sym.to_string())
+                .or_insert_with(||operator::private_name_new(key.id.sym.as_ref().into()));
+        }
+        self.private_names.push(names);
+
+        let re = self.translate_class_body(ctx, class, name).await;
+
+        self.private_names.pop();
+        ctx.done();
+        re
+    }
+
+    async fn translate_class_body(&mut self, vmctx:&mut VmContext, class:&'static Class, name:Option<&'static str>) -> Result<JValue, Error>{
+        // the inner binding of the class name, assigned once the class is defined
+        if let Some(name) = name{
+            self.bind_name(vmctx, name, JValue::Undefined, Some(VarDeclKind::Let));
+        }
+
+        let (parent, has_parent) = match &class.super_class{
+            Some(s) => (self.translate_expr(vmctx, s).await?, true),
+            None => (JValue::Undefined, false)
+        };
+
+        let ctor = class.body.iter().find_map(|m|match m{
+            ClassMember::Constructor(c) => Some(c),
+            _ => None
+        });
+        let ctor = match ctor{
+            Some(c) => {
+                let mut params = Vec::new();
+                for p in &c.params{
+                    match p{
+                        ParamOrTsParamProp::Param(p) => params.push(&p.pat),
+                        ParamOrTsParamProp::TsParamProp(_) => return Err(Error::Unimplemented("typescript parameter properties."))
+                    }
+                }
+                let body = match &c.body{
+                    Some(b) => Body::Stmts(&b.stmts),
+                    None => Body::Stmts(&[])
+                };
+                self.create_function(vmctx, params, body, false, false, false, name)?
+            },
+            None => self.default_constructor(vmctx, has_parent, name)
+        };
+
+        let ctor = builtins::Function::class_new(ctor, parent, has_parent);
+        if let Some(name) = name{
+            self.bind_name(vmctx, name, ctor, None);
+        }
+        let proto = ctor.member_str("prototype");
+
+        let mut instance_elements = Vec::new();
+        let mut static_elements = Vec::new();
+        // private methods are installed before any field
+        let mut private_methods = 0;
+
+        for m in &class.body{
+            match m{
+                ClassMember::Method(m) => {
+                    let key = self.translate_prop_name(vmctx, &m.key).await?;
+                    let func = self.translate_func(vmctx, &m.function, prop_name_str(&m.key))?;
+                    let target = if m.is_static{ctor} else{proto};
+                    builtins::Function::define_method(target, key, func, method_kind(m.kind));
+                },
+                ClassMember::PrivateMethod(m) => {
+                    let func = self.translate_func(vmctx, &m.function, Some(&*m.key.id.sym))?;
+                    let home = if m.is_static{ctor} else{proto};
+                    builtins::Function::set_home_object(func, home);
+
+                    let key = self.resolve_private(&m.key.id.sym)?;
+                    let kind = method_kind(m.kind) + 1;
+                    if m.is_static{
+                        ctor.private_define(key, func, kind);
+                    } else{
+                        instance_elements.insert(private_methods, ClassElement::PrivateMethod(key, func, kind));
+                        private_methods += 1;
+                    }
+                },
+                ClassMember::ClassProp(p) => {
+                    // computed keys are evaluated once when the class is defined
+                    let key = self.translate_prop_name(vmctx, &p.key).await?;
+                    let e = ClassElement::Field(key, p.value.as_deref());
+                    if p.is_static{
+                        static_elements.push(e);
+                    } else{
+                        instance_elements.push(e);
+                    }
+                },
+                ClassMember::PrivateProp(p) => {
+                    let key = self.resolve_private(&p.key.id.sym)?;
+                    let e = ClassElement::PrivateField(key, p.value.as_deref());
+                    if p.is_static{
+                        static_elements.push(e);
+                    } else{
+                        instance_elements.push(e);
+                    }
+                },
+                ClassMember::StaticBlock(b) => {
+                    static_elements.push(ClassElement::StaticBlock(&b.body));
+                },
+                ClassMember::Constructor(_) |
+                ClassMember::TsIndexSignature(_) |
+                ClassMember::Empty(_) => {}
+            }
+        }
+
+        if !instance_elements.is_empty(){
+            let init = self.class_initializer(vmctx, instance_elements);
+            builtins::Function::set_home_object(init, proto);
+            builtins::Function::class_fields(ctor, init);
+        }
+
+        if !static_elements.is_empty(){
+            let init = self.class_initializer(vmctx, static_elements);
+            builtins::Function::set_home_object(init, ctor);
+            check_raw(unsafe{init.call_raw(vmctx, ctor, std::ptr::NonNull::dangling().as_ptr(), 0, false)})?;
+        }
+
+        Ok(ctor)
+    }
+
+    /// `constructor(...args){ super(...args) }` for derived classes, an empty function otherwise
+    fn default_constructor(&self, vmctx:&mut VmContext, is_derived:bool, name:Option<&str>) -> JValue{
+        let code = move |vmctx:&mut VmContext, this:JValue, args:&[JValue]|{
+            if is_derived{
+                let (re, ok) = unsafe{builtins::Function::super_call_raw(vmctx, this, args.as_ptr(), args.len() as i64, false)};
+                if !ok{
+                    operator::throw(re)
+                }
+            }
+            JValue::Undefined
+        };
+
+        let func = builtins::Function::from_object(JObject::new(), Arc::new(code), false, false);
+        self.init_function(vmctx, func, false, 0, name);
+        func
+    }
+
+    /// a function evaluating the elements on `this`
+    fn class_initializer(&self, vmctx:&mut VmContext, elements:Vec<ClassElement>) -> JValue{
+        let interpreter = self.function_interpreter(false, false);
+        let elements = Arc::new(elements);
+
+        let code = move |vmctx:&mut VmContext, this:JValue, _args:&[JValue]|{
+            let mut interpreter = interpreter.clone();
+            interpreter.this = this;
+
+            match run_sync(interpreter.translate_class_elements(vmctx, &elements)){
+                Ok(()) => JValue::Undefined,
+                Err(e) => throw_error(e, interpreter.script.source())
+            }
+        };
+
+        let func = builtins::Function::from_object(JObject::new(), Arc::new(code), false, false);
+        self.init_function(vmctx, func, false, 0, None);
+        func
+    }
+
+    async fn translate_class_elements(&mut self, vmctx:&mut VmContext, elements:&[ClassElement]) -> Result<(), Error>{
+        for e in elements{
+            match e{
+                ClassElement::Field(key, value) => {
+                    let value = match value{
+                        Some(v) => self.translate_expr(vmctx, *v).await?,
+                        None => JValue::Undefined
+                    };
+                    self.this.define_field(*key, value);
+                },
+                ClassElement::PrivateField(key, value) => {
+                    let value = match value{
+                        Some(v) => self.translate_expr(vmctx, *v).await?,
+                        None => JValue::Undefined
+                    };
+                    self.this.private_define(*key, value, 0);
+                },
+                ClassElement::PrivateMethod(key, func, kind) => {
+                    self.this.private_define(*key, *func, *kind);
+                },
+                ClassElement::StaticBlock(b) => {
+                    self.translate_block(vmctx, &b.stmts).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// bind `value` to the pattern, declaring the variables if `kind` is some.
+    ///
+    /// uses the same destructuring helpers as compiled code.
+    #[async_recursion(?Send)]
+    pub async fn translate_pat(&mut self, vmctx:&mut VmContext, p:&'static Pat, value:JValue, kind:Option<VarDeclKind>) -> Result<(), Error>{
+        match p{
+            Pat::Ident(i) => {
+                self.bind_ident(vmctx, &i.id, value, kind);
+            },
+
+            // the default is only evaluated if the value is undefined
+            Pat::Assign(a) => {
+                let value = if value.is_undefined(){
+                    self.translate_expr(vmctx, &a.right).await?
+                } else{
+                    value
+                };
+                self.translate_pat(vmctx, &a.left, value, kind).await?;
+            },
+
+            Pat::Array(a) => {
+                let iterator = check_raw(operator::get_iterator_raw(value))?;
+                let mut done = false;
+
+                for elem in &a.elems{
+                    if let Some(Pat::Rest(r)) = elem{
+                        let rest = check_raw(operator::iterator_rest_raw(iterator, done))?;
+                        done = true;
+                        self.translate_pat(vmctx, &r.arg, rest, kind).await?;
+                        continue;
+                    }
+
+                    let v = if done{
+                        JValue::Undefined
+                    } else{
+                        match operator::iterator_next_raw(iterator){
+                            (v, 0) => v,
+                            (_, 1) => {
+                                done = true;
+                                JValue::Undefined
+                            },
+                            // the iterator is not closed if `next` throws
+                            (e, _) => return Err(Error::Value(e))
+                        }
+                    };
+
+                    // the iterator is closed by exceptions thrown while binding the elements
+                    if let Some(p) = elem{
+                        if let Err(e) = catch_thrown(self.translate_pat(vmctx, p, v, kind)).await{
+                            if !done{
+                                operator::iterator_close_raw(iterator);
+                            }
+                            return Err(e)
+                        }
+                    }
+                }
+
+                if !done{
+                    check_raw(operator::iterator_close_raw(iterator))?;
+                }
+            },
+
+            Pat::Object(o) => {
+                check_raw(operator::require_object_coercible_raw(value))?;
+
+                // the keys already bound are excluded from the rest element
+                let mut keys = Vec::new();
+
+                for prop in &o.props{
+                    match prop{
+                        ObjectPatProp::KeyValue(kv) => {
+                            let key = self.translate_prop_name(vmctx, &kv.key).await?;
+                            keys.push(key);
+                            self.translate_pat(vmctx, &kv.value, value.member(key), kind).await?;
+                        },
+                        ObjectPatProp::Assign(a) => {
+                            let key:JValue = a.key.sym.as_ref().into();
+                            keys.push(key);
+
+                            let mut v = value.member(key);
+                            if let Some(default) = &a.value{
+                                if v.is_undefined(){
+                                    v = self.translate_expr(vmctx, default).await?;
+                                }
+                            }
+                            self.bind_ident(vmctx, &a.key, v, kind);
+                        },
+                        ObjectPatProp::Rest(r) => {
+                            let rest = check_raw(unsafe{operator::object_rest_raw(value, keys.as_ptr(), keys.len() as i64)})?;
+                            self.translate_pat(vmctx, &r.arg, rest, kind).await?;
+                        }
+                    }
+                }
+            },
+
+            // an assignment target
+            Pat::Expr(e) => {
+                self.assign_target(vmctx, e, value).await?;
+            },
+
+            Pat::Rest(_) => return Err(Error::SyntaxError("Rest element must be last element".to_string())),
+            Pat::Invalid(_) => return Err(Error::SyntaxError("Invalid destructuring assignment target".to_string())),
+        }
+        Ok(())
+    }
+
+    async fn translate_prop_name(&mut self, vmctx:&mut VmContext, key:&'static PropName) -> Result<JValue, Error>{
+        match key{
+            PropName::Ident(i) => Ok(i.sym.as_ref().into()),
+            PropName::Str(s) => Ok(s.value.as_ref().into()),
+            PropName::Num(n) => Ok(JValue::Number(n.value).to_string().as_str().into()),
+            PropName::BigInt(b) => Ok(b.value.to_string().as_str().into()),
+            PropName::Computed(c) => self.translate_expr(vmctx, &c.expr).await,
+        }
+    }
+
+    async fn translate_prop(&mut self, vmctx:&mut VmContext, expr:&'static Expr, computed:bool) -> Result<JValue, Error>{
+        if computed{
+            self.translate_expr(vmctx, expr).await
+        } else{
+            match expr{
+                Expr::Ident(i) => Ok(i.sym.as_ref().into()),
+                Expr::PrivateName(p) => self.resolve_private(&p.id.sym),
+                _ => Err(Error::Unimplemented("non computed property expression."))
+            }
+        }
+    }
+
+    /// resolve the key of a private name from the enclosing classes
+    fn resolve_private(&self, name:&str) -> Result<JValue, Error>{
+        match self.private_names.iter().rev().find_map(|m|m.get(name).copied()){
+            Some(key) => Ok(key),
+            None => Err(Error::SyntaxError(format!("Private field '#{}' must be declared in an enclosing class", name)))
+        }
+    }
+
+    fn resolve_ident(&self, vmctx:&mut VmContext, name:&str) -> Result<JValue, Error>{
+        let id = variable_id(vmctx, name);
+        check_raw(vmctx.get_variable_raw(id))
+    }
+
+    /// declare the variable if `kind` is some, else assign to an existing variable
+    fn bind_ident(&mut self, vmctx:&mut VmContext, ident:&Ident, value:JValue, kind:Option<VarDeclKind>){
+        self.bind_name(vmctx, &ident.sym, value, kind)
+    }
+
+    fn bind_name(&mut self, vmctx:&mut VmContext, name:&str, value:JValue, kind:Option<VarDeclKind>){
+        let id = variable_id(vmctx, name);
+        match kind{
+            Some(kind) => vmctx.declare_variable(id, value, match kind{
+                VarDeclKind::Var => 0,
+                VarDeclKind::Let => 1,
+                VarDeclKind::Const => 2,
+            }),
+            None => vmctx.set_variable(id, value)
+        }
+    }
+}
+
+/// suspends the body of a generator or async function at a `yield` or `await`.
+///
+/// the first poll saves the value in the generator and returns pending,
+/// the next poll happens once the generator is resumed and returns the value and mode it is resumed with.
+struct Suspend{
+    /// the context owning the generator
+    genctx:*mut VmContext,
+    value:Option<JValue>,
+}
+
+impl Suspend{
+    fn new(vmctx:&mut VmContext, value:JValue) -> Self{
+        // the body runs in child contexts of the generator context
+        let mut ctx = vmctx as *mut VmContext;
+        unsafe{
+            while (*ctx).generator.is_none(){
+                match &mut (*ctx).parent{
+                    Some(p) => ctx = &mut **p as *mut VmContext,
+                    None => break
+                }
+            }
+        }
+        Self{
+            genctx:ctx,
+            value:Some(value)
+        }
+    }
+}
+
+impl Future for Suspend{
+    type Output = (JValue, i8);
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let vmctx = unsafe{&mut *self.genctx};
+        match self.value.take(){
+            Some(v) => {
+                Generator::yield_raw(vmctx, std::ptr::null_mut(), v, 1);
+                Poll::Pending
+            },
+            None => Poll::Ready(Generator::resume_raw(vmctx))
+        }
+    }
+}
+
+/// run a future which never suspends.
+///
+/// only the bodies of generators and async functions suspend,
+/// they are polled by their generator instead.
+fn run_sync<F:Future>(f:F) -> F::Output{
+    let mut f = Box::pin(f);
+    match f.as_mut().poll(&mut TaskContext::from_waker(noop_waker_ref())){
+        Poll::Ready(v) => v,
+        Poll::Pending => unreachable!("suspended outside of a generator or async function")
+    }
+}
+
+/// turn exceptions thrown by builtins and runtime helpers into `Error::Value`
+async fn catch_thrown<T, F>(f:F) -> Result<T, Error> where F:Future<Output = Result<T, Error>>{
+    match AssertUnwindSafe(f).catch_unwind().await{
+        Ok(re) => re,
+        Err(err) => match err.downcast_ref::<JValue>(){
+            Some(v) => Err(Error::Value(*v)),
+            None => resume_unwind(err)
+        }
+    }
+}
+
+/// convert the result of a `_raw` helper, the value is an exception if not ok
+fn check_raw((value, ok):(JValue, bool)) -> Result<JValue, Error>{
+    if ok{
+        Ok(value)
+    } else{
+        Err(Error::Value(value))
+    }
+}
+
+/// throw an error out of an interpreted function,
+/// errors other than exceptions are thrown as `Error` objects.
+fn throw_error(e:Error, source:&str) -> !{
+    match e{
+        Error::Value(v) => operator::throw(v),
+        e => operator::throw(builtins::Error::newError(e.locate(source).to_string()))
+    }
+}
+
+fn variable_id(vmctx:&mut VmContext, name:&str) -> u64{
+    vmctx.runtime.new_variable_name(name) as u64
+}
+
+fn unparen(e:&Expr) -> &Expr{
+    let mut e = e;
+    while let Expr::Paren(p) = e{
+        e = &p.expr;
+    }
+    e
+}
+
+/// function declarations are created by `hoist_functions` when their statement list starts
+fn is_function_decl(s:&Stmt) -> bool{
+    matches!(s, Stmt::Decl(Decl::Fn(_)))
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    fn run(mode:ExecutionMode, script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        runtime.set_tier_up_threshold(2);
+        match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    #[test]
+    fn matches_the_jit(){
+        let cases = [
+            ("let r = hoisted(); function hoisted(){ return 4 } r", "4"),
+            ("function f(){ return typeof g; function g(){} } f()", "function"),
+            ("{ let r = h(); function h(){ return 6 } r }", "6"),
+            ("function f(){ return x } let x = 1; f()", "1"),
+            ("let f = function fact(n){ return n < 2 ? 1 : n * fact(n - 1) }; f(6)", "720"),
+            ("let fact = 3; let f = function fact(){ return typeof fact }; f() + fact", "function3"),
+            ("let f = function me(){ me = 1 }; let r; try { f() } catch(e) { r = e instanceof TypeError } r", "true"),
+            ("let fs = []; for (let i = 0; i < 3; i++){ fs[i] = () => i } fs[0]() + fs[1]() + fs[2]()", "3"),
+            ("let fs = []; for (let i = 0, j = 10; i < 3; i++, j--){ fs[i] = () => i + j } fs[0]() + fs[1]() + fs[2]()", "30"),
+            ("for (let i = 0; i < 3; i++){} typeof i", "undefined"),
+            ("function fib(n){ return n < 2 ? n : fib(n - 1) + fib(n - 2) } fib(15)", "610"),
+            ("let s = 0; for (let i = 0; i < 40; i++){ if (i % 3 == 0) continue; s += i } s", "507"),
+            ("let c = 0; label: while (true){ c++; if (c > 4) break label } c", "5"),
+            ("let { a, b:{ c } } = { a:1, b:{ c:2 } }; a + c", "3"),
+            ("class A { #p = 2; get p(){ return this.#p } static make(){ return new A() } } A.make().p", "2"),
+            ("class A { m(){ return 'a' } } class B extends A { m(){ return super.m() + 'b' } } new B().m()", "ab"),
+            ("function* g(){ let x = yield 1; yield x * 2 } let it = g(); it.next(); it.next(5).value", "10"),
+            ("let r = ''; try { throw 'e' } catch(e) { r += e } finally { r += 'f' } r", "ef"),
+            ("let n = 0; let o = { get v(){ return ++n } }; o.v + o.v", "3"),
+            ("`a${1 + 1}b` + typeof missing + (1 / 0)", "a2bundefinedInfinity"),
+        ];
+        for (script, expected) in cases{
+            let interpreted = run(ExecutionMode::Interpreter, script);
+            assert_eq!(interpreted, expected, "{}", script);
+            assert_eq!(run(ExecutionMode::Jit, script), interpreted, "Jit {}", script);
+            assert_eq!(run(ExecutionMode::Tiered, script), interpreted, "Tiered {}", script);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use swc_ecma_ast::{ForStmt, Pat, Stmt, VarDeclKind, VarDeclOrExpr};
+
+use crate::builtins::function::{Code, Function};
+use crate::bytecode::scope::{captured_names, pat_names, runs_detached};
+use crate::jit::builder::BuilderContext;
+use crate::runtime::RUNTIME;
+use crate::utils::ToMutable;
+use crate::value::JValue;
+use crate::vm::VmContext;
+
+use super::Body;
+
+/// the profile of an interpreted function in `ExecutionMode::Tiered`.
+///
+/// calls and loop iterations are counted, once the count reaches the
+/// threshold of the runtime the body is compiled and replaces the code of the function.
+///
+/// a hot loop also compiles the function for its next call, the running call
+/// continues the loop in compiled code if it can, see `LoopProfiles`.
+/// top level code runs once and has no profile.
+pub(crate) struct Profile{
+    count:Cell<u32>,
+    /// set once the function is compiled or failed to compile, it is not counted anymore
+    done:Cell<bool>,
+    /// the function object, set once it is created
+    function:Cell<JValue>,
+
+    params:Vec<&'static Pat>,
+    body:Body,
+    is_async:bool,
+    is_generator:bool,
+}
+
+impl Profile{
+    pub(crate) fn new(params:Vec<&'static Pat>, body:Body, is_async:bool, is_generator:bool) -> Self{
+        Self{
+            count:Cell::new(0),
+            done:Cell::new(false),
+            function:Cell::new(JValue::Undefined),
+
+            params,
+            body,
+            is_async,
+            is_generator,
+        }
+    }
+
+    pub(crate) fn set_function(&self, func:JValue){
+        self.function.set(func);
+    }
+
+    /// count an iteration of a loop in the body and compile the function once it is hot
+    pub(crate) fn back_edge(&self, vmctx:&mut VmContext){
+        self.count_and_tier_up(vmctx)
+    }
+
+    /// count a call and compile the function once it is hot.
+    ///
+    /// the call itself keeps running in the interpreter.
+    pub(crate) fn enter(&self, vmctx:&mut VmContext){
+        self.count_and_tier_up(vmctx)
+    }
+
+    fn count_and_tier_up(&self, vmctx:&mut VmContext){
+        if self.done.get(){
+            return
+        }
+
+        let count = self.count.get().saturating_add(1);
+        self.count.set(count);
+        if count < vmctx.runtime.tier_up_threshold(){
+            return
+        }
+        self.done.set(true);
+
+        let (stmts, expr) = match self.body{
+            Body::Stmts(s) => (s, None),
+            Body::Expr(e) => (&[][..], Some(e))
+        };
+
+        // bodies the jit cannot compile on their own, such as methods
+        // using the private names of their class, stay interpreted.
+        let mem = match BuilderContext::compile_function_body(vmctx.runtime.arc(), &self.params, stmts, expr, self.is_async, self.is_generator){
+            Ok(mem) => mem,
+            Err(_) => return
+        };
+
+        if let Some(f) = self.function.get().object().and_then(|o|o.inner.function()){
+            f.to_mut().replace_code(mem);
+        }
+    }
+}
+
+/// the loops of a script in `ExecutionMode::Tiered`, shared by the interpreters running it.
+///
+/// the iterations of every loop are counted at its header. once a loop is hot it is compiled
+/// on its own and entered on stack: the running call or script continues the loop in compiled code.
+/// `while`, `do while` and `for` loops are compiled, `for in` and `for of` loops stay interpreted.
+#[derive(Default)]
+pub(crate) struct LoopProfiles{
+    loops:RefCell<HashMap<*const Stmt, LoopState>>,
+}
+
+enum LoopState{
+    Counting(u32),
+    Compiled(Code, *mut u8),
+    /// the loop cannot run detached from its function or failed to compile
+    Interpreted,
+}
+
+impl LoopProfiles{
+    /// count an iteration of the loop `stmt` labeled `label`, return its compiled code once it is hot.
+    ///
+    /// the code runs the loop from its header, before the test of a `while` or `for` loop
+    /// and before the body of a `do while` loop. a `for` loop is compiled without its initializer.
+    pub(crate) fn back_edge(&self, vmctx:&mut VmContext, stmt:&Stmt, label:&Option<String>) -> Option<Code>{
+        let mut loops = self.loops.borrow_mut();
+        let state = loops.entry(stmt as *const Stmt).or_insert(LoopState::Counting(0));
+        let count = match state{
+            LoopState::Compiled(code, _) => return Some(code.clone()),
+            LoopState::Interpreted => return None,
+            LoopState::Counting(c) => {
+                *c += 1;
+                *c
+            }
+        };
+        if count < vmctx.runtime.tier_up_threshold(){
+            return None
+        }
+
+        let header = match stmt{
+            Stmt::For(f) if f.init.is_some() => Stmt::For(ForStmt{init:None, ..f.clone()}),
+            stmt => stmt.clone()
+        };
+        // the compiled loop keeps a single binding of the `let` initializer,
+        // a body capturing it needs a binding for every iteration
+        let captures_init = match stmt{
+            Stmt::For(ForStmt{init:Some(VarDeclOrExpr::VarDecl(d)), body, ..}) if d.kind == VarDeclKind::Let => {
+                let captured = captured_names([&**body]);
+                let mut names = Vec::new();
+                d.decls.iter().for_each(|d|pat_names(&d.name, &mut names));
+                names.iter().any(|n|captured.contains(n))
+            },
+            _ => false
+        };
+        if captures_init || !runs_detached(&header, label.as_deref()){
+            *state = LoopState::Interpreted;
+            return None
+        }
+
+        match BuilderContext::compile_loop(vmctx.runtime.arc(), &header, label.clone()){
+            Ok(mem) => {
+                let code = Function::compiled_code(mem);
+                *state = LoopState::Compiled(code.clone(), mem);
+                Some(code)
+            },
+            Err(_) => {
+                *state = LoopState::Interpreted;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for LoopProfiles{
+    fn drop(&mut self){
+        for state in self.loops.get_mut().values(){
+            if let LoopState::Compiled(_, mem) = state{
+                RUNTIME.with(|runtime|{
+                    runtime.to_mut().release_compiled_fn(*mem);
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    fn run(mode:ExecutionMode, script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        runtime.set_tier_up_threshold(2);
+        match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    #[test]
+    fn hot_loops_match_the_interpreter(){
+        let scripts = [
+            "let s = 0; for (let i = 0; i < 50; i++){ s += i } s",
+            "let s = 0, i = 0; while (i < 20){ s += i; i++ } s + i",
+            "let i = 0; do { i++ } while (i < 10); i",
+            "let x = 0; while (x < 100) x++",
+            "let s = 0; outer: for (let i = 0; i < 10; i++){ for (let j = 0; j < 10; j++){ if (j > i) continue outer; if (i > 7) break outer; s += j } } s",
+            "let s = 0; for (let i = 0; i < 10; i++){ s += i; if (i == 6) throw s }",
+            "let r = 0; for (let i = 0; i < 10; i++){ try { if (i % 3 == 0) throw i } catch(e) { r += e } finally { r += 1 } } r",
+            "let o = { n:0 }; for (let i = 0; i < 30; i++){ o.n += i * 2 } o.n",
+            "function f(n){ let s = 0; for (let i = 0; i < n; i++){ if (i == 5) return i; s += i } return s } f(3) + f(20)",
+            "function h(){ let a = 0; for (let i = 0; i < 10; i++){ a += this.k } return a } let o = { k:3, h }; o.h()",
+            "function* gen(){ for (let i = 0; i < 6; i++) yield i } let s = 0; for (const v of gen()) s += v; s",
+            "let fs = []; for (let i = 0; i < 30; i++){ fs[i] = () => i } fs[0]() + fs[29]()",
+        ];
+        for s in scripts{
+            assert_eq!(run(ExecutionMode::Tiered, s), run(ExecutionMode::Interpreter, s), "{}", s);
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use std::alloc::Layout;
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::mem::size_of;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use cranelift::codegen::Context;
 use cranelift::codegen::ir::FuncRef;
@@ -14,18 +15,98 @@ use swc_ecma_ast::*;
 
 use parking_lot::RwLock;
 
-use num_traits::ToPrimitive;
-
-use crate::builtins::Symbol;
 use crate::builtins::regexp::RegExp;
+use crate::builtins::{bigint, object, Array, JObject};
 use crate::runtime::Runtime;
-use crate::error::Error;
-use crate::value::JValue;
+use crate::vm::VmContext;
+use crate::error::{Error, Position};
+use crate::value::{self, JValue};
+use crate::builtins::generator::ResumeMode;
+
+use super::inline_cache::{self, InlineCache};
+use super::scope::{self, ContextBinding, FunctionScope, Slot};
 
 pub struct LoopExit{
     label:Option<String>,
     exit_block:Block,
-    continue_block:Block,
+    /// none for `switch` statements and labeled blocks, which cannot be continued
+    continue_block:Option<Block>,
+    /// a labeled statement that is not a loop, only exited by a labeled break
+    labeled_only:bool,
+    /// the depth of the unwind stack inside the loop,
+    /// entries above are unwound when jumping to the loop.
+    unwind:usize,
+}
+
+/// an enclosing statement to unwind on abrupt completions
+#[derive(Clone)]
+enum Unwind{
+    /// the catch block of a `try` statement, receives the thrown value
+    Catch(Block),
+    /// the finalizer of a `try` statement, every exit jumps to it with its completion
+    Finally(Arc<RefCell<Finalizer>>),
+    /// the hidden variable holding the iterator of a `for of` loop, closed on exit
+    Iterator(String),
+    /// a block scope pushed at runtime, the variables holding its context and the enclosing one
+    Scope(Variable, Variable),
+}
+
+/// the finalizer of a `try` statement, compiled once.
+///
+/// its block receives the kind of the completion and its value, the kind is
+/// 0 for the normal completion, 1 for a throw, 2 for a return and `3 + i` for the jump `i`.
+/// the completion continues once the finalizer runs.
+struct Finalizer{
+    block:Block,
+    /// the loop exits and continue blocks reached through the finalizer, with their unwind depth
+    jumps:Vec<(Block, usize)>,
+}
+
+/// an abrupt completion leaving the enclosing statements
+#[derive(Clone, Copy)]
+enum Exit{
+    /// break or continue to a block
+    Jump(Block),
+    Return(Value),
+    Throw(Value),
+}
+
+/// where a binding is stored, resolved when compiling
+#[derive(Clone, Copy)]
+enum Place{
+    Slot(Slot),
+    /// a binding in the record of the context held by the variable
+    Record(Variable, ContextBinding),
+    /// a binding captured by the function being compiled, in the closure of its context
+    Closure(ContextBinding),
+    /// a top level binding of a script, in the record of the script context
+    Script(ContextBinding),
+    /// a name not declared in a detached body, resolved by name from the calling context
+    Named,
+    /// a name no scope declares, resolved from the script context and the global object
+    Free,
+}
+
+/// an element evaluated by a class initializer
+enum ClassElement<'b>{
+    Field(FieldKey<'b>, Option<&'b Expr>),
+    PrivateField(&'b PrivateName, Option<&'b Expr>),
+    /// the private name, the variable holding the method and the kind passed to `private_define`
+    PrivateMethod(&'b PrivateName, String, i8),
+    StaticBlock(&'b BlockStmt),
+}
+
+enum FieldKey<'b>{
+    Name(&'b PropName),
+    /// computed keys are stored in a hidden variable
+    Hidden(String),
+}
+
+/// an operand of a binary operator on a loop counter
+#[derive(Clone, Copy)]
+enum CounterOperand{
+    Counter(Slot),
+    Value(Value),
 }
 
 pub struct BuilderContext<'a>{
@@ -39,14 +120,55 @@ pub struct BuilderContext<'a>{
     func_ctx:*mut FunctionBuilderContext,
 
     jit_module:Arc<JITModule>,
-    variables:HashSet<usize>,
+    /// the bindings of this scope declared in the record of its context
+    variables:HashMap<usize, ContextBinding>,
+    /// the length of the record of the context of this scope
+    record:Arc<Cell<u32>>,
+    /// the bindings of this scope kept in cranelift variables
+    slots:HashMap<usize, Slot>,
+    /// the bindings of the function being compiled
+    scope:Arc<FunctionScope>,
+    /// true for a body compiled without its enclosing functions,
+    /// names it does not declare are resolved from the captures and the calling context.
+    detached:bool,
+    /// true for the scopes of a script, their bindings live in the script context.
+    script:bool,
+    /// true if the scope declares its bindings in a context pushed when entered, left by `close`
+    scoped:bool,
 
     loop_exits:Arc<RefCell<Vec<LoopExit>>>,
 
-    is_in_try:bool,
-    try_exits:Arc<RefCell<Vec<Block>>>,
-
-    need_capture:Arc<RefCell<Vec<u64>>>,
+    /// the enclosing try statements and iterators, innermost last
+    unwind:Arc<RefCell<Vec<Unwind>>>,
+
+    /// the bindings captured by the function, in the order of its closure
+    need_capture:Arc<RefCell<Vec<usize>>>,
+    /// the names a function nested in a detached body captures by name
+    named_captures:Arc<RefCell<Vec<u64>>>,
+
+    is_generator:bool,
+    /// async functions are compiled like generators, every `await` is a resume point
+    is_async:bool,
+    /// the blocks resuming a generator after each `yield`
+    resume_points:Arc<RefCell<Vec<Block>>>,
+    /// the block jumping to the resume points and the resume point of the generator
+    dispatch:Option<(Block, Value)>,
+    /// true if the expression being translated may contain a `yield`,
+    /// every value evaluated before it in the same statement is held as a temporary.
+    yield_position:bool,
+    /// the values held while the next operands are evaluated, innermost last.
+    /// they are spilled to hidden bindings across every `yield` and `await`.
+    temporaries:Arc<RefCell<Vec<Variable>>>,
+
+    /// the exit block of the optional chain being compiled, 
+    /// a short circuited `?.` jumps to it with undefined.
+    chain_exit:Option<Block>,
+    /// the chain exit passed to the next translated expression, 
+    /// only the object of a member or the callee of a call continues the chain.
+    opt_chain:Option<Block>,
+
+    /// private names of the enclosing classes, mapped to the hidden variable holding the key
+    private_names:Arc<RefCell<Vec<HashMap<String, String>>>>,
 
     pointer_type:Type,
 
@@ -57,29 +179,87 @@ pub struct BuilderContext<'a>{
 
     vmctx:Variable,
     this:Variable,
+    /// the value of the last evaluated expression statement
+    completion:Variable,
 
-    /// fn(*mut VmContext, id:i64) -> JValue
+    /// fn(*mut VmContext, id:i64) -> (JValue, ok:bool)
     resolve_var:FuncRef,
-    /// fn(*mut VmContext, id:i64, value:JValue)
+    /// fn(*mut VmContext, id:i64, value:JValue) -> (JValue, ok:bool)
     set_var:FuncRef,
+    /// fn(*mut VmContext, index:i64, value:JValue, kind:i8)
+    declare_slot:FuncRef,
+    /// fn(*mut VmContext, index:i64) -> JValue
+    get_slot:FuncRef,
+    /// fn(*mut VmContext, index:i64, value:JValue)
+    set_slot:FuncRef,
+    /// fn(*mut VmContext, index:i64) -> JValue, a binding captured by the function
+    get_closure:FuncRef,
+    /// fn(*mut VmContext, index:i64, value:JValue)
+    set_closure:FuncRef,
+    /// fn(*mut VmContext, index:i64, id:i64, value:JValue, kind:i8), a top level binding of a script
+    declare_script_slot:FuncRef,
+    /// fn(*mut VmContext, index:i64, id:i64), declare a top level `var` undefined unless already declared
+    hoist_script_slot:FuncRef,
+    /// fn(*mut VmContext) -> *mut VmContext, enter a block scope
+    push_scope:FuncRef,
+    /// fn(*mut VmContext), leave a block scope
+    pop_scope:FuncRef,
+    /// fn(*mut VmContext) -> *mut VmContext, the context enclosing a block scope
+    scope_parent:FuncRef,
+    /// fn(*mut VmContext, id:i64) -> (JValue, ok:bool), names declared by no enclosing function
+    resolve_free:FuncRef,
+    /// fn(*mut VmContext, id:i64, value:JValue) -> (JValue, ok:bool)
+    set_free:FuncRef,
+    /// fn(*mut VmContext, id:i64) -> (JValue, ok:bool)
+    typeof_free:FuncRef,
+    /// fn(*mut VmContext, id:i64) -> JValue, the error of a binding read before its declaration
+    uninitialized_error:FuncRef,
+    /// fn(*mut VmContext) -> JValue, the error of an assignment to a `const`
+    const_error:FuncRef,
     /// fn(argv:*mut JValue, argc:i64, idx:i64) -> JValue
     resolve_argument:FuncRef,
+    /// fn(argv:*mut JValue, argc:i64, idx:i64) -> JValue
+    rest_arguments:FuncRef,
+    /// fn(value:JValue) -> (JValue, ok:bool)
+    require_object_coercible:FuncRef,
+    /// fn(value:JValue, keys:*const JValue, count:i64) -> (JValue, ok:bool)
+    object_rest:FuncRef,
+    /// fn(iterator:JValue, done:bool) -> (JValue, ok:bool)
+    iterator_rest:FuncRef,
 
     /// fn(value:JValue) -> bool
     to_bool:FuncRef,
-    /// fn(value:JValue) -> !
-    throw:FuncRef,
+    /// fn(value:JValue) -> bool
+    is_nullish:FuncRef,
+    /// fn(*mut VmContext, value:JValue)
+    set_exception:FuncRef,
 
-    /// fn(self:JValue, key:JValue) -> JValue
+    /// fn(self:JValue, key:JValue) -> (JValue, ok:bool)
     member:FuncRef,
+    /// fn(*mut VmContext, this:JValue, key:JValue) -> (JValue, ok:bool)
     superMember:FuncRef,
-    /// fn(self:JValue, key:JValue, value:JValue)
+    /// fn(self:JValue, key:JValue, value:JValue) -> (JValue, ok:bool)
     set_memebr:FuncRef,
-    /// fn(self:JValue, key:JValue, value:JValue, op:i8)
-    assign_member:FuncRef,
+    /// fn(cache:*mut InlineCache, self:JValue) -> (JValue, ok:bool)
+    ic_get:FuncRef,
+    /// fn(cache:*mut InlineCache, self:JValue, value:JValue) -> (JValue, ok:bool)
+    ic_set:FuncRef,
     /// use for object construction
-    /// fn(self:JValue, spreadable:JValue)
+    /// fn(self:JValue, spreadable:JValue) -> (JValue, ok:bool)
     set_member_spread:FuncRef,
+    /// fn(self:JValue, key:JValue, value:JValue) -> (JValue, ok:bool)
+    define_field:FuncRef,
+
+    /// fn(self:JValue, key:JValue) -> (JValue, ok:bool)
+    private_get:FuncRef,
+    /// fn(self:JValue, key:JValue, value:JValue, op:i8) -> (JValue, ok:bool)
+    private_set:FuncRef,
+    /// fn(self:JValue, key:JValue, value:JValue, kind:i8) -> (JValue, ok:bool)
+    private_define:FuncRef,
+    /// fn(self:JValue, key:JValue) -> (JValue, ok:bool)
+    private_in:FuncRef,
+    /// fn(description:JValue) -> JValue
+    private_name_new:FuncRef,
 
     /// fn(callee:JValue, *mut VmContext, this:JValue, argv:*mut JValue, argc:i64, spread:bool) -> (JValue, ok:bool)
     call:FuncRef,
@@ -91,20 +271,61 @@ pub struct BuilderContext<'a>{
     memberCall:FuncRef,
     /// fn(self:JValue, member:JValue, *mut VmContext, argv:*mut JValue, argc:i64, spread:bool) -> (Jvalue, ok:bool)
     superMemberCall:FuncRef,
+    /// fn(*mut VmContext, this:JValue, argv:*mut JValue, argc:i64, spread:bool) -> (JValue, ok:bool)
+    super_call:FuncRef,
     
-    /// fn(argv:*mut JValue, argc:i64) -> JValue
+    /// fn(value:JValue) -> (JValue, ok:bool)
+    get_iterator:FuncRef,
+    /// fn(value:JValue) -> JValue
+    for_in_iterator:FuncRef,
+    /// fn(iterator:JValue) -> (JValue, status:i8), status 0 value, 1 done, 2 thrown
+    iterator_next:FuncRef,
+    /// fn(iterator:JValue) -> (JValue, ok:bool)
+    iterator_close:FuncRef,
+
+    /// fn(*mut VmContext) -> i64
+    generator_state:FuncRef,
+    /// fn(*mut VmContext, scope:*mut VmContext, value:JValue, resume_point:i64)
+    generator_yield:FuncRef,
+    /// fn(*mut VmContext) -> *mut VmContext, the innermost block scope saved by `generator_yield`
+    generator_scope:FuncRef,
+    /// fn(*mut VmContext) -> (JValue, mode:i8)
+    generator_resume:FuncRef,
+    /// fn(*mut VmContext, iterator:JValue, sent:JValue, mode:i8) -> (JValue, status:i8), status 3 thrown
+    generator_delegate:FuncRef,
+
+    /// fn(argv:*mut JValue, argc:i64) -> (JValue, ok:bool)
     tpl_new:FuncRef,
 
-    /// fn(argv:*mut JValue, argc:i64, spread:bool) -> Arc<dyn JObjectInner>
+    /// fn(argv:*mut JValue, argc:i64, spread:bool) -> (JValue, ok:bool)
     array_new:FuncRef,
+    /// fn(array:JValue, value:JValue)
+    array_push:FuncRef,
+    /// fn(array:JValue, iterable:JValue) -> (JValue, ok:bool)
+    array_spread:FuncRef,
     /// fn(vmctx:*mut VmContext, mem:*mut u8, async:bool, generator:bool) -> JValue
     function_new:FuncRef,
-
+    /// fn(func:JValue, *mut VmContext, id:i64), capture a binding by name
+    function_capture:FuncRef,
+    /// fn(func:JValue, *mut VmContext, index:i64), capture a binding of the record
+    function_capture_slot:FuncRef,
+    /// fn(func:JValue, *mut VmContext, index:i64), capture a binding of the closure
+    function_capture_closure:FuncRef,
+    /// fn(func:JValue, *mut VmContext, this:JValue)
+    function_bind_this:FuncRef,
+    /// fn(func:JValue, home:JValue)
+    set_home_object:FuncRef,
+    /// fn(target:JValue, key:JValue, func:JValue, kind:i8) -> (JValue, ok:bool)
+    define_method:FuncRef,
+    /// fn(ctor:JValue, parent:JValue, has_parent:bool) -> (JValue, ok:bool)
+    class_new:FuncRef,
+    /// fn(ctor:JValue, initializer:JValue)
+    class_fields:FuncRef,
+
+    /// fn() -> JValue
     new_object:FuncRef,
-
-    /// fn(Arc<dyn JObjectInner>) -> JValue
-    object_from_inner:FuncRef,
     
+    /// fn(lhs:JValue, rhs:JValue) -> (JValue, ok:bool)
     add:FuncRef,
     bitAnd:FuncRef,
     bitOr:FuncRef,
@@ -118,18 +339,30 @@ pub struct BuilderContext<'a>{
     In:FuncRef,
     instanceof:FuncRef,
     lshift:FuncRef,
-    and:FuncRef,
-    or:FuncRef,
     lt:FuncRef,
     lteq:FuncRef,
     Mod:FuncRef,
     mul:FuncRef,
     noteq:FuncRef,
     noteqeq:FuncRef,
-    nullishCoalscing:FuncRef,
     rshift:FuncRef,
     sub:FuncRef,
     unsignedRShift:FuncRef,
+
+    /// fn(value:JValue) -> JValue
+    typeOf:FuncRef,
+    /// fn(vmctx, name:u64) -> (JValue, ok:bool)
+    typeof_var:FuncRef,
+    /// fn(obj:JValue, key:JValue) -> (JValue, ok:bool)
+    delete_member:FuncRef,
+    not:FuncRef,
+    /// fn(value:JValue) -> (JValue, ok:bool)
+    neg:FuncRef,
+    pos:FuncRef,
+    bitNot:FuncRef,
+    to_numeric:FuncRef,
+    increment:FuncRef,
+    decrement:FuncRef,
 }
 
 impl<'a> BuilderContext<'a>{
@@ -137,27 +370,86 @@ impl<'a> BuilderContext<'a>{
     pub fn new(runtime:Arc<Runtime>, module:Arc<cranelift_jit::JITModule>, ctx:&mut Context) -> Self{
         
         let func = &mut ctx.func;
-        
+        let pointer_type = module.isa().pointer_type();
+
+        // fn(*mut VmContext, this:JValue, argv:*mut JValue, argc:i64) -> JValue
+        func.signature = Signature{
+            params:vec![
+                AbiParam::new(pointer_type),
+                AbiParam::new(JValue::TYPE),
+                AbiParam::new(pointer_type),
+                AbiParam::new(types::I64)
+            ],
+            returns:vec![AbiParam::new(JValue::TYPE)],
+            call_conv:module.target_config().default_call_conv
+        };
+
 
         let resolve_var= module.declare_func_in_func(runtime.builtin_functions["resolve_var"], func);
         let set_var= module.declare_func_in_func(runtime.builtin_functions["set_var"], func);
+        let declare_slot= module.declare_func_in_func(runtime.builtin_functions["declare_slot"], func);
+        let get_slot= module.declare_func_in_func(runtime.builtin_functions["get_slot"], func);
+        let set_slot= module.declare_func_in_func(runtime.builtin_functions["set_slot"], func);
+        let get_closure= module.declare_func_in_func(runtime.builtin_functions["get_closure"], func);
+        let set_closure= module.declare_func_in_func(runtime.builtin_functions["set_closure"], func);
+        let declare_script_slot= module.declare_func_in_func(runtime.builtin_functions["declare_script_slot"], func);
+        let hoist_script_slot= module.declare_func_in_func(runtime.builtin_functions["hoist_script_slot"], func);
+        let resolve_free= module.declare_func_in_func(runtime.builtin_functions["resolve_free"], func);
+        let set_free= module.declare_func_in_func(runtime.builtin_functions["set_free"], func);
+        let typeof_free= module.declare_func_in_func(runtime.builtin_functions["typeof_free"], func);
+        let uninitialized_error= module.declare_func_in_func(runtime.builtin_functions["uninitialized_error"], func);
+        let const_error= module.declare_func_in_func(runtime.builtin_functions["const_error"], func);
+        let push_scope= module.declare_func_in_func(runtime.builtin_functions["push_scope"], func);
+        let pop_scope= module.declare_func_in_func(runtime.builtin_functions["pop_scope"], func);
+        let scope_parent= module.declare_func_in_func(runtime.builtin_functions["scope_parent"], func);
         let resolve_argument= module.declare_func_in_func(runtime.builtin_functions["resolve_argument"], func);
+        let rest_arguments= module.declare_func_in_func(runtime.builtin_functions["rest_arguments"], func);
+        let require_object_coercible= module.declare_func_in_func(runtime.builtin_functions["require_object_coercible"], func);
+        let object_rest= module.declare_func_in_func(runtime.builtin_functions["object_rest"], func);
+        let iterator_rest= module.declare_func_in_func(runtime.builtin_functions["iterator_rest"], func);
         let to_bool= module.declare_func_in_func(runtime.builtin_functions["to_bool"], func);
-        let throw =  module.declare_func_in_func(runtime.builtin_functions["throw"], func);
+        let is_nullish= module.declare_func_in_func(runtime.builtin_functions["is_nullish"], func);
+        let set_exception =  module.declare_func_in_func(runtime.builtin_functions["set_exception"], func);
         let member= module.declare_func_in_func(runtime.builtin_functions["member"], func);
         let superMember= module.declare_func_in_func(runtime.builtin_functions["superMember"], func);
         let set_memebr= module.declare_func_in_func(runtime.builtin_functions["set_member"], func);
-        let assign_member = module.declare_func_in_func(runtime.builtin_functions["assign_member"], func);
         let set_member_spread= module.declare_func_in_func(runtime.builtin_functions["set_member_spread"], func);
+        let ic_get= module.declare_func_in_func(runtime.builtin_functions["ic_get"], func);
+        let ic_set= module.declare_func_in_func(runtime.builtin_functions["ic_set"], func);
         let call= module.declare_func_in_func(runtime.builtin_functions["call"], func);
         let construct= module.declare_func_in_func(runtime.builtin_functions["construct"], func);
         let memberCall= module.declare_func_in_func(runtime.builtin_functions["memberCall"], func);
         let superMemberCall= module.declare_func_in_func(runtime.builtin_functions["superMemberCall"], func);
         let tpl_new= module.declare_func_in_func(runtime.builtin_functions["tpl_new"], func);
         let array_new= module.declare_func_in_func(runtime.builtin_functions["array_new"], func);
+        let array_push= module.declare_func_in_func(runtime.builtin_functions["array_push"], func);
+        let array_spread= module.declare_func_in_func(runtime.builtin_functions["array_spread"], func);
         let function_new= module.declare_func_in_func(runtime.builtin_functions["function_new"], func);
+        let function_capture= module.declare_func_in_func(runtime.builtin_functions["function_capture"], func);
+        let function_capture_slot= module.declare_func_in_func(runtime.builtin_functions["function_capture_slot"], func);
+        let function_capture_closure= module.declare_func_in_func(runtime.builtin_functions["function_capture_closure"], func);
+        let function_bind_this= module.declare_func_in_func(runtime.builtin_functions["function_bind_this"], func);
+        let define_field= module.declare_func_in_func(runtime.builtin_functions["define_field"], func);
+        let private_get= module.declare_func_in_func(runtime.builtin_functions["private_get"], func);
+        let private_set= module.declare_func_in_func(runtime.builtin_functions["private_set"], func);
+        let private_define= module.declare_func_in_func(runtime.builtin_functions["private_define"], func);
+        let private_in= module.declare_func_in_func(runtime.builtin_functions["private_in"], func);
+        let private_name_new= module.declare_func_in_func(runtime.builtin_functions["private_name_new"], func);
+        let super_call= module.declare_func_in_func(runtime.builtin_functions["super_call"], func);
+        let set_home_object= module.declare_func_in_func(runtime.builtin_functions["set_home_object"], func);
+        let define_method= module.declare_func_in_func(runtime.builtin_functions["define_method"], func);
+        let class_new= module.declare_func_in_func(runtime.builtin_functions["class_new"], func);
+        let class_fields= module.declare_func_in_func(runtime.builtin_functions["class_fields"], func);
+        let get_iterator= module.declare_func_in_func(runtime.builtin_functions["get_iterator"], func);
+        let for_in_iterator= module.declare_func_in_func(runtime.builtin_functions["for_in_iterator"], func);
+        let iterator_next= module.declare_func_in_func(runtime.builtin_functions["iterator_next"], func);
+        let iterator_close= module.declare_func_in_func(runtime.builtin_functions["iterator_close"], func);
+        let generator_state= module.declare_func_in_func(runtime.builtin_functions["generator_state"], func);
+        let generator_yield= module.declare_func_in_func(runtime.builtin_functions["generator_yield"], func);
+        let generator_scope= module.declare_func_in_func(runtime.builtin_functions["generator_scope"], func);
+        let generator_resume= module.declare_func_in_func(runtime.builtin_functions["generator_resume"], func);
+        let generator_delegate= module.declare_func_in_func(runtime.builtin_functions["generator_delegate"], func);
         let new_object= module.declare_func_in_func(runtime.builtin_functions["new_object"], func);
-        let object_from_inner = module.declare_func_in_func(runtime.builtin_functions["object_from_inner"], func);
         let add = module.declare_func_in_func(runtime.builtin_functions["add"], func);
         let bitAnd= module.declare_func_in_func(runtime.builtin_functions["bitAnd"], func);
         let bitOr = module.declare_func_in_func(runtime.builtin_functions["bitOr"], func);
@@ -171,27 +463,48 @@ impl<'a> BuilderContext<'a>{
         let In= module.declare_func_in_func(runtime.builtin_functions["in"], func);
         let instanceof= module.declare_func_in_func(runtime.builtin_functions["instanceof"], func);
         let lshift= module.declare_func_in_func(runtime.builtin_functions["lshift"], func);
-        let and= module.declare_func_in_func(runtime.builtin_functions["and"], func);
-        let or= module.declare_func_in_func(runtime.builtin_functions["or"], func);
         let lt= module.declare_func_in_func(runtime.builtin_functions["lt"], func);
         let lteq= module.declare_func_in_func(runtime.builtin_functions["lteq"], func);
         let Mod= module.declare_func_in_func(runtime.builtin_functions["mod"], func);
         let mul= module.declare_func_in_func(runtime.builtin_functions["mul"], func);
         let noteq= module.declare_func_in_func(runtime.builtin_functions["noteq"], func);
         let noteqeq= module.declare_func_in_func(runtime.builtin_functions["noteqeq"], func);
-        let nullishCoalscing= module.declare_func_in_func(runtime.builtin_functions["nullishCoalscing"], func);
         let rshift= module.declare_func_in_func(runtime.builtin_functions["rshift"], func);
         let sub= module.declare_func_in_func(runtime.builtin_functions["sub"], func);
         let unsignedRShift= module.declare_func_in_func(runtime.builtin_functions["unsignedRShift"], func);
 
+        let typeOf= module.declare_func_in_func(runtime.builtin_functions["typeof"], func);
+        let typeof_var= module.declare_func_in_func(runtime.builtin_functions["typeof_var"], func);
+        let delete_member= module.declare_func_in_func(runtime.builtin_functions["delete_member"], func);
+        let not= module.declare_func_in_func(runtime.builtin_functions["not"], func);
+        let neg= module.declare_func_in_func(runtime.builtin_functions["neg"], func);
+        let pos= module.declare_func_in_func(runtime.builtin_functions["pos"], func);
+        let bitNot= module.declare_func_in_func(runtime.builtin_functions["bitNot"], func);
+        let to_numeric= module.declare_func_in_func(runtime.builtin_functions["to_numeric"], func);
+        let increment= module.declare_func_in_func(runtime.builtin_functions["increment"], func);
+        let decrement= module.declare_func_in_func(runtime.builtin_functions["decrement"], func);
+
 
         let mut func_ctx = Box::leak(Box::new(FunctionBuilderContext::new()));
         let func_ctx_ptr = func_ctx as *mut FunctionBuilderContext;
         let builder = Box::leak(Box::new(FunctionBuilder::new(unsafe{std::mem::transmute_copy(&func)}, func_ctx)));
 
-        builder.declare_var(Variable::with_u32(0), module.isa().pointer_type());
+        builder.declare_var(Variable::with_u32(0), pointer_type);
         builder.declare_var(Variable::with_u32(1), JValue::TYPE);
+        builder.declare_var(Variable::with_u32(2), JValue::TYPE);
+
         let entry_block = builder.create_block();
+        builder.append_block_params_for_function_params(entry_block);
+        builder.switch_to_block(entry_block);
+        builder.seal_block(entry_block);
+
+        let vmctx = builder.block_params(entry_block)[0];
+        let this = builder.block_params(entry_block)[1];
+        builder.def_var(Variable::with_u32(0), vmctx);
+        builder.def_var(Variable::with_u32(1), this);
+
+        let undefined = builder.ins().iconst(JValue::TYPE, JValue::Undefined.to_bits() as i64);
+        builder.def_var(Variable::with_u32(2), undefined);
 
         return Self { 
             runtime:runtime.clone(), 
@@ -203,37 +516,100 @@ impl<'a> BuilderContext<'a>{
             jit_module:module.clone(),
 
             variables: Default::default(), 
+            record: Arc::new(Cell::new(0)),
+            slots: Default::default(),
+            scope: Arc::new(FunctionScope::script()),
+            detached: false,
+            script: true,
+            scoped: false,
             loop_exits: Arc::new(RefCell::new(Vec::new())), 
-            is_in_try: false, 
-            try_exits: Arc::new(RefCell::new(Vec::new())), 
+            unwind: Arc::new(RefCell::new(Vec::new())), 
             need_capture: Arc::new(RefCell::new(Vec::new())), 
+            named_captures: Arc::new(RefCell::new(Vec::new())), 
+            private_names: Arc::new(RefCell::new(Vec::new())),
+
+            is_generator: false,
+            is_async: false,
+            resume_points: Arc::new(RefCell::new(Vec::new())),
+            dispatch: None,
+            yield_position: false,
+            temporaries: Arc::new(RefCell::new(Vec::new())),
+            chain_exit: None,
+            opt_chain: None,
 
             entry_block,
 
             vmctx: Variable::with_u32(0), 
             this: Variable::with_u32(1), 
+            completion: Variable::with_u32(2),
 
-            pointer_type:module.isa().pointer_type(),
+            pointer_type,
 
             resolve_var,
             set_var,
+            declare_slot,
+            get_slot,
+            set_slot,
+            get_closure,
+            set_closure,
+            declare_script_slot,
+            hoist_script_slot,
+            resolve_free,
+            set_free,
+            typeof_free,
+            uninitialized_error,
+            const_error,
+            push_scope,
+            pop_scope,
+            scope_parent,
             resolve_argument,
+            rest_arguments,
+            require_object_coercible,
+            object_rest,
+            iterator_rest,
             to_bool,
-            throw,
+            is_nullish,
+            set_exception,
             member,
             superMember,
             set_memebr,
-            assign_member,
             set_member_spread,
+            ic_get,
+            ic_set,
             call,
             construct,
             memberCall,
             superMemberCall,
             tpl_new,
             function_new,
+            function_capture,
+            function_capture_slot,
+            function_capture_closure,
+            function_bind_this,
             array_new,
+            array_push,
+            array_spread,
             new_object,
-            object_from_inner,
+            get_iterator,
+            for_in_iterator,
+            iterator_next,
+            iterator_close,
+            generator_state,
+            generator_yield,
+            generator_scope,
+            generator_resume,
+            generator_delegate,
+            define_field,
+            private_get,
+            private_set,
+            private_define,
+            private_in,
+            private_name_new,
+            super_call,
+            set_home_object,
+            define_method,
+            class_new,
+            class_fields,
             add,
             bitAnd,
             bitOr,
@@ -247,18 +623,26 @@ impl<'a> BuilderContext<'a>{
             In,
             instanceof,
             lshift,
-            and,
-            or,
             lt,
             lteq,
             Mod,
             mul,
             noteq,
             noteqeq,
-            nullishCoalscing,
             rshift,
             sub,
             unsignedRShift,
+
+            typeOf,
+            typeof_var,
+            delete_member,
+            not,
+            neg,
+            pos,
+            bitNot,
+            to_numeric,
+            increment,
+            decrement,
         }
     }
 
@@ -273,12 +657,19 @@ impl<'a> BuilderContext<'a>{
                 jit_module:self.jit_module.clone(),
 
                 variables:Default::default(),
+                record:self.record.clone(),
+                slots:Default::default(),
+                scope:self.scope.clone(),
+                scoped:false,
                 loop_exits:self.loop_exits.clone(),
 
-                is_in_try:self.is_in_try,
-                try_exits:self.try_exits.clone(),
+                unwind:self.unwind.clone(),
 
                 need_capture:self.need_capture.clone(),
+                named_captures:self.named_captures.clone(),
+                private_names:self.private_names.clone(),
+                resume_points:self.resume_points.clone(),
+                temporaries:self.temporaries.clone(),
 
                 ..*self
             }
@@ -286,432 +677,1332 @@ impl<'a> BuilderContext<'a>{
         
     }
 
-    pub fn new_function(&mut self) -> (Self, &'static mut Context){
-        let mut context = Box::leak(Box::new(self.jit_module.make_context()));
-        let mut f = Self::new(self.runtime.clone(), self.jit_module.clone(), context);
+    /// the builder of a nested function and the cranelift context holding it, 
+    /// the context is freed after the builder.
+    pub fn new_function(&mut self) -> (Box<Context>, Self){
+        let mut context = Box::new(self.jit_module.make_context());
+        let mut f = Self::new(self.runtime.clone(), self.jit_module.clone(), &mut context);
         f.parent = Some(unsafe{std::mem::transmute_copy(&self)});
-        (f, context)
+        f.private_names = self.private_names.clone();
+        f.script = false;
+        (context, f)
     }
 
+    /// leave the scope, the context pushed by `push_scope` is popped
     pub fn close(&mut self){
+        if !self.scoped{
+            return
+        }
+        let top = self.unwind.borrow_mut().pop();
+        debug_assert!(matches!(top, Some(Unwind::Scope(v, _)) if v == self.vmctx), "scopes closed out of order");
+
+        let scope = self.builder.use_var(self.vmctx);
+        self.builder.ins().call(self.pop_scope, &[scope]);
+        self.scoped = false;
+    }
+
+    /// declare the context bindings of this scope in a new context, 
+    /// shadowing the bindings of the enclosing scopes with the same name.
+    fn push_scope(&mut self){
+        if self.scoped{
+            return
+        }
+        let outer = self.vmctx;
+        let vmctx = self.builder.use_var(outer);
+        let ins = self.builder.ins().call(self.push_scope, &[vmctx]);
+        let scope = self.builder.inst_results(ins)[0];
+
+        let var = self.scope.new_variable();
+        self.builder.declare_var(var, self.pointer_type);
+        self.builder.def_var(var, scope);
+
+        self.vmctx = var;
+        self.record = Arc::new(Cell::new(0));
+        self.scoped = true;
+        // the names declared by the scope are no longer resolved from the script context
+        self.script = false;
+        self.unwind.borrow_mut().push(Unwind::Scope(var, outer));
+    }
+
+    /// the context of the function being compiled, 
+    /// used by the helpers not resolving bindings.
+    fn function_context(&mut self) -> Value{
+        self.builder.use_var(Variable::with_u32(0))
+    }
+
+    /// translate the statements of a script, its declarations are hoisted.
+    pub fn translate_script(&mut self, stmts:&[&Stmt]) -> Result<(), Error>{
+        // the bindings of the previous scripts keep their index in the script context
+        self.record.set(self.runtime.realm().script_bindings.len);
+
+        let stmts = stmts.iter().copied();
+        self.declare_scope(scope::function_names(&[], stmts.clone()), true)?;
+        self.hoist_functions(stmts.clone(), true)?;
+        for s in stmts{
+            self.translate_stmt(s, None)?;
+        }
+        Ok(())
+    }
 
+    /// return the completion value and finish the function.
+    /// 
+    /// used by script bodies, the builder must not be used afterwards.
+    /// the top level bindings of the script are resolved by index from the following scripts.
+    pub fn finalize(&mut self){
+        let bindings = &mut self.runtime.realm().script_bindings;
+        bindings.names.extend(self.variables.iter().map(|(id, b)|(*id, *b)));
+        bindings.len = self.record.get();
+
+        let re = self.builder.use_var(self.completion);
+        self.close();
+        self.builder.ins().return_(&[re]);
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
     }
 
 
-    /// return a B8 value
+    /// return an I8 value, nonzero if `v` is truthy, booleans are tested inline
     pub fn to_bool(&mut self, v:Value) -> Value{
+        let not_true = self.builder.create_block();
+        let slow_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.append_block_param(exit_block, types::I8);
+
+        let is_true = self.builder.ins().icmp_imm(IntCC::Equal, v, value::TRUE as i64);
+        let t = self.builder.ins().iconst(types::I8, 1);
+        self.builder.ins().brnz(is_true, exit_block, &[t]);
+        self.builder.ins().jump(not_true, &[]);
+
+        self.builder.switch_to_block(not_true);
+        self.builder.seal_block(not_true);
+        let is_false = self.builder.ins().icmp_imm(IntCC::Equal, v, value::FALSE as i64);
+        let f = self.builder.ins().iconst(types::I8, 0);
+        self.builder.ins().brnz(is_false, exit_block, &[f]);
+        self.builder.ins().jump(slow_block, &[]);
+
+        self.builder.switch_to_block(slow_block);
+        self.builder.seal_block(slow_block);
         let ins = self.builder.ins().call(self.to_bool, &[v]);
-        self.builder.inst_results(ins)[0]
-    }
+        let b = self.builder.inst_results(ins)[0];
+        self.builder.ins().jump(exit_block, &[b]);
 
-    pub fn eqeqeq(&mut self, v:Value, v1:Value) -> Value{
-        let ins = self.builder.ins().call(self.eqeqeq, &[v, v1]);
-        self.builder.inst_results(ins)[0]
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        self.builder.block_params(exit_block)[0]
     }
 
-    pub fn const_value(&mut self, value:JValue) -> Value{
-        todo!()
+    /// return a B1 value, true if `v` is a number
+    fn is_number(&mut self, v:Value) -> Value{
+        self.builder.ins().icmp_imm(IntCC::UnsignedLessThan, v, value::TAG_MIN as i64)
     }
 
-    pub fn has_variable(&self, v:usize) -> bool{
-        if self.variables.contains(&v){
-            true
-        } else{
-            if let Some(p) = &self.parent{
-                p.has_variable(v)
-            } else{
-                false
-            }
-        }
+    /// the value of the float `f`, a NaN is replaced by the canonical NaN
+    fn box_number(&mut self, f:Value) -> Value{
+        let bits = self.builder.ins().bitcast(JValue::TYPE, f);
+        let nan = self.builder.ins().fcmp(FloatCC::Unordered, f, f);
+        let canonical = self.builder.ins().iconst(JValue::TYPE, value::CANONICAL_NAN as i64);
+        self.builder.ins().select(nan, canonical, bits)
     }
 
-    pub fn translate_stmt(&mut self, stmt:&Stmt, label:Option<String>) -> Result<(), Error>{
-        match stmt{
-            Stmt::Block(b) => {
-                let mut ctx = self.new_context();
-                for i in &b.stmts{
-                    ctx.translate_stmt(i, None)?;
-                }
-                ctx.close();
-            },
+    /// the value of the B1 `b`
+    fn box_bool(&mut self, b:Value) -> Value{
+        let t = self.builder.ins().iconst(JValue::TYPE, value::TRUE as i64);
+        let f = self.builder.ins().iconst(JValue::TYPE, value::FALSE as i64);
+        self.builder.ins().select(b, t, f)
+    }
 
-            Stmt::Break(b) => {
-                
-                if self.loop_exits.borrow().is_empty(){
-                    return Err(Error::IllegalBreakStatement)
-                }
+    /// continue in a new block if `cond` is true, jump to `fail` otherwise
+    fn guard(&mut self, cond:Value, fail:Block){
+        let next = self.builder.create_block();
+        self.builder.ins().brz(cond, fail, &[]);
+        self.builder.ins().jump(next, &[]);
+        self.builder.switch_to_block(next);
+        self.builder.seal_block(next);
+    }
 
-                if let Some(l) = &b.label{
+    /// compute `fast` inline if both operands are numbers, call `helper` otherwise.
+    /// 
+    /// `fast` receives the operands as floats, it may give up by jumping to the block it is passed.
+    fn number_op<F>(&mut self, left:Value, right:Value, helper:FuncRef, fast:F) -> Result<Value, Error>
+    where F:FnOnce(&mut Self, Value, Value, Block) -> Value{
+        let slow_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.append_block_param(exit_block, JValue::TYPE);
+
+        let l = self.is_number(left);
+        self.guard(l, slow_block);
+        let r = self.is_number(right);
+        self.guard(r, slow_block);
+
+        let a = self.builder.ins().bitcast(types::F64, left);
+        let b = self.builder.ins().bitcast(types::F64, right);
+        let v = fast(self, a, b, slow_block);
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(slow_block);
+        self.builder.seal_block(slow_block);
+        let v = self.call_helper(helper, &[left, right])?;
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        Ok(self.builder.block_params(exit_block)[0])
+    }
 
-                    // lable exist
-                    for i in self.loop_exits.borrow().iter(){
-                        if let Some(s) = &i.label{
-                            if s.as_str() == &l.sym{
-                                self.builder.ins().jump(i.exit_block, &[]);
-                                return Ok(())
-                            }
-                        }
-                    }
+    /// the I32 value of the float `f`, jump to `fail` if `f` is not an int32
+    fn int32_of(&mut self, f:Value, fail:Block) -> Value{
+        let i = self.builder.ins().fcvt_to_sint_sat(types::I32, f);
+        let back = self.builder.ins().fcvt_from_sint(types::F64, i);
+        let exact = self.builder.ins().fcmp(FloatCC::Equal, back, f);
+        self.guard(exact, fail);
+        i
+    }
 
-                } else{
-                    let e = self.loop_exits.borrow();
-                    let exit = e.last().unwrap();
-                    self.builder.ins().jump(exit.exit_block, &[]);
-                }
-            },
+    /// compare the shape of `obj` with the entries of `cache`, 
+    /// return the address of the cached slot or jump to `miss` if no entry matches.
+    fn cache_lookup(&mut self, obj:Value, cache:*mut InlineCache, miss:Block) -> Value{
+        let tag = self.builder.ins().band_imm(obj, !value::PAYLOAD_MASK as i64);
+        let is_object = self.builder.ins().icmp_imm(IntCC::Equal, tag, value::TAG_OBJECT as i64);
+        self.guard(is_object, miss);
 
-            Stmt::Continue(c) => {
-                if self.loop_exits.borrow().is_empty(){
-                    return Err(Error::IllegalContinueStatment)
-                }
+        let ptr = self.builder.ins().band_imm(obj, value::PAYLOAD_MASK as i64);
+        let shape = self.builder.ins().load(types::I64, MemFlags::trusted(), ptr, object::SHAPE_OFFSET);
+        let cache = self.builder.ins().iconst(types::I64, cache as i64);
 
-                if let Some(l) = &c.label{
+        let hit_block = self.builder.create_block();
+        self.builder.append_block_param(hit_block, types::I64);
 
-                    // lable exist
-                    for i in self.loop_exits.borrow().iter(){
-                        if let Some(s) = &i.label{
-                            if s.as_str() == &l.sym{
-                                self.builder.ins().jump(i.continue_block, &[]);
-                                return Ok(())
-                            }
-                        }
-                    }
+        for i in 0..inline_cache::ENTRIES{
+            let entry = self.builder.ins().load(types::I64, MemFlags::trusted(), cache, InlineCache::shape_offset(i));
+            let eq = self.builder.ins().icmp(IntCC::Equal, shape, entry);
 
-                    // lable does not exist
-                    return Err(Error::UndefinedLabel(l.sym.to_string()))
+            let found = self.builder.create_block();
+            let next = self.builder.create_block();
+            self.builder.ins().brnz(eq, found, &[]);
+            self.builder.ins().jump(next, &[]);
 
-                } else{
-                    let e = self.loop_exits.borrow();
-                    let exit = e.last().unwrap();
-                    self.builder.ins().jump(exit.continue_block, &[]);
-                }
-            },
+            self.builder.switch_to_block(found);
+            self.builder.seal_block(found);
+            let slot = self.builder.ins().uload32(MemFlags::trusted(), cache, InlineCache::slot_offset(i));
+            self.builder.ins().jump(hit_block, &[slot]);
 
-            Stmt::Debugger(d) => {
-                todo!()
-            },
+            self.builder.switch_to_block(next);
+            self.builder.seal_block(next);
+        }
+        self.builder.ins().jump(miss, &[]);
+
+        self.builder.switch_to_block(hit_block);
+        self.builder.seal_block(hit_block);
+        let slot = self.builder.block_params(hit_block)[0];
+        let values = self.builder.ins().load(types::I64, MemFlags::trusted(), ptr, object::SLOTS_OFFSET);
+        let offset = self.builder.ins().imul_imm(slot, size_of::<JValue>() as i64);
+        self.builder.ins().iadd(values, offset)
+    }
 
-            Stmt::Decl(d) => {
-                match d{
-                    Decl::Class(c) => {
-                        let class = self.translate_class(&c.class, Some(&c.ident.sym))?;
-                        self.translate_ident_decl(&c.ident, class)?;
-                    },
-                    Decl::Fn(f) => {
-                        let func = self.translate_func(&f.function, Some(&f.ident.sym))?;
-                    },
-                    Decl::TsEnum(e) => {
-                        todo!()
-                    },
-                    Decl::TsInterface(i) => {
-                        todo!()
-                    },
-                    Decl::TsModule(m) => {
-                        todo!()
-                    },
-                    Decl::TsTypeAlias(t) => {
-                        todo!()
-                    },
-                    Decl::Var(v) => {
-                        self.translate_var_decl(v)?;
-                    }
-                }
-            },
+    /// `obj.name` through an inline cache
+    fn cached_member(&mut self, obj:Value, name:&str) -> Result<Value, Error>{
+        let cache = InlineCache::new(name);
+        let miss_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.append_block_param(exit_block, JValue::TYPE);
+
+        let addr = self.cache_lookup(obj, cache, miss_block);
+        let v = self.builder.ins().load(JValue::TYPE, MemFlags::trusted(), addr, 0);
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(miss_block);
+        self.builder.seal_block(miss_block);
+        let cache = self.builder.ins().iconst(types::I64, cache as i64);
+        let v = self.call_helper(self.ic_get, &[cache, obj])?;
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        Ok(self.builder.block_params(exit_block)[0])
+    }
 
-            Stmt::DoWhile(d) => {
-                let entry_block = self.builder.create_block();
-                let exit_block = self.builder.create_block();
+    /// `obj.name = value` through an inline cache
+    fn cached_set_member(&mut self, obj:Value, name:&str, value:Value) -> Result<(), Error>{
+        let cache = InlineCache::new(name);
+        let miss_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
 
-                self.loop_exits.as_ref().borrow_mut().push(LoopExit { 
-                    label: label, 
-                    exit_block, 
-                    continue_block: entry_block 
-                });
+        let addr = self.cache_lookup(obj, cache, miss_block);
+        self.builder.ins().store(MemFlags::trusted(), value, addr, 0);
+        self.builder.ins().jump(exit_block, &[]);
 
-                self.builder.ins().jump(entry_block, &[]);
-                
-                self.builder.switch_to_block(entry_block);
+        self.builder.switch_to_block(miss_block);
+        self.builder.seal_block(miss_block);
+        let cache = self.builder.ins().iconst(types::I64, cache as i64);
+        self.call_helper(self.ic_set, &[cache, obj, value])?;
+        self.builder.ins().jump(exit_block, &[]);
 
-                let mut ctx = self.new_context();
-                ctx.translate_stmt(d.body.as_ref(), None)?;
-                
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        Ok(())
+    }
 
-                let test_re = ctx.translate_expr(&d.test)?;
-                let b = ctx.to_bool(test_re);
-                
-                ctx.close();
-                self.loop_exits.as_ref().borrow_mut().pop();
+    /// translate a binary operator.
+    /// 
+    /// arithmetic and comparisons of numbers are computed inline, 
+    /// bitwise operators inline int32 operands, the other operands call the runtime.
+    /// operators on loop counters are computed on integers by `translate_counter_binary`.
+    pub fn translate_binary(&mut self, op:BinaryOp, left:Value, right:Value) -> Result<Value, Error>{
+        let helper = match op{
+            BinaryOp::Add => self.add,
+            BinaryOp::BitAnd => self.bitAnd,
+            BinaryOp::BitOr => self.bitOr,
+            BinaryOp::BitXor => self.bitXor,
+            BinaryOp::Div => self.div,
+            BinaryOp::EqEq => self.eqeq,
+            BinaryOp::EqEqEq => self.eqeqeq,
+            BinaryOp::Exp => self.exp,
+            BinaryOp::Gt => self.gt,
+            BinaryOp::GtEq => self.gteq,
+            BinaryOp::In => self.In,
+            BinaryOp::InstanceOf => self.instanceof,
+            BinaryOp::LShift => self.lshift,
+            BinaryOp::LogicalAnd | 
+            BinaryOp::LogicalOr |
+            BinaryOp::NullishCoalescing => unreachable!(),
+            BinaryOp::Lt => self.lt,
+            BinaryOp::LtEq => self.lteq,
+            BinaryOp::Mod => self.Mod,
+            BinaryOp::Mul => self.mul,
+            BinaryOp::NotEq => self.noteq,
+            BinaryOp::NotEqEq => self.noteqeq,
+            BinaryOp::RShift => self.rshift,
+            BinaryOp::Sub => self.sub,
+            BinaryOp::ZeroFillRShift => self.unsignedRShift,
+        };
+
+        match op{
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                self.number_op(left, right, helper, |ctx, a, b, _|{
+                    let f = match op{
+                        BinaryOp::Add => ctx.builder.ins().fadd(a, b),
+                        BinaryOp::Sub => ctx.builder.ins().fsub(a, b),
+                        BinaryOp::Mul => ctx.builder.ins().fmul(a, b),
+                        _ => ctx.builder.ins().fdiv(a, b),
+                    };
+                    ctx.box_number(f)
+                })
+            },
+            BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq |
+            BinaryOp::EqEq | BinaryOp::EqEqEq | BinaryOp::NotEq | BinaryOp::NotEqEq => {
+                self.number_op(left, right, helper, |ctx, a, b, _|{
+                    // comparisons with NaN are false, except inequality
+                    let cc = match op{
+                        BinaryOp::Lt => FloatCC::LessThan,
+                        BinaryOp::LtEq => FloatCC::LessThanOrEqual,
+                        BinaryOp::Gt => FloatCC::GreaterThan,
+                        BinaryOp::GtEq => FloatCC::GreaterThanOrEqual,
+                        BinaryOp::EqEq | BinaryOp::EqEqEq => FloatCC::Equal,
+                        _ => FloatCC::NotEqual,
+                    };
+                    let c = ctx.builder.ins().fcmp(cc, a, b);
+                    ctx.box_bool(c)
+                })
+            },
+            BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor |
+            BinaryOp::LShift | BinaryOp::RShift | BinaryOp::ZeroFillRShift => {
+                self.number_op(left, right, helper, |ctx, a, b, slow_block|{
+                    let x = ctx.int32_of(a, slow_block);
+                    let y = ctx.int32_of(b, slow_block);
+                    // shifts count modulo 32 like in javascript
+                    let i = match op{
+                        BinaryOp::BitAnd => ctx.builder.ins().band(x, y),
+                        BinaryOp::BitOr => ctx.builder.ins().bor(x, y),
+                        BinaryOp::BitXor => ctx.builder.ins().bxor(x, y),
+                        BinaryOp::LShift => ctx.builder.ins().ishl(x, y),
+                        BinaryOp::RShift => ctx.builder.ins().sshr(x, y),
+                        _ => ctx.builder.ins().ushr(x, y),
+                    };
+                    // the result of `>>>` is unsigned
+                    let i = if op == BinaryOp::ZeroFillRShift{
+                        ctx.builder.ins().uextend(types::I64, i)
+                    } else{
+                        ctx.builder.ins().sextend(types::I64, i)
+                    };
+                    let f = ctx.builder.ins().fcvt_from_sint(types::F64, i);
+                    ctx.builder.ins().bitcast(JValue::TYPE, f)
+                })
+            },
+            _ => self.call_helper(helper, &[left, right])
+        }
+    }
 
-                self.builder.ins().brz(b, exit_block, &[]);
-                self.builder.ins().jump(entry_block, &[]);
+    /// the slot of the loop counter `e` refers to, if any
+    fn counter_slot(&mut self, e:&Expr) -> Option<Slot>{
+        let mut e = e;
+        while let Expr::Paren(p) = e{
+            e = &p.expr;
+        }
+        match e{
+            Expr::Ident(i) => {
+                let id = self.runtime.to_mut().new_variable_name(&i.sym);
+                self.find_slot(id).filter(|s|s.counter)
+            },
+            _ => None
+        }
+    }
 
-                self.builder.seal_block(entry_block);
-                self.builder.seal_block(exit_block);
+    /// translate `+`, `-`, `*` and the comparisons with a loop counter operand on integers.
+    ///
+    /// the other operand must be an int32 number and the result of the arithmetic an int32 other than zero,
+    /// which may be a negative zero. other operands and results are computed by `translate_binary`.
+    /// return none if `b` is not such an operator on a counter.
+    fn translate_counter_binary(&mut self, b:&BinExpr, yield_position:bool) -> Result<Option<Value>, Error>{
+        if !matches!(b.op, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul |
+            BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq |
+            BinaryOp::EqEq | BinaryOp::EqEqEq | BinaryOp::NotEq | BinaryOp::NotEqEq){
+            return Ok(None)
+        }
+        let l = self.counter_slot(&b.left);
+        let r = self.counter_slot(&b.right);
+        if l.is_none() && r.is_none(){
+            return Ok(None)
+        }
 
-                self.builder.switch_to_block(exit_block);
+        // the operands cannot assign the counter, it is read once both are evaluated
+        let left = match l{
+            Some(s) => CounterOperand::Counter(s),
+            None => CounterOperand::Value(self.translate_operand(&b.left, yield_position)?)
+        };
+        let right = match r{
+            Some(s) => CounterOperand::Counter(s),
+            None => CounterOperand::Value(self.translate_operand(&b.right, yield_position)?)
+        };
+
+        let slow_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.append_block_param(exit_block, JValue::TYPE);
+
+        let x = self.int32_operand(left, slow_block);
+        let y = self.int32_operand(right, slow_block);
+        let v = match b.op{
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+                // int32 operands do not overflow an I64
+                let i = match b.op{
+                    BinaryOp::Add => self.builder.ins().iadd(x, y),
+                    BinaryOp::Sub => self.builder.ins().isub(x, y),
+                    _ => self.builder.ins().imul(x, y),
+                };
+                let n = self.builder.ins().ireduce(types::I32, i);
+                let n = self.builder.ins().sextend(types::I64, n);
+                let exact = self.builder.ins().icmp(IntCC::Equal, n, i);
+                self.guard(exact, slow_block);
+                let nonzero = self.builder.ins().icmp_imm(IntCC::NotEqual, i, 0);
+                self.guard(nonzero, slow_block);
+
+                let f = self.builder.ins().fcvt_from_sint(types::F64, i);
+                self.builder.ins().bitcast(JValue::TYPE, f)
             },
+            op => {
+                let cc = match op{
+                    BinaryOp::Lt => IntCC::SignedLessThan,
+                    BinaryOp::LtEq => IntCC::SignedLessThanOrEqual,
+                    BinaryOp::Gt => IntCC::SignedGreaterThan,
+                    BinaryOp::GtEq => IntCC::SignedGreaterThanOrEqual,
+                    BinaryOp::EqEq | BinaryOp::EqEqEq => IntCC::Equal,
+                    _ => IntCC::NotEqual,
+                };
+                let c = self.builder.ins().icmp(cc, x, y);
+                self.box_bool(c)
+            }
+        };
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(slow_block);
+        self.builder.seal_block(slow_block);
+        let left = self.counter_operand_value(left);
+        let right = self.counter_operand_value(right);
+        let v = self.translate_binary(b.op, left, right)?;
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        Ok(Some(self.builder.block_params(exit_block)[0]))
+    }
 
-            Stmt::Empty(e) => {},
-            Stmt::Expr(e) => {
-                self.translate_expr(&e.expr)?;
+    /// the I64 value of an operand in the int32 range, jump to `fail` otherwise
+    fn int32_operand(&mut self, operand:CounterOperand, fail:Block) -> Value{
+        match operand{
+            CounterOperand::Counter(slot) => {
+                // the counter is an I64, it leaves the int32 range past `i32::MAX` iterations
+                let i = self.builder.use_var(slot.var);
+                let n = self.builder.ins().ireduce(types::I32, i);
+                let n = self.builder.ins().sextend(types::I64, n);
+                let fits = self.builder.ins().icmp(IntCC::Equal, n, i);
+                self.guard(fits, fail);
+                i
             },
-            Stmt::For(f) => {
-                let entry_block = self.builder.create_block();
-                let exit_block = self.builder.create_block();
+            CounterOperand::Value(v) => {
+                let n = self.is_number(v);
+                self.guard(n, fail);
+                let f = self.builder.ins().bitcast(types::F64, v);
+                let i = self.int32_of(f, fail);
+                self.builder.ins().sextend(types::I64, i)
+            }
+        }
+    }
 
-                self.loop_exits.as_ref().borrow_mut().push(LoopExit { 
-                    label: label, 
-                    exit_block, 
-                    continue_block: entry_block 
-                });
-                
-                // init
-                if let Some(d) = &f.init{
-                    match d{
-                        VarDeclOrExpr::Expr(e) => {
-                            self.translate_expr(&e)?;
-                        },
-                        VarDeclOrExpr::VarDecl(d) => {
-                            self.translate_var_decl(d)?;
-                        },
-                    }
-                }
+    fn counter_operand_value(&mut self, operand:CounterOperand) -> Value{
+        match operand{
+            CounterOperand::Counter(slot) => self.use_slot(slot),
+            CounterOperand::Value(v) => v
+        }
+    }
 
-                self.builder.ins().jump(entry_block, &[]);
-                
-                self.builder.switch_to_block(entry_block);
+    /// `++` or `--` on `old`, return the old value converted to a numeric and the new value.
+    /// 
+    /// numbers are computed inline.
+    fn translate_increment(&mut self, old:Value, op:UpdateOp) -> Result<(Value, Value), Error>{
+        let slow_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.append_block_param(exit_block, JValue::TYPE);
+        self.builder.append_block_param(exit_block, JValue::TYPE);
+
+        let n = self.is_number(old);
+        self.guard(n, slow_block);
+        let a = self.builder.ins().bitcast(types::F64, old);
+        let one = self.builder.ins().f64const(1.0);
+        let f = match op{
+            UpdateOp::PlusPlus => self.builder.ins().fadd(a, one),
+            UpdateOp::MinusMinus => self.builder.ins().fsub(a, one),
+        };
+        let new = self.box_number(f);
+        self.builder.ins().jump(exit_block, &[old, new]);
+
+        self.builder.switch_to_block(slow_block);
+        self.builder.seal_block(slow_block);
+        let helper = match op{
+            UpdateOp::PlusPlus => self.increment,
+            UpdateOp::MinusMinus => self.decrement,
+        };
+        let numeric = self.call_helper(self.to_numeric, &[old])?;
+        let new = self.call_helper(helper, &[numeric])?;
+        self.builder.ins().jump(exit_block, &[numeric, new]);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        let params = self.builder.block_params(exit_block);
+        Ok((params[0], params[1]))
+    }
 
-                let mut ctx = self.new_context();
+    /// compile an element of an optional chain.
+    /// 
+    /// `exit` is the exit block of the enclosing chain, 
+    /// none if `expr` is the outermost element.
+    fn translate_chain<F>(&mut self, expr:&Expr, exit:Option<Block>, f:F) -> Result<Value, Error>
+    where F:FnOnce(&mut Self) -> Result<Value, Error>{
+        if exit.is_some() || !has_opt_chain(expr){
+            let outer = std::mem::replace(&mut self.chain_exit, exit);
+            let re = f(self);
+            self.chain_exit = outer;
+            return re
+        }
 
-                // test
-                if let Some(e) = &f.test{
-                    let v = ctx.translate_expr(&e)?;
-                    let b = ctx.to_bool(v);
-                    ctx.builder.ins().brnz(b, exit_block, &[]);
-                }
+        let exit = self.builder.create_block();
+        self.builder.append_block_param(exit, JValue::TYPE);
 
-                ctx.translate_stmt(f.body.as_ref(), None)?;
+        let outer = self.chain_exit.replace(exit);
+        let re = f(self);
+        self.chain_exit = outer;
 
-                if let Some(e) = &f.update{
-                    ctx.translate_expr(&e)?;
-                }
+        let v = re?;
+        self.builder.ins().jump(exit, &[v]);
+        self.builder.switch_to_block(exit);
+        self.builder.seal_block(exit);
+        Ok(self.builder.block_params(exit)[0])
+    }
 
-                ctx.close();
-                self.loop_exits.as_ref().borrow_mut().pop();
+    /// short circuit the optional chain if `v` is null or undefined
+    fn check_opt_chain(&mut self, v:Value){
+        let exit = self.chain_exit.expect("optional chain without exit block");
+        let nullish = self.is_nullish(v);
+        let undefined = self.const_value(JValue::Undefined);
+        let next = self.builder.create_block();
+
+        self.builder.ins().brnz(nullish, exit, &[undefined]);
+        self.builder.ins().jump(next, &[]);
+        self.builder.switch_to_block(next);
+        self.builder.seal_block(next);
+    }
 
-                self.builder.ins().jump(entry_block, &[]);
+    /// translate the object of a member or the callee of a call, continuing the optional chain
+    fn translate_chain_object(&mut self, e:&Expr, yield_position:bool) -> Result<Value, Error>{
+        self.opt_chain = self.chain_exit;
+        self.translate_operand(e, yield_position)
+    }
 
-                self.builder.seal_block(entry_block);
-                self.builder.seal_block(exit_block);
+    /// `obj.prop`, `obj?.prop` if optional
+    pub fn translate_member(&mut self, m:&MemberExpr, optional:bool, yield_position:bool) -> Result<Value, Error>{
+        match &m.obj{
+            ExprOrSuper::Expr(e) => {
+                let obj = self.translate_chain_object(&e, yield_position)?;
+                if optional{
+                    self.check_opt_chain(obj);
+                }
 
-                self.builder.switch_to_block(exit_block);
+                if let Some(name) = cached_name(m){
+                    return self.cached_member(obj, name)
+                }
 
+                let held = self.hold(obj);
+                self.yield_position = yield_position && m.computed;
+                let prop = self.translate_prop(&m.prop, m.computed)?;
+                let obj = self.release(held);
+                let getter = if is_private(&m.prop){
+                    self.private_get
+                } else{
+                    self.member
+                };
+                self.call_helper(getter, &[obj, prop])
             },
-            Stmt::ForIn(f) => {
-                todo!()
-            },
-            Stmt::ForOf(f) => {
-                todo!()
-            },
-            Stmt::If(i) => {
-                let entry_block = self.builder.create_block();
-                let exit_block = self.builder.create_block();
+            ExprOrSuper::Super(s) => {
+                self.yield_position = yield_position && m.computed;
+                let prop = self.translate_prop(&m.prop, m.computed)?;
+                let vmctx = self.function_context();
+                let this = self.builder.use_var(self.this);
+                self.call_helper(self.superMember, &[vmctx, this, prop])
+            }
+        }
+    }
 
-                let test_re = self.translate_expr(&i.test)?;
-                let b = self.to_bool(test_re);
+    /// `callee(args)`, `callee?.(args)` if optional.
+    /// 
+    /// the callee is evaluated before the arguments.
+    pub fn translate_call(&mut self, c:&CallExpr, optional:bool, yield_position:bool) -> Result<Value, Error>{
+        let callee = match &c.callee{
+            ExprOrSuper::Expr(e) => e.as_ref(),
+            ExprOrSuper::Super(_) => {
+                let (argv, argc, spread) = self.translate_args(&c.args, yield_position)?;
+                let vmctx = self.function_context();
+                let this = self.builder.use_var(self.this);
+                let ins = self.builder.ins().call(self.super_call, &[vmctx, this, argv, argc, spread]);
+                let v = self.builder.inst_results(ins);
+                let (re, ok) = (v[0], v[1]);
 
-                self.builder.ins().brnz(b, entry_block, &[]);
+                self.check_call_result(re, ok)?;
+                return Ok(re)
+            }
+        };
+
+        // member calls pass the object as this, `a?.b()` is a member call too
+        let member = match callee{
+            Expr::Member(m) => Some((m, false)),
+            Expr::OptChain(o) => match o.expr.as_ref(){
+                Expr::Member(m) => Some((m, true)),
+                _ => None
+            },
+            _ => None
+        };
 
-                // fall through
-                if let Some(alt) = &i.alt{
-                    // if there is alternative (else)
-                    let mut ctx = self.new_context();
-                    ctx.translate_stmt(&alt, None)?;
-                    ctx.close();
+        let (re, ok) = match member{
+            Some((m, _)) if matches!(m.obj, ExprOrSuper::Super(_)) => {
+                self.yield_position = yield_position && m.computed;
+                let prop = self.translate_prop(&m.prop, m.computed)?;
+                let held = self.hold(prop);
+                let (argv, argc, spread) = self.translate_args(&c.args, yield_position)?;
+                let prop = self.release(held);
+                let this = self.builder.use_var(self.this);
+                let vmctx = self.function_context();
+
+                let ins = self.builder.ins().call(self.superMemberCall, &[
+                    this, 
+                    prop,
+                    vmctx, 
+                    argv, argc, spread
+                ]);
+                let v = self.builder.inst_results(ins);
+                (v[0], v[1])
+            },
 
-                } else{
-                    // jump to exit directly
-                    self.builder.ins().jump(exit_block, &[]);
+            // the method is resolved before the call if it can short circuit, 
+            // private methods are always resolved before the call
+            Some((m, optional_member)) if optional || optional_member || is_private(&m.prop) => {
+                let obj = match &m.obj{
+                    ExprOrSuper::Expr(e) => self.translate_chain_object(&e, yield_position)?,
+                    ExprOrSuper::Super(_) => unreachable!()
+                };
+                if optional_member{
+                    self.check_opt_chain(obj);
                 }
 
-                self.builder.seal_block(entry_block);
-                self.builder.switch_to_block(entry_block);
-
-                let mut ctx = self.new_context();
-                ctx.translate_stmt(&i.cons, None)?;
-                ctx.close();
-
-                self.builder.ins().jump(exit_block, &[]);
+                let held_obj = self.hold(obj);
+                self.yield_position = yield_position && m.computed;
+                let prop = self.translate_prop(&m.prop, m.computed)?;
+                let obj = self.builder.use_var(held_obj);
+                let getter = if is_private(&m.prop){
+                    self.private_get
+                } else{
+                    self.member
+                };
+                let callee = self.call_helper(getter, &[obj, prop])?;
+                if optional{
+                    self.check_opt_chain(callee);
+                }
 
-                self.builder.seal_block(exit_block);
-                self.builder.switch_to_block(exit_block);
+                let held_callee = self.hold(callee);
+                let (argv, argc, spread) = self.translate_args(&c.args, yield_position)?;
+                let callee = self.release(held_callee);
+                let obj = self.release(held_obj);
+                let vmctx = self.function_context();
+                let ins = self.builder.ins().call(self.call, &[callee, vmctx, obj, argv, argc, spread]);
+                let v = self.builder.inst_results(ins);
+                (v[0], v[1])
             },
 
-            Stmt::Labeled(l) => {
-                return self.translate_stmt(&l.body, Some(l.label.sym.to_string()));
+            Some((m, _)) => {
+                let obj = match &m.obj{
+                    ExprOrSuper::Expr(e) => self.translate_chain_object(&e, yield_position)?,
+                    ExprOrSuper::Super(_) => unreachable!()
+                };
+                let held_obj = self.hold(obj);
+                self.yield_position = yield_position && m.computed;
+                let prop = self.translate_prop(&m.prop, m.computed)?;
+                let held_prop = self.hold(prop);
+                let (argv, argc, spread) = self.translate_args(&c.args, yield_position)?;
+                let prop = self.release(held_prop);
+                let obj = self.release(held_obj);
+                let vmctx = self.function_context();
+                let ins = self.builder.ins().call(self.memberCall, &[obj, prop, vmctx, argv, argc, spread]);
+                let v = self.builder.inst_results(ins);
+                (v[0], v[1])
             },
 
-            Stmt::Return(r) => {
-                
-                let re = if let Some(e) = &r.arg{
-                    self.translate_expr(&e)?
-                } else{
-                    self.builder.ins().iconst(types::I128, 0)
-                };
-                self.close();
-                self.builder.ins().return_(&[re]);
-            },
+            // this is a function call
+            None => {
+                let callee = self.translate_chain_object(callee, yield_position)?;
+                if optional{
+                    self.check_opt_chain(callee);
+                }
 
-            Stmt::Switch(s) => {
-                let discrim = self.translate_expr(&s.discriminant)?;
+                let held = self.hold(callee);
+                let (argv, argc, spread) = self.translate_args(&c.args, yield_position)?;
+                let callee = self.release(held);
+                let vmctx = self.function_context();
+                let this = self.builder.use_var(self.this);
+                let ins = self.builder.ins().call(self.call, &[
+                    callee, 
+                    vmctx, 
+                    this, 
+                    argv, argc, spread
+                ]);
+                let v = self.builder.inst_results(ins);
+                (v[0], v[1])
+            }
+        };
 
-                let exit_block = self.builder.create_block();
+        self.check_call_result(re, ok)?;
+        Ok(re)
+    }
 
-                let mut ctx = self.new_context();
+    /// `target op= value`, the target is evaluated once before the value.
+    /// 
+    /// logical assignments only evaluate the value and assign if they do not short circuit.
+    fn translate_compound_assign(&mut self, a:&AssignExpr, yield_position:bool) -> Result<Value, Error>{
+        let mut target = match &a.left{
+            PatOrExpr::Expr(e) => e.as_ref(),
+            PatOrExpr::Pat(p) => match p.as_ref(){
+                Pat::Expr(e) => e.as_ref(),
+                Pat::Ident(i) => {
+                    let old = self.resolve_binding(&i.id.sym)?;
+                    return self.translate_assign_op(a, old, yield_position, |ctx, v|ctx.translate_ident_decl(&i.id, v, None))
+                },
+                _ => return Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+            }
+        };
+        while let Expr::Paren(p) = target{
+            target = &p.expr;
+        }
 
-                for cas in &s.cases{
-                    if let Some(d) = &cas.test{
+        match target{
+            Expr::Ident(i) => {
+                let old = self.resolve_binding(&i.sym)?;
+                self.translate_assign_op(a, old, yield_position, |ctx, v|ctx.translate_ident_decl(i, v, None))
+            },
+            Expr::Member(m) => {
+                let obj = match &m.obj{
+                    ExprOrSuper::Expr(e) => self.translate_operand(&e, yield_position)?,
+                    // `super.x op= v` reads from the home object and assigns to `this`
+                    ExprOrSuper::Super(_) => self.builder.use_var(self.this)
+                };
+                let held_obj = self.hold(obj);
+                self.yield_position = yield_position && m.computed;
+                let prop = self.translate_prop(&m.prop, m.computed)?;
+                let held_prop = self.hold(prop);
+                let obj = self.builder.use_var(held_obj);
+                let private = is_private(&m.prop);
+
+                let old = if let Some(name) = cached_name(m){
+                    self.cached_member(obj, name)?
+                } else if private{
+                    self.call_helper(self.private_get, &[obj, prop])?
+                } else if let ExprOrSuper::Super(_) = &m.obj{
+                    let vmctx = self.function_context();
+                    self.call_helper(self.superMember, &[vmctx, obj, prop])?
+                } else{
+                    self.call_helper(self.member, &[obj, prop])?
+                };
 
-                        let val = self.translate_expr(&d)?;
-                        let b = self.eqeqeq(discrim, val);
-                        let b = self.to_bool(b);
+                // the value may suspend the function, the target is reloaded to store it
+                let re = self.translate_assign_op(a, old, yield_position, |ctx, v|{
+                    let obj = ctx.builder.use_var(held_obj);
+                    let prop = ctx.builder.use_var(held_prop);
+                    if private{
+                        let op = ctx.builder.ins().iconst(types::I8, AssignOp::Assign as i8 as i64);
+                        ctx.call_helper(ctx.private_set, &[obj, prop, v, op])?;
+                    } else if let Some(name) = cached_name(m){
+                        ctx.cached_set_member(obj, name, v)?;
+                    } else{
+                        ctx.call_helper(ctx.set_memebr, &[obj, prop, v])?;
+                    }
+                    Ok(())
+                });
+                self.release(held_prop);
+                self.release(held_obj);
+                re
+            },
+            _ => Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+        }
+    }
 
-                        let current = self.builder.current_block().unwrap();
-                        let case_block = self.builder.create_block();
+    /// compute the new value of a compound assignment from the current value `old` and store it.
+    fn translate_assign_op<F>(&mut self, a:&AssignExpr, old:Value, yield_position:bool, store:F) -> Result<Value, Error>
+    where F:Fn(&mut Self, Value) -> Result<(), Error>{
+        let assign = |ctx:&mut Self| -> Result<Value, Error>{
+            let v = ctx.translate_operand(&a.right, yield_position)?;
+            store(ctx, v)?;
+            Ok(v)
+        };
+        let keep = |_:&mut Self| -> Result<Value, Error>{Ok(old)};
+
+        let op = match a.op{
+            AssignOp::AndAssign => {
+                let test = self.to_bool(old);
+                return self.translate_select(test, assign, keep)
+            },
+            AssignOp::OrAssign => {
+                let test = self.to_bool(old);
+                return self.translate_select(test, keep, assign)
+            },
+            AssignOp::NullishAssign => {
+                let test = self.is_nullish(old);
+                return self.translate_select(test, assign, keep)
+            },
+            AssignOp::Assign => return assign(self),
+            AssignOp::AddAssign => BinaryOp::Add,
+            AssignOp::SubAssign => BinaryOp::Sub,
+            AssignOp::MulAssign => BinaryOp::Mul,
+            AssignOp::DivAssign => BinaryOp::Div,
+            AssignOp::ModAssign => BinaryOp::Mod,
+            AssignOp::ExpAssign => BinaryOp::Exp,
+            AssignOp::BitAndAssign => BinaryOp::BitAnd,
+            AssignOp::BitOrAssign => BinaryOp::BitOr,
+            AssignOp::BitXorAssign => BinaryOp::BitXor,
+            AssignOp::LShiftAssign => BinaryOp::LShift,
+            AssignOp::RShiftAssign => BinaryOp::RShift,
+            AssignOp::ZeroFillRShiftAssign => BinaryOp::ZeroFillRShift,
+        };
+
+        let held = self.hold(old);
+        let value = self.translate_operand(&a.right, yield_position)?;
+        let old = self.release(held);
+        let v = self.translate_binary(op, old, value)?;
+        store(self, v)?;
+        Ok(v)
+    }
 
-                        self.builder.ins().brnz(b, case_block, &[]);
+    /// return an I8 value, nonzero if `v` is null or undefined
+    pub fn is_nullish(&mut self, v:Value) -> Value{
+        let ins = self.builder.ins().call(self.is_nullish, &[v]);
+        self.builder.inst_results(ins)[0]
+    }
 
-                        self.builder.seal_block(case_block);
-                        self.builder.switch_to_block(case_block);
+    /// compile a conditional expression, only the taken branch is evaluated.
+    /// 
+    /// return the value of the taken branch.
+    pub fn translate_select<T, E>(&mut self, cond:Value, then:T, otherwise:E) -> Result<Value, Error>
+    where T:FnOnce(&mut Self) -> Result<Value, Error>, E:FnOnce(&mut Self) -> Result<Value, Error>{
+        let then_block = self.builder.create_block();
+        let else_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.append_block_param(exit_block, JValue::TYPE);
+
+        self.builder.ins().brnz(cond, then_block, &[]);
+        self.builder.ins().jump(else_block, &[]);
+
+        self.builder.switch_to_block(then_block);
+        self.builder.seal_block(then_block);
+        let v = then(self)?;
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(else_block);
+        self.builder.seal_block(else_block);
+        let v = otherwise(self)?;
+        self.builder.ins().jump(exit_block, &[v]);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        Ok(self.builder.block_params(exit_block)[0])
+    }
 
-                        for s in &cas.cons{
-                            ctx.translate_stmt(s, None)?;
-                        }
-                        self.builder.ins().jump(exit_block, &[]);
+    /// `v === v1`, strict equality never throws so the completion is not checked
+    pub fn eqeqeq(&mut self, v:Value, v1:Value) -> Value{
+        let ins = self.builder.ins().call(self.eqeqeq, &[v, v1]);
+        self.builder.inst_results(ins)[0]
+    }
 
-                        self.builder.switch_to_block(current);
+    pub fn const_value(&mut self, value:JValue) -> Value{
+        self.builder.ins().iconst(JValue::TYPE, value.to_bits() as i64)
+    }
 
-                    } else{
-                        let default_block = self.builder.create_block();
+    /// resolve where the binding `id` is stored, from the innermost scope.
+    ///
+    /// a binding declared by an enclosing function is added to the closure 
+    /// of every function in between.
+    fn place(&mut self, id:usize) -> Place{
+        if let Some(s) = self.slots.get(&id){
+            return Place::Slot(*s)
+        }
+        if let Some(b) = self.variables.get(&id){
+            return if self.script{
+                Place::Script(*b)
+            } else{
+                Place::Record(self.vmctx, *b)
+            }
+        }
 
-                        self.builder.ins().jump(default_block, &[]);
-                        self.builder.seal_block(default_block);
+        let outer = match &mut self.parent{
+            Some(p) => p.place(id),
+            // a detached body resolves what it does not declare from the calling context
+            None if self.detached => Place::Named,
+            None => return match self.runtime.realm().script_bindings.names.get(&id){
+                Some(b) => Place::Script(*b),
+                None => Place::Free
+            }
+        };
+        if !self.is_func{
+            return outer
+        }
 
-                        self.builder.switch_to_block(default_block);
-                        for s in &cas.cons{
-                            ctx.translate_stmt(s, None)?;
-                        }
-                        self.builder.ins().jump(exit_block, &[]);
+        match outer{
+            Place::Record(_, b) | Place::Closure(b) => {
+                let mut captures = self.need_capture.borrow_mut();
+                let index = match captures.iter().position(|c|*c == id){
+                    Some(i) => i,
+                    None => {
+                        captures.push(id);
+                        captures.len() - 1
                     }
+                };
+                Place::Closure(ContextBinding{index:index as u32, kind:b.kind})
+            },
+            Place::Named => {
+                let mut captures = self.named_captures.borrow_mut();
+                if !captures.contains(&(id as u64)){
+                    captures.push(id as u64);
                 }
+                Place::Named
+            },
+            Place::Slot(_) => unreachable!("the bindings referred to by nested functions are not kept in slots"),
+            p => p
+        }
+    }
 
-                ctx.close();
+    /// the script context of the realm the code is compiled in, 
+    /// the code of a realm runs in it whichever realm calls it.
+    fn script_context(&mut self) -> Value{
+        let context = &mut self.runtime.realm().context as *mut VmContext;
+        self.builder.ins().iconst(self.pointer_type, context as i64)
+    }
 
-                self.builder.ins().jump(exit_block, &[]);
+    /// the context holding a binding stored in a record
+    fn record_context(&mut self, place:Place) -> Value{
+        match place{
+            Place::Record(var, _) => self.builder.use_var(var),
+            Place::Closure(_) => self.function_context(),
+            _ => self.script_context()
+        }
+    }
 
-                self.builder.seal_block(exit_block);
-                self.builder.switch_to_block(exit_block);
+    /// the value of the binding at `place`, throws if read before its declaration
+    fn read_place(&mut self, place:Place, id:usize) -> Result<Value, Error>{
+        let b = match place{
+            Place::Slot(slot) => {
+                let v = self.use_slot(slot);
+                if !slot.initialized && slot.kind != VarDeclKind::Var{
+                    self.check_initialized(v, id)?;
+                }
+                return Ok(v)
             },
-            
-            Stmt::Throw(t) => {
+            Place::Named | Place::Free => {
+                let helper = if let Place::Named = place{self.resolve_var} else{self.resolve_free};
+                let vmctx = self.builder.use_var(self.vmctx);
+                let id = self.builder.ins().iconst(types::I64, id as i64);
+                return self.call_helper(helper, &[vmctx, id])
+            },
+            Place::Record(_, b) | Place::Closure(b) | Place::Script(b) => b
+        };
+        let helper = if let Place::Closure(_) = place{self.get_closure} else{self.get_slot};
+        let ctx = self.record_context(place);
+        let index = self.builder.ins().iconst(types::I64, b.index as i64);
+        let inst = self.builder.ins().call(helper, &[ctx, index]);
+        let v = self.builder.inst_results(inst)[0];
+        if b.kind != VarDeclKind::Var{
+            self.check_initialized(v, id)?;
+        }
+        Ok(v)
+    }
+
+    /// write the binding at `place` without checking it, used by declarations
+    fn write_place(&mut self, place:Place, val:Value){
+        let index = match place{
+            Place::Slot(slot) => {
+                debug_assert!(!slot.counter, "loop counter redeclared");
+                self.builder.def_var(slot.var, val);
+                return
+            },
+            Place::Record(_, b) | Place::Closure(b) | Place::Script(b) => b.index,
+            Place::Named | Place::Free => unreachable!("declarations are resolved when compiling")
+        };
+        let helper = if let Place::Closure(_) = place{self.set_closure} else{self.set_slot};
+        let ctx = self.record_context(place);
+        let index = self.builder.ins().iconst(types::I64, index as i64);
+        self.builder.ins().call(helper, &[ctx, index, val]);
+    }
+
+    /// the place of a binding declared by the scopes of the current function, innermost first
+    fn function_place(&self, id:usize) -> Option<Place>{
+        if let Some(s) = self.slots.get(&id){
+            return Some(Place::Slot(*s))
+        }
+        if let Some(b) = self.variables.get(&id){
+            return Some(if self.script{Place::Script(*b)} else{Place::Record(self.vmctx, *b)})
+        }
+        match &self.parent{
+            Some(p) if !self.is_func => p.function_place(id),
+            _ => None
+        }
+    }
+
+    /// find the slot of `id` in the scopes of the current function, innermost first
+    fn find_slot(&self, id:usize) -> Option<Slot>{
+        if let Some(s) = self.slots.get(&id){
+            return Some(*s)
+        }
+        match &self.parent{
+            Some(p) if !self.is_func => p.find_slot(id),
+            _ => None
+        }
+    }
+
+    /// create a slot for `id` in the current scope
+    fn new_slot(&mut self, id:usize, kind:VarDeclKind, dominates:bool) -> Variable{
+        let var = self.scope.new_variable();
+        self.builder.declare_var(var, JValue::TYPE);
+        self.slots.insert(id, Slot{
+            var,
+            kind,
+            initialized: kind == VarDeclKind::Var,
+            dominates,
+            counter: false,
+        });
+        var
+    }
+
+    /// create the slot of a loop counter holding the integer `start`
+    fn new_counter(&mut self, name:&str, start:i32){
+        let id = self.runtime.to_mut().new_variable_name(name);
+        let var = self.scope.new_variable();
+        self.builder.declare_var(var, types::I64);
+        self.slots.insert(id, Slot{
+            var,
+            kind: VarDeclKind::Let,
+            initialized: true,
+            dominates: true,
+            counter: true,
+        });
+        let start = self.builder.ins().iconst(types::I64, start as i64);
+        self.builder.def_var(var, start);
+    }
+
+    /// the value of a slot, a counter is converted to a number
+    fn use_slot(&mut self, slot:Slot) -> Value{
+        let v = self.builder.use_var(slot.var);
+        if slot.counter{
+            let f = self.builder.ins().fcvt_from_sint(types::F64, v);
+            return self.builder.ins().bitcast(JValue::TYPE, f)
+        }
+        v
+    }
+
+    /// the value of a `let` or `const` slot before its declaration runs
+    fn uninitialized(&mut self) -> Value{
+        self.builder.ins().iconst(JValue::TYPE, value::UNINITIALIZED as i64)
+    }
+
+    /// throw a ReferenceError if the slot value `v` of `id` is uninitialized
+    fn check_initialized(&mut self, v:Value, id:usize) -> Result<(), Error>{
+        let uninit = self.builder.ins().icmp_imm(IntCC::Equal, v, value::UNINITIALIZED as i64);
+
+        let throw_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.ins().brnz(uninit, throw_block, &[]);
+        self.builder.ins().jump(exit_block, &[]);
+
+        self.builder.seal_block(throw_block);
+        self.builder.switch_to_block(throw_block);
+
+        let vmctx = self.builder.use_var(self.vmctx);
+        let id = self.builder.ins().iconst(types::I64, id as i64);
+        let inst = self.builder.ins().call(self.uninitialized_error, &[vmctx, id]);
+        let err = self.builder.inst_results(inst)[0];
+        self.emit_throw(err)?;
 
-                let arg = self.translate_expr(&t.arg)?;
-                self.close();
+        self.builder.seal_block(exit_block);
+        self.builder.switch_to_block(exit_block);
+        Ok(())
+    }
 
-                if self.is_in_try{
-                    self.builder.ins().jump(*self.try_exits.borrow().last().unwrap(), &[arg]);
+    /// declare the bindings of a scope as it is entered.
+    ///
+    /// the slots of `let` and `const` stay uninitialized until their declaration runs,
+    /// the bindings in the context are declared undefined so that functions 
+    /// created before the declaration capture them.
+    fn declare_scope(&mut self, names:Vec<(String, VarDeclKind)>, dominates:bool) -> Result<(), Error>{
+        // a block declaring bindings in the context gets its own
+        if !self.is_func && names.iter().any(|(n, _)|!self.scope.is_slot(n)){
+            self.push_scope();
+        }
+        for (name, kind) in names{
+            let id = self.runtime.to_mut().new_variable_name(&name);
+            if self.scope.is_slot(&name){
+                let var = self.new_slot(id, kind, dominates);
+                let v = if kind == VarDeclKind::Var{
+                    self.const_value(JValue::Undefined)
                 } else{
-                    self.builder.ins().call(self.throw, &[arg]);
-                }
-                
-            },
+                    self.uninitialized()
+                };
+                self.builder.def_var(var, v);
+            } else if kind == VarDeclKind::Var && self.script{
+                // a `var` of the script keeps the value of a previous script
+                let index = self.allocate(id);
+                self.variables.insert(id, ContextBinding{index, kind});
+                let root = self.script_context();
+                let index = self.builder.ins().iconst(types::I64, index as i64);
+                let id = self.builder.ins().iconst(types::I64, id as i64);
+                self.builder.ins().call(self.hoist_script_slot, &[root, index, id]);
+            } else{
+                let v = if kind == VarDeclKind::Var{
+                    self.const_value(JValue::Undefined)
+                } else{
+                    self.uninitialized()
+                };
+                self.declare_named(&name, v, kind);
+            }
+        }
+        Ok(())
+    }
 
-            Stmt::Try(t) => {
-                let mut ctx = self.new_context();
+    /// create the function declarations of a scope before its statements run
+    fn hoist_functions<'s, I:IntoIterator<Item = &'s Stmt>>(&mut self, stmts:I, top_level:bool) -> Result<(), Error>{
+        for s in stmts{
+            if let Stmt::Decl(Decl::Fn(f)) = s{
+                let func = self.translate_func(&f.function, Some(&f.ident.sym))?;
+                let kind = if top_level{
+                    VarDeclKind::Var
+                } else{
+                    VarDeclKind::Let
+                };
+                self.translate_ident_decl(&f.ident, func, Some(kind))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// enter the scope of a block, its bindings are declared and its functions created
+    fn enter_block<'s, I:IntoIterator<Item = &'s Stmt> + Clone>(&mut self, stmts:I, dominates:bool) -> Result<(), Error>{
+        self.declare_scope(scope::block_names(stmts.clone()), dominates)?;
+        self.hoist_functions(stmts, false)
+    }
 
-                let catch_block = self.builder.create_block();
-                let final_block = self.builder.create_block();
+    fn declare_binding(&mut self, name:&str, val:Value, kind:VarDeclKind) -> Result<(), Error>{
+        let id = self.runtime.to_mut().new_variable_name(name);
+
+        // the binding was declared when the scope was entered, 
+        // functions created before the declaration keep it.
+        let place = match kind{
+            VarDeclKind::Var => self.function_place(id),
+            _ if self.slots.contains_key(&id) || self.variables.contains_key(&id) => self.function_place(id),
+            _ => None
+        };
+        if let Some(place) = place{
+            self.write_place(place, val);
+            if let Some(s) = self.slots.get_mut(&id){
+                s.initialized |= s.dominates;
+            }
+            return Ok(())
+        }
 
-                self.builder.append_block_param(catch_block, JValue::TYPE);
+        if self.scope.is_slot(name){
+            let var = self.new_slot(id, kind, true);
+            self.builder.def_var(var, val);
+            self.slots.get_mut(&id).unwrap().initialized = true;
+            return Ok(())
+        }
+        self.declare_named(name, val, kind);
+        Ok(())
+    }
 
-                // register the exit
-                ctx.is_in_try = true;
-                ctx.try_exits.as_ref().borrow_mut().push(catch_block);
+    fn assign_binding(&mut self, name:&str, val:Value) -> Result<(), Error>{
+        let id = self.runtime.to_mut().new_variable_name(name);
 
-                for i in &t.block.stmts{
-                    ctx.translate_stmt(i, None)?;
+        let place = self.place(id);
+        let kind = match place{
+            Place::Slot(slot) => {
+                if !slot.initialized && slot.kind != VarDeclKind::Var{
+                    let v = self.builder.use_var(slot.var);
+                    self.check_initialized(v, id)?;
+                }
+                slot.kind
+            },
+            Place::Record(_, b) | Place::Closure(b) | Place::Script(b) => {
+                if b.kind != VarDeclKind::Var{
+                    self.read_place(place, id)?;
                 }
+                b.kind
+            },
+            Place::Named | Place::Free => {
+                let helper = if let Place::Named = place{self.set_var} else{self.set_free};
+                let vmctx = self.builder.use_var(self.vmctx);
+                let id = self.builder.ins().iconst(types::I64, id as i64);
+                self.call_helper(helper, &[vmctx, id, val])?;
+                return Ok(())
+            }
+        };
+
+        // the value is evaluated before the assignment throws
+        if kind == VarDeclKind::Const{
+            let vmctx = self.builder.use_var(self.vmctx);
+            let inst = self.builder.ins().call(self.const_error, &[vmctx]);
+            let err = self.builder.inst_results(inst)[0];
+            return self.emit_throw(err)
+        }
+        debug_assert!(!matches!(place, Place::Slot(s) if s.counter), "loop counter assigned");
+        self.write_place(place, val);
+        Ok(())
+    }
 
-                // remove the exit
-                ctx.try_exits.as_ref().borrow_mut().pop();
-                ctx.close();
-                
-                // jump to the final block if fallthrough
-                self.builder.ins().jump(final_block, &[]);
+    /// resolve an identifier, from its slot if it has one
+    pub fn resolve_binding(&mut self, name:&str) -> Result<Value, Error>{
+        let id = self.runtime.to_mut().new_variable_name(name);
+        let place = self.place(id);
+        self.read_place(place, id)
+    }
 
-                // the catch block
-                self.builder.seal_block(catch_block);
-                self.builder.switch_to_block(catch_block);
+    /// `typeof` an identifier, undeclared names are "undefined"
+    pub fn typeof_binding(&mut self, name:&str) -> Result<Value, Error>{
+        let id = self.runtime.to_mut().new_variable_name(name);
 
-                
-                if let Some(clause) = &t.handler{
-                    ctx = self.new_context();
-                    
-                    if let Some(p) = &clause.param{
-                        let err = self.builder.block_params(catch_block)[0];
-                        self.translate_pat(p, err, Some(VarDeclKind::Var), AssignOp::Assign)?;
-                    }
+        let place = self.place(id);
+        if let Place::Named | Place::Free = place{
+            let helper = if let Place::Named = place{self.typeof_var} else{self.typeof_free};
+            let vmctx = self.builder.use_var(self.vmctx);
+            let id = self.builder.ins().iconst(types::I64, id as i64);
+            return self.call_helper(helper, &[vmctx, id])
+        }
+        let v = self.read_place(place, id)?;
+        let inst = self.builder.ins().call(self.typeOf, &[v]);
+        Ok(self.builder.inst_results(inst)[0])
+    }
 
-                    for i in &clause.body.stmts{
-                        ctx.translate_stmt(i, None)?;
-                    }
-                    ctx.close();
+    pub fn translate_stmt(&mut self, stmt:&Stmt, label:Option<String>) -> Result<(), Error>{
+        match stmt{
+            Stmt::Block(b) => {
+                let mut ctx = self.new_context();
+                ctx.enter_block(&b.stmts, true)?;
+                for i in &b.stmts{
+                    ctx.translate_stmt(i, None)?;
                 }
-                self.builder.ins().jump(final_block, &[]);
+                ctx.close();
+            },
 
+            Stmt::Break(b) => {
+                let label = b.label.as_ref().map(|l|l.sym.as_ref());
+                let (target, depth) = self.find_loop_exit(label, false, Position::new(b.span.lo.0))?;
+                self.emit_jump_out(target, depth)?;
+            },
 
-                // the final block
-                self.builder.seal_block(final_block);
-                self.builder.switch_to_block(final_block);
+            Stmt::Continue(c) => {
+                let label = c.label.as_ref().map(|l|l.sym.as_ref());
+                let (target, depth) = self.find_loop_exit(label, true, Position::new(c.span.lo.0))?;
+                self.emit_jump_out(target, depth)?;
+            },
 
-                if let Some(c) = &t.finalizer{
-                    ctx = self.new_context();
+            // there is no debugger to break into
+            Stmt::Debugger(_) => {},
 
-                    for i in &c.stmts{
-                        ctx.translate_stmt(i, None)?;
+            Stmt::Decl(d) => {
+                match d{
+                    Decl::Class(c) => {
+                        let class = self.translate_class(&c.class, Some(&c.ident.sym))?;
+                        self.translate_ident_decl(&c.ident, class, Some(VarDeclKind::Let))?;
+                    },
+                    // created when the scope is entered
+                    Decl::Fn(_) => {},
+                    Decl::TsEnum(e) => {
+                        todo!()
+                    },
+                    Decl::TsInterface(i) => {
+                        todo!()
+                    },
+                    Decl::TsModule(m) => {
+                        todo!()
+                    },
+                    Decl::TsTypeAlias(t) => {
+                        todo!()
+                    },
+                    Decl::Var(v) => {
+                        self.translate_var_decl(v)?;
                     }
-                    ctx.close();
                 }
             },
 
-            Stmt::While(w) => {
+            Stmt::DoWhile(d) => {
                 let entry_block = self.builder.create_block();
+                let test_block = self.builder.create_block();
                 let exit_block = self.builder.create_block();
 
+                // continue jumps to the test
                 self.loop_exits.as_ref().borrow_mut().push(LoopExit { 
                     label: label, 
                     exit_block, 
-                    continue_block: entry_block 
+                    continue_block: Some(test_block),
+                    labeled_only: false,
+                    unwind: self.unwind.borrow().len(),
                 });
 
                 self.builder.ins().jump(entry_block, &[]);
                 
                 self.builder.switch_to_block(entry_block);
-                
+
                 let mut ctx = self.new_context();
+                ctx.translate_stmt(d.body.as_ref(), None)?;
+                ctx.builder.ins().jump(test_block, &[]);
 
-                // break if false
-                let test_re = ctx.translate_expr(&w.test)?;
-                let b = ctx.to_bool(test_re);
-                self.builder.ins().brz(b, exit_block, &[]);
+                ctx.builder.seal_block(test_block);
+                ctx.builder.switch_to_block(test_block);
 
-                // body
-                ctx.translate_stmt(w.body.as_ref(), None)?;
+                let test_re = ctx.translate_operand(&d.test, true)?;
+                let b = ctx.to_bool(test_re);
                 
                 ctx.close();
                 self.loop_exits.as_ref().borrow_mut().pop();
 
-                // loop
+                self.builder.ins().brz(b, exit_block, &[]);
                 self.builder.ins().jump(entry_block, &[]);
 
                 self.builder.seal_block(entry_block);
@@ -720,268 +2011,522 @@ impl<'a> BuilderContext<'a>{
                 self.builder.switch_to_block(exit_block);
             },
 
-            Stmt::With(w) => {
-                return Err(Error::Deprecated("`with` statment is deprecated and not supported."))
-            }
-        }
-        Ok(())
-    }
-
+            Stmt::Empty(e) => {},
+            Stmt::Expr(e) => {
+                self.yield_position = true;
+                let v = self.translate_expr(&e.expr)?;
+                self.builder.def_var(self.completion, v);
+            },
+            Stmt::For(f) => {
+                let entry_block = self.builder.create_block();
+                let update_block = self.builder.create_block();
+                let exit_block = self.builder.create_block();
 
-    pub fn translate_expr(&mut self, expr:&Expr) -> Result<Value, Error>{
-        match expr{
-            Expr::Array(a) => {
-                let mut spread = false;
+                // the bindings of the init are scoped to the loop
+                let mut head = self.new_context();
+                if let Some(c) = scope::counter_loop(f).filter(|c|head.scope.is_slot(c.name)){
+                    head.new_counter(c.name, c.start);
+                } else if let Some(d) = &f.init{
+                    match d{
+                        VarDeclOrExpr::Expr(e) => {
+                            head.translate_operand(&e, true)?;
+                        },
+                        VarDeclOrExpr::VarDecl(d) => {
+                            if d.kind != VarDeclKind::Var{
+                                let names = scope::decl_names(d).into_iter().map(|n|(n, d.kind)).collect();
+                                head.declare_scope(names, true)?;
+                            }
+                            head.translate_var_decl(d)?;
+                        },
+                    }
+                }
 
-                let slot = self.builder.create_stack_slot(StackSlotData { 
-                    kind: StackSlotKind::ExplicitSlot, 
-                    size: (a.elems.len() * size_of::<JValue>()) as u32,
+                // continue runs the update before the test, 
+                // the scope of the loop is left once it exits.
+                self.loop_exits.as_ref().borrow_mut().push(LoopExit { 
+                    label: label, 
+                    exit_block, 
+                    continue_block: Some(update_block),
+                    labeled_only: false,
+                    unwind: self.unwind.borrow().len(),
                 });
 
-                let mut i = 0;
-                for e in &a.elems{
-                    if let Some(e) = e{
-                        if e.spread.is_some(){
-                            spread = true;
-                        }
-
-                        let v = self.translate_expr(&e.expr)?;
-                        self.builder.ins().stack_store(v, slot, (i*size_of::<JValue>()) as i32); 
-                    } else{
-                        let v = self.builder.ins().iconst(types::I128, 0);
-                        self.builder.ins().stack_store(v, slot, (i*size_of::<JValue>()) as i32);
-                    };
-                    i += 1;
-                }
-                
-                let addr = self.builder.ins().stack_addr(types::I64, slot, 0);
-                let len = self.builder.ins().iconst(types::I64, a.elems.len() as i64);
-                let spread = self.builder.ins().bconst(types::B8, spread);
+                head.builder.ins().jump(entry_block, &[]);
                 
-                let inner = self.builder.ins().call(self.array_new, &[addr, len, spread]);
-                let inner = self.builder.inst_results(inner)[0];
+                head.builder.switch_to_block(entry_block);
 
-                let object = self.builder.ins().call(self.object_from_inner, &[inner]);
-                let object = self.builder.inst_results(object)[0];
-                Ok(object)
-            },
+                let mut ctx = head.new_context();
 
-            Expr::Arrow(a) => {
-                let (mut builder, ctx) = self.new_function();
+                // break if false
+                if let Some(e) = &f.test{
+                    let v = ctx.translate_operand(&e, true)?;
+                    let b = ctx.to_bool(v);
+                    ctx.guard(b, exit_block);
+                }
 
-                let argv = builder.builder.block_params(builder.entry_block)[1];
-                let argc = builder.builder.block_params(builder.entry_block)[2];
-                let mut i = 0;
-                for p in &a.params{
-                    let c = builder.builder.ins().iconst(types::I64, i);
-                    let v = builder.builder.ins().call(builder.resolve_argument, &[argv, argc, c]);
-                    let v = builder.builder.inst_results(v)[0];
-                    builder.translate_pat(p, v, Some(VarDeclKind::Var), AssignOp::Assign)?;
-                };
-                match &a.body{
-                    BlockStmtOrExpr::BlockStmt(b) => {
-                        for stmt in &b.stmts{
-                            builder.translate_stmt(stmt, None)?;
+                ctx.translate_stmt(f.body.as_ref(), None)?;
+                ctx.builder.ins().jump(update_block, &[]);
+
+                ctx.builder.seal_block(update_block);
+                ctx.builder.switch_to_block(update_block);
+
+                // every iteration has its own `let` bindings, 
+                // the functions created by the previous iteration keep theirs.
+                if let Some(VarDeclOrExpr::VarDecl(d)) = &f.init{
+                    if d.kind == VarDeclKind::Let{
+                        for name in scope::decl_names(d){
+                            if !ctx.scope.is_slot(&name){
+                                ctx.renew_binding(&name)?;
+                            }
                         }
-                    },
-                    BlockStmtOrExpr::Expr(e) => {
-                        let v = builder.translate_expr(&e)?;
-                        builder.close();
-                        builder.builder.ins().return_(&[v]);
                     }
                 }
 
-                builder.close();
-                let un = builder.const_value(JValue::Undefined);
-                builder.builder.ins().return_(&[un]);
-                builder.builder.seal_all_blocks();
+                if let Some(e) = &f.update{
+                    ctx.translate_operand(&e, true)?;
+                }
 
-                let re = ctx.compile(self.jit_module.isa());
-                let info = match re{
-                    Ok(v) => v,
-                    Err(e) => return Err(Error::CodegenError(Arc::new(e)))
-                };
+                ctx.close();
+                self.loop_exits.as_ref().borrow_mut().pop();
 
-                let mem = unsafe{std::alloc::alloc(Layout::array::<u8>(info.total_size as usize).unwrap())};
-                unsafe{ctx.emit_to_memory(mem)};
-                builder.builder.finalize();
-                ctx.clear();
+                self.builder.ins().jump(entry_block, &[]);
 
+                self.builder.seal_block(entry_block);
+                self.builder.seal_block(exit_block);
 
-                self.runtime.to_mut().new_compiled_fn(mem, info.total_size as usize);
+                self.builder.switch_to_block(exit_block);
+                head.close();
+            },
+            Stmt::ForIn(f) => {
+                let right = self.translate_operand(&f.right, true)?;
+                let ins = self.builder.ins().call(self.for_in_iterator, &[right]);
+                let iterator = self.builder.inst_results(ins)[0];
+                self.translate_iteration(&f.left, iterator, &f.body, label, false)?;
+            },
+            Stmt::ForOf(f) => {
+                if f.await_token.is_some(){
+                    return Err(Error::Unimplemented("for await of statement."))
+                }
+                let right = self.translate_operand(&f.right, true)?;
+                let ins = self.builder.ins().call(self.get_iterator, &[right]);
+                let (iterator, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+                self.check_call_result(iterator, ok)?;
+                self.translate_iteration(&f.left, iterator, &f.body, label, true)?;
+            },
+            Stmt::If(i) => {
+                let entry_block = self.builder.create_block();
+                let exit_block = self.builder.create_block();
 
-                let vmctx = self.builder.use_var(self.vmctx);
-                let addr = self.builder.ins().iconst(self.pointer_type, mem as i64);
-                let is_async = self.builder.ins().bconst(types::B8, a.is_async);
-                let is_generator = self.builder.ins().bconst(types::B8, a.is_generator);
+                let test_re = self.translate_operand(&i.test, true)?;
+                let b = self.to_bool(test_re);
 
-                let inst = self.builder.ins().call(self.function_new, &[vmctx, addr, is_async, is_generator]);
-                Ok(self.builder.inst_results(inst)[0])
-            },
+                if let Some(alt) = &i.alt{
+                    // the alternative (else) runs in a block of its own
+                    let alt_block = self.builder.create_block();
+                    self.builder.ins().brnz(b, entry_block, &[]);
+                    self.builder.ins().jump(alt_block, &[]);
+                    self.builder.seal_block(alt_block);
+                    self.builder.switch_to_block(alt_block);
 
-            Expr::Assign(a) => {
-                let value = self.translate_expr(&a.right)?;
+                    let mut ctx = self.new_context();
+                    ctx.translate_stmt(&alt, None)?;
+                    ctx.close();
+                } else{
+                    self.builder.ins().brnz(b, entry_block, &[]);
+                }
+                // fall through
+                self.builder.ins().jump(exit_block, &[]);
 
-                match &a.left{
-                    PatOrExpr::Expr(e) => {
-                        match e.as_ref(){
-                            Expr::Member(m) => {
+                self.builder.seal_block(entry_block);
+                self.builder.switch_to_block(entry_block);
 
-                                let prop = self.translate_prop(&m.prop, m.computed)?;
-                                let obj = match &m.obj{
-                                    ExprOrSuper::Expr(e) => self.translate_expr(&e)?,
-                                    ExprOrSuper::Super(s) => todo!()
-                                };
+                let mut ctx = self.new_context();
+                ctx.translate_stmt(&i.cons, None)?;
+                ctx.close();
 
-                                match a.op{
-                                    AssignOp::Assign => {
-                                        self.builder.ins().call(self.set_memebr, &[obj, prop, value]);
+                self.builder.ins().jump(exit_block, &[]);
 
-                                    },
-                                    op => {
-                                        let op = self.builder.ins().iconst(types::I8, op as i8 as i64);
-                                        let ins = self.builder.ins().call(self.assign_member, &[obj, prop, value, op]);
-                                        return Ok(self.builder.inst_results(ins)[0])
-                                    }
-                                }
+                self.builder.seal_block(exit_block);
+                self.builder.switch_to_block(exit_block);
+            },
 
-                            },
-                            _ => return Err(Error::Unimplemented("unimplemented assign expr."))
-                        }
+            Stmt::Labeled(l) => {
+                let name = l.label.sym.to_string();
+                if self.loop_exits.borrow().iter().any(|e|e.label.as_ref() == Some(&name)){
+                    return Err(Error::SyntaxError(format!("Label '{}' has already been declared", name)))
+                }
+
+                match l.body.as_ref(){
+                    // loops and switch statements take the label
+                    Stmt::For(_) | Stmt::ForIn(_) | Stmt::ForOf(_) |
+                    Stmt::While(_) | Stmt::DoWhile(_) | Stmt::Switch(_) => {
+                        return self.translate_stmt(&l.body, Some(name));
                     },
-                    PatOrExpr::Pat(p) => {
-                        self.translate_pat(&p, value, None, a.op);
+                    _ => {}
+                };
+
+                // a labeled block is exited by `break label`
+                let exit_block = self.builder.create_block();
+                self.loop_exits.borrow_mut().push(LoopExit { 
+                    label: Some(name), 
+                    exit_block, 
+                    continue_block: None,
+                    labeled_only: true,
+                    unwind: self.unwind.borrow().len(),
+                });
+
+                self.translate_stmt(&l.body, None)?;
+                self.loop_exits.borrow_mut().pop();
+
+                self.builder.ins().jump(exit_block, &[]);
+                self.builder.seal_block(exit_block);
+                self.builder.switch_to_block(exit_block);
+            },
+
+            Stmt::Return(r) => {
+                
+                let re = if let Some(e) = &r.arg{
+                    self.yield_position = true;
+                    self.translate_expr(&e)?
+                } else{
+                    self.const_value(JValue::Undefined)
+                };
+                self.emit_return(re)?;
+            },
+
+            Stmt::Switch(s) => {
+                let discrim = self.translate_operand(&s.discriminant, true)?;
+                let held = self.hold(discrim);
+
+                let exit_block = self.builder.create_block();
+                let case_blocks = s.cases.iter().map(|_|self.builder.create_block()).collect::<Vec<_>>();
+
+                // the cases share one scope, a case may be entered past the declarations of another
+                let mut ctx = self.new_context();
+                ctx.enter_block(s.cases.iter().flat_map(|c|c.cons.iter()), false)?;
+
+                // the cases are tested in source order, 
+                // the default case is selected only if none matches wherever it is placed
+                let mut default = None;
+                for (i, cas) in s.cases.iter().enumerate(){
+                    match &cas.test{
+                        Some(d) => {
+                            let val = ctx.translate_operand(&d, true)?;
+                            let discrim = ctx.builder.use_var(held);
+                            let b = ctx.eqeqeq(discrim, val);
+                            let b = ctx.to_bool(b);
+
+                            let next = ctx.builder.create_block();
+                            ctx.builder.ins().brnz(b, case_blocks[i], &[]);
+                            ctx.builder.ins().jump(next, &[]);
+
+                            ctx.builder.seal_block(next);
+                            ctx.builder.switch_to_block(next);
+                        },
+                        None => default = Some(case_blocks[i])
                     }
                 }
-                Ok(value)
+                ctx.release(held);
+                ctx.builder.ins().jump(default.unwrap_or(exit_block), &[]);
+
+                ctx.loop_exits.borrow_mut().push(LoopExit { 
+                    label, 
+                    exit_block, 
+                    continue_block: None,
+                    labeled_only: false,
+                    unwind: ctx.unwind.borrow().len(),
+                });
+
+                // the case bodies fall through to the next case
+                for (i, cas) in s.cases.iter().enumerate(){
+                    ctx.builder.seal_block(case_blocks[i]);
+                    ctx.builder.switch_to_block(case_blocks[i]);
+
+                    for s in &cas.cons{
+                        ctx.translate_stmt(s, None)?;
+                    }
+                    let next = case_blocks.get(i + 1).copied().unwrap_or(exit_block);
+                    ctx.builder.ins().jump(next, &[]);
+                }
+
+                self.loop_exits.borrow_mut().pop();
+
+                // break exits to the scope of the cases
+                self.builder.seal_block(exit_block);
+                self.builder.switch_to_block(exit_block);
+                ctx.close();
             },
+            
+            Stmt::Throw(t) => {
 
-            Expr::Await(a) => {
-                todo!()
+                let arg = self.translate_operand(&t.arg, true)?;
+                self.emit_throw(arg)?;
             },
 
-            Expr::Bin(b) => {
-                let right = self.translate_expr(&b.right)?;
-                let left = self.translate_expr(&b.left)?;
-
-                let ins = match b.op{
-                    BinaryOp::Add => self.builder.ins().call(self.add, &[left, right]),
-                    BinaryOp::BitAnd => self.builder.ins().call(self.bitAnd, &[left, right]),
-                    BinaryOp::BitOr => self.builder.ins().call(self.bitOr, &[left, right]),
-                    BinaryOp::BitXor => self.builder.ins().call(self.bitXor, &[left, right]),
-                    BinaryOp::Div => self.builder.ins().call(self.div, &[left, right]),
-                    BinaryOp::EqEq => self.builder.ins().call(self.eqeq, &[left, right]),
-                    BinaryOp::EqEqEq => self.builder.ins().call(self.eqeqeq, &[left, right]),
-                    BinaryOp::Exp => self.builder.ins().call(self.exp, &[left, right]),
-                    BinaryOp::Gt => self.builder.ins().call(self.gt, &[left, right]),
-                    BinaryOp::GtEq => self.builder.ins().call(self.gteq, &[left, right]),
-                    BinaryOp::In => self.builder.ins().call(self.In, &[left, right]),
-                    BinaryOp::InstanceOf => self.builder.ins().call(self.instanceof, &[left, right]),
-                    BinaryOp::LShift => self.builder.ins().call(self.lshift, &[left, right]),
-                    BinaryOp::LogicalAnd => self.builder.ins().call(self.and, &[left, right]),
-                    BinaryOp::LogicalOr => self.builder.ins().call(self.or, &[left, right]),
-                    BinaryOp::Lt => self.builder.ins().call(self.lt, &[left, right]),
-                    BinaryOp::LtEq => self.builder.ins().call(self.lteq, &[left, right]),
-                    BinaryOp::Mod => self.builder.ins().call(self.Mod, &[left, right]),
-                    BinaryOp::Mul => self.builder.ins().call(self.mul, &[left, right]),
-                    BinaryOp::NotEq => self.builder.ins().call(self.noteq, &[left, right]),
-                    BinaryOp::NotEqEq => self.builder.ins().call(self.noteqeq, &[left, right]),
-                    BinaryOp::NullishCoalescing => self.builder.ins().call(self.nullishCoalscing, &[left, right]),
-                    BinaryOp::RShift => self.builder.ins().call(self.rshift, &[left, right]),
-                    BinaryOp::Sub => self.builder.ins().call(self.sub, &[left, right]),
-                    BinaryOp::ZeroFillRShift => self.builder.ins().call(self.unsignedRShift, &[left, right]),
+            Stmt::Try(t) => {
+                let exit_block = self.builder.create_block();
+
+                // the finalizer runs on every exit of the try and catch blocks
+                let finalizer = t.finalizer.as_ref().map(|_|{
+                    let block = self.builder.create_block();
+                    self.builder.append_block_param(block, types::I64);
+                    self.builder.append_block_param(block, JValue::TYPE);
+                    let f = Arc::new(RefCell::new(Finalizer{block, jumps:Vec::new()}));
+                    self.unwind.borrow_mut().push(Unwind::Finally(f.clone()));
+                    f
+                });
+                let normal_exit = finalizer.as_ref().map_or(exit_block, |f|f.borrow().block);
+
+                let catch_block = if t.handler.is_some(){
+                    let catch_block = self.builder.create_block();
+                    self.builder.append_block_param(catch_block, JValue::TYPE);
+                    self.unwind.borrow_mut().push(Unwind::Catch(catch_block));
+                    Some(catch_block)
+                } else{
+                    None
                 };
-                Ok(self.builder.inst_results(ins)[0])
+
+                let mut ctx = self.new_context();
+                ctx.enter_block(&t.block.stmts, true)?;
+                for i in &t.block.stmts{
+                    ctx.translate_stmt(i, None)?;
+                }
+                ctx.close();
+                self.jump_completed(normal_exit, finalizer.is_some());
+
+                if let (Some(clause), Some(catch_block)) = (&t.handler, catch_block){
+                    // a throw inside the catch block is not caught by it
+                    self.unwind.borrow_mut().pop();
+
+                    self.builder.seal_block(catch_block);
+                    self.builder.switch_to_block(catch_block);
+
+                    let mut ctx = self.new_context();
+                    if let Some(p) = &clause.param{
+                        ctx.declare_scope(scope::catch_names(p), true)?;
+                        let err = ctx.builder.block_params(catch_block)[0];
+                        ctx.translate_pat(p, err, Some(VarDeclKind::Let), AssignOp::Assign)?;
+                    }
+                    ctx.enter_block(&clause.body.stmts, true)?;
+
+                    for i in &clause.body.stmts{
+                        ctx.translate_stmt(i, None)?;
+                    }
+                    ctx.close();
+                    self.jump_completed(normal_exit, finalizer.is_some());
+                }
+
+                if let (Some(finalizer), Some(block)) = (finalizer, &t.finalizer){
+                    self.unwind.borrow_mut().pop();
+                    let finalizer = finalizer.borrow();
+                    self.translate_finalizer(&finalizer, block, exit_block)?;
+                }
+
+                self.builder.seal_block(exit_block);
+                self.builder.switch_to_block(exit_block);
             },
 
-            Expr::Call(c) => {
+            Stmt::While(w) => {
+                let entry_block = self.builder.create_block();
+                let exit_block = self.builder.create_block();
 
-                let (argv, argc, spread) = self.translate_args(&c.args)?;
+                self.loop_exits.as_ref().borrow_mut().push(LoopExit { 
+                    label: label, 
+                    exit_block, 
+                    continue_block: Some(entry_block),
+                    labeled_only: false,
+                    unwind: self.unwind.borrow().len(),
+                });
 
-                match &c.callee{
-                    ExprOrSuper::Expr(e) => {
+                self.builder.ins().jump(entry_block, &[]);
+                
+                self.builder.switch_to_block(entry_block);
+                
+                let mut ctx = self.new_context();
 
-                        // determine the call
-                        let (re, ok) = match e.as_ref(){
+                // break if false
+                let test_re = ctx.translate_operand(&w.test, true)?;
+                let b = ctx.to_bool(test_re);
+                ctx.guard(b, exit_block);
 
-                            // this is a member call
-                            Expr::Member(m) => {
-                                match &m.obj{
-                                    ExprOrSuper::Expr(e) => {
-                                        let callee = self.translate_expr(&e)?;
-                                        let prop = self.translate_expr(&m.prop)?;
-                                        let vmctx = self.builder.use_var(self.vmctx);
-                                        let ins = self.builder.ins().call(self.memberCall, &[callee, prop, vmctx, argv, argc, spread]);
-                                        let v = self.builder.inst_results(ins);
-                                        (v[0], v[1])
-                                    },
-
-                                    ExprOrSuper::Super(s) => {
-                                        let prop = self.translate_expr(&m.prop)?;
-                                        let this = self.builder.use_var(self.this);
-                                        let vmctx = self.builder.use_var(self.vmctx);
-
-                                        let ins = self.builder.ins().call(self.superMemberCall, &[
-                                            this, 
-                                            prop,
-                                            vmctx, 
-                                            argv, argc, spread
-                                        ]);
-                                        let v = self.builder.inst_results(ins);
-                                        (v[0], v[1])
-                                    }
+                // body
+                ctx.translate_stmt(w.body.as_ref(), None)?;
+                
+                ctx.close();
+                self.loop_exits.as_ref().borrow_mut().pop();
 
-                                }
-                            },
+                // loop
+                self.builder.ins().jump(entry_block, &[]);
 
-                            // this is a function call
-                            callee => {
-                                let callee = self.translate_expr(callee)?;
-                                let vmctx = self.builder.use_var(self.vmctx);
-                                let this = self.builder.use_var(self.this);
-                                let ins = self.builder.ins().call(self.call, &[
-                                    callee, 
-                                    vmctx, 
-                                    this, 
-                                    argv, argc, spread
-                                ]);
-                                let v = self.builder.inst_results(ins);
-                                (v[0], v[1])
-                            }
-                        };
+                self.builder.seal_block(entry_block);
+                self.builder.seal_block(exit_block);
+
+                self.builder.switch_to_block(exit_block);
+            },
 
-                        if self.is_in_try{
+            Stmt::With(w) => {
+                return Err(Error::Deprecated("`with` statment is deprecated and not supported."))
+            }
+        }
+        Ok(())
+    }
 
-                            self.builder.ins().brz(ok, *self.try_exits.borrow().last().unwrap(), &[re]);
 
-                        } else{
-                            let throw_block = self.builder.create_block();
-                            let exit_block = self.builder.create_block();
+    pub fn translate_expr(&mut self, expr:&Expr) -> Result<Value, Error>{
+        let yield_position = std::mem::replace(&mut self.yield_position, false);
+        let opt_chain = self.opt_chain.take();
 
-                            self.builder.append_block_param(throw_block, JValue::TYPE);
+        match expr{
+            Expr::Array(a) => {
+                if a.elems.iter().any(|e|matches!(e, Some(e) if e.spread.is_some())){
+                    let elems = a.elems.iter().map(|e|e.as_ref()).collect::<Vec<_>>();
+                    return self.translate_spread_array(&elems, yield_position)
+                }
+
+                let mut held = Vec::new();
+                for e in &a.elems{
+                    let v = if let Some(e) = e{
+                        self.translate_operand(&e.expr, yield_position)?
+                    } else{
+                        self.const_value(JValue::Undefined)
+                    };
+                    held.push(self.hold(v));
+                }
 
-                            self.builder.ins().brz(ok, throw_block, &[re]);
-                            self.builder.ins().jump(exit_block, &[]);
+                let slot = self.builder.create_stack_slot(StackSlotData { 
+                    kind: StackSlotKind::ExplicitSlot, 
+                    size: (a.elems.len() * size_of::<JValue>()) as u32,
+                });
+                for (i, var) in held.into_iter().enumerate().rev(){
+                    let v = self.release(var);
+                    self.builder.ins().stack_store(v, slot, (i*size_of::<JValue>()) as i32);
+                }
+                
+                let addr = self.builder.ins().stack_addr(types::I64, slot, 0);
+                let len = self.builder.ins().iconst(types::I64, a.elems.len() as i64);
+                let spread = self.builder.ins().iconst(types::I8, 0);
+                
+                self.call_helper(self.array_new, &[addr, len, spread])
+            },
 
-                            self.builder.seal_block(throw_block);
-                            self.builder.switch_to_block(throw_block);
+            Expr::Arrow(a) => {
+                let params = a.params.iter().collect::<Vec<&Pat>>();
+                match &a.body{
+                    BlockStmtOrExpr::BlockStmt(b) => {
+                        self.translate_function_body(&params, &b.stmts, None, a.is_async, a.is_generator, true, None)
+                    },
+                    BlockStmtOrExpr::Expr(e) => {
+                        self.translate_function_body(&params, &[], Some(&e), a.is_async, a.is_generator, true, None)
+                    }
+                }
+            },
 
-                            let throw_value = self.builder.block_params(throw_block)[0];
+            Expr::Assign(a) => {
+                if a.op != AssignOp::Assign{
+                    return self.translate_compound_assign(a, yield_position)
+                }
 
-                            self.builder.ins().call(self.throw, &[throw_value]);
-                            self.builder.ins().jump(exit_block, &[]);
+                // the target is evaluated after the value
+                let mut value = self.translate_operand(&a.right, yield_position)?;
 
-                            self.builder.seal_block(exit_block);
-                            self.builder.switch_to_block(exit_block);
+                match &a.left{
+                    PatOrExpr::Expr(e) => {
+                        match e.as_ref(){
+                            Expr::Ident(i) => {
+                                self.translate_ident_decl(i, value, None)?;
+                            },
+                            Expr::Member(m) => {
+                                let held_value = self.hold(value);
+                                self.yield_position = yield_position && m.computed;
+                                let prop = self.translate_prop(&m.prop, m.computed)?;
+                                let held_prop = self.hold(prop);
+                                let obj = match &m.obj{
+                                    ExprOrSuper::Expr(e) => self.translate_operand(&e, yield_position)?,
+                                    // `super.x = v` assigns to `this`
+                                    ExprOrSuper::Super(s) => self.builder.use_var(self.this)
+                                };
+                                let prop = self.release(held_prop);
+                                value = self.release(held_value);
 
-                            
-                        };
-                        Ok(re)
+                                if is_private(&m.prop){
+                                    let op = self.builder.ins().iconst(types::I8, a.op as i8 as i64);
+                                    return self.call_helper(self.private_set, &[obj, prop, value, op])
+                                }
+                                if let Some(name) = cached_name(m){
+                                    self.cached_set_member(obj, name, value)?;
+                                } else{
+                                    self.call_helper(self.set_memebr, &[obj, prop, value])?;
+                                }
+                            },
+                            _ => return Err(Error::Unimplemented("unimplemented assign expr."))
+                        }
                     },
+                    PatOrExpr::Pat(p) => {
+                        self.translate_pat(&p, value, None, a.op)?;
+                    }
+                }
+                Ok(value)
+            },
 
-                    _ => todo!("todo: super call")
+            Expr::Await(a) => {
+                if !self.is_async{
+                    return Err(Error::SyntaxError("await is only valid in async functions".to_string()))
                 }
-                
+                if !yield_position{
+                    return Err(Error::Unimplemented("await inside an operand whose preceding operands are not held."))
+                }
+
+                self.yield_position = true;
+                let value = self.translate_expr(&a.arg)?;
+
+                // the async function is resumed with the settled value
+                let (value, mode) = self.emit_yield(value)?;
+                let ok = self.builder.ins().icmp_imm(IntCC::NotEqual, mode, ResumeMode::Throw as i64);
+                self.check_call_result(value, ok)?;
+                Ok(value)
+            },
+
+            Expr::Bin(b) => {
+                // `#x in obj`
+                if let (BinaryOp::In, Expr::PrivateName(p)) = (b.op, b.left.as_ref()){
+                    let key = self.resolve_private(&p.id.sym)?;
+                    let held = self.hold(key);
+                    let obj = self.translate_operand(&b.right, yield_position)?;
+                    let key = self.release(held);
+                    return self.call_helper(self.private_in, &[obj, key])
+                }
+
+                if let Some(v) = self.translate_counter_binary(b, yield_position)?{
+                    return Ok(v)
+                }
+
+                let left = self.translate_operand(&b.left, yield_position)?;
+
+                // the right operand is only evaluated if the left does not short circuit
+                match b.op{
+                    BinaryOp::LogicalAnd => {
+                        let test = self.to_bool(left);
+                        return self.translate_select(test, |ctx|ctx.translate_operand(&b.right, yield_position), |_|Ok(left))
+                    },
+                    BinaryOp::LogicalOr => {
+                        let test = self.to_bool(left);
+                        return self.translate_select(test, |_|Ok(left), |ctx|ctx.translate_operand(&b.right, yield_position))
+                    },
+                    BinaryOp::NullishCoalescing => {
+                        let test = self.is_nullish(left);
+                        return self.translate_select(test, |ctx|ctx.translate_operand(&b.right, yield_position), |_|Ok(left))
+                    },
+                    _ => {}
+                };
+
+                let held = self.hold(left);
+                let right = self.translate_operand(&b.right, yield_position)?;
+                let left = self.release(held);
+                self.translate_binary(b.op, left, right)
+            },
+
+            Expr::Call(c) => {
+                self.translate_chain(expr, opt_chain, |ctx|ctx.translate_call(c, false, yield_position))
             },
 
             Expr::Class(c) => {
@@ -994,34 +2539,36 @@ impl<'a> BuilderContext<'a>{
             },
 
             Expr::Cond(c) => {
-                let test = self.translate_expr(&c.test)?;
-                let cons = self.translate_expr(&c.cons)?;
-                let alt = self.translate_expr(&c.alt)?;
-
+                let test = self.translate_operand(&c.test, yield_position)?;
                 let test = self.to_bool(test);
-                let v = self.builder.ins().select(test, cons, alt);
-                return Ok(v)
+
+                self.translate_select(test, 
+                    |ctx|ctx.translate_operand(&c.cons, yield_position), 
+                    |ctx|ctx.translate_operand(&c.alt, yield_position)
+                )
             },
 
             Expr::Fn(f) => {
-                let name = if let Some(i) = &f.ident{
-                    Some(i.sym.as_ref())
-                } else{
-                    None
+                let name = match &f.ident{
+                    Some(i) => i.sym.as_ref(),
+                    None => return self.translate_func(&f.function, None)
                 };
-                self.translate_func(&f.function, name)
-            },
-
-            Expr::Ident(i) => {
-                let id = self.runtime.to_mut().variable_names.get_or_intern(i.sym.as_ref());
-                if self.has_variable(id.to_usize()){
 
+                // the name is bound in a scope of its own, visible from the body
+                let mut ctx = self.new_context();
+                ctx.push_scope();
+                let undefined = ctx.const_value(JValue::Undefined);
+                ctx.declare_named(name, undefined, VarDeclKind::Const);
+                let re = ctx.translate_func(&f.function, Some(name));
+                if let Ok(func) = re{
+                    ctx.declare_binding(name, func, VarDeclKind::Const)?;
                 }
+                ctx.close();
+                re
+            },
 
-                let vmctx = self.builder.use_var(self.vmctx);
-                let id = self.builder.ins().iconst(types::I64, id.to_usize() as i64);
-                let inst = self.builder.ins().call(self.resolve_var, &[id]);
-                Ok(self.builder.inst_results(inst)[0])
+            Expr::Ident(i) => {
+                self.resolve_binding(&i.sym)
             },
 
             Expr::Invalid(i) => {
@@ -1031,8 +2578,7 @@ impl<'a> BuilderContext<'a>{
             Expr::Lit(l) => {
                 match l{
                     Lit::BigInt(b) => {
-
-                        Ok(self.const_value(JValue::BigInt(b.value.to_i64().unwrap())))
+                        Ok(self.const_value(JValue::BigInt(bigint::from_literal(b))))
                     },
                     Lit::Bool(b) => {
                         Ok(self.const_value(JValue::Boolean(b.value)))
@@ -1054,40 +2600,29 @@ impl<'a> BuilderContext<'a>{
             },
             
             Expr::Member(m) => {
-                let prop = self.translate_prop(&m.prop, m.computed)?;
-
-                match &m.obj{
-                    ExprOrSuper::Expr(e) => {
-                        let obj = self.translate_expr(&e)?;
-                        let inst = self.builder.ins().call(self.member, &[obj, prop]);
-                        Ok(self.builder.inst_results(inst)[0])
-                    },
-                    ExprOrSuper::Super(s) => {
-                        let obj = self.builder.use_var(self.this);
-                        let inst = self.builder.ins().call(self.superMember, &[obj, prop]);
-                        Ok(self.builder.inst_results(inst)[0])
-                    }
-                }
-            },
+                self.translate_chain(expr, opt_chain, |ctx|ctx.translate_member(m, false, yield_position))
+            },
 
             Expr::MetaProp(m) => {
                 todo!()
             },
 
             Expr::New(n) => {
+                // the constructor is evaluated before the arguments
+                let callee = self.translate_operand(&n.callee, yield_position)?;
+                let held = self.hold(callee);
                 let (argv, argc, spread) = if let Some(a) = &n.args{
-                    self.translate_args(&a)?
+                    self.translate_args(&a, yield_position)?
                 } else{
                     (
                         self.builder.ins().iconst(types::I64, 0),
                         self.builder.ins().iconst(types::I64, 0),
-                        self.builder.ins().bconst(types::B8, false),
+                        self.builder.ins().iconst(types::I8, 0),
                     )
                 };
-
-                let callee = self.translate_expr(&n.callee)?;
+                let callee = self.release(held);
                 
-                let vmctx = self.builder.use_var(self.vmctx);
+                let vmctx = self.function_context();
                 let inst = self.builder.ins().call(self.construct, &[
                     callee, 
                     vmctx, 
@@ -1096,163 +2631,1296 @@ impl<'a> BuilderContext<'a>{
                 let v = self.builder.inst_results(inst);
                 let (re, ok) = (v[0], v[1]);
                 
-                if self.is_in_try{
-
-                    self.builder.ins().brz(ok, *self.try_exits.borrow().last().unwrap(), &[re]);
-
-                } else{
-                    let throw_block = self.builder.create_block();
-                    let exit_block = self.builder.create_block();
-
-                    self.builder.append_block_param(throw_block, JValue::TYPE);
-
-                    self.builder.ins().brz(ok, throw_block, &[re]);
-                    self.builder.ins().jump(exit_block, &[]);
-
-                    self.builder.seal_block(throw_block);
-                    self.builder.switch_to_block(throw_block);
-
-                    let throw_value = self.builder.block_params(throw_block)[0];
-
-                    self.builder.ins().call(self.throw, &[throw_value]);
-                    self.builder.ins().jump(exit_block, &[]);
-
-                    self.builder.seal_block(exit_block);
-                    self.builder.switch_to_block(exit_block);
-
-                    
-                };
+                self.check_call_result(re, ok)?;
                 Ok(re)
             },
 
             Expr::Object(o) => {
                 let obj = self.builder.ins().call(self.new_object, &[]);
                 let obj = self.builder.inst_results(obj)[0];
+                let held = self.hold(obj);
                 for p in &o.props{
                     match p{
                         PropOrSpread::Spread(s) => {
-                            let spread = self.translate_expr(&s.expr)?;
-                            self.builder.ins().call(self.set_member_spread, &[obj, spread]);
+                            let spread = self.translate_operand(&s.expr, yield_position)?;
+                            let obj = self.builder.use_var(held);
+                            self.call_helper(self.set_member_spread, &[obj, spread])?;
                         },
                         PropOrSpread::Prop(p) => {
                             match p.as_ref(){
                                 Prop::Shorthand(i) => {
-                                    let id = self.runtime.to_mut().variable_names.get_or_intern(i.sym.as_ref());
-                                    if self.has_variable(id.to_usize()){
+                                    let v = self.resolve_binding(&i.sym)?;
+
+                                    let prop = self.const_value(i.sym.as_ref().into());
+
+                                    let obj = self.builder.use_var(held);
+                                    self.call_helper(self.define_field, &[obj, prop, v])?;
+                                },
+                                Prop::KeyValue(k) => {
+                                    // the key is evaluated before the value
+                                    let prop = self.translate_key_operand(&k.key, yield_position)?;
+                                    let held_prop = self.hold(prop);
+                                    let v = self.translate_operand(&k.value, yield_position)?;
+                                    let prop = self.release(held_prop);
+                                    let obj = self.builder.use_var(held);
+                                    self.call_helper(self.define_field, &[obj, prop, v])?;
+                                },
+                                Prop::Method(m) => {
+                                    let prop = self.translate_key_operand(&m.key, yield_position)?;
+                                    let func = self.translate_func(&m.function, prop_name_str(&m.key))?;
+                                    let kind = self.builder.ins().iconst(types::I8, 0);
+                                    let obj = self.builder.use_var(held);
+                                    self.call_helper(self.define_method, &[obj, prop, func, kind])?;
+                                },
+                                Prop::Getter(g) => {
+                                    let prop = self.translate_key_operand(&g.key, yield_position)?;
+                                    let stmts = match &g.body{
+                                        Some(b) => b.stmts.as_slice(),
+                                        None => &[]
+                                    };
+                                    let func = self.translate_function_body(&[], stmts, None, false, false, false, prop_name_str(&g.key))?;
+                                    let kind = self.builder.ins().iconst(types::I8, 1);
+                                    let obj = self.builder.use_var(held);
+                                    self.call_helper(self.define_method, &[obj, prop, func, kind])?;
+                                },
+                                Prop::Setter(s) => {
+                                    let prop = self.translate_key_operand(&s.key, yield_position)?;
+                                    let stmts = match &s.body{
+                                        Some(b) => b.stmts.as_slice(),
+                                        None => &[]
+                                    };
+                                    let func = self.translate_function_body(&[&s.param], stmts, None, false, false, false, prop_name_str(&s.key))?;
+                                    let kind = self.builder.ins().iconst(types::I8, 2);
+                                    let obj = self.builder.use_var(held);
+                                    self.call_helper(self.define_method, &[obj, prop, func, kind])?;
+                                },
+                                Prop::Assign(_) => {
+                                    return Err(Error::SyntaxError("Invalid shorthand property initializer".to_string()))
+                                }
+                            }
+                        }
+                    }
+                };
+                Ok(self.release(held))
+            },
+
+            Expr::OptChain(o) => {
+                self.translate_chain(expr, opt_chain, |ctx|{
+                    match o.expr.as_ref(){
+                        Expr::Member(m) => ctx.translate_member(m, true, yield_position),
+                        Expr::Call(c) => ctx.translate_call(c, true, yield_position),
+                        _ => Err(Error::SyntaxError("Invalid optional chain".to_string()))
+                    }
+                })
+            },
+
+            Expr::Paren(p) => {
+                self.yield_position = yield_position;
+                self.translate_expr(&p.expr)
+            },
+
+            Expr::PrivateName(p) => {
+                Err(Error::SyntaxError(format!("Unexpected private field '#{}'", p.id.sym)))
+            },
+
+            Expr::Seq(s) => {
+                let mut v = Value::from_u32(0);
+                for i in &s.exprs{
+                    self.yield_position = yield_position;
+                    v = self.translate_expr(&i)?;
+                };
+                Ok(v)
+            },
+
+            Expr::TaggedTpl(t) => {
+                // the template object is created once per call site
+                let strings = template_object(&t.tpl);
+                let strings = self.const_value(strings);
+
+                let (callee, this) = match t.tag.as_ref(){
+                    Expr::Member(m) if !matches!(m.obj, ExprOrSuper::Super(_)) => {
+                        let obj = match &m.obj{
+                            ExprOrSuper::Expr(e) => self.translate_expr(&e)?,
+                            ExprOrSuper::Super(_) => unreachable!()
+                        };
+                        let prop = self.translate_prop(&m.prop, m.computed)?;
+                        let getter = if is_private(&m.prop){
+                            self.private_get
+                        } else{
+                            self.member
+                        };
+                        (self.call_helper(getter, &[obj, prop])?, obj)
+                    },
+                    tag => {
+                        let callee = self.translate_expr(tag)?;
+                        (callee, self.builder.use_var(self.this))
+                    }
+                };
+
+                let slot = self.builder.create_stack_slot(StackSlotData { 
+                    kind: StackSlotKind::ExplicitSlot, 
+                    size: ((t.tpl.exprs.len() + 1) * size_of::<JValue>()) as u32
+                });
+                self.builder.ins().stack_store(strings, slot, 0);
+
+                let mut i = 1;
+                for e in &t.tpl.exprs{
+                    let v = self.translate_expr(&e)?;
+                    self.builder.ins().stack_store(v, slot, (i*size_of::<JValue>()) as i32);
+                    i += 1;
+                }
+
+                let argv = self.builder.ins().stack_addr(types::I64, slot, 0);
+                let argc = self.builder.ins().iconst(types::I64, i as i64);
+                let spread = self.builder.ins().iconst(types::I8, 0);
+                let vmctx = self.function_context();
+                let ins = self.builder.ins().call(self.call, &[callee, vmctx, this, argv, argc, spread]);
+                let v = self.builder.inst_results(ins);
+                let (re, ok) = (v[0], v[1]);
+
+                self.check_call_result(re, ok)?;
+                Ok(re)
+            },
+            Expr::This(t) => {
+                Ok(self.builder.use_var(self.this))
+            },
+
+            Expr::Tpl(t) => {
+                let mut held = Vec::new();
+                for e in &t.exprs{
+                    let v = self.translate_operand(&e, yield_position)?;
+                    held.push(self.hold(v));
+                }
+                let mut exprs = Vec::with_capacity(held.len());
+                while let Some(var) = held.pop(){
+                    exprs.push(self.release(var));
+                }
+                exprs.reverse();
+
+                let mut vs = Vec::new();
+
+                let mut i = 0;
+                for e in &t.quasis{
+                    let cooked = match &e.cooked{
+                        Some(c) => c.value.as_ref(),
+                        None => return Err(Error::SyntaxError("Invalid escape sequence in template".to_string()))
+                    };
+                    vs.push(self.const_value(cooked.into()));
+
+                    if !e.tail{
+                        vs.push(exprs[i]);
+                    }
+                    i += 1;
+                }
+
+                let slot = self.builder.create_stack_slot(StackSlotData { 
+                    kind: StackSlotKind::ExplicitSlot, 
+                    size: (vs.len() *size_of::<JValue>()) as u32
+                });
+
+                let mut i = 0;
+                for v in &vs{
+                    self.builder.ins().stack_store(*v, slot, i*size_of::<JValue>() as i32);
+                    i += 1;
+                }
+
+                let addr = self.builder.ins().stack_addr(types::I64, slot, 0);
+                let len = self.builder.ins().iconst(types::I64, vs.len() as i64);
+                self.call_helper(self.tpl_new, &[addr, len])
+            },
+
+            Expr::Unary(u) => {
+                match (u.op, u.arg.as_ref()){
+                    // `typeof x` does not throw if x is not declared
+                    (UnaryOp::TypeOf, Expr::Ident(i)) => {
+                        self.typeof_binding(&i.sym)
+                    },
+
+                    (UnaryOp::Delete, Expr::Member(m)) => {
+                        if is_private(&m.prop){
+                            return Err(Error::SyntaxError("Private fields can not be deleted".to_string()))
+                        }
+                        let obj = match &m.obj{
+                            ExprOrSuper::Expr(e) => {
+                                self.yield_position = yield_position;
+                                self.translate_expr(&e)?
+                            },
+                            ExprOrSuper::Super(_) => return Err(Error::Unimplemented("delete of a super property."))
+                        };
+                        let prop = self.translate_prop(&m.prop, m.computed)?;
+                        self.call_helper(self.delete_member, &[obj, prop])
+                    },
+
+                    // declared bindings cannot be deleted
+                    (UnaryOp::Delete, Expr::Ident(_)) => {
+                        Ok(self.const_value(JValue::Boolean(false)))
+                    },
+
+                    (UnaryOp::Delete, e) => {
+                        self.yield_position = yield_position;
+                        self.translate_expr(e)?;
+                        Ok(self.const_value(JValue::Boolean(true)))
+                    },
+
+                    (op, e) => {
+                        self.yield_position = yield_position;
+                        let v = self.translate_expr(e)?;
+
+                        // `typeof` and `!` cannot throw
+                        let f = match op{
+                            UnaryOp::Void => return Ok(self.const_value(JValue::Undefined)),
+                            UnaryOp::TypeOf | UnaryOp::Bang => {
+                                let f = if op == UnaryOp::TypeOf {self.typeOf} else {self.not};
+                                let inst = self.builder.ins().call(f, &[v]);
+                                return Ok(self.builder.inst_results(inst)[0])
+                            },
+                            UnaryOp::Minus => self.neg,
+                            UnaryOp::Plus => self.pos,
+                            UnaryOp::Tilde => self.bitNot,
+                            UnaryOp::Delete => unreachable!()
+                        };
+                        self.call_helper(f, &[v])
+                    }
+                }
+            },
+
+            Expr::Update(u) => {
+                let mut arg = u.arg.as_ref();
+                while let Expr::Paren(p) = arg{
+                    arg = &p.expr;
+                }
+
+                match arg{
+                    Expr::Ident(i) => {
+                        // a loop counter is incremented as an integer
+                        let id = self.runtime.to_mut().new_variable_name(&i.sym);
+                        if let Some(slot) = self.find_slot(id).filter(|s|s.counter){
+                            let old = self.use_slot(slot);
+                            let v = self.builder.use_var(slot.var);
+                            let step = match u.op{
+                                UpdateOp::PlusPlus => 1,
+                                UpdateOp::MinusMinus => -1,
+                            };
+                            let v = self.builder.ins().iadd_imm(v, step);
+                            self.builder.def_var(slot.var, v);
+                            let new = self.use_slot(slot);
+                            return Ok(if u.prefix {new} else {old})
+                        }
+
+                        let old = self.resolve_binding(&i.sym)?;
+                        let (old, new) = self.translate_increment(old, u.op)?;
+
+                        self.translate_ident_decl(i, new, None)?;
+                        Ok(if u.prefix {new} else {old})
+                    },
+
+                    Expr::Member(m) => {
+                        let obj = match &m.obj{
+                            ExprOrSuper::Expr(e) => {
+                                self.yield_position = yield_position;
+                                self.translate_expr(&e)?
+                            },
+                            // `super.x++` reads from the home object and assigns to `this`
+                            ExprOrSuper::Super(_) => self.builder.use_var(self.this)
+                        };
+                        let prop = self.translate_prop(&m.prop, m.computed)?;
+
+                        let old = if let Some(name) = cached_name(m){
+                            self.cached_member(obj, name)?
+                        } else if is_private(&m.prop){
+                            self.call_helper(self.private_get, &[obj, prop])?
+                        } else if let ExprOrSuper::Super(_) = &m.obj{
+                            let vmctx = self.function_context();
+                            self.call_helper(self.superMember, &[vmctx, obj, prop])?
+                        } else{
+                            self.call_helper(self.member, &[obj, prop])?
+                        };
+                        let (old, new) = self.translate_increment(old, u.op)?;
+
+                        if is_private(&m.prop){
+                            let assign = self.builder.ins().iconst(types::I8, AssignOp::Assign as i8 as i64);
+                            self.call_helper(self.private_set, &[obj, prop, new, assign])?;
+                        } else if let Some(name) = cached_name(m){
+                            self.cached_set_member(obj, name, new)?;
+                        } else{
+                            self.call_helper(self.set_memebr, &[obj, prop, new])?;
+                        }
+                        Ok(if u.prefix {new} else {old})
+                    },
+
+                    _ => Err(Error::SyntaxError(format!("Invalid left-hand side expression in {} operation", if u.prefix {"prefix"} else {"postfix"})))
+                }
+            },
+
+            Expr::Yield(y) => {
+                if !self.is_generator{
+                    return Err(Error::SyntaxError("yield is only valid in generator functions".to_string()))
+                }
+                if !yield_position{
+                    return Err(Error::Unimplemented("yield inside an operand whose preceding operands are not held."))
+                }
+
+                self.yield_position = true;
+                let value = match &y.arg{
+                    Some(a) => self.translate_expr(&a)?,
+                    None => self.const_value(JValue::Undefined)
+                };
+
+                if y.delegate{
+                    return self.translate_yield_delegate(value)
+                }
+
+                let (sent, mode) = self.emit_yield(value)?;
+
+                // generator.return(value)
+                let return_block = self.builder.create_block();
+                let next_block = self.builder.create_block();
+                let is_return = self.builder.ins().icmp_imm(IntCC::Equal, mode, ResumeMode::Return as i64);
+                self.builder.ins().brnz(is_return, return_block, &[]);
+                self.builder.ins().jump(next_block, &[]);
+
+                self.builder.seal_block(return_block);
+                self.builder.switch_to_block(return_block);
+                self.emit_return(sent)?;
+
+                self.builder.seal_block(next_block);
+                self.builder.switch_to_block(next_block);
+
+                // generator.throw(value)
+                let ok = self.builder.ins().icmp_imm(IntCC::NotEqual, mode, ResumeMode::Throw as i64);
+                self.check_call_result(sent, ok)?;
+                Ok(sent)
+            }
+
+            Expr::TsAs(a) => todo!(),
+            Expr::TsConstAssertion(c) => todo!(),
+            Expr::TsNonNull(n) => todo!(),
+            Expr::TsTypeAssertion(t) => todo!(),
+
+            Expr::JSXElement(e) => todo!(),
+            Expr::JSXEmpty(e) => todo!(),
+            Expr::JSXFragment(f) => todo!(),
+            Expr::JSXMember(m) => todo!(),
+            Expr::JSXNamespacedName(n) => todo!(),
+        }
+    }
+
+    pub fn translate_func(&mut self, func:&Function, name:Option<&str>) -> Result<Value, Error>{
+        let params = func.params.iter().map(|p|&p.pat).collect::<Vec<&Pat>>();
+        let stmts = match &func.body{
+            Some(b) => b.stmts.as_slice(),
+            None => &[]
+        };
+        self.translate_function_body(&params, stmts, None, func.is_async, func.is_generator, false, name)
+    }
+
+    /// compile a function into a separate cranelift function and return the function object.
+    /// 
+    /// the body is either a list of statements or a single expression (arrow functions).
+    pub fn translate_function_body(&mut self, params:&[&Pat], stmts:&[Stmt], expr:Option<&Expr>, is_async:bool, is_generator:bool, is_arrow:bool, name:Option<&str>) -> Result<Value, Error>{
+        let (mut ctx, mut builder) = self.new_function();
+        let re = builder.translate_body(params, stmts, expr, is_async, is_generator)?;
+        self.finish_function(builder, &mut ctx, re, is_async, is_generator, is_arrow, params.len(), name)
+    }
+
+    /// compile a function body on its own, without an enclosing function.
+    ///
+    /// used to tier up interpreted functions, every variable of the body not declared by it
+    /// is resolved from the calling context and the captures of the function object.
+    pub(crate) fn compile_function_body(runtime:Arc<Runtime>, params:&[&Pat], stmts:&[Stmt], expr:Option<&Expr>, is_async:bool, is_generator:bool) -> Result<*mut u8, Error>{
+        let module = runtime.module.clone();
+        let mut ctx = Box::new(module.make_context());
+        let mut builder = Self::new(runtime, module, &mut ctx);
+        builder.detached = true;
+        builder.script = false;
+
+        let re = builder.translate_body(params, stmts, expr, is_async, is_generator)?;
+        let (mem, ..) = Self::compile_body(builder, &mut ctx, re)?;
+        Ok(mem)
+    }
+
+    /// compile the loop `stmt` labeled `label` into a function entered on stack by the interpreter.
+    ///
+    /// the function takes the completion value of the statements before the loop
+    /// and returns it updated by the loop. names are resolved from the calling context,
+    /// the loop must run detached from its function, see `runs_detached`.
+    pub(crate) fn compile_loop(runtime:Arc<Runtime>, stmt:&Stmt, label:Option<String>) -> Result<*mut u8, Error>{
+        let module = runtime.module.clone();
+        let mut ctx = Box::new(module.make_context());
+        let mut builder = Self::new(runtime, module, &mut ctx);
+        builder.detached = true;
+        builder.script = false;
+
+        let stmts = std::slice::from_ref(stmt);
+        builder.scope = Arc::new(FunctionScope::function(&[], stmts, None, true));
+        builder.declare_scope(scope::function_names(&[], stmts), true)?;
+
+        let argv = builder.builder.block_params(builder.entry_block)[2];
+        let argc = builder.builder.block_params(builder.entry_block)[3];
+        let c = builder.builder.ins().iconst(types::I64, 0);
+        let v = builder.builder.ins().call(builder.resolve_argument, &[argv, argc, c]);
+        let v = builder.builder.inst_results(v)[0];
+        builder.builder.def_var(builder.completion, v);
+
+        builder.translate_stmt(stmt, label)?;
+
+        let re = builder.builder.use_var(builder.completion);
+        let (mem, ..) = Self::compile_body(builder, &mut ctx, re)?;
+        Ok(mem)
+    }
+
+    /// bind the parameters and translate the body of a function into this builder,
+    /// return the value returned by the function.
+    fn translate_body(&mut self, params:&[&Pat], stmts:&[Stmt], expr:Option<&Expr>, is_async:bool, is_generator:bool) -> Result<Value, Error>{
+        let argv = self.builder.block_params(self.entry_block)[2];
+        let argc = self.builder.block_params(self.entry_block)[3];
+
+        if is_async && is_generator{
+            return Err(Error::Unimplemented("async generator functions."))
+        }
+
+        self.is_generator = is_generator;
+        self.is_async = is_async;
+        if is_generator || is_async{
+            self.begin_generator();
+        }
+
+        // the bindings of generators must outlive the frame between resumptions
+        self.scope = Arc::new(FunctionScope::function(params, stmts, expr, !(is_generator || is_async)));
+        self.declare_scope(scope::function_names(params, stmts), true)?;
+
+        let mut i = 0;
+        for p in params{
+            let c = self.builder.ins().iconst(types::I64, i);
+
+            // `...args` collects the remaining arguments
+            let (p, helper) = match p{
+                Pat::Rest(r) => (r.arg.as_ref(), self.rest_arguments),
+                p => (*p, self.resolve_argument)
+            };
+            let v = self.builder.ins().call(helper, &[argv, argc, c]);
+            let v = self.builder.inst_results(v)[0];
+            self.translate_pat(p, v, Some(VarDeclKind::Var), AssignOp::Assign)?;
+            i += 1;
+        };
+        self.hoist_functions(stmts, true)?;
+
+        for stmt in stmts{
+            self.translate_stmt(stmt, None)?;
+        }
+
+        if let Some(e) = expr{
+            self.translate_expr(e)
+        } else{
+            Ok(self.const_value(JValue::Undefined))
+        }
+    }
+
+    /// return `re` from the function built by `builder` and compile it.
+    ///
+    /// return the finalized code, the bindings captured by the function in the order of its closure
+    /// and the names it captures by name.
+    fn compile_body(mut builder:BuilderContext<'a>, ctx:&mut Context, re:Value) -> Result<(*mut u8, Vec<usize>, Vec<u64>), Error>{
+        builder.close();
+        builder.builder.ins().return_(&[re]);
+
+        if let Some((dispatch, state)) = builder.dispatch{
+            builder.builder.switch_to_block(dispatch);
+
+            let points = builder.resume_points.borrow().clone();
+            let mut i = 1;
+            for block in points{
+                let next = builder.builder.create_block();
+                let c = builder.builder.ins().icmp_imm(IntCC::Equal, state, i);
+                builder.builder.ins().brnz(c, block, &[]);
+                builder.builder.ins().jump(next, &[]);
+                builder.builder.switch_to_block(next);
+                i += 1;
+            }
+            builder.builder.ins().trap(TrapCode::UnreachableCodeReached);
+        }
+        builder.builder.seal_all_blocks();
+        builder.builder.finalize();
+
+        let captures = builder.need_capture.borrow().clone();
+        let named = builder.named_captures.borrow().clone();
+        let runtime = builder.runtime.clone();
+        drop(builder);
+
+        let mem = runtime.compile_function(ctx)?;
+        Ok((mem, captures, named))
+    }
+
+    /// return `re` from the function built by `builder`, compile it and create the function object.
+    fn finish_function(&mut self, builder:BuilderContext<'a>, ctx:&mut Context, re:Value, is_async:bool, is_generator:bool, is_arrow:bool, length:usize, name:Option<&str>) -> Result<Value, Error>{
+        let (mem, captures, named) = Self::compile_body(builder, ctx, re)?;
+
+        let vmctx = self.builder.use_var(self.vmctx);
+        let addr = self.builder.ins().iconst(self.pointer_type, mem as i64);
+        let is_async = self.builder.ins().iconst(types::I8, is_async as i64);
+        let is_generator = self.builder.ins().iconst(types::I8, is_generator as i64);
+
+        let inst = self.builder.ins().call(self.function_new, &[vmctx, addr, is_async, is_generator]);
+        let func = self.builder.inst_results(inst)[0];
+
+        // the captured bindings are shared when the function object is created
+        for id in captures{
+            let place = self.place(id);
+            let (helper, index) = match place{
+                Place::Record(_, b) => (self.function_capture_slot, b.index),
+                Place::Closure(b) => (self.function_capture_closure, b.index),
+                _ => unreachable!("captured bindings are declared by a function")
+            };
+            let ctx = self.record_context(place);
+            let index = self.builder.ins().iconst(types::I64, index as i64);
+            self.builder.ins().call(helper, &[func, ctx, index]);
+        }
+        for id in named{
+            let vmctx = self.builder.use_var(self.vmctx);
+            let id = self.builder.ins().iconst(types::I64, id as i64);
+            self.builder.ins().call(self.function_capture, &[func, vmctx, id]);
+        }
+
+        // arrow functions use the `this` of the enclosing function
+        if is_arrow{
+            let this = self.builder.use_var(self.this);
+            self.builder.ins().call(self.function_bind_this, &[func, vmctx, this]);
+        }
+
+        let key = self.const_value("length".into());
+        let length = self.const_value(JValue::Number(length as f64));
+        self.call_helper(self.set_memebr, &[func, key, length])?;
+
+        if let Some(name) = name{
+            let key = self.const_value("name".into());
+            let name = self.const_value(name.into());
+            self.call_helper(self.set_memebr, &[func, key, name])?;
+        }
+
+        Ok(func)
+    }
+
+    /// the target block and unwind depth of a break or continue.
+    ///
+    /// an unlabeled break exits the nearest loop or switch, 
+    /// an unlabeled continue the nearest loop.
+    fn find_loop_exit(&self, label:Option<&str>, is_continue:bool, position:Position) -> Result<(Block, usize), Error>{
+        let exits = self.loop_exits.borrow();
+        let exit = match label{
+            Some(l) => match exits.iter().rev().find(|e|e.label.as_deref() == Some(l)){
+                Some(e) => e,
+                None => return Err(Error::UndefinedLabel(l.to_string(), position))
+            },
+            None => {
+                let exit = if is_continue{
+                    exits.iter().rev().find(|e|e.continue_block.is_some())
+                } else{
+                    exits.iter().rev().find(|e|!e.labeled_only)
+                };
+                match (exit, is_continue){
+                    (Some(e), _) => e,
+                    (None, true) => return Err(Error::IllegalContinueStatment(position)),
+                    (None, false) => return Err(Error::IllegalBreakStatement(position)),
+                }
+            }
+        };
+
+        if !is_continue{
+            return Ok((exit.exit_block, exit.unwind))
+        }
+        match exit.continue_block{
+            Some(b) => Ok((b, exit.unwind)),
+            None => Err(Error::IllegalContinueStatment(position))
+        }
+    }
+
+    /// jump out of the enclosing statements to a loop exit or continue block
+    fn emit_jump_out(&mut self, target:Block, depth:usize) -> Result<(), Error>{
+        if self.unwind_to(depth, Exit::Jump(target))?{
+            self.builder.ins().jump(target, &[]);
+        }
+
+        // anything after break or continue is unreachable
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+        Ok(())
+    }
+
+    /// unwind the entries above `depth`, innermost first.
+    /// iterators are closed and block scopes popped.
+    /// 
+    /// a thrown value jumps to the first catch block and any exit to the first finalizer, 
+    /// return false if the exit was taken over by either.
+    fn unwind_to(&mut self, depth:usize, exit:Exit) -> Result<bool, Error>{
+        // the code following a popped scope runs in the enclosing context
+        let vmctx = self.vmctx;
+        let re = self.unwind_entries(depth, exit);
+        self.vmctx = vmctx;
+        re
+    }
+
+    fn unwind_entries(&mut self, depth:usize, exit:Exit) -> Result<bool, Error>{
+        let mut i = self.unwind.borrow().len();
+        while i > depth{
+            i -= 1;
+
+            // an exit inside the entry itself unwinds from below it
+            let entries = self.unwind.borrow_mut().split_off(i);
+
+            let re = match &entries[0]{
+                Unwind::Catch(block) => {
+                    if let Exit::Throw(v) = exit{
+                        self.builder.ins().jump(*block, &[v]);
+                        self.unwind.borrow_mut().extend(entries);
+                        return Ok(false)
+                    }
+                    Ok(())
+                },
+                Unwind::Finally(f) => {
+                    let mut f = f.borrow_mut();
+                    let (kind, value) = match exit{
+                        Exit::Throw(v) => (1, v),
+                        Exit::Return(v) => (2, v),
+                        Exit::Jump(b) => {
+                            let i = match f.jumps.iter().position(|j|*j == (b, depth)){
+                                Some(i) => i,
+                                None => {
+                                    f.jumps.push((b, depth));
+                                    f.jumps.len() - 1
+                                }
+                            };
+                            (3 + i as i64, self.const_value(JValue::Undefined))
+                        }
+                    };
+                    let kind = self.builder.ins().iconst(types::I64, kind);
+                    self.builder.ins().jump(f.block, &[kind, value]);
+                    drop(f);
+                    self.unwind.borrow_mut().extend(entries);
+                    return Ok(false)
+                },
+                Unwind::Scope(scope, outer) => {
+                    let v = self.builder.use_var(*scope);
+                    self.builder.ins().call(self.pop_scope, &[v]);
+                    self.vmctx = *outer;
+                    Ok(())
+                },
+                Unwind::Iterator(name) => {
+                    let iterator = self.resolve_binding(name)?;
+                    let ins = self.builder.ins().call(self.iterator_close, &[iterator]);
+                    let (re, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+
+                    // errors from closing are ignored while an exception propagates
+                    if !matches!(exit, Exit::Throw(_)){
+                        self.check_call_result(re, ok)
+                    } else{
+                        Ok(())
+                    }
+                },
+            };
+
+            self.unwind.borrow_mut().extend(entries);
+            re?;
+        }
+        Ok(true)
+    }
+
+    /// the normal completion of a try or catch block, through the finalizer if any
+    fn jump_completed(&mut self, target:Block, finalized:bool){
+        if finalized{
+            let kind = self.builder.ins().iconst(types::I64, 0);
+            let undefined = self.const_value(JValue::Undefined);
+            self.builder.ins().jump(target, &[kind, undefined]);
+        } else{
+            self.builder.ins().jump(target, &[]);
+        }
+    }
+
+    /// run the finalizer and continue the completion it received
+    fn translate_finalizer(&mut self, finalizer:&Finalizer, block:&BlockStmt, exit_block:Block) -> Result<(), Error>{
+        self.builder.seal_block(finalizer.block);
+        self.builder.switch_to_block(finalizer.block);
+
+        // the completion is kept across a `yield` in the finalizer
+        let kind = self.builder.block_params(finalizer.block)[0];
+        let value = self.builder.block_params(finalizer.block)[1];
+        let kind = self.hold(kind);
+        let value = self.hold(value);
+
+        let mut ctx = self.new_context();
+        ctx.enter_block(&block.stmts, true)?;
+        for i in &block.stmts{
+            ctx.translate_stmt(i, None)?;
+        }
+        ctx.close();
+
+        let value = self.release(value);
+        let kind = self.release(kind);
+
+        let mut next = |this:&mut Self, k:i64|{
+            let taken = this.builder.create_block();
+            let next = this.builder.create_block();
+            let c = this.builder.ins().icmp_imm(IntCC::Equal, kind, k);
+            this.builder.ins().brnz(c, taken, &[]);
+            this.builder.ins().jump(next, &[]);
+            this.builder.seal_block(taken);
+            this.builder.seal_block(next);
+            this.builder.switch_to_block(taken);
+            next
+        };
+
+        let block = next(self, 0);
+        self.builder.ins().jump(exit_block, &[]);
+        self.builder.switch_to_block(block);
+
+        let block = next(self, 1);
+        self.emit_throw(value)?;
+        self.builder.switch_to_block(block);
+
+        let block = next(self, 2);
+        self.emit_return(value)?;
+        self.builder.switch_to_block(block);
+
+        for (i, (target, depth)) in finalizer.jumps.iter().enumerate(){
+            let block = next(self, 3 + i as i64);
+            self.emit_jump_out(*target, *depth)?;
+            self.builder.switch_to_block(block);
+        }
+        self.builder.ins().trap(TrapCode::UnreachableCodeReached);
+        Ok(())
+    }
+
+    /// unwind the enclosing statements and return from the function
+    fn emit_return(&mut self, value:Value) -> Result<(), Error>{
+        if self.unwind_to(0, Exit::Return(value))?{
+            self.builder.ins().return_(&[value]);
+        }
+
+        // anything after return is unreachable
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+        Ok(())
+    }
+
+    /// jump to the nearest catch block, 
+    /// or store the exception in the context and return to the caller if uncaught.
+    fn emit_throw(&mut self, value:Value) -> Result<(), Error>{
+        if self.unwind_to(0, Exit::Throw(value))?{
+            let vmctx = self.function_context();
+            self.builder.ins().call(self.set_exception, &[vmctx, value]);
+
+            let undefined = self.const_value(JValue::Undefined);
+            self.builder.ins().return_(&[undefined]);
+        }
+
+        // anything after throw is unreachable
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+        Ok(())
+    }
+
+    /// loop over the iterator, binding each value to `left`.
+    /// 
+    /// `close` is true for `for of` loops, the iterator is closed when the loop exits early.
+    fn translate_iteration(&mut self, left:&VarDeclOrPat, iterator:Value, body:&Stmt, label:Option<String>, close:bool) -> Result<(), Error>{
+        // the iterator must survive `yield` in generators
+        let hidden = Self::hidden_name("iterator");
+        self.declare_named(&hidden, iterator, VarDeclKind::Let);
+
+        let header = self.builder.create_block();
+        let body_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        let break_block = if close{
+            self.builder.create_block()
+        } else{
+            exit_block
+        };
+
+        // the iterator is closed by exits from the body
+        self.loop_exits.borrow_mut().push(LoopExit { 
+            label, 
+            exit_block: break_block, 
+            continue_block: Some(header), 
+            labeled_only: false,
+            unwind: self.unwind.borrow().len() + close as usize,
+        });
+
+        self.builder.ins().jump(header, &[]);
+        self.builder.switch_to_block(header);
+
+        let mut ctx = self.new_context();
+
+        let iterator = ctx.resolve_binding(&hidden)?;
+        let ins = ctx.builder.ins().call(ctx.iterator_next, &[iterator]);
+        let (value, status) = (ctx.builder.inst_results(ins)[0], ctx.builder.inst_results(ins)[1]);
+
+        // the iterator is not closed if `next` throws
+        let ok = ctx.builder.ins().icmp_imm(IntCC::NotEqual, status, 2);
+        ctx.check_call_result(value, ok)?;
+
+        let done = ctx.builder.ins().icmp_imm(IntCC::Equal, status, 1);
+        ctx.builder.ins().brnz(done, exit_block, &[]);
+        ctx.builder.ins().jump(body_block, &[]);
+        ctx.builder.seal_block(body_block);
+        ctx.builder.switch_to_block(body_block);
+
+        if close{
+            ctx.unwind.borrow_mut().push(Unwind::Iterator(hidden.clone()));
+        }
+
+        // a new binding is declared for every iteration
+        match left{
+            VarDeclOrPat::VarDecl(d) => {
+                if d.kind != VarDeclKind::Var{
+                    let names = scope::decl_names(d).into_iter().map(|n|(n, d.kind)).collect();
+                    ctx.declare_scope(names, true)?;
+                }
+                ctx.translate_pat(&d.decls[0].name, value, Some(d.kind), AssignOp::Assign)?;
+            },
+            VarDeclOrPat::Pat(p) => {
+                ctx.translate_pat(&p, value, None, AssignOp::Assign)?;
+            }
+        };
+
+        ctx.translate_stmt(body, None)?;
+        ctx.close();
+
+        self.builder.ins().jump(header, &[]);
+        self.loop_exits.borrow_mut().pop();
+
+        if close{
+            self.unwind.borrow_mut().pop();
+
+            self.builder.seal_block(break_block);
+            self.builder.switch_to_block(break_block);
+            let iterator = self.resolve_binding(&hidden)?;
+            let ins = self.builder.ins().call(self.iterator_close, &[iterator]);
+            let (re, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+            self.check_call_result(re, ok)?;
+            self.builder.ins().jump(exit_block, &[]);
+        }
+
+        self.builder.seal_block(header);
+        self.builder.seal_block(exit_block);
+        self.builder.switch_to_block(exit_block);
+        Ok(())
+    }
+
+    /// switch to a generator or async function, the entry jumps to the saved resume point
+    fn begin_generator(&mut self){
+        let vmctx = self.function_context();
+        let ins = self.builder.ins().call(self.generator_state, &[vmctx]);
+        let state = self.builder.inst_results(ins)[0];
+
+        let dispatch = self.builder.create_block();
+        let body = self.builder.create_block();
+        self.builder.ins().brnz(state, dispatch, &[]);
+        self.builder.ins().jump(body, &[]);
+
+        self.builder.seal_block(body);
+        self.builder.switch_to_block(body);
+        self.dispatch = Some((dispatch, state));
+    }
+
+    /// keep `v` alive while the next operands are evaluated, they may suspend the function
+    fn hold(&mut self, v:Value) -> Variable{
+        let var = self.scope.new_variable();
+        self.builder.declare_var(var, JValue::TYPE);
+        self.builder.def_var(var, v);
+        self.temporaries.borrow_mut().push(var);
+        var
+    }
+
+    /// the value held by `var`, the temporaries are released in reverse order
+    fn release(&mut self, var:Variable) -> Value{
+        let top = self.temporaries.borrow_mut().pop();
+        debug_assert!(top == Some(var), "temporaries released out of order");
+        self.builder.use_var(var)
+    }
+
+    /// translate an operand evaluated after the values held by the caller
+    fn translate_operand(&mut self, e:&Expr, yield_position:bool) -> Result<Value, Error>{
+        self.yield_position = yield_position;
+        self.translate_expr(e)
+    }
+
+    /// suspend the generator and return the value and mode it is resumed with.
+    /// 
+    /// the frame does not survive the suspension, the held temporaries
+    /// are saved to hidden bindings and reloaded once resumed.
+    fn emit_yield(&mut self, value:Value) -> Result<(Value, Value), Error>{
+        let held = self.temporaries.borrow().clone();
+        let mut spilled = Vec::with_capacity(held.len());
+        for var in held{
+            let name = Self::hidden_name("held");
+            let v = self.builder.use_var(var);
+            self.declare_named(&name, v, VarDeclKind::Let);
+            spilled.push((var, name));
+        }
 
-                                    }
-                                    let id = self.builder.ins().iconst(types::I64, id.to_usize() as i64);
-                                    let inst = self.builder.ins().call(self.resolve_var, &[id]);
-                                    let v = self.builder.inst_results(inst)[0];
+        let point = self.resume_points.borrow().len() as i64 + 1;
 
-                                    let prop = self.const_value(i.sym.as_ref().into());
+        let vmctx = self.function_context();
+        let scope = self.builder.use_var(self.vmctx);
+        let point = self.builder.ins().iconst(types::I64, point);
+        self.builder.ins().call(self.generator_yield, &[vmctx, scope, value, point]);
 
-                                    self.builder.ins().call(self.set_memebr, &[prop, v]);
-                                },
-                                Prop::KeyValue(k) => {
+        let undefined = self.const_value(JValue::Undefined);
+        self.builder.ins().return_(&[undefined]);
 
-                                },
-                                _ => todo!()
-                            }
-                        }
-                    }
-                };
-                Ok(obj)
-            },
+        let resume = self.builder.create_block();
+        self.resume_points.borrow_mut().push(resume);
+        self.builder.switch_to_block(resume);
 
-            Expr::OptChain(o) => {
-                todo!()
-            },
+        let vmctx = self.function_context();
+        let ins = self.builder.ins().call(self.generator_resume, &[vmctx]);
+        let (sent, mode) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
 
-            Expr::Paren(p) => {
-                self.translate_expr(&p.expr)
-            },
+        self.restore_scopes();
+        for (var, name) in spilled{
+            let v = self.resolve_binding(&name)?;
+            self.builder.def_var(var, v);
+        }
+        Ok((sent, mode))
+    }
 
-            Expr::PrivateName(p) => {
-                Ok(self.const_value(Symbol::new_private(&p.id.sym)))
-            },
+    /// define the contexts of the block scopes entered by the generator once resumed, 
+    /// from the innermost scope saved by the `yield`.
+    fn restore_scopes(&mut self){
+        let scopes:Vec<Variable> = self.unwind.borrow().iter().rev().filter_map(|u|match u{
+            Unwind::Scope(v, _) => Some(*v),
+            _ => None
+        }).collect();
+        if scopes.is_empty(){
+            return
+        }
 
-            Expr::Seq(s) => {
-                let mut v = Value::from_u32(0);
-                for i in &s.exprs{
-                    v = self.translate_expr(&i)?;
-                };
-                Ok(v)
-            },
+        let vmctx = self.function_context();
+        let ins = self.builder.ins().call(self.generator_scope, &[vmctx]);
+        let mut scope = self.builder.inst_results(ins)[0];
+        for (i, var) in scopes.into_iter().enumerate(){
+            if i > 0{
+                let ins = self.builder.ins().call(self.scope_parent, &[scope]);
+                scope = self.builder.inst_results(ins)[0];
+            }
+            self.builder.def_var(var, scope);
+        }
+    }
 
-            Expr::TaggedTpl(t) => {
-                todo!()
-            },
-            Expr::This(t) => {
-                Ok(self.builder.use_var(self.this))
-            },
+    /// `yield* iterable`, forwarding every resumption to the inner iterator
+    fn translate_yield_delegate(&mut self, iterable:Value) -> Result<Value, Error>{
+        let ins = self.builder.ins().call(self.get_iterator, &[iterable]);
+        let (iterator, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+        self.check_call_result(iterator, ok)?;
+
+        let hidden = Self::hidden_name("delegate");
+        self.declare_named(&hidden, iterator, VarDeclKind::Let);
+
+        let header = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.append_block_param(header, JValue::TYPE);
+        self.builder.append_block_param(header, types::I8);
+        self.builder.append_block_param(exit_block, JValue::TYPE);
+
+        let undefined = self.const_value(JValue::Undefined);
+        let next = self.builder.ins().iconst(types::I8, ResumeMode::Next as i64);
+        self.builder.ins().jump(header, &[undefined, next]);
+
+        self.builder.switch_to_block(header);
+        let sent = self.builder.block_params(header)[0];
+        let mode = self.builder.block_params(header)[1];
+
+        let iterator = self.resolve_binding(&hidden)?;
+        let vmctx = self.function_context();
+        let ins = self.builder.ins().call(self.generator_delegate, &[vmctx, iterator, sent, mode]);
+        let (value, status) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+
+        // status 3: the inner iterator throws
+        let ok = self.builder.ins().icmp_imm(IntCC::NotEqual, status, 3);
+        self.check_call_result(value, ok)?;
+
+        // status 1: the inner iterator is done
+        let return_check = self.builder.create_block();
+        let is_done = self.builder.ins().icmp_imm(IntCC::Equal, status, 1);
+        self.builder.ins().brnz(is_done, exit_block, &[value]);
+        self.builder.ins().jump(return_check, &[]);
+        self.builder.seal_block(return_check);
+        self.builder.switch_to_block(return_check);
+
+        // status 2: return from the generator
+        let return_block = self.builder.create_block();
+        let yield_block = self.builder.create_block();
+        let is_return = self.builder.ins().icmp_imm(IntCC::Equal, status, 2);
+        self.builder.ins().brnz(is_return, return_block, &[]);
+        self.builder.ins().jump(yield_block, &[]);
+
+        self.builder.seal_block(return_block);
+        self.builder.switch_to_block(return_block);
+        self.emit_return(value)?;
+        
+        // status 0: yield the value and forward the resumption
+        self.builder.seal_block(yield_block);
+        self.builder.switch_to_block(yield_block);
+        let (sent, mode) = self.emit_yield(value)?;
+        self.builder.ins().jump(header, &[sent, mode]);
+
+        self.builder.seal_block(header);
+        self.builder.seal_block(exit_block);
+        self.builder.switch_to_block(exit_block);
+        Ok(self.builder.block_params(exit_block)[0])
+    }
 
-            Expr::Tpl(t) => {
-                let mut vs = Vec::new();
+    /// compile a class and return its constructor.
+    /// 
+    /// instance fields are compiled into an initializer run by the constructor,
+    /// static fields and blocks into an initializer called once the class is defined.
+    pub fn translate_class(&mut self, class:&Class, name:Option<&str>) -> Result<Value, Error>{
+        // the class scope, declaring the inner binding of the name and the private names
+        let mut ctx = self.new_context();
+        let private = class.body.iter().any(|m|matches!(m, ClassMember::PrivateMethod(_) | ClassMember::PrivateProp(_)));
+        if name.is_some() || private{
+            ctx.push_scope();
+        }
 
-                let mut i = 0;
-                for e in &t.quasis{
-                    vs.push(self.const_value(e.raw.value.as_ref().into()));
+        // every evaluation of the class creates new private names
+        let mut names = HashMap::new();
+        for m in &class.body{
+            let key = match m{
+                ClassMember::PrivateMethod(p) => &p.key,
+                ClassMember::PrivateProp(p) => &p.key,
+                _ => continue
+            };
+            // a getter and setter pair shares the name
+            if names.contains_key(key.id.sym.as_ref()){
+                continue;
+            }
+            let hidden = Self::hidden_name("private");
+            let desc = ctx.const_value(key.id.sym.as_ref().into());
+            let ins = ctx.builder.ins().call(ctx.private_name_new, &[desc]);
+            let v = ctx.builder.inst_results(ins)[0];
+            ctx.declare_named(&hidden, v, VarDeclKind::Const);
+            names.insert(key.id.sym.to_string(), hidden);
+        }
+        ctx.private_names.borrow_mut().push(names);
 
-                    if !e.tail{
-                        vs.push(self.translate_expr(&t.exprs[i])?);
-                    }
-                    i += 1;
-                }
+        let re = ctx.translate_class_body(class, name);
 
-                let slot = self.builder.create_stack_slot(StackSlotData { 
-                    kind: StackSlotKind::ExplicitSlot, 
-                    size: (vs.len() *size_of::<JValue>()) as u32
-                });
+        ctx.private_names.borrow_mut().pop();
+        ctx.close();
+        re
+    }
 
-                let mut i = 0;
-                for v in &vs{
-                    self.builder.ins().stack_store(*v, slot, i*size_of::<JValue>() as i32);
-                    i += 1;
-                }
+    fn translate_class_body(&mut self, class:&Class, name:Option<&str>) -> Result<Value, Error>{
+        // the inner binding of the class name, assigned once the class is defined
+        if let Some(name) = name{
+            let undefined = self.const_value(JValue::Undefined);
+            self.declare_named(name, undefined, VarDeclKind::Let);
+        }
 
-                let addr = self.builder.ins().stack_addr(types::I64, slot, 0);
-                let len = self.builder.ins().iconst(types::I64, vs.len() as i64);
-                let inst = self.builder.ins().call(self.tpl_new, &[addr, len]);
-                Ok(self.builder.inst_results(inst)[0])
-            },
+        let (parent, has_parent) = match &class.super_class{
+            Some(s) => (self.translate_expr(&s)?, true),
+            None => (self.const_value(JValue::Undefined), false)
+        };
 
-            Expr::Unary(u) => {
-                todo!()
+        let ctor = class.body.iter().find_map(|m|match m{
+            ClassMember::Constructor(c) => Some(c),
+            _ => None
+        });
+        let ctor = match ctor{
+            Some(c) => {
+                let mut params = Vec::new();
+                for p in &c.params{
+                    match p{
+                        ParamOrTsParamProp::Param(p) => params.push(&p.pat),
+                        ParamOrTsParamProp::TsParamProp(_) => return Err(Error::Unimplemented("typescript parameter properties."))
+                    }
+                }
+                let stmts = match &c.body{
+                    Some(b) => b.stmts.as_slice(),
+                    None => &[]
+                };
+                self.translate_function_body(&params, stmts, None, false, false, false, name)?
             },
+            None => self.translate_default_constructor(has_parent, name)?
+        };
 
-            Expr::Update(u) => {
-                todo!()
-            },
+        let has_parent = self.builder.ins().iconst(types::I8, has_parent as i64);
+        let ctor = self.call_helper(self.class_new, &[ctor, parent, has_parent])?;
 
-            Expr::Yield(y) => {
-                todo!()
+        if let Some(name) = name{
+            self.declare_binding(name, ctor, VarDeclKind::Let)?;
+        }
+
+        let key = self.const_value("prototype".into());
+        let proto = self.call_helper(self.member, &[ctor, key])?;
+
+        let mut instance_elements = Vec::new();
+        let mut static_elements = Vec::new();
+        // private methods are installed before any field
+        let mut private_methods = 0;
+
+        for m in &class.body{
+            match m{
+                ClassMember::Method(m) => {
+                    let key = self.translate_prop_name(&m.key)?;
+                    let func = self.translate_func(&m.function, prop_name_str(&m.key))?;
+                    let target = if m.is_static{ctor} else{proto};
+                    let kind = self.builder.ins().iconst(types::I8, method_kind(m.kind) as i64);
+                    self.call_helper(self.define_method, &[target, key, func, kind])?;
+                },
+                ClassMember::PrivateMethod(m) => {
+                    let func = self.translate_func(&m.function, Some(&m.key.id.sym))?;
+                    let home = if m.is_static{ctor} else{proto};
+                    self.builder.ins().call(self.set_home_object, &[func, home]);
+
+                    let kind = method_kind(m.kind) + 1;
+                    if m.is_static{
+                        let key = self.resolve_private(&m.key.id.sym)?;
+                        let kind = self.builder.ins().iconst(types::I8, kind as i64);
+                        self.call_helper(self.private_define, &[ctor, key, func, kind])?;
+                    } else{
+                        let hidden = Self::hidden_name("method");
+                        self.declare_named(&hidden, func, VarDeclKind::Const);
+                        instance_elements.insert(private_methods, ClassElement::PrivateMethod(&m.key, hidden, kind));
+                        private_methods += 1;
+                    }
+                },
+                ClassMember::ClassProp(p) => {
+                    // computed keys are evaluated once when the class is defined
+                    let key = match &p.key{
+                        PropName::Computed(c) => {
+                            let v = self.translate_expr(&c.expr)?;
+                            let hidden = Self::hidden_name("key");
+                            self.declare_named(&hidden, v, VarDeclKind::Const);
+                            FieldKey::Hidden(hidden)
+                        },
+                        k => FieldKey::Name(k)
+                    };
+                    let e = ClassElement::Field(key, p.value.as_deref());
+                    if p.is_static{
+                        static_elements.push(e);
+                    } else{
+                        instance_elements.push(e);
+                    }
+                },
+                ClassMember::PrivateProp(p) => {
+                    let e = ClassElement::PrivateField(&p.key, p.value.as_deref());
+                    if p.is_static{
+                        static_elements.push(e);
+                    } else{
+                        instance_elements.push(e);
+                    }
+                },
+                ClassMember::StaticBlock(b) => {
+                    static_elements.push(ClassElement::StaticBlock(&b.body));
+                },
+                ClassMember::Constructor(_) |
+                ClassMember::TsIndexSignature(_) |
+                ClassMember::Empty(_) => {}
             }
+        }
 
-            Expr::TsAs(a) => todo!(),
-            Expr::TsConstAssertion(c) => todo!(),
-            Expr::TsNonNull(n) => todo!(),
-            Expr::TsTypeAssertion(t) => todo!(),
+        if !instance_elements.is_empty(){
+            let init = self.translate_class_initializer(&instance_elements)?;
+            self.builder.ins().call(self.set_home_object, &[init, proto]);
+            self.builder.ins().call(self.class_fields, &[ctor, init]);
+        }
 
-            Expr::JSXElement(e) => todo!(),
-            Expr::JSXEmpty(e) => todo!(),
-            Expr::JSXFragment(f) => todo!(),
-            Expr::JSXMember(m) => todo!(),
-            Expr::JSXNamespacedName(n) => todo!(),
+        if !static_elements.is_empty(){
+            let init = self.translate_class_initializer(&static_elements)?;
+            self.builder.ins().call(self.set_home_object, &[init, ctor]);
+
+            let (argv, argc, spread) = self.translate_args(&[], false)?;
+            let vmctx = self.function_context();
+            let ins = self.builder.ins().call(self.call, &[init, vmctx, ctor, argv, argc, spread]);
+            let v = self.builder.inst_results(ins);
+            let (re, ok) = (v[0], v[1]);
+            self.check_call_result(re, ok)?;
         }
+
+        Ok(ctor)
     }
 
-    pub fn translate_func(&self, func:&Function, name:Option<&str>) -> Result<Value, Error>{
-        todo!()
+    /// `constructor(...args){ super(...args) }` for derived classes, an empty function otherwise
+    fn translate_default_constructor(&mut self, is_derived:bool, name:Option<&str>) -> Result<Value, Error>{
+        let (mut ctx, mut builder) = self.new_function();
+
+        if is_derived{
+            let vmctx = builder.function_context();
+            let this = builder.builder.use_var(builder.this);
+            let argv = builder.builder.block_params(builder.entry_block)[2];
+            let argc = builder.builder.block_params(builder.entry_block)[3];
+            let spread = builder.builder.ins().iconst(types::I8, 0);
+
+            let ins = builder.builder.ins().call(builder.super_call, &[vmctx, this, argv, argc, spread]);
+            let v = builder.builder.inst_results(ins);
+            let (re, ok) = (v[0], v[1]);
+            builder.check_call_result(re, ok)?;
+        }
+
+        let re = builder.const_value(JValue::Undefined);
+        self.finish_function(builder, &mut ctx, re, false, false, false, 0, name)
     }
 
-    pub fn translate_class(&self, class:&Class, name:Option<&str>) -> Result<Value, Error>{
-        todo!()
+    /// compile the elements into a function evaluating them on `this`
+    fn translate_class_initializer(&mut self, elements:&[ClassElement]) -> Result<Value, Error>{
+        let (mut ctx, mut builder) = self.new_function();
+
+        for e in elements{
+            match e{
+                ClassElement::Field(key, value) => {
+                    let key = match key{
+                        FieldKey::Name(k) => builder.translate_prop_name(k)?,
+                        FieldKey::Hidden(h) => builder.resolve_binding(h)?
+                    };
+                    let value = match value{
+                        Some(v) => builder.translate_expr(v)?,
+                        None => builder.const_value(JValue::Undefined)
+                    };
+                    let this = builder.builder.use_var(builder.this);
+                    builder.call_helper(builder.define_field, &[this, key, value])?;
+                },
+                ClassElement::PrivateField(name, value) => {
+                    let key = builder.resolve_private(&name.id.sym)?;
+                    let value = match value{
+                        Some(v) => builder.translate_expr(v)?,
+                        None => builder.const_value(JValue::Undefined)
+                    };
+                    let this = builder.builder.use_var(builder.this);
+                    let kind = builder.builder.ins().iconst(types::I8, 0);
+                    builder.call_helper(builder.private_define, &[this, key, value, kind])?;
+                },
+                ClassElement::PrivateMethod(name, hidden, kind) => {
+                    let key = builder.resolve_private(&name.id.sym)?;
+                    let func = builder.resolve_binding(hidden)?;
+                    let this = builder.builder.use_var(builder.this);
+                    let kind = builder.builder.ins().iconst(types::I8, *kind as i64);
+                    builder.call_helper(builder.private_define, &[this, key, func, kind])?;
+                },
+                ClassElement::StaticBlock(b) => {
+                    // a static block is scoped like a function body
+                    let mut ctx = builder.new_context();
+                    ctx.declare_scope(scope::function_names(&[], &b.stmts), true)?;
+                    ctx.hoist_functions(&b.stmts, true)?;
+                    for s in &b.stmts{
+                        ctx.translate_stmt(s, None)?;
+                    }
+                    ctx.close();
+                }
+            }
+        }
+
+        let re = builder.const_value(JValue::Undefined);
+        self.finish_function(builder, &mut ctx, re, false, false, false, 0, None)
     }
 
     pub fn translate_var_decl(&mut self, decl:&VarDecl) -> Result<(), Error>{
         for dec in &decl.decls{
+            // the binding is hoisted, a `var` without initializer keeps its value
+            if dec.init.is_none() && decl.kind == VarDeclKind::Var{
+                continue;
+            }
             let val = if let Some(e) = &dec.init{
+                self.yield_position = true;
                 self.translate_expr(&e)?
             } else{
                 self.const_value(JValue::Undefined)
@@ -1263,85 +3931,649 @@ impl<'a> BuilderContext<'a>{
         Ok(())
     }
 
+    /// bind `val` to the pattern, declaring the variables if `kind` is some.
     pub fn translate_pat(&mut self, p:&Pat, val:Value, kind:Option<VarDeclKind>, op:AssignOp) -> Result<(), Error>{
         match p{
+            // the default is only evaluated if the value is undefined
             Pat::Assign(a) => {
-                let right = self.translate_expr(&a.right)?;
-                let val = self.builder.ins().select(val, val, right);
+                let undefined = self.const_value(JValue::Undefined);
+                let test = self.eqeqeq(val, undefined);
+                let test = self.to_bool(test);
+                let val = self.translate_select(test, |ctx|ctx.translate_expr(&a.right), |_|Ok(val))?;
                 return self.translate_pat(&a.left, val, kind, op)
             },
             Pat::Array(a) => {
-                
+                self.translate_array_pat(a, val, kind, op)?;
+            },
+            Pat::Expr(e) => {
+                match e.as_ref(){
+                    Expr::Ident(i) => {
+                        self.translate_ident_decl(i, val, None)?;
+                    },
+                    Expr::Member(m) => {
+                        let obj = match &m.obj{
+                            ExprOrSuper::Expr(e) => self.translate_expr(&e)?,
+                            ExprOrSuper::Super(_) => self.builder.use_var(self.this)
+                        };
+                        let prop = self.translate_prop(&m.prop, m.computed)?;
+                        if is_private(&m.prop){
+                            let op = self.builder.ins().iconst(types::I8, AssignOp::Assign as i8 as i64);
+                            self.call_helper(self.private_set, &[obj, prop, val, op])?;
+                        } else if let Some(name) = cached_name(m){
+                            self.cached_set_member(obj, name, val)?;
+                        } else{
+                            self.call_helper(self.set_memebr, &[obj, prop, val])?;
+                        }
+                    },
+                    _ => return Err(Error::SyntaxError("Invalid left-hand side in assignment".to_string()))
+                }
             },
-            Pat::Expr(e) => todo!(),
             Pat::Ident(i) => {
-                self.translate_ident_decl(&i.id, val)?;
+                self.translate_ident_decl(&i.id, val, kind)?;
+            },
+            Pat::Invalid(i) => {
+                return Err(Error::SyntaxError("Invalid destructuring assignment target".to_string()))
             },
-            Pat::Invalid(i) => todo!(),
             Pat::Object(o) => {
+                self.translate_object_pat(o, val, kind, op)?;
+            }
+            Pat::Rest(r) => {
+                return Err(Error::SyntaxError("Rest element must be last element".to_string()))
+            }
+        }
+        Ok(())
+    }
+
+    /// `[a, , b = 1, ...rest] = val`, the iterator is closed unless it is done.
+    fn translate_array_pat(&mut self, a:&ArrayPat, val:Value, kind:Option<VarDeclKind>, op:AssignOp) -> Result<(), Error>{
+        let ins = self.builder.ins().call(self.get_iterator, &[val]);
+        let (iterator, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+        self.check_call_result(iterator, ok)?;
+
+        // the iterator is closed by exceptions thrown while binding the elements
+        let hidden = Self::hidden_name("iterator");
+        self.declare_named(&hidden, iterator, VarDeclKind::Let);
+
+        let mut done = self.builder.ins().bconst(types::B1, false);
+
+        for elem in &a.elems{
+            if let Some(Pat::Rest(r)) = elem{
+                let d = self.builder.ins().bint(types::I8, done);
+                let ins = self.builder.ins().call(self.iterator_rest, &[iterator, d]);
+                let (rest, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+                self.check_call_result(rest, ok)?;
+                done = self.builder.ins().bconst(types::B1, true);
+
+                self.translate_pat(&r.arg, rest, kind, op)?;
+                continue;
+            }
+
+            let (value, d) = self.pattern_next(iterator, done)?;
+            done = d;
 
+            if let Some(p) = elem{
+                self.unwind.borrow_mut().push(Unwind::Iterator(hidden.clone()));
+                let re = self.translate_pat(p, value, kind, op);
+                self.unwind.borrow_mut().pop();
+                re?;
             }
-            Pat::Rest(r) => unimplemented!()
         }
+
+        let close_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.ins().brnz(done, exit_block, &[]);
+        self.builder.ins().jump(close_block, &[]);
+
+        self.builder.switch_to_block(close_block);
+        self.builder.seal_block(close_block);
+        let ins = self.builder.ins().call(self.iterator_close, &[iterator]);
+        let (re, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+        self.check_call_result(re, ok)?;
+        self.builder.ins().jump(exit_block, &[]);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
         Ok(())
     }
 
-    pub fn translate_ident_decl(&mut self, ident:&Ident, val:Value) -> Result<(), Error>{
-        let id = self.runtime.to_mut().new_variable_name(&ident.sym);
-        let id_const = self.builder.ins().iconst(types::I64, id as i64);
+    /// step the iterator of an array pattern unless it is done.
+    /// 
+    /// return the value, undefined once the iterator is done, and whether it is done.
+    fn pattern_next(&mut self, iterator:Value, done:Value) -> Result<(Value, Value), Error>{
+        let step_block = self.builder.create_block();
+        let merge_block = self.builder.create_block();
+        self.builder.append_block_param(merge_block, JValue::TYPE);
+        self.builder.append_block_param(merge_block, types::B1);
+
+        let undefined = self.const_value(JValue::Undefined);
+        self.builder.ins().brnz(done, merge_block, &[undefined, done]);
+        self.builder.ins().jump(step_block, &[]);
+
+        self.builder.switch_to_block(step_block);
+        self.builder.seal_block(step_block);
+
+        let ins = self.builder.ins().call(self.iterator_next, &[iterator]);
+        let (value, status) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+
+        // the iterator is not closed if `next` throws
+        let ok = self.builder.ins().icmp_imm(IntCC::NotEqual, status, 2);
+        self.check_call_result(value, ok)?;
+
+        let is_done = self.builder.ins().icmp_imm(IntCC::Equal, status, 1);
+        let value = self.builder.ins().select(is_done, undefined, value);
+        self.builder.ins().jump(merge_block, &[value, is_done]);
+
+        self.builder.switch_to_block(merge_block);
+        self.builder.seal_block(merge_block);
+        let params = self.builder.block_params(merge_block);
+        Ok((params[0], params[1]))
+    }
 
-        let vmctx = self.builder.use_var(self.vmctx);
-        self.builder.ins().call(self.set_var, &[vmctx, id_const, val]);
+    /// `{a, b: c, [key]: d = 1, ...rest} = val`
+    fn translate_object_pat(&mut self, o:&ObjectPat, val:Value, kind:Option<VarDeclKind>, op:AssignOp) -> Result<(), Error>{
+        let ins = self.builder.ins().call(self.require_object_coercible, &[val]);
+        let (re, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+        self.check_call_result(re, ok)?;
+
+        // the keys already bound are excluded from the rest element
+        let mut keys = Vec::new();
+
+        for prop in &o.props{
+            match prop{
+                ObjectPatProp::KeyValue(kv) => {
+                    let key = self.translate_prop_name(&kv.key)?;
+                    keys.push(key);
+
+                    let v = self.call_helper(self.member, &[val, key])?;
+                    self.translate_pat(&kv.value, v, kind, op)?;
+                },
+                ObjectPatProp::Assign(a) => {
+                    let key = self.const_value(a.key.sym.as_ref().into());
+                    keys.push(key);
+
+                    let mut v = self.call_helper(self.member, &[val, key])?;
+
+                    if let Some(default) = &a.value{
+                        let undefined = self.const_value(JValue::Undefined);
+                        let test = self.eqeqeq(v, undefined);
+                        let test = self.to_bool(test);
+                        v = self.translate_select(test, |ctx|ctx.translate_expr(&default), |_|Ok(v))?;
+                    }
+                    self.translate_ident_decl(&a.key, v, kind)?;
+                },
+                ObjectPatProp::Rest(r) => {
+                    let slot = self.builder.create_stack_slot(StackSlotData { 
+                        kind: StackSlotKind::ExplicitSlot, 
+                        size: (keys.len() * size_of::<JValue>()) as u32
+                    });
+                    for (i, k) in keys.iter().enumerate(){
+                        self.builder.ins().stack_store(*k, slot, (i*size_of::<JValue>()) as i32);
+                    }
+                    let addr = self.builder.ins().stack_addr(types::I64, slot, 0);
+                    let count = self.builder.ins().iconst(types::I64, keys.len() as i64);
+
+                    let ins = self.builder.ins().call(self.object_rest, &[val, addr, count]);
+                    let (rest, ok) = (self.builder.inst_results(ins)[0], self.builder.inst_results(ins)[1]);
+                    self.check_call_result(rest, ok)?;
+
+                    self.translate_pat(&r.arg, rest, kind, op)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// declare the variable if `kind` is some, else assign to an existing variable
+    pub fn translate_ident_decl(&mut self, ident:&Ident, val:Value, kind:Option<VarDeclKind>) -> Result<(), Error>{
+        match kind{
+            Some(kind) => self.declare_binding(&ident.sym, val, kind),
+            None => self.assign_binding(&ident.sym, val)
+        }
+    }
+
+    /// declare again the `let` binding `name` of an enclosing scope with its value, 
+    /// the functions created before keep the previous binding.
+    fn renew_binding(&mut self, name:&str) -> Result<(), Error>{
+        let id = self.runtime.to_mut().new_variable_name(name);
+        let place = self.function_place(id);
+        if let Some(place @ Place::Record(var, b)) = place{
+            let v = self.read_place(place, id)?;
+            let vmctx = self.builder.use_var(var);
+            let index = self.builder.ins().iconst(types::I64, b.index as i64);
+            let kind = self.builder.ins().iconst(types::I8, 1);
+            self.builder.ins().call(self.declare_slot, &[vmctx, index, v, kind]);
+        }
         Ok(())
     }
 
+    /// the index of the binding `id` in the record of the context of this scope, 
+    /// a binding redeclared in the same scope keeps its index.
+    fn allocate(&mut self, id:usize) -> u32{
+        if let Some(b) = self.variables.get(&id){
+            return b.index
+        }
+        if self.script{
+            if let Some(b) = self.runtime.realm().script_bindings.names.get(&id){
+                return b.index
+            }
+        }
+        let index = self.record.get();
+        self.record.set(index + 1);
+        index
+    }
+
+    /// declare a binding in the context of this scope, 
+    /// the functions created before keep the binding it replaces.
+    pub fn declare_named(&mut self, name:&str, val:Value, kind:VarDeclKind){
+        let id = self.runtime.to_mut().new_variable_name(name);
+        let index = self.allocate(id);
+        self.variables.insert(id, ContextBinding{index, kind});
+
+        let index = self.builder.ins().iconst(types::I64, index as i64);
+        let kind = self.builder.ins().iconst(types::I8, match kind{
+            VarDeclKind::Var => 0,
+            VarDeclKind::Let => 1,
+            VarDeclKind::Const => 2,
+        });
+        if self.script{
+            let root = self.script_context();
+            let id = self.builder.ins().iconst(types::I64, id as i64);
+            self.builder.ins().call(self.declare_script_slot, &[root, index, id, val, kind]);
+        } else{
+            let vmctx = self.builder.use_var(self.vmctx);
+            self.builder.ins().call(self.declare_slot, &[vmctx, index, val, kind]);
+        }
+    }
+
+    /// return a variable name that cannot collide with any identifier
+    fn hidden_name(prefix:&str) -> String{
+        static COUNTER:AtomicUsize = AtomicUsize::new(0);
+        format!("%{}{}", prefix, COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// resolve the key of a private name from the enclosing classes
+    pub fn resolve_private(&mut self, name:&str) -> Result<Value, Error>{
+        let hidden = self.private_names.borrow().iter().rev().find_map(|m|m.get(name).cloned());
+        match hidden{
+            Some(h) => self.resolve_binding(&h),
+            None => Err(Error::SyntaxError(format!("Private field '#{}' must be declared in an enclosing class", name)))
+        }
+    }
+
+    pub fn translate_prop_name(&mut self, key:&PropName) -> Result<Value, Error>{
+        match key{
+            PropName::Ident(i) => Ok(self.const_value(i.sym.as_ref().into())),
+            PropName::Str(s) => Ok(self.const_value(s.value.as_ref().into())),
+            PropName::Num(n) => Ok(self.const_value(JValue::Number(n.value).to_string().as_str().into())),
+            PropName::BigInt(b) => Ok(self.const_value(b.value.to_string().as_str().into())),
+            PropName::Computed(c) => self.translate_expr(&c.expr),
+        }
+    }
+
+    /// translate a property name evaluated after the values held by the caller
+    fn translate_key_operand(&mut self, key:&PropName, yield_position:bool) -> Result<Value, Error>{
+        self.yield_position = yield_position && matches!(key, PropName::Computed(_));
+        self.translate_prop_name(key)
+    }
+
     pub fn translate_prop(&mut self, expr:&Expr, computed:bool) -> Result<Value, Error>{
         if computed{
             self.translate_expr(expr)
         } else{
             match expr{
                 Expr::Ident(i) => Ok(self.const_value(i.sym.as_ref().into())),
+                Expr::PrivateName(p) => self.resolve_private(&p.id.sym),
                 _ => return Err(Error::Unimplemented("non computed property expression."))
             }
         }
     }
 
-    /// return *mut u8, i64, bool
-    pub fn translate_args(&mut self, v:&[ExprOrSpread]) -> Result<(Value, Value, Value), Error>{
+    /// call a runtime helper returning its completion value and whether it completed normally,
+    /// the value is thrown if the helper threw.
+    pub fn call_helper(&mut self, helper:FuncRef, args:&[Value]) -> Result<Value, Error>{
+        let inst = self.builder.ins().call(helper, args);
+        let v = self.builder.inst_results(inst);
+        let (re, ok) = (v[0], v[1]);
 
-        let mut spread = false;
+        self.check_call_result(re, ok)?;
+        Ok(re)
+    }
 
-        let slot = self.builder.create_stack_slot(StackSlotData { 
-            kind: StackSlotKind::ExplicitSlot, 
-            size: (v.len() * size_of::<JValue>()) as u32,
-        });
+    /// throw `re` if the call did not complete normally
+    pub fn check_call_result(&mut self, re:Value, ok:Value) -> Result<(), Error>{
+        let throw_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
 
-        let mut i = 0;
-        for e in v{
-            if e.spread.is_some(){
-                spread = true;
+        self.builder.append_block_param(throw_block, JValue::TYPE);
+
+        self.builder.ins().brz(ok, throw_block, &[re]);
+        self.builder.ins().jump(exit_block, &[]);
+
+        self.builder.seal_block(throw_block);
+        self.builder.switch_to_block(throw_block);
+
+        let throw_value = self.builder.block_params(throw_block)[0];
+        self.emit_throw(throw_value)?;
+
+        self.builder.seal_block(exit_block);
+        self.builder.switch_to_block(exit_block);
+        Ok(())
+    }
+
+    /// translate the arguments of a call, they are stored once all of them are evaluated.
+    /// 
+    /// arguments containing a spread are collected in an array passed as the spread last argument.
+    /// 
+    /// return *mut u8, i64, bool
+    pub fn translate_args(&mut self, v:&[ExprOrSpread], yield_position:bool) -> Result<(Value, Value, Value), Error>{
+        let spread = v.iter().any(|e|e.spread.is_some());
+
+        let mut held = Vec::new();
+        if spread{
+            let elems = v.iter().map(Some).collect::<Vec<_>>();
+            let array = self.translate_spread_array(&elems, yield_position)?;
+            held.push(self.hold(array));
+        } else{
+            for e in v{
+                let v = self.translate_operand(&e.expr, yield_position)?;
+                held.push(self.hold(v));
             }
+        }
 
-            let v = self.translate_expr(&e.expr)?;
+        let slot = self.builder.create_stack_slot(StackSlotData { 
+            kind: StackSlotKind::ExplicitSlot, 
+            size: (held.len() * size_of::<JValue>()) as u32,
+        });
+        let len = held.len();
+        for (i, var) in held.into_iter().enumerate().rev(){
+            let v = self.release(var);
             self.builder.ins().stack_store(v, slot, (i*size_of::<JValue>()) as i32);
-
-            i += 1;
         }
         Ok((
             self.builder.ins().stack_addr(types::I64, slot, 0), 
-            self.builder.ins().iconst(types::I64, v.len() as i64),
-            self.builder.ins().bconst(types::B8, spread),
+            self.builder.ins().iconst(types::I64, len as i64),
+            self.builder.ins().iconst(types::I8, spread as i64),
         ))
     }
+
+    /// build an array from elements containing spreads, every element is appended once evaluated
+    fn translate_spread_array(&mut self, elems:&[Option<&ExprOrSpread>], yield_position:bool) -> Result<Value, Error>{
+        // no element is read from the dangling pointer
+        let argv = self.builder.ins().iconst(types::I64, std::mem::align_of::<JValue>() as i64);
+        let argc = self.builder.ins().iconst(types::I64, 0);
+        let spread = self.builder.ins().iconst(types::I8, 0);
+        let array = self.call_helper(self.array_new, &[argv, argc, spread])?;
+        let held = self.hold(array);
+
+        for e in elems{
+            let v = match e{
+                Some(e) => self.translate_operand(&e.expr, yield_position)?,
+                None => self.const_value(JValue::Undefined)
+            };
+            let array = self.builder.use_var(held);
+            if matches!(e, Some(e) if e.spread.is_some()){
+                self.call_helper(self.array_spread, &[array, v])?;
+            } else{
+                self.builder.ins().call(self.array_push, &[array, v]);
+            }
+        }
+        Ok(self.release(held))
+    }
 }
 
 impl<'a> Drop for BuilderContext<'a>{
     fn drop(&mut self) {
-        if self.parent.is_none(){
+        // the scopes of a function share its builder
+        if self.is_func{
             unsafe{
-                std::ptr::drop_in_place(self.builder);
-                std::ptr::drop_in_place(self.func_ctx);
+                drop(Box::from_raw(self.builder as *mut FunctionBuilder));
+                drop(Box::from_raw(self.func_ctx));
+            }
+        }
+    }
+}
+
+/// return true if a member or call chain contains an optional element
+fn has_opt_chain(e:&Expr) -> bool{
+    match e{
+        Expr::OptChain(_) => true,
+        Expr::Member(m) => match &m.obj{
+            ExprOrSuper::Expr(e) => has_opt_chain(e),
+            ExprOrSuper::Super(_) => false
+        },
+        Expr::Call(c) => match &c.callee{
+            ExprOrSuper::Expr(e) => has_opt_chain(e),
+            ExprOrSuper::Super(_) => false
+        },
+        _ => false
+    }
+}
+
+pub(crate) fn is_private(prop:&Expr) -> bool{
+    match prop{
+        Expr::PrivateName(_) => true,
+        _ => false
+    }
+}
+
+/// the name of `obj.name`, such accesses go through an inline cache
+fn cached_name(m:&MemberExpr) -> Option<&str>{
+    match (&m.obj, m.computed, m.prop.as_ref()){
+        (ExprOrSuper::Expr(_), false, Expr::Ident(i)) => Some(&i.sym),
+        _ => None
+    }
+}
+
+/// 0 for method, 1 for getter, 2 for setter
+pub(crate) fn method_kind(kind:MethodKind) -> i8{
+    match kind{
+        MethodKind::Method => 0,
+        MethodKind::Getter => 1,
+        MethodKind::Setter => 2,
+    }
+}
+
+/// the name of a non computed property key
+pub(crate) fn prop_name_str(key:&PropName) -> Option<&str>{
+    match key{
+        PropName::Ident(i) => Some(&i.sym),
+        PropName::Str(s) => Some(&s.value),
+        _ => None
+    }
+}
+
+/// the frozen strings array passed to the tag of a tagged template, 
+/// with the raw strings as the `raw` property.
+/// 
+/// invalid escape sequences are undefined in the cooked strings.
+pub(crate) fn template_object(t:&Tpl) -> JValue{
+    let cooked:Vec<JValue> = t.quasis.iter().map(|q|match &q.cooked{
+        Some(c) => c.value.as_ref().into(),
+        None => JValue::Undefined
+    }).collect();
+    let raw:Vec<JValue> = t.quasis.iter().map(|q|q.raw.value.as_ref().into()).collect();
+
+    let raw = Array::new(JObject::new(), &raw);
+    raw.object().unwrap().freeze();
+
+    let strings = Array::new(JObject::new(), &cooked);
+    let obj = strings.object().unwrap();
+    obj.define_own("raw", raw);
+    obj.freeze();
+
+    strings.keep_alive(true);
+    strings
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    fn run(mode:ExecutionMode, script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    #[test]
+    fn finalizers_run_once_per_completion(){
+        let cases = [
+            ("let r = ''; try { r += 'a'; throw 1 } catch (e) { r += 'c' } finally { r += 'f' } r", "acf"),
+            ("let r = ''; function f(){ try { return 'x' } finally { r += 'f' } } f() + r", "xf"),
+            ("let r = ''; function f(){ try { try { throw 'e' } finally { r += '1' } } finally { r += '2' } } try { f() } catch (e) { r += e } r", "12e"),
+            ("let r = ''; for (let i = 0; i < 3; i++){ try { if (i == 1) continue; if (i == 2) break; r += i } finally { r += 'f' } } r", "0fff"),
+            ("function f(){ try { return 1 } finally { return 2 } } f()", "2"),
+            ("function f(){ for (;;) { try { return 1 } finally { break } } return 3 } f()", "3"),
+            ("let r = ''; function f(){ try { throw 1 } catch (e) { throw e + 1 } finally { r += 'f' } } try { f() } catch (e) { r += e } r", "f2"),
+            ("let r = ''; out: for (const a of [1, 2]) { for (const b of [3, 4]) { try { if (b == 4) continue out; r += a + '' + b } finally { r += '.' } } } r", "13..23.."),
+            ("let r = ''; { let x = 'o'; try { let x = 'i'; throw 0 } catch (e) {} finally { r += x } } r", "o"),
+            ("let r; function* g(){ try { yield 1; yield 2 } finally { r = 'f'; yield 3 } } let it = g(); '' + it.next().value + it.return(9).value + it.next().value + it.next().done + r", "139truef"),
+        ];
+        for (script, expected) in cases{
+            assert_eq!(run(ExecutionMode::Jit, script), expected, "{}", script);
+            assert_eq!(run(ExecutionMode::Bytecode, script), expected, "{}", script);
+        }
+    }
+
+    const MODES:[ExecutionMode; 4] = [ExecutionMode::Jit, ExecutionMode::Bytecode, ExecutionMode::Tiered, ExecutionMode::Interpreter];
+
+    fn check(cases:&[(&str, &str)]){
+        for mode in MODES{
+            for (script, expected) in cases{
+                assert_eq!(run(mode, script), *expected, "{:?} {}", mode, script);
+            }
+        }
+    }
+
+    #[test]
+    fn script_statements(){
+        check(&[
+            ("let a = 1; if (a) { a = 2 } else { a = 3 } a", "2"),
+            ("let a = 0; if (a) a = 2; else if (a === 0) { let b = 7; a = b } else a = 9; a", "7"),
+            ("function f(a){ if (a) { return 2 } else { return 3 } } f(1) * 10 + f(0)", "23"),
+            ("let a = 2; switch (a) { case 2: a += 10; case 3: a += 100; break; default: a = 0 } a", "112"),
+            ("var n = 0; do { n++ } while (n < 3); label: { n += 1; break label; n = 0 } n", "4"),
+            ("let r = ''; for (const k in {a:1, b:2}) r += k; try { throw 1 } catch { r += 'c' } r", "abc"),
+        ]);
+    }
+
+    #[test]
+    fn functions_and_closures(){
+        let cases = [
+            ("function add(a, b = 2){ return a + b } add(1) * 10 + add(1, 1)", "32"),
+            ("function counter(){ let c = 0; return { inc: () => ++c, get: () => c } } let c = counter(); c.inc(); c.inc(); c.get()", "2"),
+            ("hoisted(); function hoisted(){ return 1 } typeof hoisted", "function"),
+            ("function outer(){ function inner(x){ return x * 2 } return inner(inner(3)) } outer()", "12"),
+            ("function sum(...xs){ let s = 0; for (const x of xs) s += x; return s } sum(1, 2, 3) + sum(...[4, 5])", "15"),
+            ("let f = function fact(n){ return n <= 1 ? 1 : n * fact(n - 1) }; f(5)", "120"),
+            ("function g(){ let f = function fact(n){ return n <= 1 ? 1 : n * fact(n - 1) }; return f(4) } g()", "24"),
+            ("let fact = 3; let f = function fact(){ return typeof fact }; f() + fact", "function3"),
+            ("let f = function me(){ me = 1 }; let r; try { f() } catch(e) { r = e instanceof TypeError } r", "true"),
+        ];
+        check(&cases);
+    }
+
+    #[test]
+    fn classes(){
+        let cases = [
+            ("class A { #p = 1; static s = 2; constructor(x){ this.x = x } get double(){ return this.x * 2 } set double(v){ this.x = v / 2 } static make(){ return new A(3) } } let a = A.make(); a.double = 16; a.x + a.double + A.s", "26"),
+            ("class A { m(){ return 'a' } } class B extends A { m(){ return super.m() + 'b' } } let b = new B(); b.m() + (b instanceof A)", "abtrue"),
+            ("class F { constructor(v){ this.v = v } } class G extends F { constructor(){ super(7); this.w = this.v + 1 } } new G().w", "8"),
+            ("class C { static #count = 0; static { C.#count = 4 } #priv(){ return 1 } static total(){ return C.#count + new C().#priv() } } C.total()", "5"),
+            ("class P { #x; static has(o){ return #x in o } } '' + P.has(new P()) + ',' + P.has({})", "true,false"),
+            ("class Q { #y = 1; static read(o){ return o.#y } } let r; try { Q.read({}) } catch(e) { r = e instanceof TypeError } r", "true"),
+            ("class N { static name2 = N.name } N.name2 + typeof N", "Nfunction"),
+            ("let E = class Inner { who(){ return Inner.name } }; new E().who()", "Inner"),
+            ("class H { get [('k' + 1)](){ return 9 } } new H().k1", "9"),
+        ];
+        // the bytecode compiler does not lower classes
+        for mode in [ExecutionMode::Jit, ExecutionMode::Tiered, ExecutionMode::Interpreter]{
+            for (script, expected) in cases{
+                assert_eq!(run(mode, script), expected, "{:?} {}", mode, script);
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unary_update_and_binary_operators(){
+        check(&[
+            ("let x = 5, y; '' + typeof x + typeof y + (-x) + (+'3') + (!x) + (~x) + (void x)", "numberundefined-53false-6undefined"),
+            ("let o = { a:1 }; '' + delete o.a + ('a' in o) + (2 ** 10) + (7 % 3) + (-1 >> 1) + (-1 >>> 28) + (5 & 3) + (5 | 3) + (5 ^ 3) + (1 << 4)", "truefalse10241-11517616"),
+            ("let i = 1; let a = i++ + ++i; let o = { n:1 }; o.n--; --o.n; '' + a + i + (o.n + 1)", "430"),
+            ("'' + (1 + '2') + (1 + 2 + '3') + ('3' * '4') + (null + 1) + (true + true)", "12331212"),
+            ("'' + (1 == '1') + (null == void 0) + (null == 0) + ('b' > 'a') + ('10' < '9') + (10 < 9) + ((0 / 0) === (0 / 0))", "truetruefalsetruetruefalsefalse"),
+            ("let v = { valueOf(){ return 4 }, toString(){ return 's' } }; '' + (v + 1) + (v * 2) + `${v}` + (v > 3)", "58strue"),
+            ("'' + ([] instanceof Array) + ({} instanceof Array) + (null == false) + (0 === -0) + (1 != '1')", "truefalsefalsetruefalse"),
+        ]);
+    }
+
+    #[test]
+    fn optional_chains_and_short_circuits(){
+        check(&[
+            ("let o = { x:1 }, a = { b:{ c:1 } }; '' + o?.x + a?.b?.c + a.z?.c + o?.[0] + a.f?.()", "11undefinedundefinedundefined"),
+            ("let g = null; let c = 0; g?.m(c++); '' + c + (g?.a.b.c)", "0undefined"),
+            ("let n = null; '' + (n ?? 5) + (0 ?? 1) + ('' || 'a') + (1 && 'b')", "50ab"),
+            ("let a = null, b = 1, c = 0; a ??= 3; b &&= 1; c ||= 0; '' + a + b + c", "310"),
+            ("let o = { p:0 }, n = 0; o.p ??= 5; o.q ??= 6; o.p ||= (n++, 2); o['p'] &&= (n++, 3); '' + o.p + o.q + n", "362"),
+            ("let n = 0; function f(){ n++; return true } false && f(); true || f(); 1 ?? f(); let o = null; o?.[f()]; f(); n", "1"),
+        ]);
+    }
+
+    #[test]
+    fn templates_and_tags(){
+        check(&[
+            (r"function tag(s, ...v){ return s.raw[0] + v[0] + s[1] } tag`a\n${2}c`", r"a\n2c"),
+            (r"String.raw`a\n${1}b`", r"a\n1b"),
+            ("function t(s){ return s } function f(){ return t`x` } f() === f()", "true"),
+            ("function t(s){ return s } let s = t`a${1}b`; '' + Object.isFrozen(s) + Object.isFrozen(s.raw) + s.length", "truetrue2"),
+            ("function t(s, a, b){ return a + b } t`${1}${2}`", "3"),
+            ("let o = { toString(){ return 'o' } }; `${o}-${[1, 2]}-${null}-${1 + 1}`", "o-1,2-null-2"),
+            ("let o = { [Symbol.toPrimitive](h){ return h } }; `${o}` + (o + '')", "stringdefault"),
+        ]);
+    }
+
+    #[test]
+    fn destructuring(){
+        check(&[
+            ("let { a, b:{ c = 3 } = {}, ...rest } = { a:1, d:4, e:5 }; '' + a + c + Object.keys(rest).length", "132"),
+            ("let [a = 1, [b] = [2], ...c] = [void 0, void 0, 3, 4]; '' + a + b + c.length", "122"),
+            ("let k = 'dyn'; let { [k]:d, [k + 2]:e = 'e' } = { dyn:'D' }; d + e", "De"),
+            ("function f({ x, y = 2 }, [z, ...w]){ return x + y + z + w.length } f({ x:1 }, [2, 3])", "6"),
+            ("let a = 1, b = 2; [a, b] = [b, a]; let o = {}; ({ p:o.q, r = 5 } = { p:7 }); '' + a + b + r + o.q", "2157"),
+            ("let r = ''; for (const [k, { v }] of [['a', { v:1 }], ['b', { v:2 }]]) r += k + v; for (let [x] of [[3]]) r += x; r", "a1b23"),
+            ("try { throw { code:4, msg:'m' } } catch ({ code, msg }) { msg + code }", "m4"),
+        ]);
+    }
+
+    #[test]
+    fn counter_operators_match_numbers(){
+        let cases = [
+            ("function f(){ let s = 0; for (let i = 0; i < 10; i++){ s += i * 3 - 1 } return s } f()", "125"),
+            ("function f(){ let r = 0; for (let i = 46340; i < 46342; i++){ r = i * i } return r } f()", "2147488281"),
+            ("function f(){ let r = 0; for (let i = -2147483647; i > -2147483650; i--){ r = i - 2 } return r } f()", "-2147483651"),
+            ("function f(){ let r = 0; for (let i = 0; i < 1; i++){ r = 1 / (i * -1) } return r } f()", "-Infinity"),
+            ("function f(){ let r = 0; for (let i = 0; i < 1; i++){ r = 1 / (-0 + i) } return r } f()", "Infinity"),
+            ("function f(){ let n = 0; for (let i = 0; i < 3.5; i++) n++; return n } f()", "4"),
+            ("function f(){ let n = 0; for (let i = 0; i <= '3'; i++) n++; return n } f()", "4"),
+            ("function f(){ let n = 0; for (let i = 0; i < 5; i++){ if (i == '2' || 3 === i) n++ } return n } f()", "2"),
+            ("function f(){ let n = 0, z = 0 / 0; for (let i = 0; i < z; i++) n++; return n } f()", "0"),
+            ("function f(){ let n = 0; for (let i = 2147483646; i < 2147483649; i++) n++; return n } f()", "3"),
+            ("function f(){ let r = ''; for (let i = 0; i < 3; i++) r = r + (i + 'a'); return r } f()", "0a1a2a"),
+        ];
+        for (script, expected) in cases{
+            assert_eq!(run(ExecutionMode::Jit, script), expected, "{}", script);
+            assert_eq!(run(ExecutionMode::Interpreter, script), expected, "{}", script);
+        }
+    }
+
+    #[test]
+    fn uncaught_throw_reaches_the_caller(){
+        assert_eq!(run(ExecutionMode::Jit, "function f(){ throw new TypeError('x') } f()"), "throw TypeError: x");
+        assert_eq!(run(ExecutionMode::Jit, "function f(){ try { throw 1 } finally { } } try { f() } catch (e) { e + 1 }"), "2");
+    }
+}
@@ -0,0 +1,106 @@
+//! inline caches of named property accesses.
+//!
+//! a cache records the shapes seen at a `obj.name` site with the slot of `name`,
+//! compiled code compares the shape of the object with each entry and reads or
+//! writes the slot directly on a hit. a miss calls the runtime which performs
+//! the full lookup and records the shape if the property is an own data property.
+//! the attributes are part of a shape, a shape cached by a store has the property writable.
+
+use crate::builtins::shape::Shape;
+use crate::value::JValue;
+
+/// the number of shapes a site holds, older entries are replaced when it is full
+pub(crate) const ENTRIES:usize = 4;
+
+#[repr(C)]
+pub(crate) struct InlineCache{
+    shapes:[*const Shape; ENTRIES],
+    slots:[u32; ENTRIES],
+    /// the entry replaced by the next miss
+    next:u32,
+    name:String,
+}
+
+impl InlineCache{
+    /// the offset of the shape of entry `i`
+    pub(crate) fn shape_offset(i:usize) -> i32{
+        (std::mem::offset_of!(InlineCache, shapes) + i * std::mem::size_of::<*const Shape>()) as i32
+    }
+
+    /// the offset of the slot of entry `i`
+    pub(crate) fn slot_offset(i:usize) -> i32{
+        (std::mem::offset_of!(InlineCache, slots) + i * std::mem::size_of::<u32>()) as i32
+    }
+
+    /// a cache for the property `name`, it lives as long as the compiled code
+    pub(crate) fn new(name:&str) -> *mut InlineCache{
+        Box::into_raw(Box::new(InlineCache{
+            shapes:[std::ptr::null(); ENTRIES],
+            slots:[0; ENTRIES],
+            next:0,
+            name:name.to_string()
+        }))
+    }
+
+    /// record the slot of the property if `obj` holds it as an own data property,
+    /// a store also requires the property to be writable.
+    fn update(&mut self, obj:JValue, store:bool){
+        let o = match obj.object(){
+            Some(o) => o,
+            None => return
+        };
+        let shape = o.shape();
+        if unsafe{&*shape}.is_dictionary() || self.shapes.contains(&(shape as *const Shape)){
+            return
+        }
+        if let Some(p) = o.own_property(&self.name){
+            if p.attributes.is_accessor() || (store && !p.attributes.writable()){
+                return
+            }
+            let i = self.next as usize;
+            self.shapes[i] = shape;
+            self.slots[i] = p.slot;
+            self.next = ((i + 1) % ENTRIES) as u32;
+        }
+    }
+
+    /// `obj.name` on a miss
+    pub fn get(cache:&mut InlineCache, obj:JValue) -> JValue{
+        let v = obj.member_str(&cache.name);
+        cache.update(obj, false);
+        v
+    }
+
+    /// `obj.name = value` on a miss
+    pub fn set(cache:&mut InlineCache, obj:JValue, value:JValue){
+        obj.set_member(cache.name.as_str().into(), value);
+        cache.update(obj, true);
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    fn run(script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(ExecutionMode::Jit);
+        runtime.clone().exec("test.js", script).unwrap().value.to_string()
+    }
+
+    #[test]
+    fn sites_follow_shape_changes(){
+        // more shapes than entries at one site
+        assert_eq!(run("function f(o){ return o.x } let r = 0; let os = [{x:1}, {y:0, x:2}, {x:3, z:1}, {x:4}, {w:1, x:5}, {v:1, x:6}]; for (let i = 0; i < 12; i++) r += f(os[i % 6]); r"), "42");
+        assert_eq!(run("function g(o){ return o.x } let a = {x:1}, b = {x:2}; g(a); g(a); let r = g(b); b.y = 1; r + g(b) + g(a)"), "5");
+        assert_eq!(run("function f(o){ return o.x } let o = {x:1}; f(o); delete o.x; f(o)"), "undefined");
+    }
+
+    #[test]
+    fn cached_stores_respect_attributes(){
+        assert_eq!(run("function s(o, v){ o.x = v } let o = {x:0}; s(o, 1); s(o, 2); Object.freeze(o); s(o, 3); o.x"), "2");
+        assert_eq!(run("function s(o, v){ o.x = v } let o = {x:0}; s(o, 1); s(o, 2); Object.defineProperty(o, 'x', { writable:false }); s(o, 3); o.x"), "2");
+        assert_eq!(run("function s(o, v){ o.x = v } let o = {x:0}; s(o, 1); let r = 0; Object.defineProperty(o, 'x', { set(v){ r = v } }); s(o, 5); r"), "5");
+        assert_eq!(run("function g(o){ return o.x } let o = {x:1}; g(o); g(o); Object.defineProperty(o, 'x', { get(){ return 9 } }); g(o)"), "9");
+    }
+}
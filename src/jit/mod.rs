@@ -1 +1,3 @@
-pub(crate) mod builder;
\ No newline at end of file
+pub mod builder;
+pub(crate) mod inline_cache;
+pub(crate) mod scope;
@@ -0,0 +1,231 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+
+use cranelift::prelude::Variable;
+use swc_ecma_ast::*;
+
+use crate::bytecode::scope::{assigned_names, captured_names_in_function, lexical_names, pat_names, var_names};
+
+/// the first cranelift variable free for bindings,
+/// the lower ones hold the context, `this` and the completion value.
+const FIRST_SLOT:u32 = 3;
+
+/// the bindings of a function resolved before its body is translated.
+///
+/// a binding that no nested function or class refers to is kept in a cranelift variable,
+/// the others are declared in the `VmContext` of the call so they can be captured.
+pub(crate) struct FunctionScope{
+    /// the names referenced inside nested functions and classes
+    captured:HashSet<String>,
+    /// false for scripts, generators and async functions.
+    /// script bindings are global and the body of a generator returns on every `yield`,
+    /// their bindings must outlive the compiled frame.
+    slots:bool,
+    next:Cell<u32>,
+}
+
+/// a binding kept in a cranelift variable
+#[derive(Clone, Copy)]
+pub(crate) struct Slot{
+    pub(crate) var:Variable,
+    pub(crate) kind:VarDeclKind,
+    /// true once the declaration is translated if the declaration runs before
+    /// any code following it in its scope, `let` and `const` are not checked afterwards.
+    pub(crate) initialized:bool,
+    /// false for the declarations of switch cases, a case may be entered past them.
+    pub(crate) dominates:bool,
+    /// true if `var` holds the integer value of a loop counter instead of a `JValue`
+    pub(crate) counter:bool,
+}
+
+/// a binding declared in the record of a context, addressed by its index
+#[derive(Clone, Copy)]
+pub(crate) struct ContextBinding{
+    pub(crate) index:u32,
+    pub(crate) kind:VarDeclKind,
+}
+
+/// the top level bindings of the scripts compiled in a realm, 
+/// a script resolves the bindings of the previous ones by their index in the script context.
+#[derive(Default)]
+pub(crate) struct ScriptBindings{
+    pub(crate) names:HashMap<usize, ContextBinding>,
+    /// the length of the record of the script context, hidden bindings included
+    pub(crate) len:u32,
+}
+
+/// the binding of a loop `for (let i = start; test; i++)` only written by its update, `i--` included.
+/// 
+/// the counter stays an I64 integer, it is converted to a number when read.
+/// `+`, `-`, `*` and the comparisons on it are computed on integers while its value is an int32.
+pub(crate) struct Counter<'a>{
+    pub(crate) name:&'a str,
+    pub(crate) start:i32,
+}
+
+impl FunctionScope{
+    pub(crate) fn script() -> Self{
+        Self{
+            captured:HashSet::new(),
+            slots:false,
+            next:Cell::new(FIRST_SLOT),
+        }
+    }
+
+    pub(crate) fn function(params:&[&Pat], stmts:&[Stmt], expr:Option<&Expr>, slots:bool) -> Self{
+        Self{
+            captured:captured_names_in_function(params, stmts, expr),
+            slots,
+            next:Cell::new(FIRST_SLOT),
+        }
+    }
+
+    /// true if the binding `name` is kept in a cranelift variable
+    pub(crate) fn is_slot(&self, name:&str) -> bool{
+        self.slots && !self.captured.contains(name)
+    }
+
+    pub(crate) fn new_variable(&self) -> Variable{
+        let v = self.next.get();
+        self.next.set(v + 1);
+        Variable::with_u32(v)
+    }
+}
+
+/// the bindings of a function body or a script: the parameters, `var` declarations 
+/// and the lexical declarations of its top level.
+pub(crate) fn function_names<'a, I:IntoIterator<Item = &'a Stmt> + Clone>(params:&[&Pat], stmts:I) -> Vec<(String, VarDeclKind)>{
+    let mut names = Vec::new();
+    for p in params{
+        pat_names(p, &mut names);
+    }
+    for n in var_names(stmts.clone()){
+        if !names.contains(&n){
+            names.push(n);
+        }
+    }
+
+    let mut names:Vec<(String, VarDeclKind)> = names.into_iter().map(|n|(n, VarDeclKind::Var)).collect();
+    for (n, kind) in lexical_names(stmts, true){
+        if !names.iter().any(|(m, _)|*m == n){
+            names.push((n, kind));
+        }
+    }
+    names
+}
+
+/// the bindings of a block
+pub(crate) fn block_names<'a, I:IntoIterator<Item = &'a Stmt>>(stmts:I) -> Vec<(String, VarDeclKind)>{
+    lexical_names(stmts, false)
+}
+
+/// the bindings of the parameter of a catch clause
+pub(crate) fn catch_names(param:&Pat) -> Vec<(String, VarDeclKind)>{
+    let mut names = Vec::new();
+    pat_names(param, &mut names);
+    names.into_iter().map(|n|(n, VarDeclKind::Let)).collect()
+}
+
+/// the names declared by a variable declaration
+pub(crate) fn decl_names(decl:&VarDecl) -> Vec<String>{
+    let mut names = Vec::new();
+    for d in &decl.decls{
+        pat_names(&d.name, &mut names);
+    }
+    names
+}
+
+/// recognize a loop with an integer counter
+pub(crate) fn counter_loop(f:&ForStmt) -> Option<Counter>{
+    let decl = match &f.init{
+        Some(VarDeclOrExpr::VarDecl(d)) if d.kind == VarDeclKind::Let && d.decls.len() == 1 => &d.decls[0],
+        _ => return None
+    };
+    let name = match &decl.name{
+        Pat::Ident(i) => i.id.sym.as_ref(),
+        _ => return None
+    };
+    let start = match decl.init.as_deref(){
+        Some(Expr::Lit(Lit::Num(n))) if n.value.fract() == 0.0 && n.value >= i32::MIN as f64 && n.value <= i32::MAX as f64 => n.value as i32,
+        _ => return None
+    };
+    match f.update.as_deref(){
+        Some(Expr::Update(u)) if matches!(u.arg.as_ref(), Expr::Ident(i) if i.sym.as_ref() == name) => {},
+        _ => return None
+    }
+
+    let test:Vec<&Expr> = f.test.iter().map(|e|e.as_ref()).collect();
+    if assigned_names([f.body.as_ref()], &test).contains(name){
+        return None
+    }
+    Some(Counter{name, start})
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::error::Error;
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    fn run(script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(ExecutionMode::Jit);
+        match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    #[test]
+    fn block_bindings_shadow(){
+        assert_eq!(run("let x = 1; { let x = 2; } x"), "1");
+        assert_eq!(run("let x = 1; { let x = 2; { let x = 3; } } x"), "1");
+        assert_eq!(run("let v = 9; for (let v of [1]){} v"), "9");
+        assert_eq!(run("let r = 0; { let y = 5; try { { let y = 6; throw y; } } catch(e) { r = e + y; } } r"), "11");
+        // captured bindings live in the context
+        assert_eq!(run("let g; function f(){ let x = 1; { let x = 2; g = () => x; } return x + g(); } f()"), "3");
+        assert_eq!(run("function* g(){ let a = 1; { let a = 2; yield a; yield a; } yield a; } let it = g(); it.next().value * 100 + it.next().value * 10 + it.next().value"), "221");
+    }
+
+    #[test]
+    fn loop_bindings_are_per_iteration(){
+        assert_eq!(run("let fs = []; for (let i = 0; i < 3; i++){ fs[i] = () => i; } fs[0]() + fs[1]() + fs[2]()"), "3");
+        assert_eq!(run("function f(){ let fs = []; for (let i = 0; i < 3; i++){ fs[i] = () => i; } return fs[0]() * 100 + fs[1]() * 10 + fs[2](); } f()"), "12");
+        assert_eq!(run("let fs = [], n = 0; for (const v of [1, 2, 3]){ fs[n++] = () => v; } fs[0]() + fs[2]()"), "4");
+        assert_eq!(run("function* g(){ for (let i = 0; i < 3; i++){ let k = i * 2; yield () => k; } } let t = 0; for (const f of g()) t += f(); t"), "6");
+    }
+
+    #[test]
+    fn bindings_before_declaration_throw(){
+        assert_eq!(run("try { x; let x = 1; } catch(e) { e instanceof ReferenceError }"), "true");
+        assert_eq!(run("let r; { try { y; } catch(e) { r = e instanceof ReferenceError; } let y = 1; } r"), "true");
+        assert_eq!(run("function f(){ let g = () => z; try { g(); } catch(e) { return e instanceof ReferenceError; } let z = 1; } f()"), "true");
+    }
+
+    #[test]
+    fn script_bindings_outlive_the_script(){
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(ExecutionMode::Jit);
+        let exec = |script:&str| match runtime.clone().exec("test.js", script){
+            Ok(re) => re.value.to_string(),
+            Err(Error::Value(v)) => format!("throw {}", v.to_string()),
+            Err(e) => panic!("{}", e)
+        };
+        assert_eq!(exec("let a = 1; const b = 2; var c = 3; function f(){ return a + b + c; } f()"), "6");
+        assert_eq!(exec("a = 5; c = 10; f()"), "17");
+        // a `var` redeclared by a later script keeps its value
+        assert_eq!(exec("var c; c"), "10");
+        assert_eq!(exec("try { b = 3; } catch(e) { e instanceof TypeError }"), "true");
+        // a function resolves the bindings declared by a later script
+        assert_eq!(exec("function g(){ return y; } try { g(); } catch(e) { e instanceof ReferenceError }"), "true");
+        assert_eq!(exec("let y = 9; g()"), "9");
+    }
+
+    #[test]
+    fn closures_share_captured_bindings(){
+        assert_eq!(run("function outer(){ let a = 1; function mid(){ let b = 2; return () => a + b++; } return mid(); } let h = outer(); h() + h()"), "7");
+        assert_eq!(run("function mk(){ let n = 0; return { inc(){ return ++n; }, get(){ return n; } }; } let o = mk(); o.inc(); o.inc(); o.get()"), "2");
+        assert_eq!(run("function* g(){ let a = 1; const f = () => a++; yield f(); yield f(); yield a; } let t = 0; for (const v of g()) t = t * 10 + v; t"), "123");
+        assert_eq!(run("function f(){ try { throw 1; } catch(e) { return () => e; } } f()()"), "1");
+    }
+}
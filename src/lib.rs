@@ -1,11 +1,13 @@
 #![feature(unboxed_closures)]
 #![feature(fn_traits)]
+#![feature(tuple_trait)]
 
 use std::{marker, borrow::Borrow};
 use std::ops::Deref;
 
 mod jit;
-//mod interpretor;
+mod interpretor;
+mod bytecode;
 mod operator;
 mod value;
 mod builtins;
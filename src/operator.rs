@@ -1,8 +1,19 @@
-use std::panic::{catch_unwind, panic_any};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Rem, Shl, Shr, Sub};
+use std::panic::{catch_unwind, panic_any, AssertUnwindSafe};
+use std::sync::Arc;
 
 use crate::builtins::object::JObjectInnerEnum;
-use crate::value::JValue;
+use crate::value::{JValue, JValueEnum};
 use crate::builtins::array::Array;
+use crate::builtins::object::JObject;
+use crate::builtins::property::PropertyKey;
+use crate::builtins::{Symbol, Function, Error};
+use crate::builtins::symbol;
+use crate::vm::VmContext;
+use crate::jit::inline_cache::InlineCache;
+use crate::utils::ToMutable;
 
 
 
@@ -10,23 +21,401 @@ pub fn throw(value:JValue) -> !{
     panic_any(value)
 }
 
+/// run `f`, catching any value thrown by `throw`
+pub fn catch<F>(f:F) -> (JValue, bool) where F:FnOnce() -> JValue{
+    match catch_unwind(AssertUnwindSafe(f)){
+        Ok(v) => (v, true),
+        Err(err) => {
+            if let Some(v) = err.downcast_ref::<JValue>(){
+                (*v, false)
+            } else{
+                (JValue::Undefined, false)
+            }
+        }
+    }
+}
+
+/// collect the arguments passed from jit code, spreading the last argument if `spread`
+pub unsafe fn collect_args(argv:*const JValue, argc:i64, spread:bool) -> Vec<JValue>{
+    if argc == 0{
+        return Vec::new()
+    }
+    let args = std::slice::from_raw_parts(argv, argc as usize);
+    if spread{
+        let mut v = args[..args.len()-1].to_vec();
+        v.extend(IteratorCollect(args[args.len()-1]));
+        v
+    } else{
+        args.to_vec()
+    }
+}
+
 pub fn check_throw(value:JValue, ok:bool){
     if !ok{
         throw(value)
     }
 }
 
-pub fn Await(val:JValue) -> JValue{
-    todo!()
-}
-
+/// collect the values of an iterable, used by spread and destructuring
 pub fn IteratorCollect(value:JValue) -> Vec<JValue>{
+    // arrays without a user defined iterator are copied directly
     if let Some(o) = value.object(){
-        match &o.inner{
-            JObjectInnerEnum::Array(a) => a.values.clone(),
-            _ => Vec::new()
+        if matches!(o.inner, JObjectInnerEnum::Array(_)) && o.member_str(&symbol::Iterator.to_property_key()).is_undefined(){
+            if let JObjectInnerEnum::Array(a) = &o.inner{
+                return a.values.clone()
+            }
+        }
+    }
+
+    let iterator = get_iterator(value);
+    let mut values = Vec::new();
+    loop{
+        let (v, done) = iterator_next(iterator);
+        if done{
+            break;
+        }
+        values.push(v);
+    }
+    values
+}
+
+/// return the iterator of `value` by calling its `Symbol.iterator` method
+pub fn get_iterator(value:JValue) -> JValue{
+    let method = value.member(*symbol::Iterator);
+
+    if method.is_undefined() || method.is_null(){
+        // builtin iterables are iterated natively
+        match value.unbox(){
+            JValueEnum::String(s) => {
+                let chars = s.chars().map(|c|JValue::from(c.to_string())).collect();
+                return values_iterator(chars)
+            },
+            JValueEnum::Object(o) if unsafe{&*o}.inner.is_array() => {
+                let index = Cell::new(0);
+                return native_iterator(move ||{
+                    let a = value.object()?.inner.array()?;
+                    let v = a.values.get(index.get()).copied()?;
+                    index.set(index.get() + 1);
+                    Some(v)
+                })
+            },
+            _ => throw(Error::newTypeError(format!("{} is not iterable", value.to_string())))
+        }
+    }
+
+    let iterator = match method.call(value, &[]){
+        Ok(v) => v,
+        Err(e) => throw(e)
+    };
+    if !iterator.is_object(){
+        throw(Error::newTypeError("Result of the Symbol.iterator method is not an object"))
+    }
+    iterator
+}
+
+/// an iterator over the enumerable string keys of `value` and its prototypes, used by `for in`
+pub fn for_in_iterator(value:JValue) -> JValue{
+    let mut keys = Vec::new();
+    let mut seen = HashSet::new();
+
+    match value.unbox(){
+        JValueEnum::String(s) => {
+            keys.extend((0..s.chars().count()).map(|i|JValue::from(i.to_string())));
+        },
+        JValueEnum::Object(o) => {
+            let mut obj = unsafe{o.as_mut()};
+            while let Some(o) = obj{
+                for k in o.keys(){
+                    if seen.insert(k.clone()){
+                        keys.push(JValue::from(k));
+                    }
+                }
+                obj = o.prototype();
+            }
+        },
+        _ => {}
+    };
+    values_iterator(keys)
+}
+
+/// call `next` on the iterator, return the value and whether the iterator is done
+pub fn iterator_next(iterator:JValue) -> (JValue, bool){
+    let re = match iterator.member_str("next").call(iterator, &[]){
+        Ok(v) => v,
+        Err(e) => throw(e)
+    };
+    if !re.is_object(){
+        throw(Error::newTypeError(format!("Iterator result {} is not an object", re.to_string())))
+    }
+
+    if re.member_str("done").to_bool(){
+        (JValue::Undefined, true)
+    } else{
+        (re.member_str("value"), false)
+    }
+}
+
+/// `get_iterator` for compiled code, the exception is returned instead of thrown
+pub fn get_iterator_raw(value:JValue) -> (JValue, bool){
+    catch(||get_iterator(value))
+}
+
+/// `iterator_next` for compiled code.
+/// 
+/// status 0 for a value, 1 if the iterator is done and 2 if the value is an exception
+pub fn iterator_next_raw(iterator:JValue) -> (JValue, i8){
+    let mut done = false;
+    let (v, ok) = catch(||{
+        let (v, d) = iterator_next(iterator);
+        done = d;
+        v
+    });
+    match (ok, done){
+        (false, _) => (v, 2),
+        (true, true) => (v, 1),
+        (true, false) => (v, 0),
+    }
+}
+
+/// `iterator_close` for compiled code, the exception is returned instead of thrown
+pub fn iterator_close_raw(iterator:JValue) -> (JValue, bool){
+    catch(||{
+        iterator_close(iterator);
+        JValue::Undefined
+    })
+}
+
+/// call the `return` method of the iterator when a loop exits early
+pub fn iterator_close(iterator:JValue){
+    let ret = iterator.member_str("return");
+    if ret.is_undefined() || ret.is_null(){
+        return
+    }
+    let re = match ret.call(iterator, &[]){
+        Ok(v) => v,
+        Err(e) => throw(e)
+    };
+    if !re.is_object(){
+        throw(Error::newTypeError(format!("Iterator result {} is not an object", re.to_string())))
+    }
+}
+
+/// collect the remaining values of an iterator into an array, used by rest elements.
+/// 
+/// an iterator that is already done is not called again.
+pub fn iterator_rest_raw(iterator:JValue, done:bool) -> (JValue, bool){
+    catch(||{
+        let mut values = Vec::new();
+        if !done{
+            loop{
+                let (v, done) = iterator_next(iterator);
+                if done{
+                    break;
+                }
+                values.push(v);
+            }
+        }
+        Array::new(JObject::new(), &values)
+    })
+}
+
+/// throw a TypeError if `value` cannot be destructured by an object pattern
+pub fn require_object_coercible_raw(value:JValue) -> (JValue, bool){
+    if value.is_nullish(){
+        return (Error::newTypeError(format!("Cannot destructure '{}' as it is {}.", value.to_string(), value.to_string())), false)
+    }
+    (value, true)
+}
+
+/// copy the own properties of `value` except the `count` keys at `keys`, 
+/// used by rest elements of object patterns.
+pub unsafe fn object_rest_raw(value:JValue, keys:*const JValue, count:i64) -> (JValue, bool){
+    let excluded:Vec<PropertyKey> = std::slice::from_raw_parts(keys, count as usize).iter().map(|k|k.to_property_key()).collect();
+    catch(||{
+        let obj = JObject::new();
+        for key in value.owned_keys(){
+            if !excluded.contains(&key){
+                obj.set_member_str(&key, value.member_str(&key));
+            }
         }
+        JValue::Object(obj)
+    })
+}
+
+/// the arguments from `idx` as an array, used by rest parameters
+pub unsafe fn rest_arguments(argv:*const JValue, argc:i64, idx:i64) -> JValue{
+    let args = if idx < argc{
+        std::slice::from_raw_parts(argv.add(idx as usize), (argc - idx) as usize)
     } else{
-        Vec::new()
+        &[]
+    };
+    Array::new(JObject::new(), args)
+}
+
+/// the result object of an iterator
+pub fn iterator_result(value:JValue, done:bool) -> JValue{
+    let obj = JObject::new();
+    obj.set_member_str("value", value);
+    obj.set_member_str("done", done);
+    JValue::Object(obj)
+}
+
+fn values_iterator(values:Vec<JValue>) -> JValue{
+    let index = Cell::new(0);
+    native_iterator(move ||{
+        let v = values.get(index.get()).copied()?;
+        index.set(index.get() + 1);
+        Some(v)
+    })
+}
+
+/// an iterator object calling `next` until it returns none
+fn native_iterator<F>(next:F) -> JValue where F:Fn() -> Option<JValue> + 'static{
+    let obj = JObject::new();
+    let func = Function::from_object(JObject::new(), Arc::new(move |vmctx:&mut VmContext, this:JValue, args:&[JValue]|{
+        match next(){
+            Some(v) => iterator_result(v, false),
+            None => iterator_result(JValue::Undefined, true)
+        }
+    }), false, false);
+
+    obj.builtin_member("next", func);
+    JValue::Object(obj)
+}
+
+/// create the key of a private name, evaluated once per class evaluation
+pub fn private_name_new(desc:JValue) -> JValue{
+    Symbol::new_private(&desc.to_string())
+}
+
+/// return the argument at `idx`, undefined if out of bound
+pub unsafe fn resolve_argument(argv:*const JValue, argc:i64, idx:i64) -> JValue{
+    if idx < argc{
+        *argv.add(idx as usize)
+    } else{
+        JValue::Undefined
+    }
+}
+
+/// concat the quasis and expressions of a template literal
+pub unsafe fn tpl_new(argv:*const JValue, argc:i64) -> JValue{
+    let args = std::slice::from_raw_parts(argv, argc as usize);
+    let mut s = String::new();
+    for a in args{
+        s.push_str(&a.to_js_string());
+    }
+    s.into()
+}
+
+pub unsafe fn array_new(argv:*mut JValue, argc:i64, spread:bool) -> JValue{
+    Array::new_raw(JObject::new(), argv, argc, spread)
+}
+
+/// append `value` to an array built by an array literal or by the arguments of a spread call
+pub fn array_push(array:JValue, value:JValue){
+    if let Some(a) = array.object().and_then(|o|o.inner.array()){
+        a.to_mut().values.push(value);
+    }
+}
+
+/// append the values of the iterable `src` to an array built by `array_new`
+pub fn array_spread(array:JValue, src:JValue){
+    let values = IteratorCollect(src);
+    if let Some(a) = array.object().and_then(|o|o.inner.array()){
+        a.to_mut().values.extend(values);
+    }
+}
+
+/// the value a helper completes with, undefined for helpers returning nothing
+pub(crate) trait Completion{
+    fn into_value(self) -> JValue;
+}
+
+impl Completion for JValue{
+    fn into_value(self) -> JValue{
+        self
     }
-}
\ No newline at end of file
+}
+
+impl Completion for (){
+    fn into_value(self) -> JValue{
+        JValue::Undefined
+    }
+}
+
+/// define the variants of helpers called by compiled code that may throw,
+/// returning the completion value and false if the helper threw instead of unwinding.
+macro_rules! raw_helpers{
+    ($($raw:ident = $f:path, ($($arg:ident:$ty:ty),*);)*) => {$(
+        pub(crate) fn $raw($($arg:$ty),*) -> (JValue, bool){
+            catch(move ||$f($($arg),*).into_value())
+        }
+    )*};
+}
+
+raw_helpers!{
+    resolve_var_raw = VmContext::get_variable, (vmctx:&mut VmContext, name:u64);
+    set_var_raw = VmContext::set_variable, (vmctx:&mut VmContext, name:u64, value:JValue);
+    resolve_free_raw = VmContext::get_free_variable, (vmctx:&mut VmContext, name:u64);
+    set_free_raw = VmContext::set_free_variable, (vmctx:&mut VmContext, name:u64, value:JValue);
+    typeof_var_raw = VmContext::typeof_variable, (vmctx:&mut VmContext, name:u64);
+    typeof_free_raw = VmContext::typeof_free_variable, (vmctx:&mut VmContext, name:u64);
+
+    member_raw = JValue::member, (obj:JValue, key:JValue);
+    super_member_raw = JValue::superMember, (vmctx:&mut VmContext, this:JValue, key:JValue);
+    set_member_raw = JValue::set_member, (obj:JValue, key:JValue, value:JValue);
+    set_member_spread_raw = JValue::set_member_spread, (obj:JValue, spread:JValue);
+    ic_get_raw = InlineCache::get, (cache:&mut InlineCache, obj:JValue);
+    ic_set_raw = InlineCache::set, (cache:&mut InlineCache, obj:JValue, value:JValue);
+    define_field_raw = JValue::define_field, (obj:JValue, key:JValue, value:JValue);
+    delete_member_raw = JValue::delete_member, (obj:JValue, key:JValue);
+
+    private_get_raw = JValue::private_get, (obj:JValue, key:JValue);
+    private_set_raw = JValue::private_set, (obj:JValue, key:JValue, value:JValue, op:i8);
+    private_define_raw = JValue::private_define, (obj:JValue, key:JValue, value:JValue, kind:i8);
+    private_in_raw = JValue::private_in, (obj:JValue, key:JValue);
+
+    define_method_raw = Function::define_method, (target:JValue, key:JValue, func:JValue, kind:i8);
+    class_new_raw = Function::class_new, (ctor:JValue, parent:JValue, has_parent:bool);
+    array_spread_raw = array_spread, (array:JValue, src:JValue);
+
+    add_raw = JValue::add, (lhs:JValue, rhs:JValue);
+    bitand_raw = JValue::bitand, (lhs:JValue, rhs:JValue);
+    bitor_raw = JValue::bitor, (lhs:JValue, rhs:JValue);
+    bitxor_raw = JValue::bitxor, (lhs:JValue, rhs:JValue);
+    div_raw = JValue::div, (lhs:JValue, rhs:JValue);
+    eqeq_raw = JValue::eqeq, (lhs:JValue, rhs:JValue);
+    eqeqeq_raw = JValue::eqeqeq, (lhs:JValue, rhs:JValue);
+    exp_raw = JValue::exp, (lhs:JValue, rhs:JValue);
+    gt_raw = JValue::gt, (lhs:JValue, rhs:JValue);
+    gteq_raw = JValue::gteq, (lhs:JValue, rhs:JValue);
+    in_raw = JValue::In, (lhs:JValue, rhs:JValue);
+    instanceof_raw = JValue::instanceof, (lhs:JValue, rhs:JValue);
+    shl_raw = JValue::shl, (lhs:JValue, rhs:JValue);
+    lt_raw = JValue::lt, (lhs:JValue, rhs:JValue);
+    lteq_raw = JValue::lteq, (lhs:JValue, rhs:JValue);
+    rem_raw = JValue::rem, (lhs:JValue, rhs:JValue);
+    mul_raw = JValue::mul, (lhs:JValue, rhs:JValue);
+    noteq_raw = JValue::noteq, (lhs:JValue, rhs:JValue);
+    noteqeq_raw = JValue::noteqeq, (lhs:JValue, rhs:JValue);
+    shr_raw = JValue::shr, (lhs:JValue, rhs:JValue);
+    sub_raw = JValue::sub, (lhs:JValue, rhs:JValue);
+    unsigned_shr_raw = JValue::unsignedRShift, (lhs:JValue, rhs:JValue);
+
+    neg_raw = JValue::neg, (value:JValue);
+    to_number_raw = JValue::to_number, (value:JValue);
+    bitnot_raw = JValue::bitNot, (value:JValue);
+    to_numeric_raw = JValue::to_numeric, (value:JValue);
+    increment_raw = JValue::increment, (value:JValue);
+    decrement_raw = JValue::decrement, (value:JValue);
+}
+
+/// `tpl_new` for compiled code, converting an expression to a string may throw
+pub unsafe fn tpl_new_raw(argv:*const JValue, argc:i64) -> (JValue, bool){
+    catch(move ||tpl_new(argv, argc))
+}
+
+/// `array_new` for compiled code, spreading an iterable may throw
+pub unsafe fn array_new_raw(argv:*mut JValue, argc:i64, spread:bool) -> (JValue, bool){
+    catch(move ||array_new(argv, argc, spread))
+}
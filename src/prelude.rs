@@ -5,6 +5,8 @@ use std::ops::{
     Add,Sub,Div,Mul,Shl,Shr,BitAnd,BitOr,BitXor, Index, Rem
 };
 
+use num_bigint::BigInt;
+
 use crate::bindgen;
 
 use super::value;
@@ -13,7 +15,7 @@ use super::builtins;
 pub enum StringOrNumber{
     String(String),
     Number(f64),
-    BigInt(i64)
+    BigInt(BigInt)
 }
 
 /// This is a value borrowed from the runtime, 
@@ -51,7 +53,7 @@ impl<'a> JValue<'a>{
         self.value.null()
     }
 
-    pub fn bigint(&self) -> Option<i64>{
+    pub fn bigint(&self) -> Option<BigInt>{
         self.value.bigint()
     }
 
@@ -84,10 +86,10 @@ impl<'a> AsRef<value::JValue> for JValue<'a>{
 impl<'a> Add for JValue<'a>{
     type Output = StringOrNumber;
     fn add(self, rhs: Self) -> Self::Output {
-        match (self.value + rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value + rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -96,10 +98,10 @@ impl<'a> Add for JValue<'a>{
 impl<'a> Sub for JValue<'a>{
     type Output = StringOrNumber;
     fn sub(self, rhs: Self) -> Self::Output {
-        match (self.value - rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value - rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -108,10 +110,10 @@ impl<'a> Sub for JValue<'a>{
 impl<'a> Div for JValue<'a>{
     type Output = StringOrNumber;
     fn div(self, rhs: Self) -> Self::Output {
-        match (self.value / rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value / rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -120,10 +122,10 @@ impl<'a> Div for JValue<'a>{
 impl<'a> Mul for JValue<'a>{
     type Output = StringOrNumber;
     fn mul(self, rhs: Self) -> Self::Output {
-        match (self.value * rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value * rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -132,10 +134,10 @@ impl<'a> Mul for JValue<'a>{
 impl<'a> Shl for JValue<'a>{
     type Output = StringOrNumber;
     fn shl(self, rhs: Self) -> Self::Output {
-        match (self.value << rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value << rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -144,10 +146,10 @@ impl<'a> Shl for JValue<'a>{
 impl<'a> Shr for JValue<'a>{
     type Output = StringOrNumber;
     fn shr(self, rhs: Self) -> Self::Output {
-        match (self.value >> rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value >> rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -156,10 +158,10 @@ impl<'a> Shr for JValue<'a>{
 impl<'a> Rem for JValue<'a>{
     type Output = StringOrNumber;
     fn rem(self, rhs: Self) -> Self::Output {
-        match (self.value % rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value % rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -168,10 +170,10 @@ impl<'a> Rem for JValue<'a>{
 impl<'a> BitAnd for JValue<'a>{
     type Output = StringOrNumber;
     fn bitand(self, rhs: Self) -> Self::Output {
-        match (self.value & rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value & rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -180,10 +182,10 @@ impl<'a> BitAnd for JValue<'a>{
 impl<'a> BitOr for JValue<'a>{
     type Output = StringOrNumber;
     fn bitor(self, rhs: Self) -> Self::Output {
-        match (self.value | rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value | rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
@@ -192,10 +194,10 @@ impl<'a> BitOr for JValue<'a>{
 impl<'a> BitXor for JValue<'a>{
     type Output = StringOrNumber;
     fn bitxor(self, rhs: Self) -> Self::Output {
-        match (self.value ^ rhs.value){
-            value::JValue::BigInt(i) => StringOrNumber::BigInt(i),
-            value::JValue::Number(n) => StringOrNumber::Number(n),
-            value::JValue::String(s) => StringOrNumber::String(s.to_string()),
+        match (self.value ^ rhs.value).unbox(){
+            value::JValueEnum::BigInt(i) => StringOrNumber::BigInt(i.value()),
+            value::JValueEnum::Number(n) => StringOrNumber::Number(n),
+            value::JValueEnum::String(s) => StringOrNumber::String(s.to_string()),
             _ => unreachable!()
         }
     }
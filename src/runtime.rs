@@ -1,7 +1,7 @@
 
 use std::alloc::Layout;
 use std::cell::Cell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::mem::MaybeUninit;
 use std::ops::*;
 use std::sync::Arc;
@@ -11,6 +11,7 @@ use cranelift::prelude::Signature;
 use cranelift_jit::{JITBuilder, JITModule};
 use cranelift_module::FuncId;
 use cranelift_module::Module;
+use cranelift_module::ModuleError;
 use cranelift_module::DataContext;
 
 use cranelift::prelude::{
@@ -26,11 +27,18 @@ use string_interner::{
     symbol::SymbolUsize,
 };
 use swc_ecma_ast::ModuleItem;
+use tokio::task::JoinHandle;
 
 use crate::builtins;
-use crate::builtins::JObject;
+use crate::bytecode::{self, CodeBlock};
+use crate::operator;
+use crate::builtins::{JObject, Realm};
+use crate::builtins::realm::RealmScope;
+use crate::builtins::promise::{Promise, PromiseState, Job};
 use crate::error::Error;
+use crate::interpretor::{Interpreter, Script};
 use crate::jit::builder::BuilderContext;
+use crate::jit::inline_cache::InlineCache;
 use crate::parse::parse_ecma;
 use crate::prelude::OwnedValue;
 use crate::value::JValue;
@@ -38,7 +46,8 @@ use crate::vm::VmContext;
 use crate::heap::SlabAllocator;
 
 thread_local!{
-    pub(crate) static RUNTIME:&'static mut Runtime = unsafe{std::mem::zeroed()};
+    /// the runtime of the thread, written by `Runtime::init` before any use
+    pub(crate) static RUNTIME:&'static mut Runtime = unsafe{&mut *std::ptr::NonNull::dangling().as_ptr()};
 }
 
 macro_rules! declare_fn {
@@ -56,6 +65,7 @@ macro_rules! declare_fn {
 pub(crate) struct compiled_func{
     rc:usize,
     size:usize,
+    id:FuncId,
 }
 
 pub struct Runtime{
@@ -66,15 +76,71 @@ pub struct Runtime{
 
     pub(crate) ctx:&'static mut Context,
 
-    pub(crate) context:VmContext,
-
-    pub(crate) global:&'static mut JObject,
+    /// the realm of the running code, its intrinsics and global object,
+    /// null until the main realm is created.
+    pub(crate) realm:*mut Realm,
+    /// every realm of the runtime, the main realm first
+    pub(crate) realms:Vec<*mut Realm>,
 
     pub(crate) variable_names:StringInterner<DefaultBackend<SymbolUsize>>,
 
     pub (crate) builtin_functions:HashMap<&'static str, FuncId>,
 
-    pub(crate) compiled_functions:HashMap<*mut u8, compiled_func>
+    pub(crate) compiled_functions:HashMap<*mut u8, compiled_func>,
+
+    /// the microtask queue
+    pub(crate) jobs:VecDeque<Job>,
+
+    /// rust futures spawned by `Promise::native` and the promises they settle
+    pub(crate) pending_futures:Vec<(*mut JObject, JoinHandle<JValue>)>,
+
+    /// promises rejected without handler since the last microtask checkpoint
+    pub(crate) pending_rejections:Vec<*mut JObject>,
+    /// promises reported as unhandled, reported again if a handler is added later
+    pub(crate) reported_rejections:Vec<*mut JObject>,
+    rejection_hook:Option<Box<dyn Fn(RejectionEvent, crate::prelude::JValue, crate::prelude::JValue)>>,
+
+    /// how scripts passed to `exec` are run
+    mode:ExecutionMode,
+    /// the number of calls and loop iterations after which an interpreted function is compiled
+    tier_up_threshold:u32,
+}
+
+/// a realm of a runtime, created by `Runtime::create_realm`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RealmId{
+    /// the address of the runtime owning the realm
+    runtime:usize,
+    index:usize,
+}
+
+/// how `Runtime::exec` runs a script
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExecutionMode{
+    /// compile the script to machine code, the default.
+    /// the code is lowered from the syntax tree, not from the bytecode of `Bytecode`.
+    Jit,
+    /// walk the syntax tree, for platforms where emitting machine code is not allowed
+    /// and for cross checking the compiled code.
+    Interpreter,
+    /// interpret the script and compile the functions that become hot,
+    /// short lived scripts do not pay for the compilation of code that runs once.
+    /// the top level code of the script is always interpreted.
+    Tiered,
+    /// compile the script to register bytecode and interpret it,
+    /// the bytecode can be cached with `Runtime::compile_bytecode`.
+    /// the functions that become hot are compiled from their bytecode by a baseline tier
+    /// calling the runtime helpers, generators and async functions stay interpreted.
+    Bytecode,
+}
+
+/// the events reported to the hook set by `Runtime::on_unhandled_rejection`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RejectionEvent{
+    /// a promise was rejected and no handler was added before the jobs were drained
+    Unhandled,
+    /// a handler was added to a promise previously reported as unhandled
+    Handled,
 }
 
 unsafe impl Send for Runtime{}
@@ -97,20 +163,35 @@ impl Runtime{
         let mut runtime = Arc::new(Self{
 
             allocator:SlabAllocator::new(),
-            context:VmContext::new(),
             variable_names:StringInterner::new(),
 
             module:module.clone(),
             ctx:Box::leak(Box::new(module.make_context())),
-            global:JObject::new(),
+            // the realm can only be allocated once the runtime is registered
+            realm:std::ptr::null_mut(),
+            realms:Vec::new(),
 
             builtin_functions:HashMap::new(),
             compiled_functions:Default::default(),
+
+            jobs:VecDeque::new(),
+            pending_futures:Vec::new(),
+
+            pending_rejections:Vec::new(),
+            reported_rejections:Vec::new(),
+            rejection_hook:None,
+
+            mode:ExecutionMode::Jit,
+            tier_up_threshold:1000,
         });
 
         let r = runtime.to_mut();
-        r.context.runtime = unsafe{std::mem::transmute_copy(&r)};
 
+        r.init_functions();
+
+        runtime.init();
+        let main = r.new_realm();
+        r.realm = main;
 
         return runtime
     }
@@ -121,68 +202,188 @@ impl Runtime{
         });
     }
 
+    /// the realm of the running code
+    pub(crate) fn realm(&self) -> &'static mut Realm{
+        unsafe{self.realm.as_mut().expect("the runtime has no realm")}
+    }
+
+    /// create a realm with its own global object and intrinsics
+    pub(crate) fn new_realm(&self) -> *mut Realm{
+        let realm = Realm::new() as *mut Realm;
+        self.to_mut().realms.push(realm);
+
+        // the intrinsics are allocated in the new realm
+        let _scope = RealmScope::enter(realm);
+        unsafe{(*realm).init()};
+        realm
+    }
+
     pub(crate)fn to_mut(&self) -> &'static mut Self{
         unsafe{std::mem::transmute_copy(&self)}
     }
 
+    /// a new reference to the runtime, which is always allocated by `Runtime::new`
+    pub(crate) fn arc(&self) -> Arc<Self>{
+        unsafe{
+            Arc::increment_strong_count(self as *const Self);
+            Arc::from_raw(self as *const Self)
+        }
+    }
+
+    pub(crate) fn module_mut(&self) -> &'static mut JITModule{
+        unsafe{(self.module.as_ref() as *const _ as *mut JITModule).as_mut().unwrap()}
+    }
+
     pub(crate)fn new_variable_name<T>(&mut self, name:T) -> usize where T:AsRef<str> {
         self.variable_names.get_or_intern(name).to_usize()
     }
 
-    pub(crate) fn new_compiled_fn(&mut self, mem:*mut u8, size:usize){
+    pub(crate) fn new_compiled_fn(&mut self, mem:*mut u8, size:usize, id:FuncId){
         self.compiled_functions.insert(mem, compiled_func{
             rc:0,
             size,
+            id
         });
     }
     
+    /// the code memory is owned by the jit module, 
+    /// only the record is removed here.
     pub(crate) fn release_compiled_fn(&mut self, mem:*mut u8){
-        if let Some(f) = self.compiled_functions.get(&mem){
-            unsafe{std::alloc::dealloc(mem, Layout::array::<u8>(f.size).unwrap())};
-        }
+        self.compiled_functions.remove(&mem);
     }
 
+    /// define the function held by `ctx` in the jit module and return a pointer to the finalized code.
+    /// 
+    /// `ctx` is cleared whether or not the compilation succeed.
+    pub(crate) fn compile_function(&self, ctx:&mut Context) -> Result<*mut u8, Error>{
+        let module = self.module_mut();
+
+        let re = module.declare_anonymous_function(&ctx.func.signature)
+            .and_then(|id|{
+                module.define_function(id, ctx).map(|info|(id, info))
+            });
+        
+        ctx.clear();
+
+        let (id, info) = match re{
+            Ok(v) => v,
+            Err(ModuleError::Compilation(e)) => return Err(Error::CodegenError(Arc::new(e))),
+            Err(e) => return Err(Error::ModuleError(Arc::new(e)))
+        };
 
+        module.finalize_definitions();
+
+        let mem = module.get_finalized_function(id) as *mut u8;
+        self.to_mut().new_compiled_fn(mem, info.size as usize, id);
+
+        Ok(mem)
+    }
+
+
+    /// select how scripts are run by the following calls to `exec`
+    pub fn set_execution_mode(&self, mode:ExecutionMode){
+        self.to_mut().mode = mode;
+    }
+
+    pub fn execution_mode(&self) -> ExecutionMode{
+        self.mode
+    }
+
+    /// set the number of calls and loop iterations after which
+    /// a function is compiled in `ExecutionMode::Tiered` and `ExecutionMode::Bytecode`.
+    pub fn set_tier_up_threshold(&self, threshold:u32){
+        self.to_mut().tier_up_threshold = threshold;
+    }
+
+    pub fn tier_up_threshold(&self) -> u32{
+        self.tier_up_threshold
+    }
+
+    /// the realm scripts passed to `exec` run in
+    pub fn main_realm(&self) -> RealmId{
+        self.realm_id(0)
+    }
+
+    fn realm_id(&self, index:usize) -> RealmId{
+        RealmId{runtime:self as *const Self as usize, index}
+    }
+
+    /// create a realm with its own global object and intrinsics.
+    ///
+    /// the realms of a runtime share the heap and the compiled code,
+    /// an object passed from a realm to another keeps the prototypes of its realm.
+    pub fn create_realm(&self) -> RealmId{
+        self.init();
+        self.new_realm();
+        self.realm_id(self.realms.len() - 1)
+    }
 
     pub fn exec(self:Arc<Self>,filename:&str, script:&str) -> Result<OwnedValue, Error>{
+        let main = self.main_realm();
+        self.exec_in_realm(main, filename, script)
+    }
+
+    /// run a script in `realm`, return the completion value.
+    ///
+    /// the top level declarations of the script are visible to the following scripts of the realm only.
+    /// returns `Error::ForeignRealm` if the realm was created by another runtime.
+    pub fn exec_in_realm(self:Arc<Self>, realm:RealmId, filename:&str, script:&str) -> Result<OwnedValue, Error>{
         self.init();
 
-        let builder_ctx = BuilderContext::new(self.clone(), self.module.clone(), self.to_mut().ctx);
+        if realm.runtime != Arc::as_ptr(&self) as usize{
+            return Err(Error::ForeignRealm)
+        }
+        let re = self.eval(self.realms[realm.index], filename, script);
+        self.complete(re)
+    }
 
-        let module = parse_ecma(filename, script)?;
+    /// run a script in `realm` as `Runtime::exec` does, without running the queued jobs.
+    pub(crate) fn eval(self:&Arc<Self>, realm:*mut Realm, filename:&str, script:&str) -> Result<JValue, Error>{
+        let _scope = RealmScope::enter(realm);
 
-        for item in &module.body{
-            match item{
-                ModuleItem::Stmt(s) => {
+        match self.mode{
+            ExecutionMode::Jit => self.exec_compiled(filename, script),
+            ExecutionMode::Interpreter |
+            ExecutionMode::Tiered => self.exec_interpreted(filename, script),
+            ExecutionMode::Bytecode => self.compile_code_block(filename, script)
+                .and_then(|code|self.exec_code_block(&Arc::new(code)))
+        }
+    }
 
-                },
-                _ => todo!()
-            }
-        };
+    /// compile a script to bytecode that can be cached and run by `exec_bytecode`.
+    ///
+    /// the bytecode does not depend on the runtime it is compiled by.
+    pub fn compile_bytecode(&self, filename:&str, script:&str) -> Result<Vec<u8>, Error>{
+        Ok(self.compile_code_block(filename, script)?.encode())
+    }
 
-        let info = self.to_mut().ctx.compile(self.module.isa());
-        let info = match info{
-            Ok(v) => v,
-            Err(e) => {
-                self.to_mut().ctx.clear();
-                return Err(Error::CodegenError(Arc::new(e)))
-            }
-        };
+    /// run a script compiled by `compile_bytecode`, return the completion value.
+    pub fn exec_bytecode(self:Arc<Self>, bytecode:&[u8]) -> Result<OwnedValue, Error>{
+        self.init();
 
-        let mem = unsafe{std::alloc::alloc(std::alloc::Layout::array::<u8>(info.total_size as usize).unwrap())};
-        unsafe{self.ctx.emit_to_memory(mem)};
+        let re = CodeBlock::decode(bytecode).and_then(|code|{
+            let _scope = RealmScope::enter(self.realms[0]);
+            self.exec_code_block(&Arc::new(code))
+        });
+        self.complete(re)
+    }
 
-        self.to_mut().ctx.clear();
+    /// run the jobs queued by the script and return the completion value to the host
+    fn complete(&self, re:Result<JValue, Error>) -> Result<OwnedValue, Error>{
+        // jobs queued by the script run before returning to the host
+        self.run_jobs();
 
-        let func:fn(*mut VmContext, JValue, *mut JValue, i64) -> JValue = unsafe{std::mem::transmute(mem)};
-        let v = func(&mut self.to_mut().context, JValue::Object(self.to_mut().global), 1 as _, 0);
+        let v = match re{
+            Ok(v) => v,
+            Err(Error::Value(e)) => {
+                e.keep_alive(true);
+                return Err(Error::Value(e))
+            },
+            Err(e) => return Err(e)
+        };
 
         v.keep_alive(true);
 
-        unsafe{
-            std::alloc::dealloc(mem, std::alloc::Layout::array::<u8>(info.total_size as usize).unwrap());
-        }
-
         // return the value
         Ok(OwnedValue{
             value:crate::prelude::JValue { 
@@ -191,17 +392,307 @@ impl Runtime{
             }
         })
     }
+
+    /// compile the script and run it, return the completion value.
+    fn exec_compiled(self:&Arc<Self>, filename:&str, script:&str) -> Result<JValue, Error>{
+        let module = parse_ecma(filename, script)?;
+
+        let mut builder_ctx = BuilderContext::new(self.clone(), self.module.clone(), self.to_mut().ctx);
+
+        let re = module.body.iter().map(|item|match item{
+            ModuleItem::Stmt(s) => Ok(s),
+            ModuleItem::ModuleDecl(_) => Err(Error::Unimplemented("module declarations in Runtime::exec."))
+        }).collect::<Result<Vec<_>, _>>();
+
+        if let Err(e) = re.and_then(|stmts|builder_ctx.translate_script(&stmts)){
+            drop(builder_ctx);
+            self.to_mut().ctx.clear();
+            return Err(e.locate(script))
+        }
+
+        // return the completion value of the script
+        builder_ctx.finalize();
+        drop(builder_ctx);
+
+        let mem = self.compile_function(self.to_mut().ctx)?;
+
+        let func:fn(*mut VmContext, JValue, *mut JValue, i64) -> JValue = unsafe{std::mem::transmute(mem)};
+        let v = func(&mut self.realm().context, JValue::Object(self.realm().global()), 1 as _, 0);
+        let exception = self.realm().context.exception.take();
+
+        // the script is not reachable after execution
+        self.to_mut().release_compiled_fn(mem);
+
+        match exception{
+            Some(e) => Err(Error::Value(e)),
+            None => Ok(v)
+        }
+    }
+
+    /// run the script with the interpreter, return the completion value.
+    fn exec_interpreted(&self, filename:&str, script:&str) -> Result<JValue, Error>{
+        // functions created by the script keep its syntax tree alive
+        let parsed = Script::parse(filename, script)?;
+
+        let mut interpreter = Interpreter::new(parsed);
+        interpreter.exec(&mut self.realm().context, JValue::Object(self.realm().global()))
+            .map_err(|e|e.locate(script))
+    }
+
+    fn compile_code_block(&self, filename:&str, script:&str) -> Result<CodeBlock, Error>{
+        let module = parse_ecma(filename, script)?;
+        bytecode::compile_script(&module).map_err(|e|e.locate(script))
+    }
+
+    fn exec_code_block(&self, code:&Arc<CodeBlock>) -> Result<JValue, Error>{
+        bytecode::execute_script(&mut self.realm().context, code, JValue::Object(self.realm().global()))
+    }
     
 
 
+    /// run the microtask queue until it is empty.
+    pub fn run_jobs(&self){
+        self.init();
+
+        let r = self.to_mut();
+        while let Some(job) = r.jobs.pop_front(){
+            job.run(&mut r.realm().context);
+        }
+
+        // rejections still unhandled after the checkpoint are reported
+        for promise in std::mem::take(&mut r.pending_rejections){
+            let p = Promise::from_value(JValue::Object(promise)).unwrap();
+            if !p.handled{
+                r.reported_rejections.push(promise);
+                r.report_rejection(RejectionEvent::Unhandled, promise);
+            }
+        }
+    }
+
+    /// set the hook called with the promise and the reason 
+    /// when a promise is rejected without handler, or when such promise is handled later.
+    pub fn on_unhandled_rejection<F>(&self, hook:F) where F:Fn(RejectionEvent, crate::prelude::JValue, crate::prelude::JValue) + 'static{
+        self.to_mut().rejection_hook = Some(Box::new(hook));
+    }
+
+    /// called when a handler is added to a rejected promise
+    pub(crate) fn rejection_handled(&mut self, promise:*mut JObject){
+        if let Some(i) = self.pending_rejections.iter().position(|p|*p == promise){
+            self.pending_rejections.remove(i);
+        }
+        if let Some(i) = self.reported_rejections.iter().position(|p|*p == promise){
+            self.reported_rejections.remove(i);
+            self.report_rejection(RejectionEvent::Handled, promise);
+        }
+    }
+
+    fn report_rejection(&self, event:RejectionEvent, promise:*mut JObject){
+        let reason = match Promise::from_value(JValue::Object(promise)).map(|p|p.state){
+            Some(PromiseState::Rejected(v)) => v,
+            _ => return
+        };
+        if let Some(hook) = &self.rejection_hook{
+            let wrap = |v:JValue|crate::prelude::JValue{
+                value: v,
+                marker: std::marker::PhantomData
+            };
+            hook(event, wrap(JValue::Object(promise)), wrap(reason));
+        }
+    }
+
+    /// return true if there are queued jobs or native futures not yet completed.
+    pub fn has_pending_jobs(&self) -> bool{
+        !self.jobs.is_empty() || !self.pending_futures.is_empty()
+    }
+
+    /// run the microtask queue and wait for native futures until nothing is pending.
+    pub async fn run_event_loop(self:Arc<Self>){
+        loop{
+            self.run_jobs();
+
+            if self.pending_futures.is_empty(){
+                break;
+            }
+            self.wait_future().await;
+        }
+    }
+
+    /// run the event loop until `value` is settled if it is a promise.
+    /// 
+    /// return Err with the reason if the promise is rejected.
+    pub async fn await_value(self:Arc<Self>, value:OwnedValue) -> Result<OwnedValue, OwnedValue>{
+        let promise = match Promise::from_value(value.value.value){
+            Some(p) => p,
+            None => return Ok(value)
+        };
+
+        loop{
+            self.run_jobs();
+
+            let v = match promise.state{
+                PromiseState::Fulfilled(v) => Ok(v),
+                PromiseState::Rejected(v) => Err(v),
+                PromiseState::Pending => {
+                    if self.pending_futures.is_empty(){
+                        Err(builtins::Error::newTypeError("promise can never be settled, no pending jobs left"))
+                    } else{
+                        self.wait_future().await;
+                        continue;
+                    }
+                }
+            };
+
+            let owned = |v:JValue|{
+                v.keep_alive(true);
+                OwnedValue{
+                    value:crate::prelude::JValue { 
+                        value: v, 
+                        marker: std::marker::PhantomData 
+                    }
+                }
+            };
+            return v.map(owned).map_err(owned)
+        }
+    }
+
+    /// wait for any of the native futures and settle its promise
+    async fn wait_future(&self){
+        let (mut promises, handles):(Vec<_>, Vec<_>) = std::mem::take(&mut self.to_mut().pending_futures).into_iter().unzip();
+
+        let (re, index, remain) = futures::future::select_all(handles).await;
+
+        // select_all removes the completed future with swap_remove
+        let promise = promises.swap_remove(index);
+        self.to_mut().pending_futures.extend(promises.into_iter().zip(remain));
+
+        self.init();
+        Promise::settle_native(promise, re);
+    }
+
     fn init_functions(&mut self){
 
         let default_call_conv = self.module.target_config().default_call_conv;
 
-        let module = unsafe{(self.module.as_ref() as *const _ as *mut JITModule).as_mut().unwrap()};
-
-        declare_fn!(self, module, default_call_conv, "construct"; I128 => I128);
-        declare_fn!(self, module, default_call_conv, "call"; I64, I128, I64, I64, B8 => I128, B8);
+        let module = self.module_mut();
+
+        // helpers that may throw return the exception with a false flag through `raw_helpers!`,
+        // the ones returning no flag cannot throw.
+        declare_fn!(self, module, default_call_conv, "resolve_var"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "set_var"; I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "declare_var"; I64, I64, I64, I8 => );
+        declare_fn!(self, module, default_call_conv, "resolve_free"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "set_free"; I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "typeof_free"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "uninitialized_error"; I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "const_error"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "init_var"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "push_scope"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "pop_scope"; I64 => );
+        declare_fn!(self, module, default_call_conv, "scope_parent"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "declare_slot"; I64, I64, I64, I8 => );
+        declare_fn!(self, module, default_call_conv, "get_slot"; I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "set_slot"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "get_closure"; I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "set_closure"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "declare_script_slot"; I64, I64, I64, I64, I8 => );
+        declare_fn!(self, module, default_call_conv, "hoist_script_slot"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "resolve_argument"; I64, I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "rest_arguments"; I64, I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "require_object_coercible"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "object_rest"; I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "iterator_rest"; I64, I8 => I64, I8);
+
+        declare_fn!(self, module, default_call_conv, "to_bool"; I64 => I8);
+        declare_fn!(self, module, default_call_conv, "is_nullish"; I64 => I8);
+        declare_fn!(self, module, default_call_conv, "set_exception"; I64, I64 => );
+
+        declare_fn!(self, module, default_call_conv, "member"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "superMember"; I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "set_member"; I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "set_member_spread"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "ic_get"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "ic_set"; I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "define_field"; I64, I64, I64 => I64, I8);
+
+        declare_fn!(self, module, default_call_conv, "private_get"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "private_set"; I64, I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "private_define"; I64, I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "private_in"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "private_name_new"; I64 => I64);
+
+        declare_fn!(self, module, default_call_conv, "call"; I64, I64, I64, I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "construct"; I64, I64, I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "memberCall"; I64, I64, I64, I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "superMemberCall"; I64, I64, I64, I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "super_call"; I64, I64, I64, I64, I8 => I64, I8);
+
+        declare_fn!(self, module, default_call_conv, "get_iterator"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "for_in_iterator"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "iterator_next"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "iterator_close"; I64 => I64, I8);
+
+        declare_fn!(self, module, default_call_conv, "generator_state"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "generator_yield"; I64, I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "generator_scope"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "generator_resume"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "generator_delegate"; I64, I64, I64, I8 => I64, I8);
+
+        declare_fn!(self, module, default_call_conv, "tpl_new"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "array_new"; I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "array_push"; I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "array_spread"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "function_new"; I64, I64, I8, I8 => I64);
+        declare_fn!(self, module, default_call_conv, "function_capture"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "function_capture_slot"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "function_capture_closure"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "function_bind_this"; I64, I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "set_home_object"; I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "define_method"; I64, I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "class_new"; I64, I64, I8 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "class_fields"; I64, I64 => );
+        declare_fn!(self, module, default_call_conv, "new_object"; => I64);
+
+        declare_fn!(self, module, default_call_conv, "add"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "bitAnd"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "bitOr"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "bitXor"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "div"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "eqeq"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "eqeqeq"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "exp"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "gt"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "gteq"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "in"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "instanceof"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "lshift"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "lt"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "lteq"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "mod"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "mul"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "noteq"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "noteqeq"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "rshift"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "sub"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "unsignedRShift"; I64, I64 => I64, I8);
+
+        declare_fn!(self, module, default_call_conv, "typeof"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "typeof_var"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "delete_member"; I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "not"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "neg"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "pos"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "bitNot"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "to_numeric"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "increment"; I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "decrement"; I64 => I64, I8);
+
+        declare_fn!(self, module, default_call_conv, "baseline_constant"; I64, I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "baseline_closure"; I64, I64, I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "baseline_push_scope"; I64 => I64);
+        declare_fn!(self, module, default_call_conv, "baseline_pop_scopes"; I64, I64 => I64);
+        declare_fn!(self, module, default_call_conv, "baseline_call_spread"; I64, I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "baseline_new_spread"; I64, I64, I64 => I64, I8);
+        declare_fn!(self, module, default_call_conv, "baseline_iterator_step"; I64, I64 => I64, I8);
     }
     
 }
@@ -212,12 +703,120 @@ fn init_builder(builder:&mut JITBuilder){
     //builder.symbol("alloc", heap::SlabAlloc as *const u8);
     //builder.symbol("dealloc", heap::SlabFree as *const u8);
 
-    builder.symbol("construct", JValue::new as *const u8);
-    builder.symbol("call", JValue::call_raw as *const u8);
-    builder.symbol("add", JValue::add as *const u8);
-    builder.symbol("sub", JValue::sub as *const u8);
-    builder.symbol("div", JValue::div as *const u8);
-    builder.symbol("mul", JValue::mul as *const u8);
+    builder.symbol("resolve_var", operator::resolve_var_raw as *const u8);
+    builder.symbol("set_var", operator::set_var_raw as *const u8);
+    builder.symbol("declare_var", VmContext::declare_variable as *const u8);
+    builder.symbol("resolve_free", operator::resolve_free_raw as *const u8);
+    builder.symbol("set_free", operator::set_free_raw as *const u8);
+    builder.symbol("typeof_free", operator::typeof_free_raw as *const u8);
+    builder.symbol("uninitialized_error", VmContext::uninitialized_error as *const u8);
+    builder.symbol("const_error", VmContext::const_error as *const u8);
+    builder.symbol("init_var", VmContext::initialize_variable as *const u8);
+    builder.symbol("push_scope", VmContext::push_scope as *const u8);
+    builder.symbol("pop_scope", VmContext::pop_scope as *const u8);
+    builder.symbol("scope_parent", VmContext::scope_parent as *const u8);
+    builder.symbol("declare_slot", VmContext::declare_slot as *const u8);
+    builder.symbol("get_slot", VmContext::get_slot as *const u8);
+    builder.symbol("set_slot", VmContext::set_slot as *const u8);
+    builder.symbol("get_closure", VmContext::get_closure as *const u8);
+    builder.symbol("set_closure", VmContext::set_closure as *const u8);
+    builder.symbol("declare_script_slot", VmContext::declare_script_slot as *const u8);
+    builder.symbol("hoist_script_slot", VmContext::hoist_script_slot as *const u8);
+    builder.symbol("resolve_argument", operator::resolve_argument as *const u8);
+    builder.symbol("rest_arguments", operator::rest_arguments as *const u8);
+    builder.symbol("require_object_coercible", operator::require_object_coercible_raw as *const u8);
+    builder.symbol("object_rest", operator::object_rest_raw as *const u8);
+    builder.symbol("iterator_rest", operator::iterator_rest_raw as *const u8);
+
+    builder.symbol("to_bool", JValue::to_bool as *const u8);
+    builder.symbol("is_nullish", JValue::is_nullish as *const u8);
+    builder.symbol("set_exception", VmContext::set_exception as *const u8);
+
+    builder.symbol("member", operator::member_raw as *const u8);
+    builder.symbol("superMember", operator::super_member_raw as *const u8);
+    builder.symbol("set_member", operator::set_member_raw as *const u8);
+    builder.symbol("set_member_spread", operator::set_member_spread_raw as *const u8);
+    builder.symbol("ic_get", operator::ic_get_raw as *const u8);
+    builder.symbol("ic_set", operator::ic_set_raw as *const u8);
+    builder.symbol("define_field", operator::define_field_raw as *const u8);
+
+    builder.symbol("private_get", operator::private_get_raw as *const u8);
+    builder.symbol("private_set", operator::private_set_raw as *const u8);
+    builder.symbol("private_define", operator::private_define_raw as *const u8);
+    builder.symbol("private_in", operator::private_in_raw as *const u8);
+    builder.symbol("private_name_new", operator::private_name_new as *const u8);
 
+    builder.symbol("call", JValue::call_raw as *const u8);
+    builder.symbol("construct", JValue::new_raw as *const u8);
+    builder.symbol("memberCall", JValue::memberCall_raw as *const u8);
+    builder.symbol("superMemberCall", JValue::superMemberCall_raw as *const u8);
+    builder.symbol("super_call", builtins::Function::super_call_raw as *const u8);
+
+    builder.symbol("get_iterator", operator::get_iterator_raw as *const u8);
+    builder.symbol("for_in_iterator", operator::for_in_iterator as *const u8);
+    builder.symbol("iterator_next", operator::iterator_next_raw as *const u8);
+    builder.symbol("iterator_close", operator::iterator_close_raw as *const u8);
+
+    builder.symbol("generator_state", builtins::generator::Generator::state_raw as *const u8);
+    builder.symbol("generator_yield", builtins::generator::Generator::yield_raw as *const u8);
+    builder.symbol("generator_scope", builtins::generator::Generator::scope_raw as *const u8);
+    builder.symbol("generator_resume", builtins::generator::Generator::resume_raw as *const u8);
+    builder.symbol("generator_delegate", builtins::generator::Generator::delegate_raw as *const u8);
+
+    builder.symbol("tpl_new", operator::tpl_new_raw as *const u8);
+    builder.symbol("array_new", operator::array_new_raw as *const u8);
+    builder.symbol("array_push", operator::array_push as *const u8);
+    builder.symbol("array_spread", operator::array_spread_raw as *const u8);
     builder.symbol("function_new", builtins::Function::new_from_memory as *const u8);
+    builder.symbol("function_capture", builtins::Function::capture_raw as *const u8);
+    builder.symbol("function_capture_slot", builtins::Function::capture_slot_raw as *const u8);
+    builder.symbol("function_capture_closure", builtins::Function::capture_closure_raw as *const u8);
+    builder.symbol("function_bind_this", builtins::Function::bind_this as *const u8);
+    builder.symbol("set_home_object", builtins::Function::set_home_object as *const u8);
+    builder.symbol("define_method", operator::define_method_raw as *const u8);
+    builder.symbol("class_new", operator::class_new_raw as *const u8);
+    builder.symbol("class_fields", builtins::Function::class_fields as *const u8);
+    builder.symbol("new_object", JObject::construct as *const u8);
+
+    builder.symbol("add", operator::add_raw as *const u8);
+    builder.symbol("bitAnd", operator::bitand_raw as *const u8);
+    builder.symbol("bitOr", operator::bitor_raw as *const u8);
+    builder.symbol("bitXor", operator::bitxor_raw as *const u8);
+    builder.symbol("div", operator::div_raw as *const u8);
+    builder.symbol("eqeq", operator::eqeq_raw as *const u8);
+    builder.symbol("eqeqeq", operator::eqeqeq_raw as *const u8);
+    builder.symbol("exp", operator::exp_raw as *const u8);
+    builder.symbol("gt", operator::gt_raw as *const u8);
+    builder.symbol("gteq", operator::gteq_raw as *const u8);
+    builder.symbol("in", operator::in_raw as *const u8);
+    builder.symbol("instanceof", operator::instanceof_raw as *const u8);
+    builder.symbol("lshift", operator::shl_raw as *const u8);
+    builder.symbol("lt", operator::lt_raw as *const u8);
+    builder.symbol("lteq", operator::lteq_raw as *const u8);
+    builder.symbol("mod", operator::rem_raw as *const u8);
+    builder.symbol("mul", operator::mul_raw as *const u8);
+    builder.symbol("noteq", operator::noteq_raw as *const u8);
+    builder.symbol("noteqeq", operator::noteqeq_raw as *const u8);
+    builder.symbol("rshift", operator::shr_raw as *const u8);
+    builder.symbol("sub", operator::sub_raw as *const u8);
+    builder.symbol("unsignedRShift", operator::unsigned_shr_raw as *const u8);
+
+    builder.symbol("typeof", JValue::typeOf as *const u8);
+    builder.symbol("typeof_var", operator::typeof_var_raw as *const u8);
+    builder.symbol("delete_member", operator::delete_member_raw as *const u8);
+    builder.symbol("not", JValue::not as *const u8);
+    builder.symbol("neg", operator::neg_raw as *const u8);
+    builder.symbol("pos", operator::to_number_raw as *const u8);
+    builder.symbol("bitNot", operator::bitnot_raw as *const u8);
+    builder.symbol("to_numeric", operator::to_numeric_raw as *const u8);
+    builder.symbol("increment", operator::increment_raw as *const u8);
+    builder.symbol("decrement", operator::decrement_raw as *const u8);
+
+    builder.symbol("baseline_constant", bytecode::baseline::constant as *const u8);
+    builder.symbol("baseline_closure", bytecode::baseline::closure as *const u8);
+    builder.symbol("baseline_push_scope", bytecode::baseline::push_scope as *const u8);
+    builder.symbol("baseline_pop_scopes", bytecode::baseline::pop_scopes as *const u8);
+    builder.symbol("baseline_call_spread", bytecode::baseline::call_spread as *const u8);
+    builder.symbol("baseline_new_spread", bytecode::baseline::new_spread as *const u8);
+    builder.symbol("baseline_iterator_step", bytecode::baseline::iterator_step as *const u8);
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ pub mod nohasher;
 pub use nohasher::*;
 
 pub(crate) trait ToMutable{
+    #[allow(clippy::mut_from_ref)]
     fn to_mut(&self) -> &mut Self{
         unsafe{(self as *const Self as *mut Self).as_mut().unwrap()}
     }
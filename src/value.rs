@@ -1,4 +1,4 @@
-use std::{sync::Arc, panic::{catch_unwind, UnwindSafe, RefUnwindSafe}, hash::Hash, borrow::Cow};
+use std::{sync::Arc, panic::{catch_unwind, UnwindSafe, RefUnwindSafe}, hash::Hash, borrow::Cow, cmp::Ordering};
 use std::ops::{
     Add,Sub,Div,Mul,Shl,Shr,BitAnd,BitOr,BitXor, Index, Rem
 };
@@ -6,30 +6,56 @@ use std::ops::{
 use cranelift::prelude::types;
 use swc_ecma_ast::AssignOp;
 
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
 use crate::builtins::{
-    object::JObject, 
+    object::{JObject, JObjectInnerEnum, Accessor, PrivateElement}, 
     symbol::Symbol, 
-    string::JString, self
+    string::JString, 
+    property::{Key, PropertyKey}, 
+    bigint::{self, JBigInt}, 
+    prototypes::{resolve_prototype, PrototypeKind}, self
 };
 use crate::runtime::{
     RUNTIME
 };
 use crate::vm::VmContext;
 use crate::operator;
+use crate::utils::ToMutable;
 
 
 #[test]
 fn size_assert(){
-    assert!(std::mem::size_of::<JValue>() == 16)
+    assert!(std::mem::size_of::<JValue>() == 8)
 }
 
+/// a NaN-boxed value.
+///
+/// numbers are stored as their bits, every other value is a quiet NaN with the sign bit set,
+/// tagged by its upper 16 bits with a 48 bit payload:
+///
+/// | bits 63..48 | payload |
+/// |---|---|
+/// | below `0xFFF9` | a number, NaN is canonical |
+/// | `0xFFF9` | undefined, null, false, true |
+/// | `0xFFFA` | symbol id |
+/// | `0xFFFB` | string pointer |
+/// | `0xFFFC` | bigint pointer |
+/// | `0xFFFD` | object pointer |
+/// 
+/// compiled code tests the tag with a single compare and does number arithmetic inline.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct JValue(u64);
+
+/// the decoded form of a `JValue`
 #[derive(Clone, Copy)]
-#[repr(u8)]
-pub enum JValue{    
+pub enum JValueEnum{
     Undefined,
     Null,
     Number(f64),
-    BigInt(i64),
+    BigInt(JBigInt),
     Boolean(bool),
     Symbol(Symbol),
     String(JString),
@@ -41,111 +67,184 @@ impl UnwindSafe for JValue{}
 unsafe impl Send for JValue{}
 unsafe impl Sync for JValue{}
 
+/// the bits of the canonical NaN, other NaNs are converted to it
+pub(crate) const CANONICAL_NAN:u64 = 0x7FF8_0000_0000_0000;
+
+pub(crate) const TAG_SHIFT:u64 = 48;
+pub(crate) const PAYLOAD_MASK:u64 = (1 << TAG_SHIFT) - 1;
+/// values with bits from this one are not numbers
+pub(crate) const TAG_MIN:u64 = TAG_SPECIAL;
+
+pub(crate) const TAG_SPECIAL:u64 = 0xFFF9 << TAG_SHIFT;
+pub(crate) const TAG_SYMBOL:u64 = 0xFFFA << TAG_SHIFT;
+pub(crate) const TAG_STRING:u64 = 0xFFFB << TAG_SHIFT;
+pub(crate) const TAG_BIGINT:u64 = 0xFFFC << TAG_SHIFT;
+pub(crate) const TAG_OBJECT:u64 = 0xFFFD << TAG_SHIFT;
+
+pub(crate) const UNDEFINED:u64 = TAG_SPECIAL;
+pub(crate) const NULL:u64 = TAG_SPECIAL | 1;
+pub(crate) const FALSE:u64 = TAG_SPECIAL | 2;
+pub(crate) const TRUE:u64 = TAG_SPECIAL | 3;
+/// a `let` or `const` binding before its declaration runs, never visible to scripts
+pub(crate) const UNINITIALIZED:u64 = TAG_SPECIAL | 4;
+
+#[allow(non_upper_case_globals)]
 impl JValue{
-    pub const TYPE:types::Type = types::I128;
+    pub const TYPE:types::Type = types::I64;
 
-    pub fn null(&self) -> Option<()>{
-        match self{
-            Self::Null => Some(()),
-            _ => None
+    pub const Undefined:JValue = JValue(UNDEFINED);
+    pub const Null:JValue = JValue(NULL);
+
+    pub fn Number(n:f64) -> JValue{
+        if n.is_nan(){
+            JValue(CANONICAL_NAN)
+        } else{
+            JValue(n.to_bits())
         }
     }
 
-    pub fn undefined(&self) -> Option<()>{
-        match self{
-            Self::Undefined => Some(()),
-            _ => None
+    /// the value of a bigint is allocated on the string allocator
+    pub fn BigInt(b:BigInt) -> JValue{
+        JValue(TAG_BIGINT | JBigInt::new(&b).as_ptr() as u64)
+    }
+
+    pub fn Boolean(b:bool) -> JValue{
+        JValue(if b {TRUE} else {FALSE})
+    }
+
+    pub fn Symbol(s:Symbol) -> JValue{
+        JValue(TAG_SYMBOL | s.id as u64)
+    }
+
+    pub fn String(s:JString) -> JValue{
+        JValue(TAG_STRING | s.as_ptr() as u64)
+    }
+
+    pub fn Object(o:*mut JObject) -> JValue{
+        JValue(TAG_OBJECT | o as u64)
+    }
+
+    pub(crate) fn from_bits(bits:u64) -> JValue{
+        JValue(bits)
+    }
+
+    pub(crate) fn to_bits(self) -> u64{
+        self.0
+    }
+
+    fn tag(self) -> u64{
+        self.0 & !PAYLOAD_MASK
+    }
+
+    fn payload(self) -> u64{
+        self.0 & PAYLOAD_MASK
+    }
+
+    /// decode the value to match on its type
+    pub fn unbox(self) -> JValueEnum{
+        if self.0 < TAG_MIN{
+            return JValueEnum::Number(f64::from_bits(self.0))
+        }
+        match self.tag(){
+            TAG_SYMBOL => JValueEnum::Symbol(Symbol{id:self.payload() as u32}),
+            TAG_STRING => JValueEnum::String(JString::from_ptr(self.payload() as *const u8)),
+            TAG_BIGINT => JValueEnum::BigInt(JBigInt::from_ptr(self.payload() as *const u8)),
+            TAG_OBJECT => JValueEnum::Object(self.payload() as *mut JObject),
+            _ => match self.0{
+                NULL => JValueEnum::Null,
+                FALSE => JValueEnum::Boolean(false),
+                TRUE => JValueEnum::Boolean(true),
+                _ => JValueEnum::Undefined
+            }
         }
     }
 
+    pub fn null(&self) -> Option<()>{
+        if self.0 == NULL {Some(())} else {None}
+    }
+
+    pub fn undefined(&self) -> Option<()>{
+        if self.0 == UNDEFINED {Some(())} else {None}
+    }
+
     pub fn number(&self) -> Option<f64>{
-        match self{
-            Self::Number(v) => Some(*v),
-            _ => None
-        }
+        if self.0 < TAG_MIN {Some(f64::from_bits(self.0))} else {None}
     }
 
-    pub fn bigint(&self) -> Option<i64>{
-        match self{
-            Self::BigInt(v) => Some(*v),
+    pub fn bigint(&self) -> Option<BigInt>{
+        match self.unbox(){
+            JValueEnum::BigInt(v) => Some(v.value()),
             _ => None
         }
     }
 
     pub fn symbol(&self) -> Option<Symbol>{
-        match self{
-            Self::Symbol(v) => Some(*v),
+        match self.unbox(){
+            JValueEnum::Symbol(v) => Some(v),
             _ => None
         }
     }
 
     pub fn string(&self) -> Option<JString>{
-        match self{
-            Self::String(v) => Some(*v),
+        match self.unbox(){
+            JValueEnum::String(v) => Some(v),
             _ => None
         }
     }
 
     pub fn object(&self) ->  Option<&'static mut JObject>{
-        match *self{
-            Self::Object(o) => unsafe{Some(o.as_mut().unwrap())},
-            _ => None
+        if self.tag() == TAG_OBJECT{
+            unsafe{(self.payload() as *mut JObject).as_mut()}
+        } else{
+            None
         }
     }
 
     pub fn bool(&self) -> Option<bool>{
-        match *self{
-            Self::Boolean(b) => Some(b),
+        match self.0{
+            TRUE => Some(true),
+            FALSE => Some(false),
             _ => None
         }
     }
 
     pub fn is_null(&self) -> bool{
-        match self{
-            Self::Null => true,
-            _ => false
-        }
+        self.0 == NULL
     }
 
     pub fn is_undefined(&self) -> bool{
-        match self{
-            Self::Undefined => true,
-            _ => false
-        }
+        self.0 == UNDEFINED
+    }
+
+    /// return true if the value is null or undefined
+    pub fn is_nullish(self) -> bool{
+        self.0 == NULL || self.0 == UNDEFINED
     }
 
     pub fn is_number(&self) -> bool{
-        match self{
-            Self::Number(_) => true,
-            _ => false
-        }
+        self.0 < TAG_MIN
     }
 
     pub fn is_bigint(&self) -> bool{
-        match self{
-            Self::BigInt(_) => true,
-            _ => false
-        }
+        self.tag() == TAG_BIGINT
     }
 
     pub fn is_symbol(&self) -> bool{
-        match self{
-            Self::Symbol(_) => true,
-            _ => false
-        }
+        self.tag() == TAG_SYMBOL
     }
 
     pub fn is_string(&self) -> bool{
-        match self{
-            Self::String(_) => true,
-            _ => false
-        }
+        self.tag() == TAG_STRING
     }
 
     pub fn is_object(&self) -> bool{
-        match self{
-            Self::Object(_) => true,
-            _ => false
+        self.tag() == TAG_OBJECT
+    }
+
+    pub fn is_function(&self) -> bool{
+        match self.object(){
+            Some(o) => o.inner.is_function(),
+            None => false
         }
     }
     
@@ -155,58 +254,198 @@ impl JValue{
     /// 
     /// users should use the `toOwned` function instead.
     pub fn keep_alive(&self, b:bool){
-        if let JValue::Object(o) = *self{
-            
-        } else if let JValue::String(s) = *self{
+        if let JValueEnum::Object(o) = self.unbox(){
+            unsafe{&*o}.keep_alive(b);
+        } else if let JValueEnum::String(s) = self.unbox(){
 
         }
-        todo!("user owned value not yet implemented")
     }
 
     pub fn to_float(self) -> f64{
-        match self{
-            JValue::Null => 0.0,
-            JValue::Undefined => f64::NAN,
-            JValue::BigInt(i) => i as f64,
-            JValue::Boolean(b) => b as u8 as f64,
-            JValue::Number(n) => n,
-            JValue::String(s) => {
-                if let Ok(f) = s.parse::<f64>(){
-                    f
-                } else{
-                    f64::NAN
-                }
-            },
-            JValue::Symbol(s) => f64::NAN,
-            JValue::Object(o) => {
-                f64::NAN
+        match self.unbox(){
+            JValueEnum::Null => 0.0,
+            JValueEnum::Undefined => f64::NAN,
+            JValueEnum::BigInt(i) => i.value().to_f64().unwrap_or(f64::NAN),
+            JValueEnum::Boolean(b) => b as u8 as f64,
+            JValueEnum::Number(n) => n,
+            JValueEnum::String(s) => string_to_number(&s),
+            JValueEnum::Symbol(s) => f64::NAN,
+            JValueEnum::Object(o) => {
+                self.to_primitive(Some("number")).to_float()
             },
         }
     }
 
+    /// ToInt32, the number modulo 2^32 as a signed integer
     pub fn to_i32(self) -> i32{
-        match self{
-            JValue::Null => 0,
-            JValue::Undefined => 0,
-            JValue::Boolean(b) => b as i32,
-            JValue::BigInt(b) => b as i32,
-            JValue::Number(n) => n as i32,
-            JValue::Object(o) => 0,
-            JValue::String(n) => 0,
-            JValue::Symbol(s) => 0,
+        match self.unbox(){
+            // the low 32 bits of the two's complement
+            JValueEnum::BigInt(b) => (b.value() & BigInt::from(u32::MAX)).to_u32().unwrap() as i32,
+            _ => {
+                let n = self.to_float();
+                if !n.is_finite(){
+                    return 0
+                }
+                n.trunc().rem_euclid(4294967296.0) as u32 as i32
+            }
+        }
+    }
+
+    /// ToPrimitive, call `valueOf` and `toString` on objects in the order given by `hint`.
+    /// 
+    /// hint is "number", "string" or none for default.
+    pub fn to_primitive(self, hint:Option<&str>) -> JValue{
+        let obj = match self.object(){
+            Some(o) => o,
+            None => return self
+        };
+
+        let exotic = obj.member_str(&builtins::symbol::ToPrimitive.to_property_key());
+        if !exotic.is_nullish(){
+            if !exotic.is_function(){
+                operator::throw(builtins::Error::newTypeError("Symbol.toPrimitive is not a function"))
+            }
+            let hint:JValue = hint.unwrap_or("default").into();
+            return match exotic.call(self, &[hint]){
+                Ok(v) if v.is_object() => operator::throw(builtins::Error::newTypeError("Cannot convert object to primitive value")),
+                Ok(v) => v,
+                Err(e) => operator::throw(e)
+            }
+        }
+
+        let methods = if hint == Some("string"){
+            ["toString", "valueOf"]
+        } else{
+            ["valueOf", "toString"]
+        };
+
+        let mut found = false;
+        for name in methods{
+            let method = obj.member_str(name);
+            // the builtin `Object.prototype.toString` does not hide the native conversions
+            if method.is_function() && builtins::object::is_builtin_to_string(method){
+                if let Some(v) = Self::native_primitive(obj){
+                    return v
+                }
+            }
+            if method.is_function(){
+                found = true;
+                match method.call(self, &[]){
+                    Ok(v) if !v.is_object() => return v,
+                    Ok(_) => {},
+                    Err(e) => operator::throw(e)
+                }
+            }
+        }
+
+        if found{
+            operator::throw(builtins::Error::newTypeError("Cannot convert object to primitive value"))
+        }
+
+        // objects without the builtin methods
+        Self::native_primitive(obj).unwrap_or_else(||"[object Object]".into())
+    }
+
+    /// the primitive of objects the runtime converts natively
+    fn native_primitive(obj:&JObject) -> Option<JValue>{
+        Some(match &obj.inner{
+            JObjectInnerEnum::Array(a) => {
+                let values:Vec<String> = a.values.iter().map(|v|{
+                    if v.is_null() || v.is_undefined(){
+                        String::new()
+                    } else{
+                        v.to_string()
+                    }
+                }).collect();
+                values.join(",").into()
+            },
+            JObjectInnerEnum::Function(_) => "function () { [native code] }".into(),
+            JObjectInnerEnum::Error(e) => e.to_string().into(),
+            JObjectInnerEnum::Boolean(b) => JValue::Boolean(*b),
+            JObjectInnerEnum::Number(n) => JValue::Number(*n),
+            JObjectInnerEnum::BigInt(b) => JValue::BigInt(b.value()),
+            JObjectInnerEnum::String(s) => JValue::String(*s),
+            _ => return None
+        })
+    }
+
+    /// ToString, symbols cannot be converted implicitly
+    pub fn to_js_string(self) -> String{
+        let v = self.to_primitive(Some("string"));
+        match v.unbox(){
+            JValueEnum::Symbol(_) => operator::throw(builtins::Error::newTypeError("Cannot convert a Symbol value to a string")),
+            _ => v.to_string()
+        }
+    }
+
+    /// ToNumeric, a number or a bigint
+    pub fn to_numeric(self) -> JValue{
+        let v = self.to_primitive(Some("number"));
+        match v.unbox(){
+            JValueEnum::BigInt(_) => v,
+            _ => JValue::Number(v.to_float())
+        }
+    }
+
+    /// the unary `+` operator
+    pub fn to_number(self) -> JValue{
+        let v = self.to_primitive(Some("number"));
+        match v.unbox(){
+            JValueEnum::BigInt(_) => operator::throw(builtins::Error::newTypeError("Cannot convert a BigInt value to a number")),
+            JValueEnum::Symbol(_) => operator::throw(builtins::Error::newTypeError("Cannot convert a Symbol value to a number")),
+            _ => JValue::Number(v.to_float())
+        }
+    }
+
+    /// the unary `-` operator
+    pub fn neg(self) -> JValue{
+        let v = self.to_numeric();
+        match v.unbox(){
+            JValueEnum::BigInt(b) => JValue::BigInt(-b.value()),
+            _ => JValue::Number(-v.to_float())
+        }
+    }
+
+    /// the `~` operator
+    pub fn bitNot(self) -> JValue{
+        let v = self.to_numeric();
+        match v.unbox(){
+            JValueEnum::BigInt(b) => JValue::BigInt(!b.value()),
+            _ => JValue::Number(!v.to_i32() as f64)
+        }
+    }
+
+    /// the `!` operator
+    pub fn not(self) -> JValue{
+        JValue::Boolean(!self.to_bool())
+    }
+
+    /// add one to a numeric value, used by `++`
+    pub fn increment(self) -> JValue{
+        match self.unbox(){
+            JValueEnum::BigInt(b) => JValue::BigInt(b.value() + 1),
+            _ => JValue::Number(self.to_float() + 1.0)
+        }
+    }
+
+    /// subtract one from a numeric value, used by `--`
+    pub fn decrement(self) -> JValue{
+        match self.unbox(){
+            JValueEnum::BigInt(b) => JValue::BigInt(b.value() - 1),
+            _ => JValue::Number(self.to_float() - 1.0)
         }
     }
 
     pub fn to_bool(self) -> bool{
-        match self{
-            JValue::Null => false,
-            JValue::Undefined => false,
-            JValue::BigInt(b) => b!=0,
-            JValue::Boolean(b) => b,
-            JValue::Number(n) => n!=0.0,
-            JValue::Object(o) => true,
-            JValue::String(s) => unsafe{s.len() != 0},
-            JValue::Symbol(s) => true,
+        match self.unbox(){
+            JValueEnum::Null => false,
+            JValueEnum::Undefined => false,
+            JValueEnum::BigInt(b) => !b.value().is_zero(),
+            JValueEnum::Boolean(b) => b,
+            JValueEnum::Number(n) => n!=0.0,
+            JValueEnum::Object(o) => true,
+            JValueEnum::String(s) => unsafe{s.len() != 0},
+            JValueEnum::Symbol(s) => true,
         }
     }
 
@@ -214,7 +453,7 @@ impl JValue{
         RUNTIME.with(|runtime|{
             let (re, ok) = unsafe{
                 self.new_raw(
-                    &mut runtime.to_mut().context,
+                    &mut runtime.realm().context,
                     args.as_ptr(), 
                     args.len() as i64, false)
             };
@@ -226,15 +465,41 @@ impl JValue{
         })
     }
 
-    pub fn new_raw(self, vmctx:&mut VmContext, argv:*const JValue, argc:i64, spread:bool) -> (JValue, bool){
-        todo!()
+    pub unsafe fn new_raw(self, vmctx:&mut VmContext, argv:*const JValue, argc:i64, spread:bool) -> (JValue, bool){
+        let object = self.object();
+        let func = match object.as_ref().and_then(|o|o.inner.function()){
+            Some(f) if f.is_constructor() => f,
+            _ => return (builtins::Error::newTypeError(format!("{} is not a constructor", self.to_string())), false)
+        };
+
+        // collecting the arguments and reading the prototype may throw
+        let mut args = Vec::new();
+        let (this, ok) = operator::catch(||{
+            args = operator::collect_args(argv, argc, spread);
+
+            let this = JObject::new();
+            if let JValueEnum::Object(proto) = self.member_str("prototype").unbox(){
+                this.set_prototype(proto);
+            }
+            JValue::Object(this)
+        });
+        if !ok{
+            return (this, false)
+        }
+
+        let (re, ok) = func.to_mut().construct(vmctx, this, &args, self);
+        if !ok || re.is_object(){
+            (re, ok)
+        } else{
+            (this, true)
+        }
     }
 
     pub fn call(self, this:JValue, args:&[JValue]) -> Result<JValue, JValue>{
         RUNTIME.with(|runtime|{
             let (re, ok) = unsafe{
                 self.call_raw(
-                    &mut runtime.to_mut().context,
+                    &mut runtime.realm().context,
                     this,
                     args.as_ptr(), 
                     args.len() as i64, false)
@@ -249,48 +514,26 @@ impl JValue{
     }
     
     pub(crate) unsafe fn call_raw(self, vmctx:&mut VmContext, this:JValue, argv:*const JValue, argc:i64, spread:bool) -> (JValue, bool){
-        if let Some(o) = self.object(){
-            if let Some(f) = o.inner.function(){
-                    let args = std::slice::from_raw_parts(argv, argc as usize);
-
-                    // spread the last argument
-                    let re = if spread{
-
-                        let mut v = args.to_vec();
-                        v.extend(operator::IteratorCollect(args[args.len()-1]));
-
-                        let ctx = vmctx as *mut VmContext as usize;
-
-                        catch_unwind(||{
-                            let r = self;
-                            r.object().unwrap().inner.call((ctx as *mut VmContext).as_mut().unwrap(), this, &v)
-                        })
-
-                    } else{
-                        let ctx = vmctx as *mut VmContext as usize;
-                        
-                        catch_unwind(||{
-                            let r = self;
-                            r.object().unwrap().inner.call((ctx as *mut VmContext).as_mut().unwrap(), this, args)
-                        })
-                    };
-
-                    match re{
-                        Ok(v) => return (v, true),
-                        Err(err) => {
-                            if let Some(v) = err.downcast_ref::<JValue>(){
-                                return (*v, false)
-                            } else{
-                                return (JValue::Undefined, false)
-                            }
-                        }
-                    }
-            } else{
-                return (JValue::Undefined, false)
+        let object = self.object();
+        let func = match object.as_ref().and_then(|o|o.inner.function()){
+            Some(f) => f,
+            None => return (builtins::Error::newTypeError(format!("{} is not a function", self.to_string())), false)
+        };
+
+        if spread{
+            // spread the last argument, iterating it may throw
+            let mut args = Vec::new();
+            let (e, ok) = operator::catch(||{
+                args = operator::collect_args(argv, argc, spread);
+                JValue::Undefined
+            });
+            if !ok{
+                return (e, false)
             }
+            func.to_mut().call_raw(vmctx, this, &args)
         } else{
-            // todo: type Error
-            return (JValue::Undefined, false)
+            let args = std::slice::from_raw_parts(argv, argc as usize);
+            func.to_mut().call_raw(vmctx, this, args)
         }
     }
 
@@ -299,464 +542,609 @@ impl JValue{
     }
 
     pub unsafe fn superMemberCall_raw(self, key:JValue, vmctx:&mut VmContext, argv:*const JValue, argc:i64, spread:bool) -> (JValue, bool){
-        todo!()
+        let ctx = vmctx as *mut VmContext as usize;
+        let (func, ok) = operator::catch(||{
+            JValue::superMember((ctx as *mut VmContext).as_mut().unwrap(), self, key)
+        });
+        if !ok{
+            return (func, false)
+        }
+        func.call_raw(vmctx, self, argv, argc, spread)
     }
 
-    pub fn member_str(self, name:&str) -> JValue{
-        match self{
-            JValue::Null => operator::throw(JValue::Undefined),
-            JValue::Undefined => operator::throw(JValue::Undefined),
-            JValue::Object(o) => return unsafe{&mut *o}.member_str(name),
-            _ => todo!()
+    pub fn member_str<'a>(self, name:impl Into<Key<'a>>) -> JValue{
+        let name = name.into();
+        let kind = match self.unbox(){
+            JValueEnum::Null => operator::throw(builtins::Error::newTypeError(format!("Cannot read properties of null (reading '{}')", name))),
+            JValueEnum::Undefined => operator::throw(builtins::Error::newTypeError(format!("Cannot read properties of undefined (reading '{}')", name))),
+            JValueEnum::Object(o) => return unsafe{&mut *o}.member_str(name),
+            JValueEnum::String(s) => {
+                if name == Key::String("length"){
+                    return JValue::Number(s.chars().count() as f64)
+                }
+                if let Some(Ok(i)) = name.string().map(|n|n.parse::<usize>()){
+                    if let Some(c) = s.chars().nth(i){
+                        return c.to_string().into()
+                    }
+                }
+                PrototypeKind::String
+            },
+            JValueEnum::Number(_) => PrototypeKind::Number,
+            JValueEnum::Boolean(_) => PrototypeKind::Boolean,
+            JValueEnum::Symbol(_) => PrototypeKind::Symbol,
+            JValueEnum::BigInt(_) => PrototypeKind::BigInt,
+        };
+        // the methods of primitives are read from the prototype of the current realm
+        // and called with the primitive as `this`
+        match unsafe{resolve_prototype(kind).as_mut()}{
+            Some(proto) => proto.member_str_with(name, self),
+            None => JValue::Undefined
         }
-        return JValue::Undefined
     }
 
     pub fn member(self, key:JValue) -> JValue{
-        match key{
-            JValue::String(s) => self.member_str(&s),
-            JValue::Symbol(s) => self.member_str(&s),
-            v => self.member_str(&v.to_string())
+        match key.unbox(){
+            JValueEnum::String(s) => self.member_str(&*s),
+            _ => self.member_str(&key.to_property_key())
         }
     }
 
-    pub fn set_member(self, key:JValue, value:JValue) {
-        todo!()
+    /// the key used to store the value as a property name
+    pub(crate) fn to_property_key(self) -> PropertyKey{
+        match self.unbox(){
+            JValueEnum::Symbol(s) => PropertyKey::Symbol(s.id),
+            _ => PropertyKey::String(self.to_string())
+        }
     }
 
-    
-    pub fn assign_member(self, key:JValue, value:JValue, op:i8) -> JValue{
-        
-        match unsafe{std::mem::transmute::<_, AssignOp>(op)}{
-            AssignOp::Assign => {
-                self.set_member(key, value);
-                return value
+    /// `super[key]` where self is `this`
+    pub fn superMember(vmctx:&mut VmContext, this:JValue, key:JValue) -> JValue{
+        match vmctx.super_base(){
+            Some(base) => base.member_str_with(&key.to_property_key(), this),
+            None => operator::throw(builtins::Error::newTypeError("'super' keyword unexpected here"))
+        }
+    }
+
+    /// define an own property on self, used by class fields
+    pub fn define_field(self, key:JValue, value:JValue){
+        if let Some(o) = self.object(){
+            o.define_own(&key.to_property_key(), value)
+        }
+    }
+
+    fn private_target(self, key:JValue) -> (&'static mut JObject, Symbol){
+        match (self.object(), key.symbol()){
+            (Some(o), Some(s)) => (o, s),
+            _ => operator::throw(builtins::Error::newTypeError(format!("Cannot access private member #{} of non-object", key.to_string())))
+        }
+    }
+
+    /// `self.#key`
+    pub fn private_get(self, key:JValue) -> JValue{
+        let (o, key) = self.private_target(key);
+        o.get_private(key)
+    }
+
+    /// `self.#key op= value`
+    pub fn private_set(self, key:JValue, value:JValue, op:i8) -> JValue{
+        let (o, sym) = self.private_target(key);
+
+        let op = unsafe{std::mem::transmute::<_, AssignOp>(op)};
+        let value = match op{
+            AssignOp::Assign => value,
+            op => {
+                let old = o.get_private(sym);
+                match old.apply_assign_op(value, op){
+                    Some(v) => v,
+                    // short circuited
+                    None => return old
+                }
             }
-            AssignOp::AddAssign => {
-                let v = self.member(key) + value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::AndAssign => {
-                let v = self.member(key).and(value);
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::BitAndAssign => {
-                let v = self.member(key) & value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::BitOrAssign => {
-                let v = self.member(key) | value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::BitXorAssign => {
-                let v = self.member(key) ^ value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::DivAssign => {
-                let v = self.member(key) / value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::ExpAssign => {
-                let v = self.member(key).exp(value);
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::LShiftAssign => {
-                let v = self.member(key) << value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::ModAssign => {
-                let v = self.member(key) % value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::MulAssign => {
-                let v = self.member(key) * value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::NullishAssign => {
-                let v = self.member(key);
-                if key.is_null() && key.is_undefined(){
-                    self.set_member(key, value);
-                    return value
-                };
-                return v;
-            },
-            AssignOp::OrAssign => {
-                let v = self.member(key).or(value);
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::RShiftAssign => {
-                let v = self.member(key) >> value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::SubAssign => {
-                let v = self.member(key) - value;
-                self.set_member(key, v);
-                return v
-            },
-            AssignOp::ZeroFillRShiftAssign => {
-                let v = self.member(key).unsignedRShift(value);
-                self.set_member(key, v);
-                return v
-            },
+        };
+        o.set_private(sym, value);
+        value
+    }
+
+    /// install a private element on self
+    /// 
+    /// kind: 0 for field, 1 for method, 2 for getter, 3 for setter
+    pub fn private_define(self, key:JValue, value:JValue, kind:i8){
+        let (o, key) = self.private_target(key);
+        let element = match kind{
+            1 => PrivateElement::Method(value),
+            2 => PrivateElement::Accessor(Accessor{get:Some(value), set:None}),
+            3 => PrivateElement::Accessor(Accessor{get:None, set:Some(value)}),
+            _ => PrivateElement::Field(value)
+        };
+        o.define_private(key, element);
+    }
+
+    /// `#key in self`
+    pub fn private_in(self, key:JValue) -> JValue{
+        match self.object(){
+            Some(o) => JValue::Boolean(o.has_private(key.symbol().unwrap())),
+            None => operator::throw(builtins::Error::newTypeError("Cannot use 'in' operator to search for a private field in a non-object"))
         }
     }
 
-    pub fn owned_keys(self) -> Vec<&'static str>{
-        match self{
-            JValue::Object(o) => {
-                todo!()
+    /// compute `self op value` for a compound assignment, 
+    /// returns none if the assignment short circuits.
+    pub fn apply_assign_op(self, value:JValue, op:AssignOp) -> Option<JValue>{
+        Some(match op{
+            AssignOp::Assign => value,
+            AssignOp::AddAssign => self + value,
+            AssignOp::SubAssign => self - value,
+            AssignOp::MulAssign => self * value,
+            AssignOp::DivAssign => self / value,
+            AssignOp::ModAssign => self % value,
+            AssignOp::ExpAssign => self.exp(value),
+            AssignOp::BitAndAssign => self & value,
+            AssignOp::BitOrAssign => self | value,
+            AssignOp::BitXorAssign => self ^ value,
+            AssignOp::LShiftAssign => self << value,
+            AssignOp::RShiftAssign => self >> value,
+            AssignOp::ZeroFillRShiftAssign => self.unsignedRShift(value),
+            AssignOp::AndAssign => if self.to_bool(){value} else{return None},
+            AssignOp::OrAssign => if self.to_bool(){return None} else{value},
+            AssignOp::NullishAssign => match self.unbox(){
+                JValueEnum::Null | JValueEnum::Undefined => value,
+                _ => return None
             },
+        })
+    }
+
+    pub fn set_member(self, key:JValue, value:JValue) {
+        match self.unbox(){
+            JValueEnum::Null => operator::throw(builtins::Error::newTypeError(format!("Cannot set properties of null (setting '{}')", key.to_string()))),
+            JValueEnum::Undefined => operator::throw(builtins::Error::newTypeError(format!("Cannot set properties of undefined (setting '{}')", key.to_string()))),
+            JValueEnum::Object(o) => unsafe{&mut *o}.set_member_str(&key.to_property_key(), value),
+            // setting properties on primitives has no effect
+            _ => {}
+        }
+    }
+
+    /// copy the own keys of `spread` into self, used by object literals
+    pub fn set_member_spread(self, spread:JValue){
+        for key in spread.owned_keys(){
+            self.set_member(key.to_value(), spread.member_str(&key));
+        }
+    }
+
+    /// the own enumerable property keys in property order
+    pub(crate) fn owned_keys(self) -> Vec<PropertyKey>{
+        match self.unbox(){
+            JValueEnum::Object(o) => unsafe{&*o}.own_keys(true),
+            JValueEnum::String(s) => (0..s.chars().count()).map(|i|PropertyKey::String(i.to_string())).collect(),
             _ => Vec::new()
         }
     }
 
     pub fn typeOf(self) -> JValue{
-        match self{
-            Self::Undefined => "undefined\0".into(),
-            Self::Null => "null\0".into(),
-            Self::Boolean(_) => "boolean\0".into(),
-            Self::Number(_) => "number\0".into(),
-            Self::BigInt(_) => "bigint\0".into(),
-            Self::Symbol(_) => "symbol\0".into(),
-            Self::String(_) => "string\0".into(),
-            Self::Object(_) => "object\0".into()
+        match self.unbox(){
+            JValueEnum::Undefined => "undefined".into(),
+            JValueEnum::Null => "object".into(),
+            JValueEnum::Boolean(_) => "boolean".into(),
+            JValueEnum::Number(_) => "number".into(),
+            JValueEnum::BigInt(_) => "bigint".into(),
+            JValueEnum::Symbol(_) => "symbol".into(),
+            JValueEnum::String(_) => "string".into(),
+            JValueEnum::Object(_) if self.is_function() => "function".into(),
+            JValueEnum::Object(_) => "object".into()
         }
     }
 
-    pub fn eqeqeq(self, rhs:Self) -> JValue{
-        match self{
-            JValue::String(s) => match rhs{
-                JValue::String(s1) => JValue::Boolean(s.as_ref() == s1.as_ref()),
-                _ => JValue::Boolean(false),
+    /// `delete self[key]`, return true if the property no longer exists
+    pub fn delete_member(self, key:JValue) -> JValue{
+        match self.unbox(){
+            JValueEnum::Null | JValueEnum::Undefined => operator::throw(builtins::Error::newTypeError(format!("Cannot convert undefined or null to object"))),
+            JValueEnum::Object(o) => JValue::Boolean(unsafe{&mut *o}.delete_property(&key.to_property_key())),
+            _ => JValue::Boolean(true)
+        }
+    }
+
+    /// SameValue, NaN is the same as itself and +0 differs from -0
+    pub fn same_value(self, rhs:Self) -> bool{
+        match (self.number(), rhs.number()){
+            (Some(a), Some(b)) => (a.is_nan() && b.is_nan()) || (a == b && a.is_sign_negative() == b.is_sign_negative()),
+            _ => self.strict_equals(rhs)
+        }
+    }
+
+    /// IsStrictlyEqual
+    pub fn strict_equals(self, rhs:Self) -> bool{
+        match (self.unbox(), rhs.unbox()){
+            (JValueEnum::Undefined, JValueEnum::Undefined) |
+            (JValueEnum::Null, JValueEnum::Null) => true,
+            (JValueEnum::Number(a), JValueEnum::Number(b)) => a == b,
+            (JValueEnum::BigInt(a), JValueEnum::BigInt(b)) => a.bytes() == b.bytes(),
+            (JValueEnum::Boolean(a), JValueEnum::Boolean(b)) => a == b,
+            (JValueEnum::String(a), JValueEnum::String(b)) => a.as_str() == b.as_str(),
+            (JValueEnum::Symbol(a), JValueEnum::Symbol(b)) => a.id == b.id,
+            (JValueEnum::Object(a), JValueEnum::Object(b)) => a == b,
+            _ => false
+        }
+    }
+
+    /// IsLooselyEqual
+    pub fn loose_equals(self, rhs:Self) -> bool{
+        match (self.unbox(), rhs.unbox()){
+            (JValueEnum::Null, JValueEnum::Null) |
+            (JValueEnum::Null, JValueEnum::Undefined) |
+            (JValueEnum::Undefined, JValueEnum::Null) |
+            (JValueEnum::Undefined, JValueEnum::Undefined) => true,
+            (JValueEnum::Null, _) | (_, JValueEnum::Null) |
+            (JValueEnum::Undefined, _) | (_, JValueEnum::Undefined) => false,
+
+            (JValueEnum::Number(a), JValueEnum::String(b)) => a == string_to_number(&b),
+            (JValueEnum::String(a), JValueEnum::Number(b)) => string_to_number(&a) == b,
+            (JValueEnum::BigInt(a), JValueEnum::String(b)) => {
+                bigint::string_to_bigint(&b) == Some(a.value())
             },
-            v => unsafe{JValue::Boolean(std::mem::transmute::<_,i128>(v) == std::mem::transmute::<_, i128>(rhs))}
+            (JValueEnum::String(_), JValueEnum::BigInt(_)) => rhs.loose_equals(self),
+
+            (JValueEnum::Boolean(b), _) => JValue::Number(b as u8 as f64).loose_equals(rhs),
+            (_, JValueEnum::Boolean(b)) => self.loose_equals(JValue::Number(b as u8 as f64)),
+
+            (JValueEnum::Object(_), JValueEnum::Object(_)) => self.strict_equals(rhs),
+            (JValueEnum::Object(_), _) => self.to_primitive(None).loose_equals(rhs),
+            (_, JValueEnum::Object(_)) => self.loose_equals(rhs.to_primitive(None)),
+
+            (JValueEnum::BigInt(a), JValueEnum::Number(b)) |
+            (JValueEnum::Number(b), JValueEnum::BigInt(a)) => bigint::compare_number(&a.value(), b) == Some(Ordering::Equal),
+            _ => self.strict_equals(rhs)
         }
     }
 
-    pub fn and(self, rhs:Self) -> JValue{
-        JValue::Boolean(self.to_bool() && rhs.to_bool())
+    /// IsLessThan, none if either operand is NaN.
+    /// 
+    /// `left_first` is the evaluation order of ToPrimitive.
+    fn less_than(self, rhs:Self, left_first:bool) -> Option<bool>{
+        let (x, y) = if left_first{
+            let x = self.to_primitive(Some("number"));
+            (x, rhs.to_primitive(Some("number")))
+        } else{
+            let y = rhs.to_primitive(Some("number"));
+            (self.to_primitive(Some("number")), y)
+        };
+
+        match (x.unbox(), y.unbox()){
+            // strings are compared by code units
+            (JValueEnum::String(a), JValueEnum::String(b)) => Some(a.encode_utf16().lt(b.encode_utf16())),
+            (JValueEnum::BigInt(a), JValueEnum::String(b)) => {
+                bigint::string_to_bigint(&b).map(|b|a.value() < b)
+            },
+            (JValueEnum::String(a), JValueEnum::BigInt(b)) => {
+                bigint::string_to_bigint(&a).map(|a|a < b.value())
+            },
+            _ => {
+                let (x, y) = (x.to_numeric(), y.to_numeric());
+                match (x.unbox(), y.unbox()){
+                    (JValueEnum::BigInt(a), JValueEnum::BigInt(b)) => Some(a.value() < b.value()),
+                    // bigints and numbers are compared by their mathematical values
+                    (JValueEnum::BigInt(a), _) => bigint::compare_number(&a.value(), y.to_float()).map(|o|o == Ordering::Less),
+                    (_, JValueEnum::BigInt(b)) => bigint::compare_number(&b.value(), x.to_float()).map(|o|o == Ordering::Greater),
+                    _ => {
+                        let (a, b) = (x.to_float(), y.to_float());
+                        if a.is_nan() || b.is_nan(){
+                            None
+                        } else{
+                            Some(a < b)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn eqeqeq(self, rhs:Self) -> JValue{
+        JValue::Boolean(self.strict_equals(rhs))
+    }
+
+    pub fn noteqeq(self, rhs:Self) -> JValue{
+        JValue::Boolean(!self.eqeqeq(rhs).to_bool())
+    }
+
+    pub fn eqeq(self, rhs:Self) -> JValue{
+        JValue::Boolean(self.loose_equals(rhs))
+    }
+
+    pub fn noteq(self, rhs:Self) -> JValue{
+        JValue::Boolean(!self.eqeq(rhs).to_bool())
+    }
+
+    pub fn lt(self, rhs:Self) -> JValue{
+        JValue::Boolean(self.less_than(rhs, true) == Some(true))
+    }
+
+    pub fn lteq(self, rhs:Self) -> JValue{
+        JValue::Boolean(rhs.less_than(self, false) == Some(false))
+    }
+
+    pub fn gt(self, rhs:Self) -> JValue{
+        JValue::Boolean(rhs.less_than(self, false) == Some(true))
+    }
+
+    pub fn gteq(self, rhs:Self) -> JValue{
+        JValue::Boolean(self.less_than(rhs, true) == Some(false))
     }
 
-    pub fn or(self, rhs:Self) -> JValue{
-        JValue::Boolean(self.to_bool() || rhs.to_bool())
+    /// `self in rhs`
+    pub fn In(self, rhs:Self) -> JValue{
+        match rhs.object(){
+            Some(o) => JValue::Boolean(o.has_property(&self.to_property_key())),
+            None => operator::throw(builtins::Error::newTypeError(format!("Cannot use 'in' operator to search for '{}' in {}", self.to_string(), rhs.to_string())))
+        }
+    }
+
+    /// `self instanceof rhs`
+    pub fn instanceof(self, rhs:Self) -> JValue{
+        if !rhs.is_function(){
+            operator::throw(builtins::Error::newTypeError("Right-hand side of 'instanceof' is not callable"))
+        }
+
+        let obj = match self.object(){
+            Some(o) => o,
+            None => return JValue::Boolean(false)
+        };
+
+        let proto = match rhs.member_str("prototype").unbox(){
+            JValueEnum::Object(p) => p,
+            _ => operator::throw(builtins::Error::newTypeError("Function has non-object prototype in instanceof check"))
+        };
+
+        let mut p = obj.prototype();
+        while let Some(o) = p{
+            if o as *mut JObject == proto{
+                return JValue::Boolean(true)
+            }
+            p = o.prototype();
+        }
+        JValue::Boolean(false)
     }
 
     pub fn exp(self, rhs:Self) -> JValue{
-        match self{
-            JValue::Number(n) => {
-                JValue::Number(n.powf(rhs.to_float()))
+        self.numeric_op(rhs, f64::powf, bigint::pow)
+    }
+
+    /// apply an arithmetic operator after ToNumeric on both operands.
+    /// 
+    /// `bigint` returns none on division by zero.
+    fn numeric_op(self, rhs:Self, number:fn(f64, f64) -> f64, bigint:fn(BigInt, BigInt) -> Option<BigInt>) -> JValue{
+        if let (JValueEnum::Number(a), JValueEnum::Number(b)) = (self.unbox(), rhs.unbox()){
+            return JValue::Number(number(a, b))
+        }
+
+        let (lhs, rhs) = (self.to_numeric(), rhs.to_numeric());
+        match (lhs.unbox(), rhs.unbox()){
+            (JValueEnum::BigInt(a), JValueEnum::BigInt(b)) => match bigint(a.value(), b.value()){
+                Some(v) => JValue::BigInt(v),
+                None => operator::throw(builtins::Error::newRangeError("Division by zero"))
             },
-            JValue::BigInt(b) => {
-                JValue::BigInt(b.pow(rhs.to_float() as u32))
+            (JValueEnum::BigInt(_), _) | (_, JValueEnum::BigInt(_)) => {
+                operator::throw(builtins::Error::newTypeError("Cannot mix BigInt and other types, use explicit conversions"))
             },
-            _ => JValue::Number(self.to_float().powf(rhs.to_float()))
+            _ => JValue::Number(number(lhs.to_float(), rhs.to_float()))
+        }
+    }
+
+    /// apply a bitwise operator after ToNumeric on both operands, numbers are converted by ToInt32.
+    fn bitwise_op(self, rhs:Self, int32:fn(i32, i32) -> i32, bigint:fn(BigInt, BigInt) -> BigInt) -> JValue{
+        let (lhs, rhs) = (self.to_numeric(), rhs.to_numeric());
+        match (lhs.unbox(), rhs.unbox()){
+            (JValueEnum::BigInt(a), JValueEnum::BigInt(b)) => JValue::BigInt(bigint(a.value(), b.value())),
+            (JValueEnum::BigInt(_), _) | (_, JValueEnum::BigInt(_)) => {
+                operator::throw(builtins::Error::newTypeError("Cannot mix BigInt and other types, use explicit conversions"))
+            },
+            _ => JValue::Number(int32(lhs.to_i32(), rhs.to_i32()) as f64)
         }
     }
 
     pub fn unsignedRShift(self, rhs:Self) -> JValue{
-        JValue::Number((self.to_i32() as u32 >> rhs.to_i32()) as f64)
+        let (lhs, rhs) = (self.to_numeric(), rhs.to_numeric());
+        if lhs.is_bigint() || rhs.is_bigint(){
+            operator::throw(builtins::Error::newTypeError("BigInts have no unsigned right shift, use >> instead"))
+        }
+        JValue::Number((lhs.to_i32() as u32 >> (rhs.to_i32() as u32 & 0x1f)) as f64)
     }
 }
 
 impl Add for JValue{
     type Output = Self;
     fn add(self, rhs: Self) -> Self::Output {
-        match self{
-
-            JValue::Null => match rhs{
-                JValue::String(s) => JValue::String("null"+s),
-                JValue::Null => JValue::Number(0.0),
-                _ => rhs.add(self)
-            },
+        // fast path for numbers
+        if let (JValueEnum::Number(a), JValueEnum::Number(b)) = (self.unbox(), rhs.unbox()){
+            return JValue::Number(a + b)
+        }
 
-            JValue::Symbol(_) => return JValue::Number(f64::NAN),
-            JValue::Undefined => return JValue::Number(f64::NAN),
-
-            JValue::Number(f) => match rhs{
-                JValue::Undefined => JValue::Number(f64::NAN),
-                JValue::Symbol(s) => match rhs{
-                    _ => JValue::Number(f64::NAN)
-                },
-
-                JValue::Null => return JValue::Number(f),
-                JValue::Number(f1) => JValue::Number(f+f1),
-                JValue::BigInt(b) => JValue::Number(f + b as f64),
-                JValue::Boolean(b) => JValue::Number(f + b as u8 as f64),
-                JValue::String(s) => if let Ok(v) = s.parse::<f64>(){
-                    JValue::Number(v + f)
-                } else{
-                    JValue::Number(f64::NAN)
-                },
-                JValue::Object(o) => {
-                    JValue::Number(f64::NAN)
-                }
-            },
+        let lhs = self.to_primitive(None);
+        let rhs = rhs.to_primitive(None);
 
-            JValue::BigInt(b) => match rhs{
-                JValue::Undefined => JValue::Number(f64::NAN),
-                JValue::Symbol(s) => match rhs{
-                    _ => JValue::Number(f64::NAN)
-                },
-                JValue::Null => JValue::BigInt(b),
-                JValue::Number(f) => JValue::BigInt(b + f as i64),
-                JValue::BigInt(b1) => JValue::BigInt(b + b1),
-                JValue::Boolean(b1) => JValue::BigInt(b + b1 as i64),
-                JValue::String(s) => if let Ok(v) = s.parse::<i64>(){
-                    JValue::BigInt(v+b)
-                } else{
-                    JValue::Number(f64::NAN)
-                },
-                JValue::Object(o) => {
-                    JValue::Number(f64::NAN)
-                }
-            },
+        if lhs.is_string() || rhs.is_string(){
+            return (lhs.to_js_string() + &rhs.to_js_string()).into()
+        }
 
-            JValue::Boolean(b) => match rhs{
-                JValue::Null  => JValue::Number(b as u8 as f64),
-                JValue::Undefined => JValue::Number(f64::NAN),
-                JValue::Boolean(b1) => JValue::Number((b as u8 + b1 as u8) as f64),
-                JValue::BigInt(b1) => JValue::BigInt(b1 + b as i64),
-                JValue::Number(n) => JValue::Number(n + b as u8 as f64),
-                _ => (b.to_string() + &rhs.to_string()).into()
+        let (lhs, rhs) = (lhs.to_numeric(), rhs.to_numeric());
+        match (lhs.unbox(), rhs.unbox()){
+            (JValueEnum::BigInt(a), JValueEnum::BigInt(b)) => JValue::BigInt(a.value() + b.value()),
+            (JValueEnum::BigInt(_), _) | (_, JValueEnum::BigInt(_)) => {
+                operator::throw(builtins::Error::newTypeError("Cannot mix BigInt and other types, use explicit conversions"))
             },
-
-            JValue::String(s) => (s.to_string() + &rhs.to_string()).as_str().into(),
-
-            JValue::Object(o) => match rhs{
-                _ => JValue::Number(f64::NAN)
-            }
+            _ => JValue::Number(lhs.to_float() + rhs.to_float())
         }
     }
 }
 
 impl Sub for JValue{
     type Output = Self;
-
     fn sub(self, rhs: Self) -> Self::Output {
-        match self{
-            JValue::Null => JValue::Number(0.0).sub(rhs),
-            JValue::Number(f) => match rhs{
-                JValue::Null => JValue::Number(f),
-                JValue::Number(f1) => JValue::Number(f - f1),
-                JValue::BigInt(b) => JValue::Number(f - b as f64),
-                JValue::Boolean(b) => JValue::Number(f - b as u8 as f64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::BigInt(b) => match rhs{
-                JValue::Null => JValue::BigInt(b),
-                JValue::BigInt(b1) => JValue::BigInt(b - b1),
-                JValue::Number(f) => JValue::BigInt(b - f as i64),
-                JValue::Boolean(b1) => JValue::BigInt(b - b1 as i64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::String(s) => {
-                if let Ok(v) = s.parse::<f64>(){
-                    JValue::Number(v).sub(rhs)
-                } else{
-                    JValue::Number(f64::NAN)
-                }
-            },
-            _ => JValue::Number(f64::NAN),
-        }
+        self.numeric_op(rhs, |a, b|a - b, |a, b|Some(a - b))
     }
 }
 
 impl Div for JValue{
     type Output = Self;
     fn div(self, rhs: Self) -> Self::Output {
-        match self{
-            JValue::Null => JValue::Number(0.0),
-            JValue::Number(f) => match rhs{
-                JValue::Null => JValue::Number(f / 0.0),
-                JValue::Number(f1) => JValue::Number(f / f1),
-                JValue::BigInt(b) => JValue::Number(f / b as f64),
-                JValue::Boolean(b) => JValue::Number(f/ b as u8 as f64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::BigInt(b) => match rhs{
-                JValue::Null => JValue::BigInt(b / 0),
-                JValue::BigInt(b1) => JValue::BigInt(b / b1),
-                JValue::Number(f) => JValue::BigInt(b / f as i64),
-                JValue::Boolean(b1) => JValue::BigInt(b/ b1 as i64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::String(s) => {
-                if let Ok(v) = s.parse::<f64>(){
-                    JValue::Number(v).div(rhs)
-                } else{
-                    JValue::Number(f64::NAN)
-                }
-            },
-            _ => JValue::Number(f64::NAN),
-        }
+        self.numeric_op(rhs, |a, b|a / b, |a, b|if b.is_zero() {None} else {Some(a / b)})
     }
 }
 
 impl Mul for JValue{
     type Output = Self;
     fn mul(self, rhs: Self) -> Self::Output {
-        match self{
-            JValue::Null => JValue::Number(0.0),
-            JValue::Number(f) => match rhs{
-                JValue::Null => JValue::Number(f * 0.0),
-                JValue::Number(f1) => JValue::Number(f * f1),
-                JValue::BigInt(b) => JValue::Number(f * b as f64),
-                JValue::Boolean(b) => JValue::Number(f *b as u8 as f64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::BigInt(b) => match rhs{
-                JValue::Null => JValue::BigInt(b * 0),
-                JValue::BigInt(b1) => JValue::BigInt(b * b1),
-                JValue::Number(f) => JValue::BigInt(b * f as i64),
-                JValue::Boolean(b1) => JValue::BigInt(b * b1 as i64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::String(s) => {
-                if let Ok(v) = s.parse::<f64>(){
-                    JValue::Number(v).mul(rhs)
-                } else{
-                    JValue::Number(f64::NAN)
-                }
-            },
-            _ => JValue::Number(f64::NAN),
-        }
+        self.numeric_op(rhs, |a, b|a * b, |a, b|Some(a * b))
     }
 }
 
 impl Rem for JValue{
     type Output = Self;
     fn rem(self, rhs: Self) -> Self::Output {
-        match self{
-            JValue::Null => JValue::Number(0.0),
-            JValue::Number(f) => match rhs{
-                JValue::Null => JValue::Number(f % 0.0),
-                JValue::Number(f1) => JValue::Number(f % f1),
-                JValue::BigInt(b) => JValue::Number(f % b as f64),
-                JValue::Boolean(b) => JValue::Number(f % b as u8 as f64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::BigInt(b) => match rhs{
-                JValue::Null => JValue::BigInt(b % 0),
-                JValue::BigInt(b1) => JValue::BigInt(b % b1),
-                JValue::Number(f) => JValue::BigInt(b % f as i64),
-                JValue::Boolean(b1) => JValue::BigInt(b % b1 as i64),
-                _ => JValue::Number(f64::NAN)
-            },
-            JValue::String(s) => {
-                if let Ok(v) = s.parse::<f64>(){
-                    JValue::Number(v).rem(rhs)
-                } else{
-                    JValue::Number(f64::NAN)
-                }
-            },
-            _ => JValue::Number(f64::NAN),
-        }
+        self.numeric_op(rhs, |a, b|a % b, |a, b|if b.is_zero() {None} else {Some(a % b)})
     }
 }
 
 impl BitAnd for JValue{
     type Output = Self;
     fn bitand(self, rhs: Self) -> Self::Output {
-        JValue::Number((self.to_i32() & rhs.to_i32()) as f64)
+        self.bitwise_op(rhs, |a, b|a & b, |a, b|a & b)
     }
 }
 
 impl BitOr for JValue{
     type Output = Self;
     fn bitor(self, rhs: Self) -> Self::Output {
-        JValue::Number((self.to_i32() | rhs.to_i32()) as f64)
+        self.bitwise_op(rhs, |a, b|a | b, |a, b|a | b)
     }
 }
 
 impl BitXor for JValue{
     type Output = Self;
     fn bitxor(self, rhs: Self) -> Self::Output {
-        JValue::Number((self.to_i32() ^ rhs.to_i32()) as f64)
+        self.bitwise_op(rhs, |a, b|a ^ b, |a, b|a ^ b)
     }
 }
 
 impl Shl for JValue{
     type Output = Self;
     fn shl(self, rhs: Self) -> Self::Output {
-        todo!()
+        self.bitwise_op(rhs, |a, b|a.wrapping_shl(b as u32 & 0x1f), bigint::shift_left)
     }
 }
 
 impl Shr for JValue{
     type Output = Self;
     fn shr(self, rhs: Self) -> Self::Output {
-        todo!()
+        self.bitwise_op(rhs, |a, b|a >> (b as u32 & 0x1f), bigint::shift_right)
     }
 }
 
 impl PartialEq for JValue{
     fn eq(&self, other: &Self) -> bool {
-        todo!()
+        self.strict_equals(*other)
     }
 }
 
 impl ToString for JValue{
     fn to_string(&self) -> String {
-        match *self{
-            JValue::Null => "null".to_owned(),
-            JValue::Undefined => "undefined".to_owned(),
-            JValue::BigInt(i) => i.to_string(),
-            JValue::Number(f) => f.to_string(),
-            JValue::Boolean(b) => b.to_string(),
-            JValue::Object(o) => "[object Object]".to_owned(),
-            JValue::String(s) => s.to_string(),
-            JValue::Symbol(s) => s.to_string(),
+        match self.unbox(){
+            JValueEnum::Null => "null".to_owned(),
+            JValueEnum::Undefined => "undefined".to_owned(),
+            JValueEnum::BigInt(i) => i.value().to_string(),
+            JValueEnum::Number(f) => number_to_string(f),
+            JValueEnum::Boolean(b) => b.to_string(),
+            JValueEnum::Object(o) => self.to_primitive(Some("string")).to_string(),
+            JValueEnum::String(s) => s.to_string(),
+            JValueEnum::Symbol(s) => format!("Symbol({})", s.as_ref()),
+        }
+    }
+}
+
+/// StringToNumber, surrounding whitespace is ignored and the empty string is 0
+pub(crate) fn string_to_number(s:&str) -> f64{
+    let s = s.trim();
+    if s.is_empty(){
+        return 0.0
+    }
+
+    let radix = match s.get(0..2){
+        Some("0x") | Some("0X") => 16,
+        Some("0o") | Some("0O") => 8,
+        Some("0b") | Some("0B") => 2,
+        _ => 10
+    };
+    if radix != 10{
+        return match u64::from_str_radix(&s[2..], radix){
+            Ok(v) => v as f64,
+            Err(_) => f64::NAN
         }
     }
+
+    match s{
+        "Infinity" | "+Infinity" => f64::INFINITY,
+        "-Infinity" => f64::NEG_INFINITY,
+        // rust accepts names that are not numeric literals
+        _ if s.chars().any(|c|c.is_ascii_alphabetic() && c != 'e' && c != 'E') => f64::NAN,
+        _ => s.parse::<f64>().unwrap_or(f64::NAN)
+    }
+}
+
+/// Number::toString
+pub(crate) fn number_to_string(f:f64) -> String{
+    if f.is_nan(){
+        return "NaN".to_owned()
+    }
+    if f.is_infinite(){
+        return if f > 0.0 {"Infinity".to_owned()} else {"-Infinity".to_owned()}
+    }
+    if f == 0.0{
+        return "0".to_owned()
+    }
+
+    let abs = f.abs();
+    if abs >= 1e21 || abs < 1e-6{
+        // exponent form: 1e+21, 1.5e-7
+        let s = format!("{:e}", f);
+        return match s.split_once('e'){
+            Some((m, e)) if !e.starts_with('-') => format!("{}e+{}", m, e),
+            _ => s
+        }
+    }
+    f.to_string()
 }
 
 impl Hash for JValue{
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        match self{
-            JValue::Null => {
+        match self.unbox(){
+            JValueEnum::Null => {
                 state.write_u8(0);
                 state.write_usize(0x900);
             },
-            JValue::Undefined => {
+            JValueEnum::Undefined => {
                 state.write_u8(1);
                 state.write_usize(0x901)
             },
-            JValue::BigInt(i) => {
+            JValueEnum::BigInt(i) => {
                 state.write_u8(2);
-                state.write_i64(*i)
+                state.write(i.bytes())
             },
-            JValue::Boolean(b) => {
+            JValueEnum::Boolean(b) => {
                 state.write_u8(3);
-                state.write_u8(*b as u8)
+                state.write_u8(b as u8)
             },
-            JValue::Number(n) => {
+            JValueEnum::Number(n) => {
                 state.write_u8(4);
                 state.write(&n.to_le_bytes())
             },
-            JValue::String(s) => {
+            JValueEnum::String(s) => {
                 state.write_u8(5);
                 s.hash(state)
             },
-            JValue::Symbol(s) => {
+            JValueEnum::Symbol(s) => {
                 state.write_u8(6);
                 state.write_u32(s.id);
-                s.intern.hash(state);
             },
-            JValue::Object(o) => {
+            JValueEnum::Object(o) => {
                 state.write_u8(7);
-                unsafe{(&**o).hash(state)};
+                unsafe{(&*o).hash(state)};
             }
         }
     }
@@ -768,8 +1156,85 @@ impl From<&'static mut JObject> for JValue{
     }
 }
 
+impl From<bool> for JValue{
+    fn from(b: bool) -> Self {
+        JValue::Boolean(b)
+    }
+}
+
+impl From<f64> for JValue{
+    fn from(f: f64) -> Self {
+        JValue::Number(f)
+    }
+}
+
 impl From<i32> for JValue{
     fn from(i: i32) -> Self {
         JValue::Number(i as f64)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn nan_is_canonical(){
+        assert_eq!(JValue::Number(f64::NAN).to_bits(), CANONICAL_NAN);
+        assert_eq!(JValue::Number(-f64::NAN).to_bits(), CANONICAL_NAN);
+
+        // a NaN with the bits of a tagged value must not decode as that value
+        let forged = f64::from_bits(TAG_OBJECT | 0x1000);
+        let v = JValue::Number(forged);
+        assert!(v.is_number());
+        assert!(!v.is_object());
+        assert!(v.number().unwrap().is_nan());
+    }
+
+    #[test]
+    fn numbers_round_trip(){
+        for n in [0.0, -0.0, 1.5, -1e300, f64::INFINITY, f64::NEG_INFINITY, f64::MIN_POSITIVE]{
+            let v = JValue::Number(n);
+            assert!(v.is_number());
+            assert_eq!(v.number().unwrap().to_bits(), n.to_bits());
+        }
+    }
+
+    #[test]
+    fn special_values_are_distinct(){
+        assert!(JValue::Undefined.is_undefined());
+        assert!(JValue::Null.is_null());
+        assert!(JValue::Undefined.is_nullish() && JValue::Null.is_nullish());
+        assert!(!JValue::Boolean(false).is_nullish());
+
+        assert_eq!(JValue::Boolean(true).bool(), Some(true));
+        assert_eq!(JValue::Boolean(false).bool(), Some(false));
+        assert_eq!(JValue::Null.bool(), None);
+
+        for v in [JValue::Undefined, JValue::Null, JValue::Boolean(true), JValue::from_bits(UNINITIALIZED)]{
+            assert!(!v.is_number() && !v.is_object() && !v.is_string());
+        }
+    }
+
+    #[test]
+    fn tagged_values_round_trip(){
+        let v = JValue::Symbol(Symbol{id:42});
+        assert!(v.is_symbol());
+        assert_eq!(v.symbol().unwrap().id, 42);
+
+        let _runtime = Runtime::new();
+
+        let s = JValue::from("abc");
+        assert!(s.is_string());
+        assert_eq!(s.string().unwrap().as_str(), "abc");
+
+        let b = JValue::BigInt(BigInt::from(-7));
+        assert!(b.is_bigint());
+        assert_eq!(b.bigint().unwrap(), BigInt::from(-7));
+
+        let o = JObject::new() as *mut JObject;
+        let v = JValue::Object(o);
+        assert!(v.is_object());
+        assert_eq!(v.object().unwrap() as *mut JObject, o);
+    }
+}
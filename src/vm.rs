@@ -1,13 +1,19 @@
 use std::{sync::Arc, collections::HashMap};
 
 use rustc_hash::FxHashMap;
+use string_interner::{Symbol, symbol::SymbolUsize};
 
 use crate::operator;
+use crate::builtins::JObject;
+use crate::builtins::Error;
+use crate::builtins::Realm;
+use crate::builtins::generator::Generator;
 use crate::runtime::Runtime;
-use crate::value::JValue;
+use crate::value::{self, JValue};
 use crate::utils::{
     BuildNoHasher,
-    NoHasher
+    NoHasher,
+    ToMutable
 };
 
 #[derive(Clone)]
@@ -16,26 +22,81 @@ pub enum Variable{
     Var(JValue),
     Const(JValue),
 
-    Captured(Arc<JValue>)
+    Captured(Arc<CapturedVariable>)
+}
+
+/// a binding shared with the functions capturing it
+pub struct CapturedVariable{
+    pub(crate) value:JValue,
+    /// true for `const` bindings, assignments throw a TypeError
+    pub(crate) constant:bool,
+}
+
+impl CapturedVariable{
+    fn new(value:JValue, constant:bool) -> Arc<Self>{
+        Arc::new(Self{value, constant})
+    }
+
+    /// the value written in place, seen by every function sharing the binding
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn slot(self:&Arc<Self>) -> &mut JValue{
+        &mut (*(Arc::as_ptr(self) as *mut Self)).value
+    }
+}
+
+/// the value of a `let` or `const` binding before its declaration runs
+pub(crate) fn uninitialized() -> JValue{
+    JValue::from_bits(value::UNINITIALIZED)
+}
+
+pub(crate) fn is_uninitialized(v:&JValue) -> bool{
+    v.to_bits() == value::UNINITIALIZED
 }
 
 pub struct VmContext{
     pub(crate) runtime:&'static mut Runtime,
+    /// the realm of the script context, its global object is the last scope
+    pub(crate) realm:*mut Realm,
     pub(crate) parent:Option<&'static mut VmContext>,
     pub(crate) childs:Vec<&'static mut Self>,
     pub(crate) variables:HashMap<u64, Variable, BuildNoHasher>,
 
-    pub(crate) captures:Option<Arc< HashMap<u64, Arc<JValue>, BuildNoHasher> >>
+    pub(crate) captures:Option<Arc< HashMap<u64, Arc<CapturedVariable>, BuildNoHasher> >>,
+
+    /// the bindings declared by compiled code, addressed by the index resolved when compiling
+    pub(crate) record:Vec<Variable>,
+    /// the bindings captured by the compiled function being executed, in the order its code addresses them
+    pub(crate) closure:Option<Arc<Vec<Arc<CapturedVariable>>>>,
+
+    /// the function object being executed, used to resolve `super`
+    pub(crate) function:Option<JValue>,
+
+    /// the generator owning this context
+    pub(crate) generator:Option<*mut Generator>,
+
+    /// the constructor `new` was applied to, none if the function is called
+    pub(crate) new_target:Option<JValue>,
+
+    /// the exception thrown out of compiled code, 
+    /// set instead of unwinding through the compiled frames.
+    pub(crate) exception:Option<JValue>,
 }
 
 impl VmContext{
     pub fn new() -> Self{
         Self { 
             runtime: unsafe{std::mem::transmute(0usize)}, 
+            realm: std::ptr::null_mut(),
             childs:Vec::new(),
             parent: None, 
             variables: HashMap::default(), 
-            captures: None
+            captures: None,
+            record: Vec::new(),
+            closure: None,
+            function: None,
+            generator: None,
+            new_target: None,
+            exception: None,
         }
     }
 
@@ -43,10 +104,17 @@ impl VmContext{
         
         let c = Box::leak(Box::new(Self{
             runtime:unsafe{std::ptr::read(&self.runtime)},
+            realm:self.realm,
             childs:Vec::new(),
             parent:Some(unsafe{std::mem::transmute(std::ptr::read(&self))}),
             variables:Default::default(),
             captures:None,
+            record:Vec::new(),
+            closure:None,
+            function:self.function,
+            generator:None,
+            new_target:None,
+            exception:None,
         }));
         self.childs.push(unsafe{std::ptr::read(&c)});
         c
@@ -56,32 +124,98 @@ impl VmContext{
         unsafe{std::ptr::drop_in_place(self)};
     }
 
-    pub fn attach_captures(&mut self, c:Arc<HashMap<u64, Arc<JValue>, BuildNoHasher>>){
+    /// enter a block scope of compiled code, return the context declaring its bindings
+    pub(crate) fn push_scope(&mut self) -> &'static mut Self{
+        self.new_child()
+    }
+
+    /// leave a block scope entered by `push_scope` and free its context.
+    ///
+    /// the functions created in the scope keep the bindings they captured.
+    pub(crate) unsafe fn pop_scope(scope:*mut Self){
+        if let Some(p) = &mut (*scope).parent{
+            if let Some(i) = p.childs.iter().rposition(|c|std::ptr::eq(&**c, scope)){
+                p.childs.swap_remove(i);
+            }
+        }
+        drop(Box::from_raw(scope));
+    }
+
+    /// the context enclosing a block scope
+    pub(crate) fn scope_parent(&mut self) -> *mut Self{
+        match &mut self.parent{
+            Some(p) => &mut **p,
+            None => unreachable!("the context of a script has no enclosing scope")
+        }
+    }
+
+    /// the global object of the realm
+    fn global(&self) -> &'static mut JObject{
+        unsafe{(*self.realm).global()}
+    }
+
+    /// the object `super` property lookups start from
+    pub(crate) fn super_base(&self) -> Option<&'static mut JObject>{
+        let f = self.function?.object()?;
+        let home = f.inner.function()?.home_object?;
+        home.object()?.prototype()
+    }
+
+    /// called by compiled code when an exception is not caught in the function
+    pub(crate) fn set_exception(&mut self, value:JValue){
+        self.exception = Some(value);
+    }
+
+    /// the generator being executed, used by the compiled body of generator functions
+    pub(crate) fn generator(&self) -> &'static mut Generator{
+        match self.generator{
+            Some(g) => unsafe{g.as_mut().unwrap()},
+            None => operator::throw(Error::newTypeError("yield outside of a generator"))
+        }
+    }
+
+    /// the names declared by this context and its parents, except the script context.
+    ///
+    /// captured by the functions created by the interpreter, which does not resolve free variables.
+    pub(crate) fn visible_names(&self) -> Vec<u64>{
+        let mut names = Vec::new();
+        let mut ctx = Some(self);
+        while let Some(c) = ctx{
+            if c.parent.is_none(){
+                break;
+            }
+            names.extend(c.variables.keys().copied());
+            if let Some(captures) = &c.captures{
+                names.extend(captures.keys().copied());
+            }
+            ctx = c.parent.as_deref();
+        }
+        names
+    }
+
+    pub fn attach_captures(&mut self, c:Arc<HashMap<u64, Arc<CapturedVariable>, BuildNoHasher>>){
         self.captures = Some(c)
     }
 
-    pub fn capture(&mut self, name:u64) -> Option<Arc<JValue>>{
+    pub fn attach_closure(&mut self, c:Arc<Vec<Arc<CapturedVariable>>>){
+        self.closure = Some(c)
+    }
+
+    /// the binding shared by `variable`, a `const` binding stays constant
+    fn share(variable:&mut Variable) -> Arc<CapturedVariable>{
+        let a = match variable{
+            Variable::Captured(c) => return c.clone(),
+            Variable::Const(c) => CapturedVariable::new(*c, true),
+            Variable::Let(v) | Variable::Var(v) => CapturedVariable::new(*v, false),
+        };
+        *variable = Variable::Captured(a.clone());
+        a
+    }
+
+    /// share the binding `name` with a function, a `const` binding stays constant
+    pub fn capture(&mut self, name:u64) -> Option<Arc<CapturedVariable>>{
         if let Some(variable) = self.variables.get_mut(&name){
-            match variable{
-                Variable::Captured(c) => {
-                    Some(c.clone())
-                },
-                Variable::Const(c) => {
-                    let a = Arc::new(*c);
-                    *variable = Variable::Captured(a.clone());
-                    Some(a.clone())
-                },
-                Variable::Let(l) => {
-                    let a = Arc::new(*l);
-                    *variable = Variable::Captured(a.clone());
-                    Some(a.clone())
-                },
-                Variable::Var(c) => {
-                    let a = Arc::new(*c);
-                    *variable = Variable::Captured(a.clone());
-                    Some(a.clone())
-                }
-            }
+            Some(Self::share(variable))
         } else{
             if let Some(o) = &self.captures{
                 if let Some(v) = o.get(&name){
@@ -97,30 +231,59 @@ impl VmContext{
         }
     }
 
-    /// return true if present, else false
-    pub fn get_variable_raw(&self, name:u64) -> (JValue, bool){
+    /// the value of the binding, the global object is the last scope.
+    /// 
+    /// None if the name is not declared.
+    fn lookup(&self, name:u64) -> Option<JValue>{
         if let Some(v) = self.variables.get(&name){
-            (match v{
-                Variable::Captured(c) => **c,
+            Some(match v{
+                Variable::Captured(c) => c.value,
                 Variable::Const(v) => *v,
                 Variable::Let(v) => *v,
                 Variable::Var(v) => *v
-            }, true)
+            })
         }else{
             if let Some(o) = &self.captures{
                 if let Some(v) = o.get(&name){
-                    return (**v, true)
+                    return Some(v.value)
                 };
             };
             if let Some(p) = &self.parent{
-                p.get_variable_raw(name)
+                p.lookup(name)
             } else{
-                // todo: throw reference error
-                (JValue::Undefined, false)
+                // not declared in any scope, fallback to the global object
+                let name = self.variable_name(name);
+                if self.global().has_property(name){
+                    return Some(self.global().member_str(name))
+                }
+                None
             }
         }
     }
 
+    /// return true if present, else false and the error to throw
+    pub fn get_variable_raw(&self, name:u64) -> (JValue, bool){
+        match self.lookup(name){
+            Some(v) if is_uninitialized(&v) => (self.uninitialized_error(name), false),
+            Some(v) => (v, true),
+            None => (Error::newReferenceError(format!("{} is not defined", self.variable_name(name))), false)
+        }
+    }
+
+    /// `typeof name`, an unresolvable name is not an error
+    pub fn typeof_variable(&self, name:u64) -> JValue{
+        match self.lookup(name){
+            Some(v) if is_uninitialized(&v) => operator::throw(self.uninitialized_error(name)),
+            Some(v) => v.typeOf(),
+            None => "undefined".into()
+        }
+    }
+
+    fn variable_name(&self, name:u64) -> &'static str{
+        let s = SymbolUsize::try_from_usize(name as usize).and_then(|s|self.runtime.variable_names.resolve(s));
+        unsafe{std::mem::transmute::<&str, &'static str>(s.unwrap_or_default())}
+    }
+
     pub fn get_variable(&self, name:u64) -> JValue{
         let (re, ok) = self.get_variable_raw(name);
         if !ok{
@@ -129,21 +292,38 @@ impl VmContext{
         re
     }
 
+    /// assign to the binding `name`.
+    /// 
+    /// throws a TypeError for `const` bindings and a ReferenceError for bindings 
+    /// not initialized yet, undeclared names are assigned to the global object.
     pub fn set_variable(&mut self, name:u64, value:JValue){
         if let Some(v) = self.variables.get_mut(&name){
-            match v{
+            let (slot, constant) = match v{
                 Variable::Captured(c) => unsafe{
-                    (c.as_ref() as *const JValue as *mut JValue).write(value)
+                    (c.slot(), c.constant)
                 },
-                Variable::Const(v) => *v = value,
-                Variable::Let(v) => *v = value,
-                Variable::Var(v) => *v = value
+                Variable::Const(v) => (v, true),
+                Variable::Let(v) => (v, false),
+                Variable::Var(v) => (v, false)
+            };
+            if is_uninitialized(slot){
+                operator::throw(self.uninitialized_error(name))
+            }
+            if constant{
+                operator::throw(self.const_error())
             }
+            *slot = value;
         } else{
             if let Some(o) = &self.captures{
                 if let Some(v) = o.get(&name){
+                    if is_uninitialized(&v.value){
+                        operator::throw(self.uninitialized_error(name))
+                    }
+                    if v.constant{
+                        operator::throw(self.const_error())
+                    }
                     unsafe{
-                        (v.as_ref() as *const JValue as *mut JValue).write(value)
+                        *v.slot() = value;
                     }
                     return;
                 };
@@ -151,8 +331,167 @@ impl VmContext{
 
             if let Some(p) = &mut self.parent{
                 p.set_variable(name, value);
+            } else{
+                let name = self.variable_name(name);
+                self.global().set_member_str(name, value);
+            }
+        }
+    }
+
+    /// run the declaration of `name`, 
+    /// the binding declared by the innermost context declaring it is assigned even if `const`.
+    /// 
+    /// the binding is kept if captured by a function created before the declaration ran.
+    pub fn initialize_variable(&mut self, name:u64, value:JValue){
+        if let Some(v) = self.variables.get_mut(&name){
+            match v{
+                Variable::Captured(c) => unsafe{
+                    *c.slot() = value
+                },
+                Variable::Const(v) => *v = value,
+                Variable::Let(v) => *v = value,
+                Variable::Var(v) => *v = value
             }
+        } else if let Some(p) = &mut self.parent{
+            p.initialize_variable(name, value);
+        } else{
+            self.variables.insert(name, Variable::Var(value));
+        }
+    }
+
+    /// the script context, the root of every context
+    fn root(&mut self) -> &mut Self{
+        if self.parent.is_some(){
+            self.parent.as_mut().unwrap().root()
+        } else{
+            self
+        }
+    }
+
+    /// resolve a name no enclosing function declares, 
+    /// from the script context and the global object.
+    pub fn get_free_variable(&mut self, name:u64) -> JValue{
+        self.root().get_variable(name)
+    }
+
+    pub fn set_free_variable(&mut self, name:u64, value:JValue){
+        self.root().set_variable(name, value)
+    }
+
+    pub fn typeof_free_variable(&mut self, name:u64) -> JValue{
+        self.root().typeof_variable(name)
+    }
+
+    /// the error of an assignment to a `const` binding
+    pub(crate) fn const_error(&self) -> JValue{
+        Error::newTypeError("Assignment to constant variable.")
+    }
+
+    /// the error of a `let` or `const` binding accessed before its declaration
+    pub(crate) fn uninitialized_error(&self, name:u64) -> JValue{
+        Error::newReferenceError(format!("Cannot access '{}' before initialization", self.variable_name(name)))
+    }
+
+    /// declare a variable in the current context.
+    /// 
+    /// kind: 0 for `var`, 1 for `let`, 2 for `const`
+    pub fn declare_variable(&mut self, name:u64, value:JValue, kind:i8){
+        self.variables.insert(name, Self::new_variable(value, kind));
+    }
+
+    fn new_variable(value:JValue, kind:i8) -> Variable{
+        match kind{
+            1 => Variable::Let(value),
+            2 => Variable::Const(value),
+            _ => Variable::Var(value)
+        }
+    }
+
+    /// declare the binding `index` of the record, replacing the binding it held.
+    ///
+    /// kind: 0 for `var`, 1 for `let`, 2 for `const`
+    pub(crate) fn declare_slot(&mut self, index:usize, value:JValue, kind:i8){
+        self.put_slot(index, Self::new_variable(value, kind));
+    }
+
+    fn put_slot(&mut self, index:usize, variable:Variable){
+        if self.record.len() <= index{
+            self.record.resize(index + 1, Variable::Var(uninitialized()));
         }
+        self.record[index] = variable;
+    }
+
+    /// the value of the binding `index` of the record, 
+    /// uninitialized until declared, the compiled code checks it.
+    pub(crate) fn get_slot(&self, index:usize) -> JValue{
+        match self.record.get(index){
+            Some(Variable::Captured(c)) => c.value,
+            Some(Variable::Let(v) | Variable::Var(v) | Variable::Const(v)) => *v,
+            None => uninitialized()
+        }
+    }
+
+    /// assign the binding `index` of the record, 
+    /// the compiled code throws before assigning a `const` binding.
+    pub(crate) fn set_slot(&mut self, index:usize, value:JValue){
+        match self.record.get_mut(index){
+            Some(Variable::Captured(c)) => unsafe{
+                *c.slot() = value
+            },
+            Some(Variable::Let(v) | Variable::Var(v) | Variable::Const(v)) => *v = value,
+            None => self.declare_slot(index, value, 0)
+        }
+    }
+
+    /// share the binding `index` of the record with a function
+    pub(crate) fn capture_slot(&mut self, index:usize) -> Arc<CapturedVariable>{
+        if self.record.len() <= index{
+            self.declare_slot(index, uninitialized(), 1);
+        }
+        Self::share(&mut self.record[index])
+    }
+
+    /// the binding `index` captured by the function being executed
+    fn closure_variable(&self, index:usize) -> &Arc<CapturedVariable>{
+        match &self.closure{
+            Some(c) => &c[index],
+            None => unreachable!("the function captures no binding")
+        }
+    }
+
+    pub(crate) fn get_closure(&self, index:usize) -> JValue{
+        self.closure_variable(index).value
+    }
+
+    pub(crate) fn set_closure(&mut self, index:usize, value:JValue){
+        unsafe{
+            *self.closure_variable(index).slot() = value
+        }
+    }
+
+    /// declare a top level binding of a script in the script context, 
+    /// shared by its index and its name so that the interpreter resolves it.
+    pub(crate) fn declare_script_slot(&mut self, index:usize, name:u64, value:JValue, kind:i8){
+        let a = CapturedVariable::new(value, kind == 2);
+        self.put_slot(index, Variable::Captured(a.clone()));
+        self.variables.insert(name, Variable::Captured(a));
+    }
+
+    /// declare a top level `var` of a script undefined, 
+    /// the binding of a previous script is kept.
+    pub(crate) fn hoist_script_slot(&mut self, index:usize, name:u64){
+        if matches!(self.record.get(index), Some(Variable::Captured(_))){
+            return
+        }
+        let a = match self.variables.get_mut(&name){
+            Some(v) => Self::share(v),
+            None => {
+                let a = CapturedVariable::new(JValue::Undefined, false);
+                self.variables.insert(name, Variable::Captured(a.clone()));
+                a
+            }
+        };
+        self.put_slot(index, Variable::Captured(a));
     }
 
     pub fn get_variable_str(&mut self, name:&str) -> JValue{
@@ -164,4 +503,38 @@ impl VmContext{
         let s = self.runtime.new_variable_name(name);
         self.set_variable(s as u64, value)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests{
+    use crate::runtime::{ExecutionMode, Runtime};
+
+    const MODES:[ExecutionMode; 4] = [ExecutionMode::Interpreter, ExecutionMode::Bytecode, ExecutionMode::Tiered, ExecutionMode::Jit];
+
+    fn run(mode:ExecutionMode, script:&str) -> String{
+        let runtime = Runtime::new();
+        runtime.set_execution_mode(mode);
+        runtime.set_tier_up_threshold(2);
+        runtime.clone().exec("test.js", script).unwrap().value.to_string()
+    }
+
+    #[test]
+    fn captured_const_stays_constant(){
+        let scripts = [
+            "function f(){ const c = 1; const g = () => { c = 2 }; let n = 0; for (let i = 0; i < 5; i++){ try { g() } catch(e) { if (e instanceof TypeError) n++ } } return n + c } f()",
+            "function f(){ const c = 1; const g = () => { c += 2 }; let n = 0; for (let i = 0; i < 5; i++){ try { g() } catch(e) { if (e instanceof TypeError) n++ } } return n + c } f()",
+            "function* gen(){ const c = 1; const g = () => c; yield g(); try { c = 5 } catch(e) { yield e instanceof TypeError } } let it = gen(); it.next(); it.next().value ? 6 : 0",
+        ];
+        for mode in MODES{
+            for s in scripts{
+                assert_eq!(run(mode, s), "6", "{}", s);
+            }
+        }
+    }
+
+    #[test]
+    fn captured_let_is_shared(){
+        for mode in MODES{
+            assert_eq!(run(mode, "function f(){ let c = 1; const g = () => { c = 2 }; g(); return c } f()"), "2");
+        }
+    }
+}